@@ -1,17 +1,24 @@
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tao::{
     dpi::LogicalSize,
     event::{ElementState, Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoopBuilder},
+    event_loop::{ControlFlow, EventLoopBuilder, EventLoopProxy},
     keyboard::{Key, ModifiersState},
-    window::WindowBuilder,
+    window::{Theme, WindowBuilder},
 };
 use wry::dpi::{LogicalPosition, LogicalSize as WryLogicalSize};
-use wry::{Rect, WebViewBuilder};
+use wry::{PageLoadEvent, Rect, WebContext, WebViewBuilder};
 
 #[cfg(target_os = "linux")]
 use tao::platform::unix::WindowExtUnix;
@@ -20,17 +27,215 @@ use wry::WebViewBuilderExtUnix;
 
 const SIDEBAR_WIDTH: f64 = 280.0;
 const STRIP_WIDTH: f64 = 28.0;
+/// Newest bookmarks listed in the "Recently added" section by default
+const DEFAULT_RECENTLY_ADDED: usize = 5;
+/// Range allowed for the sidebar text size multiplier
+const MIN_FONT_SCALE: f64 = 0.75;
+const MAX_FONT_SCALE: f64 = 2.0;
+/// Largest size of the pinned reference frame, and its gap from the corner
+const PIP_WIDTH: f64 = 420.0;
+const PIP_HEIGHT: f64 = 300.0;
+const PIP_MARGIN: f64 = 16.0;
+const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36";
+const RECENT_LIMIT: usize = 10;
+/// How long a freshly loaded sidebar has to report `sidebar_ready`
+const SIDEBAR_READY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest folder icon, in chars: room for a skin-toned family or a
+/// subdivision flag, but no unbounded ZWJ or tag runs
+const MAX_ICON_CHARS: usize = 16;
+const DEFAULT_BACKUP_KEEP: usize = 10;
+const DEFAULT_SYNC_TIMEOUT_SECS: u64 = 30;
+const MIN_SYNC_TIMEOUT_SECS: u64 = 5;
+const DEFAULT_PUSH_GUARD_PERCENT: u32 = 50;
+const DEFAULT_HISTORY_RETENTION_DAYS: u32 = 90;
+const DEFAULT_SEARCH_ENGINE_URL: &str = "https://duckduckgo.com/?q=%s";
+/// Every keyboard shortcut as (keys, description). The help dialog is
+/// generated from this list, so keep it in step with the key handling in
+/// `run_gui` and the sidebar's keydown listener.
+const SHORTCUTS: &[(&str, &str)] = &[
+    ("Ctrl+N", "Add bookmark"),
+    ("Ctrl+D", "Bookmark current page"),
+    ("Ctrl+Z", "Undo quick bookmark"),
+    ("Ctrl+Shift+Z", "Restore last deleted bookmark or folder"),
+    ("Ctrl+G", "Add folder"),
+    ("Ctrl+Shift+N", "New folder inline in the tree"),
+    ("F5", "Reload page"),
+    ("Alt+Home", "Go to home page"),
+    (
+        "Ctrl+= / Ctrl+-",
+        "Zoom page in / out (remembered per site)",
+    ),
+    ("Ctrl+0", "Reset page zoom"),
+    ("Ctrl+[", "Navigate back"),
+    ("Ctrl+]", "Navigate forward"),
+    ("Ctrl+L", "Go to bookmark, URL or web search"),
+    ("Ctrl+F", "Filter the sidebar (Escape clears it)"),
+    ("Alt+G, then a key", "Open the bookmark with that mnemonic"),
+    ("Ctrl+Shift+T", "Reopen previous page"),
+    ("Ctrl+Click", "Queue bookmark to read later"),
+    ("Shift+Click", "Select bookmark for tagging"),
+    ("Middle-click", "Open bookmark in system browser"),
+    ("Ctrl+J", "Open next in reading queue"),
+    ("Ctrl+B", "Toggle sidebar"),
+    ("F6", "Switch focus between sidebar and page"),
+    ("Ctrl+Shift+P", "Show / hide the pinned reference page"),
+    ("Ctrl+E", "Show / hide the scratchpad"),
+    (
+        "Ctrl+Shift+S",
+        "Bookmark the page with the selected text as its notes",
+    ),
+    ("F11", "Focus mode (hide sidebar)"),
+    ("Ctrl+U", "Push to GitHub"),
+    ("Ctrl+I", "Pull from GitHub"),
+    ("Ctrl+S", "Sync both ways (pull, merge, push)"),
+    ("\u{2191} / \u{2193}", "Move through sidebar"),
+    ("\u{2190} / \u{2192}", "Collapse / expand folder"),
+    ("Alt+\u{2191} / Alt+\u{2193}", "Move folder up / down"),
+    (
+        "Ctrl+X, Ctrl+V",
+        "Cut a bookmark, paste it before another or into a folder",
+    ),
+    ("F1 / Ctrl+/", "Show this help"),
+    ("Ctrl+Q", "Quit"),
+    ("Escape", "Close dialog"),
+];
+
+/// Catppuccin accents handed out to profiles without a `profile_color`
+const PROFILE_COLORS: [&str; 8] = [
+    "#f38ba8", "#fab387", "#f9e2af", "#a6e3a1", "#94e2d5", "#89b4fa", "#cba6f7", "#f5c2e7",
+];
 
 #[derive(Debug)]
 enum UserEvent {
     Navigate(String),
+    ReopenRecent,
+    ShowOmnibar,
+    OmnibarSubmit(String),
+    QueueBookmark(String),
+    /// Hand a URL to the system's default browser
+    OpenExternal(String),
+    OpenNextQueued,
     ToggleFolder(usize),
     ToggleSidebar,
-    AddFolder(String),
+    ReloadSidebar,
+    SidebarReady,
+    /// The ready timeout ran out for this load of the sidebar
+    SidebarCheck(u64),
+    /// Time to reload the auto-refreshing page of this navigation
+    RefreshTick(u64),
+    FocusMode(bool),
+    /// Move keyboard focus to the sidebar (true) or the content page (false)
+    FocusPane(bool),
+    /// The sidebar webview gained (true) or lost keyboard focus
+    SidebarFocused(bool),
+    /// A text field in the sidebar or the content page gained or lost focus
+    TextFocus {
+        sidebar: bool,
+        editing: bool,
+    },
+    SetViewMode(String),
+    AddFolder {
+        name: String,
+        icon: Option<String>,
+    },
+    SetFolderIcon {
+        folder_index: usize,
+        icon: Option<String>,
+    },
+    ToggleSyncExclusion(usize),
+    /// Lock a folder behind a new PIN
+    LockFolder {
+        folder_index: usize,
+        pin: String,
+    },
+    /// PIN entered to open a locked folder, or to take its lock off
+    UnlockFolder {
+        folder_index: usize,
+        pin: String,
+        remove: bool,
+    },
+    /// URL typed into the add-bookmark dialog, to pick a likely folder for
+    SuggestFolder(String),
+    /// Copy the folder's bookmarks to the clipboard as a Markdown list
+    CopyFolderMarkdown(usize),
+    SortFolders(SortMode),
+    EditBookmark {
+        folder_index: usize,
+        bookmark_index: usize,
+        name: String,
+        url: String,
+        inject_css: Option<String>,
+        inject_js: Option<String>,
+        mnemonic: Option<char>,
+        container: Option<String>,
+        notes: Option<String>,
+        refresh_secs: Option<u64>,
+    },
+    /// Open the bookmark with this mnemonic, if any has it
+    OpenMnemonic(char),
+    MoveFolder {
+        from: usize,
+        to: usize,
+    },
+    /// Paste a cut bookmark so it ends up at `to_index` in `to_folder`;
+    /// `url` guards against the tree having changed since the cut
+    MoveBookmark {
+        from_folder: usize,
+        from_index: usize,
+        url: String,
+        to_folder: usize,
+        to_index: usize,
+    },
+    /// Add `tag` to (or remove it from) each of the bookmarks at
+    /// (folder index, bookmark index)
+    TagBookmarks {
+        targets: Vec<(usize, usize)>,
+        tag: String,
+        add: bool,
+    },
+    /// Ask the Wayback Machine to save a bookmark's page
+    ArchiveBookmark {
+        folder_index: usize,
+        bookmark_index: usize,
+    },
+    /// Copy the bookmark's full record and local data as JSON, for bug reports
+    CopyBookmarkDebug {
+        folder_index: usize,
+        bookmark_index: usize,
+    },
+    /// The snapshot URL for `url`, or why saving failed
+    Archived {
+        url: String,
+        result: Result<String, String>,
+    },
+    /// Show a bookmark in the floating reference frame
+    PinReference {
+        folder_index: usize,
+        bookmark_index: usize,
+    },
+    ShowReference(bool),
+    ToggleReference,
+    ToggleScratchpad,
+    /// The sidebar wants the scratchpad's text to show it
+    LoadScratchpad,
+    SaveScratchpad(String),
+    /// Hide the reference frame and forget its page
+    CloseReference,
+    ToggleOpenOnStartup {
+        folder_index: usize,
+        bookmark_index: usize,
+    },
     AddBookmark {
         folder_index: usize,
         name: String,
         url: String,
+        notes: Option<String>,
+    },
+    /// Text selected on the content page, to bookmark the page with as notes
+    ClipSelection {
+        url: String,
+        title: String,
+        text: String,
     },
     DeleteBookmark {
         folder_index: usize,
@@ -40,33 +245,410 @@ enum UserEvent {
     SaveSettings {
         github_token: String,
         github_repo: String,
+        density: String,
+        scrollbars: String,
+        ui_font_scale: f64,
+        decorations: bool,
+        always_on_top: bool,
+        auto_sync_mode: String,
+        show_urls: bool,
+        show_unread_badge: bool,
+        theme: String,
+        new_folder_position: String,
+        new_bookmark_position: String,
+        duplicate_strategy: String,
+        close_action: String,
+        on_duplicate_folder: String,
+        confirm_form_leave: bool,
+        sync_on_focus: bool,
+        run_bookmarklets: bool,
+        block_trackers: bool,
+        show_pull_changes: bool,
+        auto_expand_active: bool,
+        pretty_json: bool,
+        recently_added: usize,
+        sync_provider: String,
+        gitea_base_url: String,
+        home_url: String,
     },
+    /// Load the home page, or the welcome page when none is set
+    GoHome,
     PushToGitHub,
+    /// The user accepted a push the safety check would otherwise refuse
+    ConfirmPush,
     PullFromGitHub,
+    /// Pull, merge with local, then push the merged store
+    SyncBoth,
+    /// The pull half of `SyncBoth` landed; `None` when the remote file doesn't exist yet
+    SyncPulled(Option<(BookmarkStore, String)>),
     AutoSync,
     SyncStatus(String),
     PushComplete(Option<String>),
+    /// Outcome across the primary target and its mirrors, after every push
+    /// when mirrors are configured
+    PushReport {
+        message: String,
+        ok: bool,
+    },
     PullComplete(BookmarkStore, String),
+    ListBackups,
+    RestoreBackup(String),
+    ImportBookmarks(String),
+    /// Bookmarks checked so far out of the total, from the import thread
+    ImportProgress {
+        done: usize,
+        total: usize,
+    },
+    /// Folders to add and what was left out, or why the file couldn't be read
+    ImportFinished(Result<(Vec<Folder>, ImportReport), String>),
+    /// A pasted or chosen image as a data URL; `None` removes the thumbnail
+    SetThumbnail {
+        folder_index: usize,
+        bookmark_index: usize,
+        image: Option<String>,
+    },
+    CheckLinks,
+    LinkChecked {
+        url: String,
+        status: Option<u16>,
+    },
+    LinkCheckDone,
+    /// Re-fetch page titles to propose new bookmark names
+    RefreshTitles,
+    TitlesProgress {
+        done: usize,
+        total: usize,
+    },
+    /// Page titles by URL, for the pages that answered
+    TitlesFetched(HashMap<String, String>),
+    /// Renames accepted from the preview
+    ApplyTitles(Vec<TitleChange>),
+    /// Remove repeated URLs, within each folder or across all of them
+    RemoveDuplicates {
+        global: bool,
+    },
+    /// List URLs that appear in more than one folder
+    FindCrossDuplicates,
+    /// Check the whole library and list what's wrong
+    ValidateLibrary,
+    /// Apply the safe fixes from the validation report
+    FixLibrary,
+    /// Keep the bookmark with this URL in one folder only
+    KeepDuplicateIn {
+        url: String,
+        folder_index: usize,
+    },
+    /// Write all bookmarks as a flat JSON array to the Downloads folder
+    ExportFlatJson,
+    /// Write a self-contained, browsable HTML page to the Downloads folder
+    ExportHtml,
+    ExportDiagnostics {
+        include_repo: bool,
+    },
+    /// Fill the sync health dialog with the current sync state
+    ShowSyncHealth {
+        include_repo: bool,
+    },
+    /// Look up the remote file's SHA to check the sync settings work
+    TestSyncHealth,
+    SyncHealthTested(Result<Option<String>, String>),
+    /// Show the config directory in the system file manager
+    OpenConfigDir,
+    /// Back up, then empty the whole library; `push` also pushes the result
+    ResetLibrary {
+        push: bool,
+    },
+    /// Re-resolve the theme (setting or system preference) and apply it
+    ApplyTheme,
+    PreviewReplaceUrls {
+        find: String,
+        replace: String,
+        regex: bool,
+    },
+    ReplaceUrls {
+        find: String,
+        replace: String,
+        regex: bool,
+    },
+    TestToken {
+        github_token: String,
+        github_repo: String,
+        api: Result<SyncApi, String>,
+    },
+    TestTokenResult(Result<String, String>),
+    AuthRequired(String),
+    AuthFailed(String),
+    ScrollPosition {
+        url: String,
+        y: f64,
+    },
+    /// The content page finished loading; the title comes from the page itself
+    PageReady {
+        url: String,
+        title: String,
+    },
+    /// Bookmark the current page into the last-used folder without a dialog
+    QuickAdd,
+    UndoQuickAdd,
+    /// Put back the most recently deleted bookmark or folder
+    RestoreDeleted,
+    PageLoading(bool),
+    /// The content pane started loading this URL
+    PageStarted(String),
+    /// The blocklist stopped the content pane from loading this host
+    NavigationBlocked(String),
+    /// Step the zoom for the current site: +1 in, -1 out, 0 reset
+    Zoom(i32),
+    /// The content page's forms were edited (true) or submitted (false)
+    FormDirty(bool),
+    /// The user chose to leave a page with unsaved form input
+    DiscardFormChanges,
+    SubmitAuth {
+        url: String,
+        username: String,
+        password: String,
+    },
+}
+
+/// A new-item position setting; anything but "top" means append.
+fn list_position(setting: &str) -> &'static str {
+    if setting == "top" {
+        "top"
+    } else {
+        "bottom"
+    }
+}
+
+fn insert_at_position<T>(items: &mut Vec<T>, item: T, position: &str) {
+    if position == "top" {
+        items.insert(0, item);
+    } else {
+        items.push(item);
+    }
+}
+
+/// `desired`, or "desired (2)", "desired (3)"… when the name is already
+/// taken. Names are compared ignoring case, so "news" collides with "News".
+fn unique_folder_name(existing: &[String], desired: &str) -> String {
+    let taken = |name: &str| {
+        existing
+            .iter()
+            .any(|e| e.to_lowercase() == name.to_lowercase())
+    };
+    if !taken(desired) {
+        return desired.to_string();
+    }
+    (2..)
+        .map(|n| format!("{desired} ({n})"))
+        .find(|name| !taken(name))
+        .unwrap_or_else(|| desired.to_string())
+}
+
+/// Moves the item at `from` to `to`, shifting the ones in between. Returns
+/// false, leaving `items` alone, when the indices are equal or out of range.
+fn move_item<T>(items: &mut Vec<T>, from: usize, to: usize) -> bool {
+    if from == to || from >= items.len() || to >= items.len() {
+        return false;
+    }
+    let item = items.remove(from);
+    items.insert(to, item);
+    true
+}
+
+/// Moves a bookmark to another place, in the same folder or another one, so
+/// that it ends up at `to_index` there. Returns false, changing nothing, for
+/// a move to where it already is or indices out of range.
+fn move_bookmark(
+    folders: &mut [Folder],
+    (from_folder, from_index): (usize, usize),
+    (to_folder, to_index): (usize, usize),
+) -> bool {
+    if from_folder == to_folder {
+        return folders
+            .get_mut(from_folder)
+            .is_some_and(|f| move_item(&mut f.bookmarks, from_index, to_index));
+    }
+    let fits = folders
+        .get(to_folder)
+        .is_some_and(|f| to_index <= f.bookmarks.len());
+    let exists = folders
+        .get(from_folder)
+        .is_some_and(|f| from_index < f.bookmarks.len());
+    if !fits || !exists {
+        return false;
+    }
+    let bookmark = folders[from_folder].bookmarks.remove(from_index);
+    folders[to_folder].bookmarks.insert(to_index, bookmark);
+    true
+}
+
+/// The last bookmark or folder deleted, with where it was, for Ctrl+Shift+Z
+#[derive(Debug, Clone, PartialEq)]
+enum Deleted {
+    Bookmark {
+        folder_index: usize,
+        folder_name: String,
+        index: usize,
+        bookmark: Bookmark,
+    },
+    Folder {
+        index: usize,
+        folder: Folder,
+    },
+}
+
+/// Puts a deleted item back at its old index, or at the end if the list is
+/// now shorter. A bookmark whose folder has since been deleted comes back in
+/// a new folder of the same name at the end. Returns what was restored.
+fn restore_deleted(store: &mut BookmarkStore, deleted: Deleted) -> String {
+    match deleted {
+        Deleted::Folder { index, folder } => {
+            let label = format!("folder \"{}\"", folder.name);
+            store.folders.insert(index.min(store.folders.len()), folder);
+            label
+        }
+        Deleted::Bookmark {
+            folder_index,
+            folder_name,
+            index,
+            bookmark,
+        } => {
+            let label = format!("bookmark \"{}\"", bookmark.name);
+            // Folders may have moved since; prefer the same index, then the name
+            let target = if store
+                .folders
+                .get(folder_index)
+                .is_some_and(|f| f.name == folder_name)
+            {
+                Some(folder_index)
+            } else {
+                store.folders.iter().position(|f| f.name == folder_name)
+            };
+            match target {
+                Some(fi) => {
+                    let bookmarks = &mut store.folders[fi].bookmarks;
+                    bookmarks.insert(index.min(bookmarks.len()), bookmark);
+                }
+                None => store.folders.push(Folder {
+                    name: folder_name,
+                    expanded: true,
+                    icon: None,
+                    exclude_from_sync: false,
+                    locked: false,
+                    pin: None,
+                    bookmarks: vec![bookmark],
+                }),
+            }
+            label
+        }
+    }
+}
+
+/// Status line after a delete, as a JS string literal
+fn deleted_status(name: &str) -> String {
+    serde_json::to_string(&format!("Deleted \"{name}\" — Ctrl+Shift+Z to restore"))
+        .unwrap_or_else(|_| "''".into())
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
 }
 
 fn default_true() -> bool {
     true
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+fn default_font_scale() -> f64 {
+    1.0
+}
+
+fn default_recently_added() -> usize {
+    DEFAULT_RECENTLY_ADDED
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 struct Bookmark {
     name: String,
     url: String,
+    /// Loaded into the content pane when the app starts
+    #[serde(default, skip_serializing_if = "is_false")]
+    open_on_startup: bool,
+    /// File name under `thumbnails/`; kept only in the local file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thumbnail: Option<String>,
+    /// When the name or URL last changed ("YYYY-MM-DD HH:MM UTC")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<String>,
+    /// When it was added here ("YYYY-MM-DD HH:MM UTC"); imported bookmarks
+    /// and ones saved before this was recorded have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created: Option<String>,
+    /// Letter or digit that opens it after the Alt+G leader; unique across
+    /// the library
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mnemonic: Option<char>,
+    /// Latest Wayback Machine snapshot saved from the app
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wayback_url: Option<String>,
+    /// Opens in its own browsing container: separate cookies and storage,
+    /// kept under containers/<name> in the config dir
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    container: Option<String>,
+    /// Free text kept with the bookmark, such as a clipped quote
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    /// Reload the page this often, in seconds, while it is showing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    refresh_secs: Option<u64>,
+    /// Set once the name is edited by hand, so refreshing titles leaves it be
+    #[serde(default, skip_serializing_if = "is_false")]
+    name_is_custom: bool,
+    /// Stylesheet added to the page when this bookmark loads. Like the
+    /// script below it is kept only in the local file: never pushed, never
+    /// taken from a pull or an import
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inject_css: Option<String>,
+    /// Script run in the page, with the page's own access, when this
+    /// bookmark loads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inject_js: Option<String>,
+    /// Free-form labels, kept trimmed and without case-insensitive repeats
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 struct Folder {
     name: String,
-    #[serde(default = "default_true")]
+    /// Local UI state. Only a collapsed folder writes the flag, and the
+    /// synced copy marks every folder expanded, so it never reaches the remote
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
     expanded: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    /// Kept only in the local file; never pushed and preserved across pulls
+    #[serde(default)]
+    exclude_from_sync: bool,
+    /// Shown collapsed, and left out of the other views, until the PIN is
+    /// entered. A sidebar gate only: bookmarks.json itself is not encrypted
+    #[serde(default, skip_serializing_if = "is_false")]
+    locked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pin: Option<PinHash>,
     bookmarks: Vec<Bookmark>,
 }
 
+/// Salted, iterated SHA-256 of a folder PIN; the PIN itself is never stored
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct PinHash {
+    hash: String,
+    salt: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 struct BookmarkStore {
     folders: Vec<Folder>,
@@ -78,33 +660,141 @@ fn default_store() -> BookmarkStore {
             Folder {
                 name: "Documentation".to_string(),
                 expanded: true,
+                icon: None,
+                exclude_from_sync: false,
+                locked: false,
+                pin: None,
                 bookmarks: vec![
                     Bookmark {
                         name: "The Rust Programming Language".to_string(),
                         url: "https://doc.rust-lang.org/book/".to_string(),
+                        ..Default::default()
                     },
                     Bookmark {
                         name: "Arch Wiki".to_string(),
                         url: "https://wiki.archlinux.org/".to_string(),
+                        ..Default::default()
                     },
                 ],
             },
             Folder {
                 name: "News".to_string(),
                 expanded: true,
+                icon: None,
+                exclude_from_sync: false,
+                locked: false,
+                pin: None,
                 bookmarks: vec![Bookmark {
                     name: "Hacker News".to_string(),
                     url: "https://news.ycombinator.com/".to_string(),
+                    ..Default::default()
                 }],
             },
         ],
     }
 }
 
+/// Normalizes a folder icon coming from the sidebar. An icon must be a single
+/// emoji or character; anything empty, containing whitespace, or holding more
+/// than one is rejected and treated as "no icon".
+fn sanitize_icon(icon: &str) -> Option<String> {
+    let icon = icon.trim();
+    if icon.chars().count() > MAX_ICON_CHARS
+        || icon.chars().any(|c| c.is_whitespace() || c.is_control())
+        || !is_one_grapheme(icon)
+    {
+        return None;
+    }
+    Some(icon.to_string())
+}
+
+/// Whether `text` is one user-perceived character, as far as emoji go: a base
+/// character with variation selectors, skin tones, tags or a keycap, ZWJ
+/// sequences of those, or a regional-indicator flag pair.
+fn is_one_grapheme(text: &str) -> bool {
+    let is_extender = |c: char| {
+        matches!(c, '\u{FE0E}' | '\u{FE0F}' | '\u{20E3}')
+            || ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)
+            || ('\u{E0020}'..='\u{E007F}').contains(&c)
+    };
+    let is_regional = |c: char| ('\u{1F1E6}'..='\u{1F1FF}').contains(&c);
+    let mut chars = text.chars().peekable();
+    loop {
+        let Some(base) = chars.next() else {
+            return false;
+        };
+        if is_extender(base) || base == '\u{200D}' {
+            return false;
+        }
+        if is_regional(base) && chars.peek().copied().is_some_and(is_regional) {
+            chars.next();
+        }
+        while chars.peek().copied().is_some_and(is_extender) {
+            chars.next();
+        }
+        match chars.next() {
+            None => return true,
+            Some('\u{200D}') => continue,
+            Some(_) => return false,
+        }
+    }
+}
+
+/// Overrides the config directory; also set by the `--config-dir` flag.
+const CONFIG_DIR_ENV: &str = "BOOKMARKS_CONFIG_DIR";
+
+/// Comma-separated experimental features to switch on, e.g. `split_view,tabs`.
+const EXPERIMENTS_ENV: &str = "BOOKMARKS_EXPERIMENTS";
+
+static EXPERIMENTS: OnceLock<BTreeSet<String>> = OnceLock::new();
+
+/// Experiment names from the environment and settings, trimmed and lowercased.
+fn parse_experiments(env: Option<&str>, configured: &[String]) -> BTreeSet<String> {
+    env.unwrap_or_default()
+        .split(',')
+        .chain(configured.iter().map(String::as_str))
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Fixes the experiment set for this run; later calls are ignored.
+fn init_experiments(settings: &Settings) {
+    let env = std::env::var(EXPERIMENTS_ENV).ok();
+    let _ = EXPERIMENTS.set(parse_experiments(env.as_deref(), &settings.experiments));
+}
+
+fn enabled_experiments() -> &'static BTreeSet<String> {
+    EXPERIMENTS.get_or_init(BTreeSet::new)
+}
+
+/// Gate for experimental code paths, checked against this run's set.
+fn experiment_enabled(name: &str) -> bool {
+    experiment_in(enabled_experiments(), name)
+}
+
+fn experiment_in(enabled: &BTreeSet<String>, name: &str) -> bool {
+    enabled.contains(&name.trim().to_lowercase())
+}
+
+/// Omnibar text matching a bookmark's name opens that bookmark
+const OMNIBAR_BOOKMARKS_EXPERIMENT: &str = "omnibar_bookmarks";
+
+/// Root for every file the app keeps: bookmarks, settings and backups.
 fn config_dir() -> PathBuf {
-    dirs::config_dir()
-        .unwrap_or_else(|| PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(".config"))
-        .join("bookmarks-browser")
+    let platform_dir = dirs::config_dir().unwrap_or_else(|| {
+        PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(".config")
+    });
+    config_dir_from(std::env::var_os(CONFIG_DIR_ENV).as_deref(), platform_dir)
+}
+
+/// `config_dir` given the value of `CONFIG_DIR_ENV` and the platform's
+/// config directory; an empty override counts as unset.
+fn config_dir_from(env_value: Option<&std::ffi::OsStr>, platform_dir: PathBuf) -> PathBuf {
+    match env_value.filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => platform_dir.join("bookmarks-browser"),
+    }
 }
 
 fn config_path() -> PathBuf {
@@ -115,87 +805,3207 @@ fn settings_path() -> PathBuf {
     config_dir().join("settings.json")
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
-struct Settings {
-    #[serde(default)]
-    sidebar_collapsed: bool,
-    #[serde(default)]
-    github_token: String,
-    #[serde(default)]
-    github_repo: String,
-    /// Legacy field — read from old settings files, never written back
-    #[serde(default, skip_serializing)]
-    #[allow(dead_code)]
-    github_gist_id: String,
+fn backups_dir() -> PathBuf {
+    config_dir().join("backups")
 }
 
-impl Settings {
-    fn load() -> Settings {
-        Self::load_from(&settings_path())
+fn thumbnails_dir() -> PathBuf {
+    config_dir().join("thumbnails")
+}
+
+/// Thumbnails are served to the sidebar through this custom protocol, whose
+/// URLs look different on each platform.
+const THUMBNAIL_PROTOCOL: &str = "bbthumb";
+#[cfg(any(target_os = "windows", target_os = "android"))]
+const THUMBNAIL_BASE_URL: &str = "http://bbthumb.localhost/";
+#[cfg(not(any(target_os = "windows", target_os = "android")))]
+const THUMBNAIL_BASE_URL: &str = "bbthumb://localhost/";
+const MAX_THUMBNAIL_BYTES: usize = 1024 * 1024;
+
+/// Decodes an image data URL from the sidebar into its bytes and a file
+/// extension. Only common web image types are accepted.
+fn decode_image_data_url(data_url: &str) -> Option<(Vec<u8>, &'static str)> {
+    let (header, data) = data_url.strip_prefix("data:")?.split_once(',')?;
+    let ext = match header.strip_suffix(";base64")? {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        _ => return None,
+    };
+    let bytes = BASE64.decode(data.trim()).ok()?;
+    (!bytes.is_empty() && bytes.len() <= MAX_THUMBNAIL_BYTES).then_some((bytes, ext))
+}
+
+/// Whether `name` is a thumbnail file name this app generated (a content
+/// hash plus extension), so the protocol handler never serves other paths.
+fn is_thumbnail_name(name: &str) -> bool {
+    match name.split_once('.') {
+        Some((stem, ext)) => {
+            stem.len() == 16
+                && stem.chars().all(|c| c.is_ascii_hexdigit())
+                && matches!(ext, "png" | "jpg" | "webp" | "gif")
+        }
+        None => false,
     }
+}
 
-    fn load_from(path: &Path) -> Settings {
-        fs::read_to_string(path)
-            .ok()
-            .and_then(|data| serde_json::from_str(&data).ok())
-            .unwrap_or_default()
+fn serve_thumbnail(path: &str) -> wry::http::Response<Cow<'static, [u8]>> {
+    let name = path.trim_start_matches('/');
+    let bytes = is_thumbnail_name(name)
+        .then(|| fs::read(thumbnails_dir().join(name)).ok())
+        .flatten();
+    let response = match bytes {
+        Some(bytes) => {
+            let mime = match name.rsplit('.').next() {
+                Some("png") => "image/png",
+                Some("jpg") => "image/jpeg",
+                Some("webp") => "image/webp",
+                _ => "image/gif",
+            };
+            wry::http::Response::builder()
+                .header("Content-Type", mime)
+                .body(Cow::Owned(bytes))
+        }
+        None => wry::http::Response::builder()
+            .status(404)
+            .body(Cow::Borrowed(&[][..])),
+    };
+    response.unwrap_or_default()
+}
+
+/// Side file for link-check results. Link health depends on this machine and
+/// when it looked, so it is kept out of the synced store.
+fn health_path() -> PathBuf {
+    config_dir().join("link_health.json")
+}
+
+fn zoom_path() -> PathBuf {
+    config_dir().join("zoom.json")
+}
+
+/// Content zoom per host; hosts at 100% have no entry
+type ZoomMap = HashMap<String, f64>;
+
+const ZOOM_STEP: f64 = 0.1;
+const MIN_ZOOM: f64 = 0.3;
+const MAX_ZOOM: f64 = 3.0;
+
+fn load_zoom(path: &Path) -> ZoomMap {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_zoom(path: &Path, zoom: &ZoomMap) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
     }
+    fs::write(path, serde_json::to_string_pretty(zoom)?)?;
+    Ok(())
+}
 
-    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        self.save_to(&settings_path())
+/// The zoom after one step in `direction` (0 resets), clamped and rounded
+/// to whole percents so repeated steps don't drift.
+fn step_zoom(current: f64, direction: i32) -> f64 {
+    if direction == 0 {
+        return 1.0;
     }
+    let next = current + ZOOM_STEP * f64::from(direction.signum());
+    (next.clamp(MIN_ZOOM, MAX_ZOOM) * 100.0).round() / 100.0
+}
 
-    fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
+/// Lowercased host of a URL, without credentials, port or trailing dot.
+/// IPv6 literals keep their brackets. `None` when there is no host.
+fn host_of(url: &str) -> Option<String> {
+    let (_, rest) = url.trim().split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host_port = authority.rsplit('@').next().unwrap_or("");
+    let host = if host_port.starts_with('[') {
+        host_port.split_inclusive(']').next().unwrap_or("")
+    } else {
+        host_port.split(':').next().unwrap_or("")
+    };
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    (!host.is_empty()).then_some(host)
+}
+
+/// Domains from a hosts-format blocklist: "0.0.0.0 ads.example.com" lines,
+/// or bare domains one per line. `#` starts a comment.
+fn parse_blocklist(text: &str) -> HashSet<String> {
+    let mut domains = HashSet::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace().peekable();
+        if tokens
+            .peek()
+            .is_some_and(|t| t.parse::<std::net::IpAddr>().is_ok())
+        {
+            tokens.next();
+        }
+        for token in tokens {
+            let domain = token.trim_end_matches('.').to_ascii_lowercase();
+            // Hosts files map these to themselves; blocking them would break things
+            if !domain.contains('.')
+                || domain.contains('/')
+                || matches!(domain.as_str(), "localhost.localdomain" | "local.host")
+            {
+                continue;
+            }
+            domains.insert(domain);
         }
-        let json = serde_json::to_string_pretty(self)?;
-        fs::write(path, json)?;
-        Ok(())
     }
+    domains
 }
 
-impl BookmarkStore {
-    fn load() -> BookmarkStore {
-        Self::load_from(&config_path())
+/// The blocklist, or an empty one when the file is missing or unreadable.
+fn load_blocklist(path: &Path) -> HashSet<String> {
+    std::fs::read_to_string(path)
+        .map(|text| parse_blocklist(&text))
+        .unwrap_or_default()
+}
+
+/// Whether `host` or any domain it belongs to is on the list, so listing
+/// "tracker.com" also blocks "cdn.tracker.com".
+fn is_blocked_host(blocklist: &HashSet<String>, host: &str) -> bool {
+    let mut rest = host;
+    loop {
+        if blocklist.contains(rest) {
+            return true;
+        }
+        match rest.split_once('.') {
+            Some((_, parent)) => rest = parent,
+            None => return false,
+        }
     }
+}
 
-    fn load_from(path: &Path) -> BookmarkStore {
-        fs::read_to_string(path)
-            .ok()
-            .and_then(|data| serde_json::from_str(&data).ok())
-            .unwrap_or_else(default_store)
+/// Content-pane script that refuses requests and elements pointing at a
+/// blocked host; the page can still run, minus those resources.
+fn blocker_script(blocklist: &HashSet<String>) -> String {
+    if blocklist.is_empty() {
+        return String::new();
     }
+    let mut domains: Vec<&String> = blocklist.iter().collect();
+    domains.sort();
+    let json = serde_json::to_string(&domains).unwrap_or_else(|_| "[]".into());
+    BLOCKER_SCRIPT.replace("__BLOCKLIST__", &json)
+}
 
-    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        self.save_to(&config_path())
+/// Outcome of the last link check for one URL.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+struct LinkHealth {
+    /// HTTP status, or `None` when the server could not be reached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_status: Option<u16>,
+    /// When the check ran, as "YYYY-MM-DD HH:MM UTC"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_checked: Option<String>,
+}
+
+type HealthMap = HashMap<String, LinkHealth>;
+
+fn load_health(path: &Path) -> HealthMap {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_health(path: &Path, health: &HealthMap) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
     }
+    fs::write(path, serde_json::to_string_pretty(health)?)?;
+    Ok(())
+}
 
-    fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        let json = serde_json::to_string_pretty(self)?;
-        fs::write(path, json)?;
-        Ok(())
+/// Indicator class for a bookmark: "ok" (2xx), "redirect" (3xx), "broken"
+/// (4xx/5xx or unreachable) or "unchecked".
+fn health_class(health: Option<&LinkHealth>) -> &'static str {
+    match health {
+        Some(LinkHealth {
+            last_checked: Some(_),
+            last_status,
+        }) => match last_status {
+            Some(200..=299) => "ok",
+            Some(300..=399) => "redirect",
+            _ => "broken",
+        },
+        _ => "unchecked",
     }
 }
 
-fn sidebar_html(store: &BookmarkStore, settings: &Settings) -> String {
-    let folders_json = serde_json::to_string(&store.folders).unwrap_or_else(|_| "[]".to_string());
-    let has_token = !settings.github_token.is_empty();
-    let repo = settings.github_repo.replace('\'', "\\'");
-    let collapsed_class = if settings.sidebar_collapsed {
-        " collapsed"
+/// What the sidebar needs per URL: the indicator class and a tooltip.
+fn health_json(health: &HealthMap) -> String {
+    let view: HashMap<&str, serde_json::Value> = health
+        .iter()
+        .map(|(url, h)| {
+            let label = match (h.last_status, &h.last_checked) {
+                (Some(status), Some(at)) => format!("HTTP {status}, checked {at}"),
+                (None, Some(at)) => format!("Unreachable, checked {at}"),
+                _ => "Not checked".to_string(),
+            };
+            (
+                url.as_str(),
+                serde_json::json!({ "class": health_class(Some(h)), "label": label }),
+            )
+        })
+        .collect();
+    script_json(&view, "{}")
+}
+
+/// JSON for a value written into the sidebar's `<script>` element. Every `<`
+/// becomes `\u003c`, which reads back the same, so stored text such as
+/// `</script>` can't end the element early.
+fn script_json<T: Serialize + ?Sized>(value: &T, fallback: &str) -> String {
+    serde_json::to_string(value).map_or_else(
+        |_| fallback.to_string(),
+        |json| json.replace('<', "\\u003c"),
+    )
+}
+
+/// Requests a URL without following redirects and returns the status, or
+/// `None` if there was no HTTP answer at all. Servers that refuse `HEAD` get
+/// a `GET` instead.
+fn check_link(agent: &ureq::Agent, url: &str) -> Option<u16> {
+    let status = |result: Result<ureq::http::Response<ureq::Body>, ureq::Error>| match result {
+        Ok(response) => Some(response.status().as_u16()),
+        Err(ureq::Error::StatusCode(code)) => Some(code),
+        Err(_) => None,
+    };
+    match status(agent.head(url).call()) {
+        Some(405 | 501) => status(agent.get(url).call()),
+        other => other,
+    }
+}
+
+/// Most of a page read while looking for its `<title>`
+const TITLE_FETCH_LIMIT: u64 = 256 * 1024;
+
+/// The text of the first `<title>` element with entities decoded and
+/// whitespace collapsed, or `None` if there isn't a non-empty one.
+fn extract_title(html: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets, so they index `html` too
+    let lower = html.to_ascii_lowercase();
+    let mut from = 0;
+    let open = loop {
+        let at = from + lower[from..].find("<title")?;
+        match lower[at + 6..].chars().next() {
+            Some('>' | ' ' | '\t' | '\n' | '\r') => break at,
+            _ => from = at + 6,
+        }
+    };
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = decode_entities(&html[start..end])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+/// Fetches a page and returns its title; `None` on any failure
+fn fetch_title(agent: &ureq::Agent, url: &str) -> Option<String> {
+    use std::io::Read;
+    let mut response = agent.get(url).call().ok()?;
+    let mut html = Vec::new();
+    response
+        .body_mut()
+        .as_reader()
+        .take(TITLE_FETCH_LIMIT)
+        .read_to_end(&mut html)
+        .ok()?;
+    extract_title(&String::from_utf8_lossy(&html))
+}
+
+/// The Internet Archive endpoint that saves the page at the URL appended to it
+const WAYBACK_SAVE_URL: &str = "https://web.archive.org/save/";
+/// Saving can take the Archive well over the sync timeout
+const WAYBACK_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The snapshot a save response points at. The Archive answers with a
+/// redirect whose `Location` (or `Content-Location`) may be a bare path.
+fn wayback_snapshot_url(location: &str) -> Option<String> {
+    let location = location.trim();
+    if location.starts_with("https://") || location.starts_with("http://") {
+        Some(location.to_string())
+    } else if location.starts_with("/web/") {
+        Some(format!("https://web.archive.org{location}"))
     } else {
-        ""
+        None
+    }
+}
+
+/// Submits `url` to the Wayback Machine and returns the snapshot's address.
+fn archive_to_wayback(url: &str) -> Result<String, String> {
+    let lower = url.to_ascii_lowercase();
+    if !lower.starts_with("http://") && !lower.starts_with("https://") {
+        return Err("Only web pages can be archived".to_string());
+    }
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(WAYBACK_TIMEOUT))
+        .max_redirects(0)
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let response = agent
+        .get(&format!("{WAYBACK_SAVE_URL}{url}"))
+        .header("User-Agent", USER_AGENT)
+        .call()
+        .map_err(|e| match e {
+            ureq::Error::Timeout(_) => {
+                "The Wayback Machine didn't answer in time — try again later".to_string()
+            }
+            ureq::Error::HostNotFound | ureq::Error::ConnectionFailed => {
+                "Could not reach the Wayback Machine — check your connection".to_string()
+            }
+            _ => format!("{e}"),
+        })?;
+    let status = response.status().as_u16();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(wayback_snapshot_url)
     };
-    format!(
-        r#"<!DOCTYPE html>
-<html>
+    match status {
+        200..=399 => Ok(header("location")
+            .or_else(|| header("content-location"))
+            // Saved, but not said where: the newest snapshot is the one
+            .unwrap_or_else(|| format!("https://web.archive.org/web/{url}"))),
+        429 => {
+            Err("The Wayback Machine is limiting saves — try again in a few minutes".to_string())
+        }
+        500..=599 => Err(format!(
+            "The Wayback Machine is unavailable (HTTP {status}) — try again later"
+        )),
+        _ => Err(format!(
+            "The Wayback Machine refused to save it (HTTP {status})"
+        )),
+    }
+}
+
+/// Records a snapshot on every bookmark of `url`. Returns how many changed.
+fn set_wayback_url(store: &mut BookmarkStore, url: &str, snapshot: &str) -> usize {
+    let mut changed = 0;
+    for bm in store.folders.iter_mut().flat_map(|f| &mut f.bookmarks) {
+        if bm.url == url && bm.wayback_url.as_deref() != Some(snapshot) {
+            bm.wayback_url = Some(snapshot.to_string());
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// A rename proposed by "Refresh titles", shown for review before applying
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct TitleChange {
+    folder_index: usize,
+    bookmark_index: usize,
+    url: String,
+    old: String,
+    new: String,
+}
+
+/// Renames for bookmarks whose page title (keyed by URL in `titles`) no
+/// longer matches their name. Names edited by hand are never proposed.
+fn title_changes(store: &BookmarkStore, titles: &HashMap<String, String>) -> Vec<TitleChange> {
+    let mut changes = Vec::new();
+    for (fi, folder) in store.folders.iter().enumerate() {
+        for (bi, bm) in folder.bookmarks.iter().enumerate() {
+            if bm.name_is_custom {
+                continue;
+            }
+            if let Some(title) = titles.get(&bm.url) {
+                if *title != bm.name {
+                    changes.push(TitleChange {
+                        folder_index: fi,
+                        bookmark_index: bi,
+                        url: bm.url.clone(),
+                        old: bm.name.clone(),
+                        new: title.clone(),
+                    });
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// Applies the renames the user accepted. A bookmark that moved, changed or
+/// was renamed by hand since the preview is skipped. Returns how many changed.
+fn apply_title_changes(store: &mut BookmarkStore, changes: &[TitleChange], now: &str) -> usize {
+    let mut applied = 0;
+    for change in changes {
+        let Some(bm) = store
+            .folders
+            .get_mut(change.folder_index)
+            .and_then(|f| f.bookmarks.get_mut(change.bookmark_index))
+        else {
+            continue;
+        };
+        let title = change.new.trim();
+        if bm.name_is_custom || bm.url != change.url || bm.name != change.old || title.is_empty() {
+            continue;
+        }
+        bm.name = title.to_string();
+        bm.modified = Some(now.to_string());
+        applied += 1;
+    }
+    applied
+}
+
+fn visits_path() -> PathBuf {
+    config_dir().join("visits.json")
+}
+
+/// Free-form notes from the scratchpad; plain text, never synced
+fn scratchpad_path() -> PathBuf {
+    config_dir().join("scratchpad.txt")
+}
+
+fn load_scratchpad(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_default()
+}
+
+fn save_scratchpad(path: &Path, text: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, text)
+}
+
+/// UTC dates ("YYYY-MM-DD") each bookmarked URL was opened on, oldest first
+type VisitMap = HashMap<String, Vec<String>>;
+
+/// Dates kept per URL; enough for any streak worth showing
+const VISIT_DAYS_KEPT: usize = 400;
+
+fn load_visits(path: &Path) -> VisitMap {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_visits(path: &Path, visits: &VisitMap) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(visits)?)?;
+    Ok(())
+}
+
+/// Notes a visit to `url` on `date`; repeat visits on one day count once.
+/// Returns whether anything changed.
+fn record_visit(visits: &mut VisitMap, url: &str, date: &str) -> bool {
+    let dates = visits.entry(url.to_string()).or_default();
+    if dates.last().map(String::as_str) == Some(date) {
+        return false;
+    }
+    dates.push(date.to_string());
+    if dates.len() > VISIT_DAYS_KEPT {
+        dates.drain(..dates.len() - VISIT_DAYS_KEPT);
+    }
+    true
+}
+
+/// Drops visits from before `cutoff` (a `day_number`; visits on that day
+/// stay), along with unreadable dates and URLs left with none. Returns
+/// whether anything was removed.
+fn prune_history(visits: &mut VisitMap, cutoff: i64) -> bool {
+    let mut changed = false;
+    for dates in visits.values_mut() {
+        let len = dates.len();
+        dates.retain(|date| day_number(date).is_some_and(|day| day >= cutoff));
+        changed |= dates.len() != len;
+    }
+    let urls = visits.len();
+    visits.retain(|_, dates| !dates.is_empty());
+    changed || visits.len() != urls
+}
+
+/// Days since 1970-01-01 for a "YYYY-MM-DD" date (inverse of the civil
+/// conversion in `format_utc`).
+fn day_number(date: &str) -> Option<i64> {
+    let mut parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146_097 + doe - 719_468)
+}
+
+/// Consecutive days up to `today` (a `day_number`) with at least one visit.
+/// A streak that reached yesterday still counts until today is over.
+fn current_streak(dates: &[String], today: i64) -> u32 {
+    let days: HashSet<i64> = dates.iter().filter_map(|d| day_number(d)).collect();
+    let mut day = if days.contains(&today) {
+        today
+    } else {
+        today - 1
+    };
+    let mut streak = 0;
+    while days.contains(&day) {
+        streak += 1;
+        day -= 1;
+    }
+    streak
+}
+
+/// Sidebar view of the visit log: URL -> { today, streak }.
+fn visits_json(visits: &VisitMap, today: i64) -> String {
+    let view: HashMap<&str, serde_json::Value> = visits
+        .iter()
+        .map(|(url, dates)| {
+            let opened_today = dates.last().and_then(|d| day_number(d)) == Some(today);
+            (
+                url.as_str(),
+                serde_json::json!({ "today": opened_today, "streak": current_streak(dates, today) }),
+            )
+        })
+        .collect();
+    serde_json::to_string(&view).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// The current time as `format_utc` writes it.
+fn utc_now() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format_utc(secs)
+}
+
+/// Today's UTC date as a `day_number` and as "YYYY-MM-DD".
+fn utc_today() -> (i64, String) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    ((secs / 86_400) as i64, format_utc(secs)[..10].to_string())
+}
+
+/// Formats Unix seconds as "YYYY-MM-DD HH:MM UTC".
+fn format_utc(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let (hour, minute) = ((secs % 86_400) / 3600, (secs % 3600) / 60);
+    // Howard Hinnant's days-to-civil algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02} UTC")
+}
+
+/// Backup file names in `dir`, oldest first. The zero-padded timestamp in
+/// the name makes lexical order chronological.
+fn list_backups(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter(|name| is_backup_name(name))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+fn is_backup_name(name: &str) -> bool {
+    name.strip_prefix("bookmarks-")
+        .and_then(|rest| rest.strip_suffix(".json"))
+        .is_some_and(|stamp| !stamp.is_empty() && stamp.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Deletes the oldest backups in `dir` so that at most `keep` remain.
+fn rotate_backups(dir: &Path, keep: usize) -> std::io::Result<()> {
+    let names = list_backups(dir);
+    let excess = names.len().saturating_sub(keep);
+    for name in &names[..excess] {
+        fs::remove_file(dir.join(name))?;
+    }
+    Ok(())
+}
+
+/// Writes `json` as a new timestamped backup unless it matches the newest
+/// one, then prunes down to `keep`.
+fn write_backup(dir: &Path, json: &str, keep: usize) -> Result<(), Box<dyn std::error::Error>> {
+    if keep == 0 {
+        return Ok(());
+    }
+    add_backup(dir, json)?;
+    rotate_backups(dir, keep)?;
+    Ok(())
+}
+
+/// Writes `json` as a new timestamped backup unless it matches the newest
+/// one, leaving the others alone. A second backup in the same second takes
+/// the next free stamp instead of replacing the first.
+fn add_backup(dir: &Path, json: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(newest) = list_backups(dir).last() {
+        if fs::read_to_string(dir.join(newest)).is_ok_and(|prev| prev == json) {
+            return Ok(());
+        }
+    }
+    fs::create_dir_all(dir)?;
+    let mut stamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    while dir.join(format!("bookmarks-{stamp:012}.json")).exists() {
+        stamp += 1;
+    }
+    fs::write(dir.join(format!("bookmarks-{stamp:012}.json")), json)?;
+    Ok(())
+}
+
+/// Saves `store` beside the rolling backups under a name rotation never
+/// touches, so it survives however many edits follow. Returns the path.
+fn write_reset_backup(
+    dir: &Path,
+    store: &BookmarkStore,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let json = serde_json::to_string_pretty(store)?;
+    fs::create_dir_all(dir)?;
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let path = dir.join(format!("before-reset-{stamp:012}.json"));
+    fs::write(&path, json)?;
+    Ok(path)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Settings {
+    #[serde(default)]
+    sidebar_collapsed: bool,
+    #[serde(default)]
+    github_token: String,
+    #[serde(default)]
+    github_repo: String,
+    /// "github" (default) or "gitea" for a self-hosted Gitea/Forgejo instance
+    #[serde(default)]
+    sync_provider: String,
+    /// Gitea/Forgejo address, e.g. `https://git.example.com`
+    #[serde(default)]
+    gitea_base_url: String,
+    /// Sidebar row density: "comfortable" (default) or "compact"
+    #[serde(default)]
+    density: String,
+    /// Sidebar scrollbars: "always" visible (default) or "overlay", shown
+    /// only while the pointer is over the list
+    #[serde(default)]
+    scrollbars: String,
+    /// Text size multiplier for the sidebar; see `ui_font_scale()`
+    #[serde(default = "default_font_scale")]
+    ui_font_scale: f64,
+    /// How many rolling backups of bookmarks.json to keep; 0 disables them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    backup_keep: Option<usize>,
+    /// Overall time limit for each GitHub request, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sync_timeout_secs: Option<u64>,
+    /// Window title bar and borders; off gives a borderless window
+    #[serde(default = "default_true")]
+    decorations: bool,
+    #[serde(default)]
+    always_on_top: bool,
+    /// Sidebar hidden entirely (no expand strip); independent of `sidebar_collapsed`
+    #[serde(default)]
+    focus_mode: bool,
+    /// "off", "push" (default: push after each edit) or "push_pull" (also pull on startup)
+    #[serde(default)]
+    auto_sync_mode: String,
+    /// Show each bookmark's URL as a dimmed second line in the sidebar
+    #[serde(default)]
+    show_urls: bool,
+    /// Count of bookmarks not opened within the history window, in the bottom bar
+    #[serde(default)]
+    show_unread_badge: bool,
+    /// Ask before a bookmark replaces a page with edited form fields
+    #[serde(default)]
+    confirm_form_leave: bool,
+    /// Pull when the window regains focus, at most every `FOCUS_PULL_INTERVAL`
+    #[serde(default)]
+    sync_on_focus: bool,
+    /// Run `javascript:` bookmarks on the current page instead of refusing
+    #[serde(default)]
+    run_bookmarklets: bool,
+    /// Keep the content pane from loading anything on the hosts blocklist;
+    /// read once at startup
+    #[serde(default)]
+    block_trackers: bool,
+    /// Sum up what a pull changed in the status line
+    #[serde(default = "default_true")]
+    show_pull_changes: bool,
+    /// Expand a collapsed folder when one of its bookmarks is being shown
+    #[serde(default = "default_true")]
+    auto_expand_active: bool,
+    /// Push `bookmarks.json` indented (readable diffs) rather than minified
+    #[serde(default = "default_true")]
+    pretty_json: bool,
+    /// How many of the newest bookmarks to list above the tree; 0 hides it
+    #[serde(default = "default_recently_added")]
+    recently_added: usize,
+    /// Sidebar layout: "tree" (default), "flat" or "gallery"
+    #[serde(default)]
+    view_mode: String,
+    /// "auto" (default: follow the system preference), "dark" or "light"
+    #[serde(default)]
+    theme: String,
+    /// Where new folders go: "bottom" (default) or "top"
+    #[serde(default)]
+    new_folder_position: String,
+    /// Where new bookmarks go within their folder: "bottom" (default) or "top"
+    #[serde(default)]
+    new_bookmark_position: String,
+    /// What an import does with a URL already saved: "skip" (default),
+    /// "keep_both" or "update_name"
+    #[serde(default)]
+    duplicate_strategy: String,
+    /// The window's close button: "quit" (default) or "minimize", which
+    /// keeps the app and its background syncing running
+    #[serde(default)]
+    close_action: String,
+    /// A new folder whose name is already taken (ignoring case): "suffix"
+    /// (default) names it "News (2)", "reject" refuses to create it
+    #[serde(default)]
+    on_duplicate_folder: String,
+    /// Web search used by the omnibar for free text; `%s` marks the query
+    #[serde(default)]
+    search_engine_url: String,
+    /// Directory that `@path` bookmarks are relative to on this machine
+    #[serde(default)]
+    base_path: String,
+    /// Hosts-format blocklist; empty means blocklist.txt in the config dir
+    #[serde(default)]
+    blocklist_path: String,
+    /// Loaded at startup and by Home in place of the welcome page; empty
+    /// keeps the welcome page
+    #[serde(default)]
+    home_url: String,
+    /// Page shown in the floating reference frame; reopened at startup
+    #[serde(default)]
+    pip_url: String,
+    /// Shown in the window title to tell instances apart; defaults to the
+    /// config directory's name when that is overridden
+    #[serde(default)]
+    profile_name: String,
+    /// Accent colour (`#rrggbb`) for this profile; derived from the name if unset
+    #[serde(default)]
+    profile_color: String,
+    /// Digest of the synced part of the store as of the last successful push
+    /// or pull, used to tell whether there are local edits not yet pushed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_synced_digest: Option<u64>,
+    /// Bookmarks on GitHub as of the last successful push or pull
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_synced_count: Option<usize>,
+    /// A push that would remove more than this percentage of the bookmarks
+    /// on GitHub needs confirmation; 100 turns the check off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    push_guard_percent: Option<u32>,
+    /// Extra repositories every push also goes to; pulls use only the
+    /// primary target above
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sync_mirrors: Vec<SyncTarget>,
+    /// Days of bookmark visits kept in visits.json; 0 turns visit tracking off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    history_retention_days: Option<u32>,
+    /// Experimental features to switch on, on top of `BOOKMARKS_EXPERIMENTS`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    experiments: Vec<String>,
+    /// Legacy field — read from old settings files, never written back
+    #[serde(default, skip_serializing)]
+    #[allow(dead_code)]
+    github_gist_id: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            sidebar_collapsed: false,
+            github_token: String::new(),
+            github_repo: String::new(),
+            sync_provider: String::new(),
+            gitea_base_url: String::new(),
+            density: String::new(),
+            scrollbars: String::new(),
+            ui_font_scale: 1.0,
+            backup_keep: None,
+            sync_timeout_secs: None,
+            decorations: true,
+            always_on_top: false,
+            focus_mode: false,
+            auto_sync_mode: String::new(),
+            show_urls: false,
+            show_unread_badge: false,
+            confirm_form_leave: false,
+            sync_on_focus: false,
+            run_bookmarklets: false,
+            block_trackers: false,
+            show_pull_changes: true,
+            auto_expand_active: true,
+            pretty_json: true,
+            recently_added: DEFAULT_RECENTLY_ADDED,
+            view_mode: String::new(),
+            theme: String::new(),
+            new_folder_position: String::new(),
+            new_bookmark_position: String::new(),
+            duplicate_strategy: String::new(),
+            close_action: String::new(),
+            on_duplicate_folder: String::new(),
+            search_engine_url: String::new(),
+            base_path: String::new(),
+            blocklist_path: String::new(),
+            home_url: String::new(),
+            pip_url: String::new(),
+            profile_name: String::new(),
+            profile_color: String::new(),
+            last_synced_digest: None,
+            last_synced_count: None,
+            push_guard_percent: None,
+            sync_mirrors: Vec::new(),
+            history_retention_days: None,
+            experiments: Vec::new(),
+            github_gist_id: String::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// The density to render with; unknown or missing values fall back to comfortable.
+    fn density(&self) -> &'static str {
+        if self.density == "compact" {
+            "compact"
+        } else {
+            "comfortable"
+        }
+    }
+
+    /// Unknown or missing values keep the scrollbars always visible.
+    fn scrollbars(&self) -> &'static str {
+        if self.scrollbars == "overlay" {
+            "overlay"
+        } else {
+            "always"
+        }
+    }
+
+    /// The sidebar layout in effect; unknown or missing values mean "tree".
+    fn view_mode(&self) -> &'static str {
+        match self.view_mode.as_str() {
+            "flat" => "flat",
+            "gallery" => "gallery",
+            "changed" => "changed",
+            _ => "tree",
+        }
+    }
+
+    /// The theme setting in effect; unknown or missing values mean "auto".
+    fn theme(&self) -> &'static str {
+        match self.theme.as_str() {
+            "dark" => "dark",
+            "light" => "light",
+            "high_contrast" => "high_contrast",
+            _ => "auto",
+        }
+    }
+
+    /// Multiplier for every text size in the sidebar and its dialogs,
+    /// kept within `MIN_FONT_SCALE..=MAX_FONT_SCALE`.
+    fn ui_font_scale(&self) -> f64 {
+        if self.ui_font_scale.is_finite() {
+            self.ui_font_scale.clamp(MIN_FONT_SCALE, MAX_FONT_SCALE)
+        } else {
+            1.0
+        }
+    }
+
+    fn new_folder_position(&self) -> &'static str {
+        list_position(&self.new_folder_position)
+    }
+
+    fn new_bookmark_position(&self) -> &'static str {
+        list_position(&self.new_bookmark_position)
+    }
+
+    fn duplicate_strategy(&self) -> DuplicateStrategy {
+        DuplicateStrategy::parse(&self.duplicate_strategy).unwrap_or(DuplicateStrategy::Skip)
+    }
+
+    fn push_guard_percent(&self) -> u32 {
+        self.push_guard_percent
+            .unwrap_or(DEFAULT_PUSH_GUARD_PERCENT)
+            .min(100)
+    }
+
+    /// The profile this instance runs as, if it is anything but the default.
+    fn profile(&self) -> Option<String> {
+        let name = self.profile_name.trim();
+        if !name.is_empty() {
+            return Some(name.to_string());
+        }
+        std::env::var_os(CONFIG_DIR_ENV)
+            .filter(|dir| !dir.is_empty())
+            .and_then(|dir| {
+                PathBuf::from(dir)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+            })
+    }
+
+    /// The profile's accent: `profile_color` when it is a valid `#rrggbb`,
+    /// otherwise a palette colour picked by hashing the profile name.
+    fn profile_color(&self, profile: &str) -> &str {
+        let color = self.profile_color.trim();
+        let valid = color.len() == 7
+            && color.starts_with('#')
+            && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if valid {
+            color
+        } else {
+            PROFILE_COLORS[(fnv1a(profile.as_bytes()) % PROFILE_COLORS.len() as u64) as usize]
+        }
+    }
+
+    fn search_engine_url(&self) -> &str {
+        if self.search_engine_url.trim().is_empty() {
+            DEFAULT_SEARCH_ENGINE_URL
+        } else {
+            self.search_engine_url.trim()
+        }
+    }
+
+    fn home_url(&self) -> Option<&str> {
+        Some(self.home_url.trim()).filter(|url| !url.is_empty())
+    }
+
+    /// The auto-sync mode in effect; unknown or missing values mean "push".
+    fn auto_sync_mode(&self) -> &'static str {
+        match self.auto_sync_mode.as_str() {
+            "off" => "off",
+            "push_pull" => "push_pull",
+            _ => "push",
+        }
+    }
+
+    /// What the close button does; anything but "minimize" quits.
+    fn close_action(&self) -> &'static str {
+        match self.close_action.as_str() {
+            "minimize" => "minimize",
+            _ => "quit",
+        }
+    }
+
+    fn blocklist_path(&self) -> PathBuf {
+        if self.blocklist_path.is_empty() {
+            config_dir().join("blocklist.txt")
+        } else {
+            PathBuf::from(&self.blocklist_path)
+        }
+    }
+
+    fn on_duplicate_folder(&self) -> &'static str {
+        match self.on_duplicate_folder.as_str() {
+            "reject" => "reject",
+            _ => "suffix",
+        }
+    }
+
+    fn history_retention_days(&self) -> u32 {
+        self.history_retention_days
+            .unwrap_or(DEFAULT_HISTORY_RETENTION_DAYS)
+    }
+
+    fn backup_keep(&self) -> usize {
+        self.backup_keep.unwrap_or(DEFAULT_BACKUP_KEEP)
+    }
+
+    fn sync_api(&self) -> Result<SyncApi, String> {
+        SyncApi::new(&self.sync_provider, &self.gitea_base_url)
+    }
+
+    /// Mirrors with both a repository and a token; the rest are ignored.
+    fn sync_mirrors(&self) -> Vec<SyncTarget> {
+        self.sync_mirrors
+            .iter()
+            .filter(|m| !m.repo.trim().is_empty() && !m.token.is_empty())
+            .cloned()
+            .collect()
+    }
+
+    fn sync_timeout(&self) -> Duration {
+        let secs = self
+            .sync_timeout_secs
+            .unwrap_or(DEFAULT_SYNC_TIMEOUT_SECS)
+            .max(MIN_SYNC_TIMEOUT_SECS);
+        Duration::from_secs(secs)
+    }
+
+    fn load() -> Settings {
+        Self::load_from(&settings_path())
+    }
+
+    fn load_from(path: &Path) -> Settings {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.save_to(&settings_path())
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+impl BookmarkStore {
+    /// Reads the store at `path`. A missing or unparsable file is `None`;
+    /// callers decide whether the sample store is an acceptable substitute.
+    fn try_load_from(path: &Path) -> Option<BookmarkStore> {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+    }
+
+    /// Saves and then snapshots the result into the rolling backup set
+    /// (`keep == 0` skips the snapshot).
+    ///
+    /// `stamp` is the state of bookmarks.json as of our last load or save. If
+    /// the file no longer matches it, someone edited it behind our back; that
+    /// version is copied into the backups before being overwritten, whatever
+    /// `keep` says and without pruning the others, and `Ok(true)` is returned
+    /// so the caller can warn.
+    fn save_tracked(
+        &self,
+        stamp: &mut Option<FileStamp>,
+        keep: usize,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let path = config_path();
+        let external = file_stamp(&path) != *stamp;
+        if external {
+            if let Ok(theirs) = fs::read_to_string(&path) {
+                add_backup(&backups_dir(), &theirs)?;
+            }
+        }
+        self.save_to(&path)?;
+        *stamp = file_stamp(&path);
+        let json = serde_json::to_string_pretty(self)?;
+        write_backup(&backups_dir(), &json, keep)?;
+        Ok(external)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// Modification time and length of a file, used to notice edits made
+/// outside the app between our own reads and writes.
+type FileStamp = (SystemTime, u64);
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+const EXTERNAL_EDIT_SCRIPT: &str = "updateSyncStatus('bookmarks.json was edited outside the app \
+     — that version was kept in backups', 'error')";
+
+/// The `bookmarks.json` that gets pushed: the synced part of the store,
+/// indented or minified. Pulls read either.
+fn sync_json(store: &BookmarkStore, pretty: bool) -> String {
+    let synced = store_for_sync(store);
+    if pretty {
+        serde_json::to_string_pretty(&synced).unwrap_or_default()
+    } else {
+        serde_json::to_string(&synced).unwrap_or_default()
+    }
+}
+
+/// Returns the subset of the store that is allowed to leave this machine:
+/// no sync-excluded folders, no thumbnails, no injected CSS or scripts, no
+/// folder locks or PIN hashes (which could be brute-forced from the remote)
+/// and no collapsed state, so expanding or collapsing a folder never changes
+/// what gets pushed.
+fn store_for_sync(store: &BookmarkStore) -> BookmarkStore {
+    BookmarkStore {
+        folders: store
+            .folders
+            .iter()
+            .filter(|f| !f.exclude_from_sync)
+            .map(|f| Folder {
+                expanded: true,
+                locked: false,
+                pin: None,
+                bookmarks: f
+                    .bookmarks
+                    .iter()
+                    .map(|bm| Bookmark {
+                        thumbnail: None,
+                        inject_css: None,
+                        inject_js: None,
+                        ..bm.clone()
+                    })
+                    .collect(),
+                ..f.clone()
+            })
+            .collect(),
+    }
+}
+
+/// What changed between two versions of the store, counted for a summary.
+#[derive(Debug, Default, PartialEq)]
+struct StoreDiff {
+    folders_added: usize,
+    folders_removed: usize,
+    /// Same bookmarks under a new name
+    folders_renamed: usize,
+    bookmarks_added: usize,
+    bookmarks_removed: usize,
+    /// Same URL, new name
+    bookmarks_renamed: usize,
+    /// Same URL, now in a different folder
+    bookmarks_moved: usize,
+}
+
+impl StoreDiff {
+    /// e.g. "3 bookmarks added, 1 folder renamed", or "no changes"
+    fn summary(&self) -> String {
+        let parts: Vec<String> = [
+            (self.bookmarks_added, "bookmark", "added"),
+            (self.bookmarks_removed, "bookmark", "removed"),
+            (self.bookmarks_renamed, "bookmark", "renamed"),
+            (self.bookmarks_moved, "bookmark", "moved"),
+            (self.folders_added, "folder", "added"),
+            (self.folders_removed, "folder", "removed"),
+            (self.folders_renamed, "folder", "renamed"),
+        ]
+        .iter()
+        .filter(|(count, _, _)| *count > 0)
+        .map(|(count, noun, verb)| {
+            let plural = if *count == 1 { "" } else { "s" };
+            format!("{count} {noun}{plural} {verb}")
+        })
+        .collect();
+        if parts.is_empty() {
+            "no changes".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Compares two stores. Bookmarks are matched by `normalize_url` (the first
+/// of any repeats) and folders by name; a folder that vanished while one
+/// holding exactly the same URLs appeared counts as renamed.
+fn diff_stores(before: &BookmarkStore, after: &BookmarkStore) -> StoreDiff {
+    let index = |store: &BookmarkStore| {
+        let mut map: HashMap<String, (&str, &str)> = HashMap::new();
+        for folder in &store.folders {
+            for bm in &folder.bookmarks {
+                map.entry(normalize_url(&bm.url))
+                    .or_insert((folder.name.as_str(), bm.name.as_str()));
+            }
+        }
+        map
+    };
+    let urls_of = |folder: &Folder| -> BTreeSet<String> {
+        folder
+            .bookmarks
+            .iter()
+            .map(|bm| normalize_url(&bm.url))
+            .collect()
+    };
+    let (old, new) = (index(before), index(after));
+    let mut diff = StoreDiff::default();
+    for (url, (folder, name)) in &new {
+        match old.get(url) {
+            None => diff.bookmarks_added += 1,
+            Some((old_folder, old_name)) => {
+                if old_name != name {
+                    diff.bookmarks_renamed += 1;
+                }
+                if old_folder != folder {
+                    diff.bookmarks_moved += 1;
+                }
+            }
+        }
+    }
+    diff.bookmarks_removed = old.keys().filter(|url| !new.contains_key(*url)).count();
+
+    let names = |store: &BookmarkStore| -> HashSet<String> {
+        store.folders.iter().map(|f| f.name.clone()).collect()
+    };
+    let (old_names, new_names) = (names(before), names(after));
+    let mut gone: Vec<BTreeSet<String>> = before
+        .folders
+        .iter()
+        .filter(|f| !new_names.contains(&f.name))
+        .map(urls_of)
+        .collect();
+    for folder in after
+        .folders
+        .iter()
+        .filter(|f| !old_names.contains(&f.name))
+    {
+        let urls = urls_of(folder);
+        match gone.iter().position(|g| *g == urls) {
+            Some(i) => {
+                gone.swap_remove(i);
+                diff.folders_renamed += 1;
+            }
+            None => diff.folders_added += 1,
+        }
+    }
+    diff.folders_removed = gone.len();
+    // A renamed folder's bookmarks didn't really move
+    if diff.folders_renamed > 0 {
+        let renamed_from: HashSet<&str> = before
+            .folders
+            .iter()
+            .filter(|f| !new_names.contains(&f.name))
+            .map(|f| f.name.as_str())
+            .collect();
+        diff.bookmarks_moved -= new
+            .iter()
+            .filter(|(url, (folder, _))| {
+                !old_names.contains(*folder)
+                    && old
+                        .get(*url)
+                        .is_some_and(|(old_folder, _)| renamed_from.contains(old_folder))
+            })
+            .count()
+            .min(diff.bookmarks_moved);
+    }
+    diff
+}
+
+/// FNV-1a digest of the part of the store that gets synced. Stable across
+/// builds (unlike `DefaultHasher`), so it can be persisted in settings.
+fn store_digest(store: &BookmarkStore) -> u64 {
+    let json = serde_json::to_string(&store_for_sync(store)).unwrap_or_default();
+    fnv1a(json.as_bytes())
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes.iter().fold(String::new(), |mut out, byte| {
+        let _ = write!(out, "{byte:02x}");
+        out
+    })
+}
+
+/// 16 random bytes as hex. `RandomState` is seeded from the OS, which is
+/// plenty for a salt and saves pulling in a RNG crate.
+fn new_pin_salt() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let bytes: Vec<u8> = (0..2)
+        .flat_map(|_| {
+            let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+            hasher.write_u128(nanos);
+            hasher.finish().to_le_bytes()
+        })
+        .collect();
+    to_hex(&bytes)
+}
+
+const PIN_HASH_ROUNDS: u32 = 10_000;
+
+fn hash_pin(salt: &str, pin: &str) -> String {
+    let mut digest = Sha256::digest(format!("{salt}:{pin}"));
+    for _ in 1..PIN_HASH_ROUNDS {
+        digest = Sha256::digest(digest);
+    }
+    to_hex(&digest)
+}
+
+/// Whether `input` is the PIN behind `hash`. Compares every byte so the
+/// time taken doesn't hint at how much of a guess matched.
+fn verify_pin(hash: &str, salt: &str, input: &str) -> bool {
+    let candidate = hash_pin(salt, input);
+    candidate.len() == hash.len()
+        && candidate
+            .bytes()
+            .zip(hash.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// The configured repository for reports, with the owner masked unless
+/// `include_repo`.
+fn shown_repo(settings: &Settings, include_repo: bool) -> String {
+    if settings.github_repo.is_empty() {
+        "(not set)".to_string()
+    } else if include_repo {
+        settings.github_repo.clone()
+    } else {
+        match settings.github_repo.split_once('/') {
+            Some((_, name)) => format!("***/{name}"),
+            None => "***".to_string(),
+        }
+    }
+}
+
+/// Label/value rows for the sync health dialog. Like the diagnostics report
+/// it never shows the token, only whether one is set.
+fn sync_health_rows(
+    store: &BookmarkStore,
+    settings: &Settings,
+    include_repo: bool,
+    remote_sha: Option<&str>,
+    last_sync_at: Option<&str>,
+    last_sync_error: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let provider = match settings.sync_api() {
+        Ok(SyncApi::GitHub) => "GitHub".to_string(),
+        Ok(SyncApi::Gitea(base)) => format!("Gitea ({base})"),
+        Err(e) => e,
+    };
+    vec![
+        ("Provider", provider),
+        ("Repository", shown_repo(settings, include_repo)),
+        (
+            "Token",
+            if settings.github_token.is_empty() {
+                "not set"
+            } else {
+                "set"
+            }
+            .to_string(),
+        ),
+        ("Auto-sync", settings.auto_sync_mode().to_string()),
+        (
+            "Last sync",
+            last_sync_at.unwrap_or("not this session").to_string(),
+        ),
+        ("Last error", last_sync_error.unwrap_or("none").to_string()),
+        (
+            "Remote SHA",
+            remote_sha.map_or("unknown until a push or pull".to_string(), |sha| {
+                sha.chars().take(12).collect()
+            }),
+        ),
+        (
+            "Unpushed changes",
+            if has_unsynced_changes(store, settings) {
+                "yes"
+            } else {
+                "no"
+            }
+            .to_string(),
+        ),
+    ]
+}
+
+/// What the sync health test found, and whether that's good news.
+fn sync_test_message(
+    result: &Result<Option<String>, String>,
+    remote_sha: Option<&str>,
+) -> (String, bool) {
+    match result {
+        Ok(Some(sha)) if remote_sha == Some(sha.as_str()) => (
+            "Connected — bookmarks.json is the version last synced".to_string(),
+            true,
+        ),
+        Ok(Some(sha)) => (
+            format!(
+                "Connected — bookmarks.json ({}) changed since the last sync here",
+                sha.chars().take(12).collect::<String>()
+            ),
+            true,
+        ),
+        Ok(None) => (
+            "Connected, but the repository has no bookmarks.json yet".to_string(),
+            true,
+        ),
+        Err(e) => (e.clone(), false),
+    }
+}
+
+/// Plain-text report for bug reports. The token is never included, only
+/// whether one is set; the repo owner is masked unless `include_repo`.
+fn diagnostics_report(
+    store: &BookmarkStore,
+    settings: &Settings,
+    include_repo: bool,
+    last_sync_error: Option<&str>,
+) -> String {
+    let bookmarks: usize = store.folders.iter().map(|f| f.bookmarks.len()).sum();
+    let local_only = store.folders.iter().filter(|f| f.exclude_from_sync).count();
+    let repo = shown_repo(settings, include_repo);
+    let token = if settings.github_token.is_empty() {
+        "not set"
+    } else {
+        "set"
+    };
+    [
+        format!("Bookmarks Browser {}", env!("CARGO_PKG_VERSION")),
+        format!("OS: {} ({})", std::env::consts::OS, std::env::consts::ARCH),
+        format!("Config dir: {}", config_dir().display()),
+        format!("Folders: {} ({local_only} local only)", store.folders.len()),
+        format!("Bookmarks: {bookmarks}"),
+        format!("GitHub token: {token}"),
+        format!("GitHub repo: {repo}"),
+        format!("Mirrors: {}", settings.sync_mirrors().len()),
+        format!("Auto-sync: {}", settings.auto_sync_mode()),
+        format!(
+            "Unpushed changes: {}",
+            if has_unsynced_changes(store, settings) {
+                "yes"
+            } else {
+                "no"
+            }
+        ),
+        format!("Last sync error: {}", last_sync_error.unwrap_or("none")),
+        format!(
+            "Experiments: {}",
+            if enabled_experiments().is_empty() {
+                "none".to_string()
+            } else {
+                enabled_experiments()
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        ),
+    ]
+    .join("\n")
+        + "\n"
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Table rows for the help dialog.
+fn help_rows(shortcuts: &[(&str, &str)]) -> String {
+    shortcuts
+        .iter()
+        .map(|(keys, description)| {
+            format!(
+                "      <tr><td class=\"help-key\">{}</td><td>{}</td></tr>",
+                escape_html(keys),
+                escape_html(description)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn window_title(profile: Option<&str>) -> String {
+    match profile {
+        Some(name) => format!("Bookmarks Browser \u{2014} {name}"),
+        None => "Bookmarks Browser".to_string(),
+    }
+}
+
+/// Whether the store has edits that were never pushed (or pulled over).
+/// With no sync on record, any store counts as dirty.
+fn has_unsynced_changes(store: &BookmarkStore, settings: &Settings) -> bool {
+    settings.last_synced_digest != Some(store_digest(store))
+}
+
+/// Sidebar call that refreshes the unpushed-changes marker on the Push button.
+fn dirty_script(store: &BookmarkStore, settings: &Settings) -> String {
+    format!("setDirty({})", has_unsynced_changes(store, settings))
+}
+
+/// Number of bookmarks a push of this store would upload.
+fn synced_bookmark_count(store: &BookmarkStore) -> usize {
+    store
+        .folders
+        .iter()
+        .filter(|f| !f.exclude_from_sync)
+        .map(|f| f.bookmarks.len())
+        .sum()
+}
+
+/// Guards against wiping the remote: refuses a push that would leave GitHub
+/// with more than `max_removed_percent` fewer bookmarks than it had at the
+/// last sync. With no sync on record there is nothing to compare against.
+fn check_push(
+    outgoing: usize,
+    remote: Option<usize>,
+    max_removed_percent: u32,
+) -> Result<(), String> {
+    let Some(remote) = remote.filter(|n| *n > 0) else {
+        return Ok(());
+    };
+    let removed = remote.saturating_sub(outgoing);
+    if removed * 100 > remote * max_removed_percent as usize {
+        Err(format!(
+            "This push would remove {removed} of the {remote} bookmarks on GitHub"
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Inverse of `store_for_sync`: applies a pulled store while keeping the
+/// local sync-excluded folders at their previous positions. A remote folder
+/// sharing a name with a local excluded one is a stale copy from before the
+/// exclusion and is dropped in favour of the local version. Local thumbnails
+/// carry over to pulled bookmarks with the same URL, and local collapsed
+/// state and locks to pulled folders with the same name. Injected CSS and scripts
+/// always come from the local bookmark with the same URL, whatever the
+/// remote file says, so a shared repository can't run code in your pages.
+fn merge_excluded(remote: BookmarkStore, local: &BookmarkStore) -> BookmarkStore {
+    let thumbnails: HashMap<&str, &String> = local
+        .folders
+        .iter()
+        .flat_map(|f| &f.bookmarks)
+        .filter_map(|bm| bm.thumbnail.as_ref().map(|t| (bm.url.as_str(), t)))
+        .collect();
+    let injections: HashMap<&str, &Bookmark> = local
+        .folders
+        .iter()
+        .flat_map(|f| &f.bookmarks)
+        .filter(|bm| bm.inject_css.is_some() || bm.inject_js.is_some())
+        .map(|bm| (bm.url.as_str(), bm))
+        .collect();
+    let collapsed: HashSet<&str> = local
+        .folders
+        .iter()
+        .filter(|f| !f.expanded)
+        .map(|f| f.name.as_str())
+        .collect();
+    let locks: HashMap<&str, &Folder> = local
+        .folders
+        .iter()
+        .filter(|f| f.locked || f.pin.is_some())
+        .map(|f| (f.name.as_str(), f))
+        .collect();
+    let mut remote = remote;
+    for folder in &mut remote.folders {
+        folder.expanded = !collapsed.contains(folder.name.as_str());
+        if let Some(local_folder) = locks.get(folder.name.as_str()) {
+            folder.locked = local_folder.locked;
+            folder.pin = local_folder.pin.clone();
+        }
+    }
+    for bm in remote
+        .folders
+        .iter_mut()
+        .flat_map(|f| f.bookmarks.iter_mut())
+    {
+        if bm.thumbnail.is_none() {
+            bm.thumbnail = thumbnails.get(bm.url.as_str()).map(|t| (*t).clone());
+        }
+        let local_bm = injections.get(bm.url.as_str());
+        bm.inject_css = local_bm.and_then(|l| l.inject_css.clone());
+        bm.inject_js = local_bm.and_then(|l| l.inject_js.clone());
+    }
+    let excluded: Vec<(usize, &Folder)> = local
+        .folders
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| f.exclude_from_sync)
+        .collect();
+    let mut folders: Vec<Folder> = remote
+        .folders
+        .into_iter()
+        .filter(|f| !excluded.iter().any(|(_, e)| e.name == f.name))
+        .collect();
+    for (index, folder) in excluded {
+        let at = index.min(folders.len());
+        folders.insert(at, folder.clone());
+    }
+    BookmarkStore { folders }
+}
+
+/// Applies a finished pull over `local`, unless the store's synced part
+/// changed since the pull was requested (`requested_digest`): an edit made
+/// while the pull was in flight is kept, and left for the next push, rather
+/// than replaced by the remote copy.
+fn apply_pull(
+    local: &BookmarkStore,
+    remote: BookmarkStore,
+    requested_digest: u64,
+) -> Option<BookmarkStore> {
+    (store_digest(local) == requested_digest).then(|| merge_excluded(remote, local))
+}
+
+/// Two-way merge for the combined sync: the remote store plus every local
+/// folder and bookmark it lacks. Folders match by name and bookmarks by
+/// normalized URL, so neither side loses anything, though a bookmark deleted
+/// on one side comes back from the other. Local-only folders and thumbnails
+/// are kept as for a pull, and local folders keep their expanded state.
+fn merge_stores(local: &BookmarkStore, remote: BookmarkStore) -> BookmarkStore {
+    let mut merged = merge_excluded(remote, local);
+    for folder in local.folders.iter().filter(|f| !f.exclude_from_sync) {
+        let target = merged
+            .folders
+            .iter_mut()
+            .find(|f| !f.exclude_from_sync && f.name == folder.name);
+        match target {
+            Some(target) => {
+                let mut seen: HashSet<String> = target
+                    .bookmarks
+                    .iter()
+                    .map(|bm| normalize_url(&bm.url))
+                    .collect();
+                for bm in &folder.bookmarks {
+                    if seen.insert(normalize_url(&bm.url)) {
+                        target.bookmarks.push(bm.clone());
+                    }
+                }
+                target.expanded = folder.expanded;
+            }
+            None => merged.folders.push(folder.clone()),
+        }
+    }
+    merged
+}
+
+/// A bulleted `[name](url)` list of the folder's bookmarks. Brackets and
+/// backslashes in names are escaped; parentheses and spaces in URLs are
+/// percent-encoded so they can't end the link early.
+fn folder_to_markdown(folder: &Folder) -> String {
+    folder
+        .bookmarks
+        .iter()
+        .map(|bm| {
+            let name = bm
+                .name
+                .replace('\\', "\\\\")
+                .replace('[', "\\[")
+                .replace(']', "\\]");
+            let url = bm
+                .url
+                .replace(' ', "%20")
+                .replace('(', "%28")
+                .replace(')', "%29");
+            format!("- [{name}]({url})\n")
+        })
+        .collect()
+}
+
+/// Everything known about one bookmark, pretty-printed for a bug report: the
+/// stored record with its local-only fields, plus what the side files hold
+/// for its URL. Nothing is redacted; it's one bookmark, copied on request.
+fn bookmark_debug_json(
+    folder: &Folder,
+    bookmark: &Bookmark,
+    health: &HealthMap,
+    visits: &VisitMap,
+    zoom: &ZoomMap,
+) -> String {
+    let host = host_of(&bookmark.url);
+    let report = serde_json::json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "folder": folder.name,
+        "bookmark": bookmark,
+        "normalized_url": normalize_url(&bookmark.url),
+        "host": host,
+        "link_health": health.get(&bookmark.url),
+        "visits": visits.get(&bookmark.url),
+        "zoom": host.as_ref().and_then(|h| zoom.get(h)),
+        "thumbnail_path": bookmark
+            .thumbnail
+            .as_ref()
+            .map(|name| thumbnails_dir().join(name).display().to_string()),
+    });
+    serde_json::to_string_pretty(&report).unwrap_or_else(|_| "{}".into())
+}
+
+/// The stored URL of the first bookmark pointing at `url`, compared in
+/// normalized form so a trailing slash or host case doesn't hide a match.
+fn bookmark_url_for(store: &BookmarkStore, url: &str) -> Option<String> {
+    let target = normalize_url(url);
+    store
+        .folders
+        .iter()
+        .flat_map(|f| &f.bookmarks)
+        .find(|bm| normalize_url(&bm.url) == target)
+        .map(|bm| bm.url.clone())
+}
+
+/// Expands the folder holding the bookmark saved as `url` so its highlight
+/// can be seen. Nothing changes when a folder showing it is already open,
+/// and locked folders stay shut. Returns whether a folder was expanded.
+fn expand_folder_of(store: &mut BookmarkStore, url: &str) -> bool {
+    let holds = |folder: &Folder| folder.bookmarks.iter().any(|bm| bm.url == url);
+    if store.folders.iter().any(|f| f.expanded && holds(f)) {
+        return false;
+    }
+    match store.folders.iter_mut().find(|f| !f.locked && holds(f)) {
+        Some(folder) => {
+            folder.expanded = true;
+            true
+        }
+        None => false,
+    }
+}
+
+/// The folder holding the most bookmarks on the same site as `url`, for the
+/// add dialog to pre-select. A leading `www.` is ignored; ties go to the
+/// folder higher in the sidebar. `None` when no bookmark shares the site.
+fn suggest_folder(store: &BookmarkStore, url: &str) -> Option<usize> {
+    let site = |url: &str| {
+        host_of(url).map(|host| match host.strip_prefix("www.") {
+            Some(rest) => rest.to_string(),
+            None => host,
+        })
+    };
+    let target = site(url)?;
+    store
+        .folders
+        .iter()
+        .enumerate()
+        .map(|(index, folder)| {
+            let score = folder
+                .bookmarks
+                .iter()
+                .filter(|bm| site(&bm.url).as_deref() == Some(target.as_str()))
+                .count();
+            (index, score)
+        })
+        .filter(|(_, score)| *score > 0)
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+        .map(|(index, _)| index)
+}
+
+type UrlRewrite<'a> = Box<dyn Fn(&str) -> Option<String> + 'a>;
+
+/// Builds the rewrite used by find & replace: a plain substring replacement,
+/// or with `regex` a pattern whose replacement may use `$1`-style groups.
+/// Returns `None` for URLs the rewrite leaves unchanged.
+fn url_rewriter<'a>(
+    find: &'a str,
+    replace: &'a str,
+    regex: bool,
+) -> Result<UrlRewrite<'a>, String> {
+    if find.is_empty() {
+        return Err("Enter the text to find".to_string());
+    }
+    if regex {
+        let re = Regex::new(find).map_err(|e| format!("Invalid pattern: {e}"))?;
+        Ok(Box::new(move |url| {
+            let new = re.replace_all(url, replace);
+            (new != url).then(|| new.into_owned())
+        }))
+    } else {
+        Ok(Box::new(move |url| {
+            let new = url.replace(find, replace);
+            (new != url).then_some(new)
+        }))
+    }
+}
+
+/// Bookmarks a find & replace would change, as (name, old URL, new URL).
+fn preview_replace_urls(
+    store: &BookmarkStore,
+    find: &str,
+    replace: &str,
+    regex: bool,
+) -> Result<Vec<(String, String, String)>, String> {
+    let rewrite = url_rewriter(find, replace, regex)?;
+    Ok(store
+        .folders
+        .iter()
+        .flat_map(|folder| &folder.bookmarks)
+        .filter_map(|bm| rewrite(&bm.url).map(|new| (bm.name.clone(), bm.url.clone(), new)))
+        .collect())
+}
+
+/// Reduces a URL to the form used to spot duplicates: scheme and host are
+/// lowercased, default ports, the fragment and a trailing slash dropped.
+/// The scheme itself is kept, so http and https stay distinct.
+fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    let url = url.split_once('#').map_or(url, |(before, _)| before);
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let scheme = scheme.to_ascii_lowercase();
+    let (authority, path) = match rest.find(['/', '?']) {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    let mut authority = authority.to_ascii_lowercase();
+    let default_port = match scheme.as_str() {
+        "http" => Some(":80"),
+        "https" => Some(":443"),
+        _ => None,
+    };
+    if let Some(port) = default_port {
+        if let Some(host) = authority.strip_suffix(port) {
+            authority = host.to_string();
+        }
+    }
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path, None),
+    };
+    let path = path.trim_end_matches('/');
+    match query {
+        Some(query) => format!("{scheme}://{authority}{path}?{query}"),
+        None => format!("{scheme}://{authority}{path}"),
+    }
+}
+
+/// Keeps the first bookmark for each normalized URL, within each folder or
+/// across the whole store, and returns how many were removed.
+fn dedupe_bookmarks(store: &mut BookmarkStore, global: bool) -> usize {
+    let mut seen = HashSet::new();
+    let mut removed = 0;
+    for folder in &mut store.folders {
+        if !global {
+            seen.clear();
+        }
+        let before = folder.bookmarks.len();
+        folder
+            .bookmarks
+            .retain(|bm| seen.insert(normalize_url(&bm.url)));
+        removed += before - folder.bookmarks.len();
+    }
+    removed
+}
+
+/// Applies an edit from the edit dialog, stamping `modified` with `now` only
+/// if the name or URL really changed. Returns whether anything did.
+fn edit_bookmark(bm: &mut Bookmark, name: &str, url: &str, now: &str) -> bool {
+    let (name, url) = (name.trim(), url.trim());
+    if name.is_empty() || url.is_empty() || (name == bm.name && url == bm.url) {
+        return false;
+    }
+    if name != bm.name {
+        bm.name_is_custom = true;
+    }
+    bm.name = name.to_string();
+    bm.url = url.to_string();
+    bm.modified = Some(now.to_string());
+    true
+}
+
+/// A tag as stored: trimmed with inner runs of whitespace collapsed.
+/// `None` if nothing is left.
+fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!tag.is_empty()).then_some(tag)
+}
+
+/// Normalizes tags and drops repeats, compared case-insensitively; the
+/// first spelling wins.
+fn clean_tags(tags: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.iter()
+        .filter_map(|tag| normalize_tag(tag))
+        .filter(|tag| seen.insert(tag.to_lowercase()))
+        .collect()
+}
+
+/// Longest note kept on a bookmark; a clipped selection is cut to this
+const MAX_NOTES_CHARS: usize = 2000;
+
+/// Notes as saved: trimmed and cut to `MAX_NOTES_CHARS`, `None` when blank.
+fn clean_notes(notes: &str) -> Option<String> {
+    let notes = notes.trim();
+    (!notes.is_empty()).then(|| notes.chars().take(MAX_NOTES_CHARS).collect())
+}
+
+/// Shortest auto-refresh interval; anything faster would make a page unusable
+const MIN_REFRESH_SECS: u64 = 5;
+
+/// A bookmark's refresh interval as saved: 0 turns it off, and anything
+/// shorter than `MIN_REFRESH_SECS` is raised to it.
+fn refresh_interval(secs: u64) -> Option<u64> {
+    (secs > 0).then(|| secs.max(MIN_REFRESH_SECS))
+}
+
+/// Sends `RefreshTick(generation)` after `secs` seconds.
+fn schedule_refresh(proxy: EventLoopProxy<UserEvent>, generation: u64, secs: u64) {
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_secs(secs));
+        let _ = proxy.send_event(UserEvent::RefreshTick(generation));
+    });
+}
+
+/// A container name as typed: trimmed and lower-cased, `None` when blank.
+/// It names a directory, so only letters, digits, `-` and `_` are allowed.
+fn normalize_container(name: &str) -> Result<Option<String>, String> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return Ok(None);
+    }
+    if name.chars().count() > 32
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "\u{201c}{name}\u{201d} can't be a container; use up to 32 letters, digits, - or _"
+        ));
+    }
+    Ok(Some(name))
+}
+
+/// Where a container keeps its cookies, storage and cache.
+fn container_dir(name: &str) -> PathBuf {
+    config_dir().join("containers").join(name)
+}
+
+/// The container of the bookmark saved as `url`, or `None` when no bookmark
+/// has that URL, in which case the pane keeps whatever container it is in.
+/// The name is checked again here, since a pull, an import or a hand edit
+/// can bring in one that never went through the Edit dialog; an unusable
+/// name (say `../../.ssh`) means the default container.
+fn bookmark_container(store: &BookmarkStore, url: &str) -> Option<Option<String>> {
+    store
+        .folders
+        .iter()
+        .flat_map(|f| &f.bookmarks)
+        .find(|bm| bm.url == url)
+        .map(|bm| {
+            bm.container
+                .as_deref()
+                .and_then(|name| normalize_container(name).ok().flatten())
+        })
+}
+
+/// How long after the Alt+G leader the mnemonic key still counts
+const MNEMONIC_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Mnemonics are one ASCII letter or digit, compared lower-case.
+fn normalize_mnemonic(c: char) -> Option<char> {
+    c.is_ascii_alphanumeric().then(|| c.to_ascii_lowercase())
+}
+
+/// The first bookmark carrying mnemonic `c`, as (folder, bookmark) indices.
+fn mnemonic_owner(store: &BookmarkStore, c: char) -> Option<(usize, usize)> {
+    let c = normalize_mnemonic(c)?;
+    store.folders.iter().enumerate().find_map(|(fi, folder)| {
+        folder
+            .bookmarks
+            .iter()
+            .position(|bm| bm.mnemonic == Some(c))
+            .map(|bi| (fi, bi))
+    })
+}
+
+/// Gives the bookmark a mnemonic, or clears it with `None`. A mnemonic
+/// another bookmark already has is refused. Returns whether anything changed.
+fn set_mnemonic(
+    store: &mut BookmarkStore,
+    folder_index: usize,
+    bookmark_index: usize,
+    mnemonic: Option<char>,
+) -> Result<bool, String> {
+    let mnemonic = match mnemonic {
+        Some(c) => Some(normalize_mnemonic(c).ok_or_else(|| {
+            format!("\u{201c}{c}\u{201d} can't be a mnemonic; use a letter or digit")
+        })?),
+        None => None,
+    };
+    if let Some(c) = mnemonic {
+        if let Some((fi, bi)) = mnemonic_owner(store, c) {
+            if (fi, bi) != (folder_index, bookmark_index) {
+                let owner = &store.folders[fi].bookmarks[bi].name;
+                return Err(format!("\u{201c}{c}\u{201d} already opens {owner}"));
+            }
+        }
+    }
+    let Some(bm) = store
+        .folders
+        .get_mut(folder_index)
+        .and_then(|f| f.bookmarks.get_mut(bookmark_index))
+    else {
+        return Ok(false);
+    };
+    if bm.mnemonic == mnemonic {
+        return Ok(false);
+    }
+    bm.mnemonic = mnemonic;
+    Ok(true)
+}
+
+/// The pending half of the Alt+G, letter sequence typed into the window.
+#[derive(Default)]
+struct MnemonicLeader {
+    since: Option<Instant>,
+}
+
+impl MnemonicLeader {
+    fn start(&mut self, now: Instant) {
+        self.since = Some(now);
+    }
+
+    /// The mnemonic `key` completes, if the leader came just before it. Any
+    /// key ends the sequence, whether or not it completed it.
+    fn finish(&mut self, key: &str, now: Instant) -> Option<char> {
+        let since = self.since.take()?;
+        if now.duration_since(since) > MNEMONIC_TIMEOUT {
+            return None;
+        }
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => normalize_mnemonic(c),
+            _ => None,
+        }
+    }
+}
+
+/// Adds `tag` to, or removes it from, every bookmark in `targets`, matching
+/// case-insensitively. Targets that don't exist are skipped. Returns how
+/// many bookmarks changed, each stamped `modified` with `now`; adding a tag a
+/// bookmark has already, or removing one it hasn't, changes nothing.
+fn tag_bookmarks(
+    store: &mut BookmarkStore,
+    targets: &[(usize, usize)],
+    tag: &str,
+    add: bool,
+    now: &str,
+) -> usize {
+    let Some(tag) = normalize_tag(tag) else {
+        return 0;
+    };
+    let key = tag.to_lowercase();
+    let mut changed = 0;
+    for &(fi, bi) in targets {
+        let Some(bm) = store
+            .folders
+            .get_mut(fi)
+            .and_then(|f| f.bookmarks.get_mut(bi))
+        else {
+            continue;
+        };
+        let before = bm.tags.clone();
+        let mut tags = clean_tags(std::mem::take(&mut bm.tags));
+        let has = tags.iter().any(|t| t.to_lowercase() == key);
+        if add && !has {
+            tags.push(tag.clone());
+        } else if !add {
+            tags.retain(|t| t.to_lowercase() != key);
+        }
+        if tags != before {
+            changed += 1;
+            bm.modified = Some(now.to_string());
+        }
+        bm.tags = tags;
+    }
+    changed
+}
+
+/// Sets the CSS and script injected for a bookmark; blank text clears them.
+/// Returns whether either changed.
+fn set_injection(bm: &mut Bookmark, css: Option<&str>, js: Option<&str>) -> bool {
+    let clean = |text: Option<&str>| {
+        text.map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+    };
+    let (css, js) = (clean(css), clean(js));
+    if css == bm.inject_css && js == bm.inject_js {
+        return false;
+    }
+    bm.inject_css = css;
+    bm.inject_js = js;
+    true
+}
+
+/// Scripts that apply a bookmark's injections to the loaded page: the CSS
+/// as a single `<style>` element (replaced if the page is reinjected), then
+/// the user's script in its own function so a syntax error in it can't stop
+/// the stylesheet. Nothing when `page_url` isn't the bookmark's own page, so
+/// a redirect to another site never runs the bookmark's script.
+fn injection_scripts(bm: &Bookmark, page_url: &str) -> Vec<String> {
+    let mut scripts = Vec::new();
+    if normalize_url(page_url) != normalize_url(&bm.url) {
+        return scripts;
+    }
+    if let Some(css) = &bm.inject_css {
+        let css = serde_json::to_string(css).unwrap_or_else(|_| "''".into());
+        scripts.push(format!(
+            "(function() {{ var s = document.getElementById('__bbInjectedCss') || \
+             document.createElement('style'); s.id = '__bbInjectedCss'; s.textContent = {css}; \
+             (document.head || document.documentElement).appendChild(s); }})();"
+        ));
+    }
+    if let Some(js) = &bm.inject_js {
+        // The newline keeps a trailing `//` comment from swallowing the close
+        scripts.push(format!("(function() {{\n{js}\n}})();"));
+    }
+    scripts
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum IssueKind {
+    EmptyName,
+    /// Leading or trailing whitespace in a name
+    UntrimmedName,
+    MalformedUrl,
+    /// Leading or trailing whitespace around a URL
+    UntrimmedUrl,
+    DuplicateUrl,
+    DuplicateFolderName,
+    /// A thumbnail reference that can't be a file this app wrote
+    OrphanedThumbnail,
+    /// A lock flag with no PIN behind it
+    OrphanedLock,
+}
+
+impl IssueKind {
+    /// Whether `fix_store` repairs this without losing anything
+    fn fixable(self) -> bool {
+        matches!(
+            self,
+            IssueKind::UntrimmedName
+                | IssueKind::UntrimmedUrl
+                | IssueKind::OrphanedThumbnail
+                | IssueKind::OrphanedLock
+        )
+    }
+}
+
+/// A problem found by `validate_store`. `bookmark_index` is None when it
+/// concerns the folder itself.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+struct ValidationIssue {
+    kind: IssueKind,
+    folder_index: usize,
+    bookmark_index: Option<usize>,
+    detail: String,
+    fixable: bool,
+}
+
+/// Whether `url` looks like something a webview can open: a scheme, no
+/// spaces inside, and a host for the network schemes.
+fn is_well_formed_url(url: &str) -> bool {
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return false;
+    };
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !valid_scheme || rest.is_empty() || url.chars().any(char::is_whitespace) {
+        return false;
+    }
+    match scheme.to_ascii_lowercase().as_str() {
+        "http" | "https" | "ftp" => host_of(url).is_some(),
+        _ => true,
+    }
+}
+
+/// Checks every folder and bookmark for empty or padded names, malformed,
+/// padded or repeated URLs, repeated folder names and leftover data
+/// (unusable thumbnail references, locks without a PIN). Issues come in
+/// sidebar order.
+fn validate_store(store: &BookmarkStore) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let mut push = |kind: IssueKind, fi: usize, bi: Option<usize>, detail: String| {
+        issues.push(ValidationIssue {
+            kind,
+            folder_index: fi,
+            bookmark_index: bi,
+            detail,
+            fixable: kind.fixable(),
+        });
+    };
+    let mut folder_names: HashMap<&str, usize> = HashMap::new();
+    let mut urls: HashMap<String, (usize, usize)> = HashMap::new();
+    for (fi, folder) in store.folders.iter().enumerate() {
+        if folder.name.trim().is_empty() {
+            push(IssueKind::EmptyName, fi, None, "Folder has no name".into());
+        } else if folder.name.trim() != folder.name {
+            push(
+                IssueKind::UntrimmedName,
+                fi,
+                None,
+                "Folder name has extra spaces".into(),
+            );
+        }
+        if let Some(&first) = folder_names.get(folder.name.trim()) {
+            push(
+                IssueKind::DuplicateFolderName,
+                fi,
+                None,
+                format!("Same name as folder {}; syncs merge them", first + 1),
+            );
+        } else {
+            folder_names.insert(folder.name.trim(), fi);
+        }
+        if folder.locked && folder.pin.is_none() {
+            push(
+                IssueKind::OrphanedLock,
+                fi,
+                None,
+                "Marked locked but has no PIN".into(),
+            );
+        }
+        for (bi, bm) in folder.bookmarks.iter().enumerate() {
+            let at = Some(bi);
+            if bm.name.trim().is_empty() {
+                push(IssueKind::EmptyName, fi, at, "Bookmark has no name".into());
+            } else if bm.name.trim() != bm.name {
+                push(
+                    IssueKind::UntrimmedName,
+                    fi,
+                    at,
+                    "Name has extra spaces".into(),
+                );
+            }
+            let url = bm.url.trim();
+            if !is_well_formed_url(url) {
+                let detail = if url.is_empty() {
+                    "No URL".to_string()
+                } else {
+                    format!("Malformed URL: {url}")
+                };
+                push(IssueKind::MalformedUrl, fi, at, detail);
+            } else if url != bm.url {
+                push(
+                    IssueKind::UntrimmedUrl,
+                    fi,
+                    at,
+                    "URL has extra spaces".into(),
+                );
+            }
+            if !url.is_empty() {
+                match urls.get(&normalize_url(url)) {
+                    Some(&(ofi, obi)) => push(
+                        IssueKind::DuplicateUrl,
+                        fi,
+                        at,
+                        format!(
+                            "Same URL as \"{}\" in {}",
+                            store.folders[ofi].bookmarks[obi].name.trim(),
+                            store.folders[ofi].name.trim()
+                        ),
+                    ),
+                    None => {
+                        urls.insert(normalize_url(url), (fi, bi));
+                    }
+                }
+            }
+            if bm
+                .thumbnail
+                .as_deref()
+                .is_some_and(|t| !is_thumbnail_name(t))
+            {
+                push(
+                    IssueKind::OrphanedThumbnail,
+                    fi,
+                    at,
+                    "Thumbnail reference points at no usable file".into(),
+                );
+            }
+        }
+    }
+    issues
+}
+
+/// Applies the safe fixes for `validate_store` issues: trims names (unless
+/// that would leave them empty) and URLs, and drops unusable thumbnail
+/// references and PIN-less locks. Returns how many things changed.
+fn fix_store(store: &mut BookmarkStore) -> usize {
+    fn trim(value: &mut String) -> bool {
+        let trimmed = value.trim();
+        if trimmed.is_empty() || trimmed == value {
+            return false;
+        }
+        *value = trimmed.to_string();
+        true
+    }
+    let mut fixed = 0;
+    for folder in &mut store.folders {
+        fixed += usize::from(trim(&mut folder.name));
+        if folder.locked && folder.pin.is_none() {
+            folder.locked = false;
+            fixed += 1;
+        }
+        for bm in &mut folder.bookmarks {
+            fixed += usize::from(trim(&mut bm.name));
+            fixed += usize::from(trim(&mut bm.url));
+            if bm
+                .thumbnail
+                .as_deref()
+                .is_some_and(|t| !is_thumbnail_name(t))
+            {
+                bm.thumbnail = None;
+                fixed += 1;
+            }
+        }
+    }
+    fixed
+}
+
+/// URLs (compared with `normalize_url`) found in more than one folder, as
+/// first written, with the indices of the folders holding them. Listed in
+/// order of first appearance.
+fn cross_folder_duplicates(store: &BookmarkStore) -> Vec<(String, Vec<usize>)> {
+    let mut found: Vec<(String, Vec<usize>)> = Vec::new();
+    let mut by_key: HashMap<String, usize> = HashMap::new();
+    for (fi, folder) in store.folders.iter().enumerate() {
+        for bm in &folder.bookmarks {
+            match by_key.get(&normalize_url(&bm.url)) {
+                Some(&i) => {
+                    if found[i].1.last() != Some(&fi) {
+                        found[i].1.push(fi);
+                    }
+                }
+                None => {
+                    by_key.insert(normalize_url(&bm.url), found.len());
+                    found.push((bm.url.clone(), vec![fi]));
+                }
+            }
+        }
+    }
+    found.retain(|(_, folders)| folders.len() > 1);
+    found
+}
+
+/// Removes copies of `url` from every folder but `keep`, and returns how many
+/// went. Repeats inside `keep` are left for the ordinary dedupe.
+fn keep_only_in(store: &mut BookmarkStore, url: &str, keep: usize) -> usize {
+    if keep >= store.folders.len() {
+        return 0;
+    }
+    let key = normalize_url(url);
+    let mut removed = 0;
+    for (fi, folder) in store.folders.iter_mut().enumerate() {
+        if fi != keep {
+            let before = folder.bookmarks.len();
+            folder.bookmarks.retain(|bm| normalize_url(&bm.url) != key);
+            removed += before - folder.bookmarks.len();
+        }
+    }
+    removed
+}
+
+/// Rewrites matching bookmark URLs in place and returns how many changed.
+/// An empty or invalid pattern changes nothing.
+fn replace_urls(
+    store: &mut BookmarkStore,
+    find: &str,
+    replace: &str,
+    regex: bool,
+    now: &str,
+) -> usize {
+    let Ok(rewrite) = url_rewriter(find, replace, regex) else {
+        return 0;
+    };
+    let mut changed = 0;
+    for bm in store
+        .folders
+        .iter_mut()
+        .flat_map(|f| f.bookmarks.iter_mut())
+    {
+        if let Some(new) = rewrite(&bm.url) {
+            bm.url = new;
+            bm.modified = Some(now.to_string());
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// One entry of the flat bookmark list other tools exchange.
+#[derive(Debug, Serialize, Deserialize)]
+struct FlatBookmark {
+    title: String,
+    url: String,
+    #[serde(default)]
+    folder: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Flattens the store into a JSON array of `{title, url, folder, tags}`.
+fn to_flat_json(store: &BookmarkStore) -> String {
+    let flat: Vec<FlatBookmark> = store
+        .folders
+        .iter()
+        .flat_map(|folder| {
+            folder.bookmarks.iter().map(|bm| FlatBookmark {
+                title: bm.name.clone(),
+                url: bm.url.clone(),
+                folder: folder.name.clone(),
+                tags: bm.tags.clone(),
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&flat).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// One self-contained page listing every folder and bookmark, styled with
+/// the app's palette for `theme` ("dark" or "light"), for reading or
+/// sharing rather than re-import. Only http(s) URLs become links; anything
+/// else is shown as text so the file can't carry a `javascript:` link.
+fn to_standalone_html(store: &BookmarkStore, theme: &str) -> String {
+    let (base, mantle, text, subtext, accent) = if theme == "light" {
+        ("#eff1f5", "#e6e9ef", "#4c4f69", "#6c6f85", "#8839ef")
+    } else {
+        ("#1e1e2e", "#181825", "#cdd6f4", "#a6adc8", "#cba6f7")
+    };
+    let count: usize = store.folders.iter().map(|f| f.bookmarks.len()).sum();
+    let mut body = String::new();
+    for folder in &store.folders {
+        let icon = folder
+            .icon
+            .as_deref()
+            .map(|icon| format!("{} ", escape_html(icon)))
+            .unwrap_or_default();
+        body.push_str(&format!(
+            "<section>\n<h2>{icon}{}</h2>\n<ul>\n",
+            escape_html(&folder.name)
+        ));
+        for bm in &folder.bookmarks {
+            let name = escape_html(&bm.name);
+            let url = escape_html(&bm.url);
+            if bm.url.starts_with("http://") || bm.url.starts_with("https://") {
+                body.push_str(&format!(
+                    "<li><a href=\"{url}\">{name}</a><span>{url}</span></li>\n"
+                ));
+            } else {
+                body.push_str(&format!("<li>{name}<span>{url}</span></li>\n"));
+            }
+        }
+        body.push_str("</ul>\n</section>\n");
+    }
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Bookmarks</title>
+<style>
+  body {{ margin: 0 auto; max-width: 760px; padding: 32px 20px; background: {base}; color: {text}; font: 15px/1.5 system-ui, sans-serif; }}
+  h1 {{ font-size: 24px; }}
+  h2 {{ margin-top: 28px; color: {subtext}; font-size: 13px; text-transform: uppercase; letter-spacing: 0.05em; }}
+  ul {{ margin: 8px 0; padding: 0; list-style: none; background: {mantle}; border-radius: 8px; }}
+  li {{ padding: 6px 12px; overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }}
+  a {{ color: {accent}; text-decoration: none; }}
+  a:hover {{ text-decoration: underline; }}
+  span {{ margin-left: 10px; color: {subtext}; font-size: 12px; }}
+</style>
+</head>
+<body>
+<h1>Bookmarks</h1>
+<p>{count} bookmarks in {folders} folders</p>
+{body}</body>
+</html>
+"#,
+        folders = store.folders.len(),
+    )
+}
+
+/// Inverse of `to_flat_json`: groups entries into folders by their `folder`
+/// field, in order of first appearance. Entries without one go to "Imported".
+/// Entries that don't parse are left out and described in the second list.
+fn from_flat_json(json: &str) -> Result<(Vec<Folder>, Vec<String>), String> {
+    let entries: Vec<serde_json::Value> =
+        serde_json::from_str(json).map_err(|e| format!("Invalid bookmarks list: {e}"))?;
+    let mut folders: Vec<Folder> = Vec::new();
+    let mut failures = Vec::new();
+    for (i, entry) in entries.into_iter().enumerate() {
+        let entry: FlatBookmark = match serde_json::from_value(entry) {
+            Ok(entry) => entry,
+            Err(e) => {
+                failures.push(format!("entry {}: {e}", i + 1));
+                continue;
+            }
+        };
+        let name = match entry.folder.trim() {
+            "" => "Imported",
+            name => name,
+        };
+        push_to_folder(
+            &mut folders,
+            name,
+            Bookmark {
+                name: entry.title,
+                url: entry.url,
+                tags: clean_tags(entry.tags),
+                ..Default::default()
+            },
+        );
+    }
+    Ok((folders, failures))
+}
+
+/// Adds `bookmark` to the folder called `name`, which is appended first if
+/// there isn't one yet.
+fn push_to_folder(folders: &mut Vec<Folder>, name: &str, bookmark: Bookmark) {
+    let index = match folders.iter().position(|f| f.name == name) {
+        Some(index) => index,
+        None => {
+            folders.push(Folder {
+                name: name.to_string(),
+                expanded: true,
+                icon: None,
+                exclude_from_sync: false,
+                locked: false,
+                pin: None,
+                bookmarks: vec![],
+            });
+            folders.len() - 1
+        }
+    };
+    folders[index].bookmarks.push(bookmark);
+}
+
+/// Reads bookmarks from an import file, telling this app's own JSON format,
+/// a flat bookmark list, OPML, a bookmarks HTML file (browsers, Pocket) and
+/// an Instapaper CSV apart by their first markup or header. Only a file
+/// that can't be read at all is an error; folders or entries that don't
+/// parse are skipped and described in the second list.
+fn parse_import(content: &str) -> Result<(Vec<Folder>, Vec<String>), String> {
+    let content = content.trim_start_matches('\u{feff}').trim_start();
+    if content.starts_with('[') {
+        return from_flat_json(content);
+    }
+    if content.starts_with('{') {
+        let value: serde_json::Value =
+            serde_json::from_str(content).map_err(|e| format!("Invalid bookmarks file: {e}"))?;
+        let Some(entries) = value.get("folders").and_then(|f| f.as_array()) else {
+            return Err("Invalid bookmarks file: no folders list".to_string());
+        };
+        let mut folders = Vec::new();
+        let mut failures = Vec::new();
+        for (i, entry) in entries.iter().enumerate() {
+            match serde_json::from_value::<Folder>(entry.clone()) {
+                Ok(folder) => folders.push(folder),
+                Err(e) => failures.push(format!("folder {}: {e}", i + 1)),
+            }
+        }
+        return Ok((folders, failures));
+    }
+    if content.contains("<opml") {
+        let folders = parse_opml(content);
+        if folders.is_empty() {
+            return Err("No links found in the OPML file".to_string());
+        }
+        return Ok((folders, Vec::new()));
+    }
+    if content.starts_with('<') {
+        let folders = parse_netscape(content);
+        if folders.is_empty() {
+            return Err("No links found in the HTML file".to_string());
+        }
+        return Ok((folders, Vec::new()));
+    }
+    let header = content.lines().next().unwrap_or("");
+    if header
+        .split(',')
+        .any(|column| column.trim().trim_matches('"').eq_ignore_ascii_case("url"))
+    {
+        return parse_instapaper_csv(content);
+    }
+    Err("Unrecognized file — expected bookmarks JSON, a bookmark list, OPML, bookmarks HTML or an Instapaper CSV".to_string())
+}
+
+/// Minimum time between import progress updates, so a big file doesn't
+/// flood the event loop
+const IMPORT_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What an import added, and what it left out
+#[derive(Debug, Default, PartialEq)]
+struct ImportReport {
+    added: usize,
+    /// Already in the library, or repeated within the file, and left out
+    duplicates: usize,
+    /// Already in the library but added again (counted in `added` too)
+    kept_both: usize,
+    /// New names for existing bookmarks, by `normalize_url` of their URL
+    renames: Vec<(String, String)>,
+    /// Why each unusable entry was left out
+    skipped: Vec<String>,
+}
+
+impl ImportReport {
+    /// One status line; only the first few skip reasons are spelled out.
+    fn summary(&self) -> String {
+        let mut msg = format!("Imported {} bookmarks", self.added);
+        if self.duplicates > 0 {
+            msg.push_str(&format!(", {} duplicates left out", self.duplicates));
+        }
+        if self.kept_both > 0 {
+            msg.push_str(&format!(", {} duplicates added anyway", self.kept_both));
+        }
+        if !self.renames.is_empty() {
+            msg.push_str(&format!(", {} names updated", self.renames.len()));
+        }
+        if !self.skipped.is_empty() {
+            let shown = self.skipped[..self.skipped.len().min(3)].join("; ");
+            let more = if self.skipped.len() > 3 { "; …" } else { "" };
+            msg.push_str(&format!(", {} skipped ({shown}{more})", self.skipped.len()));
+        }
+        msg
+    }
+}
+
+/// How an import treats a URL the library already has
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DuplicateStrategy {
+    Skip,
+    /// Add it again alongside the existing bookmark
+    KeepBoth,
+    /// Leave it out, but give the existing bookmark the imported name
+    UpdateName,
+}
+
+impl DuplicateStrategy {
+    fn parse(strategy: &str) -> Option<DuplicateStrategy> {
+        match strategy {
+            "skip" => Some(DuplicateStrategy::Skip),
+            "keep_both" => Some(DuplicateStrategy::KeepBoth),
+            "update_name" => Some(DuplicateStrategy::UpdateName),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            DuplicateStrategy::Skip => "skip",
+            DuplicateStrategy::KeepBoth => "keep_both",
+            DuplicateStrategy::UpdateName => "update_name",
+        }
+    }
+}
+
+/// Sorts imported bookmarks against `existing` (each saved URL's
+/// `normalize_url` form mapped to its name) according to `strategy`.
+/// Repeats within the import and entries without a usable URL are always
+/// dropped, then any folders left empty. `progress` gets (done, total)
+/// after each bookmark.
+fn filter_import(
+    folders: Vec<Folder>,
+    existing: &HashMap<String, String>,
+    strategy: DuplicateStrategy,
+    mut progress: impl FnMut(usize, usize),
+) -> (Vec<Folder>, ImportReport) {
+    let total: usize = folders.iter().map(|f| f.bookmarks.len()).sum();
+    let mut report = ImportReport::default();
+    let mut seen = HashSet::new();
+    let mut done = 0;
+    let mut kept = Vec::new();
+    for mut folder in folders {
+        let bookmarks = std::mem::take(&mut folder.bookmarks);
+        for bm in bookmarks {
+            done += 1;
+            let url = bm.url.trim();
+            if url.is_empty() {
+                report.skipped.push(format!("\"{}\" has no URL", bm.name));
+            } else if is_bookmarklet(url) {
+                report
+                    .skipped
+                    .push(format!("\"{}\" is a bookmarklet", bm.name));
+            } else {
+                let key = normalize_url(url);
+                let saved = existing.get(&key);
+                let keep = match (saved, strategy) {
+                    _ if !seen.insert(key.clone()) => false,
+                    (None, _) => true,
+                    (Some(_), DuplicateStrategy::KeepBoth) => {
+                        report.kept_both += 1;
+                        true
+                    }
+                    (Some(name), DuplicateStrategy::UpdateName) => {
+                        let new_name = bm.name.trim();
+                        if !new_name.is_empty() && new_name != name {
+                            report.renames.push((key, new_name.to_string()));
+                        }
+                        false
+                    }
+                    (Some(_), DuplicateStrategy::Skip) => false,
+                };
+                if !keep {
+                    report.duplicates += 1;
+                } else {
+                    report.added += 1;
+                    // Scripts are only ever added by hand, as with bookmarklets
+                    folder.bookmarks.push(Bookmark {
+                        inject_css: None,
+                        inject_js: None,
+                        // It could clash with one already taken here
+                        mnemonic: None,
+                        ..bm
+                    });
+                }
+            }
+            progress(done, total);
+        }
+        if !folder.bookmarks.is_empty() {
+            kept.push(folder);
+        }
+    }
+    (kept, report)
+}
+
+/// Renames every bookmark whose `normalize_url` matches one in `renames`,
+/// stamping `modified`. Returns how many bookmarks changed.
+fn apply_import_renames(
+    store: &mut BookmarkStore,
+    renames: &[(String, String)],
+    now: &str,
+) -> usize {
+    if renames.is_empty() {
+        return 0;
+    }
+    let names: HashMap<&str, &str> = renames
+        .iter()
+        .map(|(key, name)| (key.as_str(), name.as_str()))
+        .collect();
+    let mut changed = 0;
+    for bm in store
+        .folders
+        .iter_mut()
+        .flat_map(|f| f.bookmarks.iter_mut())
+    {
+        if let Some(name) = names.get(normalize_url(&bm.url).as_str()) {
+            if bm.name != *name {
+                bm.name = name.to_string();
+                bm.modified = Some(now.to_string());
+                changed += 1;
+            }
+        }
+    }
+    changed
+}
+
+/// Maps OPML outlines onto folders. Outlines with an `htmlUrl`, `xmlUrl` or
+/// `url` become bookmarks in the nearest enclosing folder; the rest become
+/// folders. Folders only go one level deep here, so nested groups are
+/// flattened into "Parent / Child" names, and links outside any group are
+/// collected under the document title.
+fn parse_opml(xml: &str) -> Vec<Folder> {
+    let mut folders: Vec<Folder> = Vec::new();
+    let mut loose: Vec<Bookmark> = Vec::new();
+    let mut title = String::new();
+    // One entry per open <outline>: the folder its children go into, if it made one
+    let mut open: Vec<Option<usize>> = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(end) = tag_end(rest) else {
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if tag == "title" && open.is_empty() && title.is_empty() {
+            let text = rest.find('<').map_or(rest, |end| &rest[..end]);
+            title = decode_entities(text.trim());
+        } else if tag.trim_end() == "/outline" {
+            open.pop();
+        } else if let Some(body) = tag.strip_prefix("outline") {
+            if !body.is_empty() && !body.starts_with(|c: char| c.is_whitespace() || c == '/') {
+                continue;
+            }
+            let self_closing = body.ends_with('/');
+            let attrs = xml_attrs(body.trim_end_matches('/'));
+            let attr = |name: &str| {
+                attrs
+                    .iter()
+                    .find(|(key, value)| key == name && !value.trim().is_empty())
+                    .map(|(_, value)| value.trim().to_string())
+            };
+            let text = attr("text").or_else(|| attr("title")).unwrap_or_default();
+            let parent = open.iter().rev().find_map(|folder| *folder);
+            let mut made = None;
+
+            if let Some(url) = attr("htmlUrl")
+                .or_else(|| attr("xmlUrl"))
+                .or_else(|| attr("url"))
+            {
+                let name = if text.is_empty() { url.clone() } else { text };
+                let bookmark = Bookmark {
+                    name,
+                    url,
+                    ..Default::default()
+                };
+                match parent {
+                    Some(index) => folders[index].bookmarks.push(bookmark),
+                    None => loose.push(bookmark),
+                }
+            } else {
+                let name = match parent {
+                    Some(index) => format!("{} / {}", folders[index].name, text),
+                    None => text,
+                };
+                folders.push(Folder {
+                    name,
+                    expanded: true,
+                    icon: None,
+                    exclude_from_sync: false,
+                    locked: false,
+                    pin: None,
+                    bookmarks: vec![],
+                });
+                made = Some(folders.len() - 1);
+            }
+            if !self_closing {
+                open.push(made);
+            }
+        }
+    }
+
+    folders.retain(|folder| !folder.bookmarks.is_empty());
+    if !loose.is_empty() {
+        let name = if title.is_empty() {
+            "Imported".to_string()
+        } else {
+            title
+        };
+        folders.insert(
+            0,
+            Folder {
+                name,
+                expanded: true,
+                icon: None,
+                exclude_from_sync: false,
+                locked: false,
+                pin: None,
+                bookmarks: loose,
+            },
+        );
+    }
+    folders
+}
+
+/// Reads a Netscape bookmark file, the HTML that browsers and Pocket export.
+/// An `<h3>` names the `<dl>` list after it, and nested lists become
+/// `Parent / Child` folders. Links outside any named list go under the last
+/// `<h1>`/`<h2>` heading, which is how Pocket splits "Unread" from "Read
+/// Archive". A link's `time_added` or `add_date` (Unix seconds) becomes its
+/// creation stamp, and its comma-separated `tags` its tags.
+fn parse_netscape(html: &str) -> Vec<Folder> {
+    let mut folders: Vec<Folder> = Vec::new();
+    let mut section = String::new();
+    let mut heading: Option<String> = None;
+    // One entry per open <dl>/<ul>: the folder its links go into, if it has one
+    let mut open: Vec<Option<String>> = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(end) = tag_end(rest) else {
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        let (name, body) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let text = || decode_entities(rest.find('<').map_or(rest, |end| &rest[..end]).trim());
+
+        match name.to_ascii_lowercase().as_str() {
+            "h1" | "h2" => section = text(),
+            "h3" => heading = Some(text()),
+            "dl" | "ul" => open.push(heading.take()),
+            "/dl" | "/ul" => {
+                open.pop();
+            }
+            "a" => {
+                let attrs = xml_attrs(body);
+                let attr = |name: &str| {
+                    attrs
+                        .iter()
+                        .find(|(key, value)| {
+                            key.eq_ignore_ascii_case(name) && !value.trim().is_empty()
+                        })
+                        .map(|(_, value)| value.trim().to_string())
+                };
+                let Some(url) = attr("href") else {
+                    continue;
+                };
+                let title = text();
+                let path: Vec<&str> = open.iter().flatten().map(String::as_str).collect();
+                let folder = if !path.is_empty() {
+                    path.join(" / ")
+                } else if !section.is_empty() {
+                    section.clone()
+                } else {
+                    "Imported".to_string()
+                };
+                let tags = attr("tags")
+                    .map(|tags| clean_tags(tags.split(',').map(str::to_string).collect()))
+                    .unwrap_or_default();
+                push_to_folder(
+                    &mut folders,
+                    &folder,
+                    Bookmark {
+                        name: if title.is_empty() { url.clone() } else { title },
+                        url,
+                        created: attr("time_added")
+                            .or_else(|| attr("add_date"))
+                            .and_then(|secs| secs.parse().ok())
+                            .map(format_utc),
+                        tags,
+                        ..Default::default()
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+    folders
+}
+
+/// Splits CSV text into rows of fields. Quoted fields may hold commas,
+/// line breaks and doubled quotes; blank lines are dropped.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, _) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|row| row.iter().any(|field| !field.trim().is_empty()));
+    rows
+}
+
+/// Reads an Instapaper CSV export. Columns are found by their header:
+/// `URL` and `Title`, with `Folder` (Unread, Archive, Starred or one of
+/// your own) naming the folder, and `Timestamp` (Unix seconds) and `Tags`
+/// used when the export has them. Rows without a URL are described in the
+/// second list.
+fn parse_instapaper_csv(text: &str) -> Result<(Vec<Folder>, Vec<String>), String> {
+    let mut rows = parse_csv(text).into_iter();
+    let header = rows.next().unwrap_or_default();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+    };
+    let (Some(url_column), Some(title_column)) = (column("url"), column("title")) else {
+        return Err("Unrecognized CSV — expected URL and Title columns".to_string());
+    };
+    let (folder_column, time_column, tags_column) =
+        (column("folder"), column("timestamp"), column("tags"));
+    let mut folders = Vec::new();
+    let mut failures = Vec::new();
+    for (i, row) in rows.enumerate() {
+        let cell = |column: Option<usize>| {
+            column
+                .and_then(|c| row.get(c))
+                .map_or("", |value| value.trim())
+        };
+        let url = cell(Some(url_column));
+        if url.is_empty() {
+            failures.push(format!("row {}: no URL", i + 2));
+            continue;
+        }
+        let title = cell(Some(title_column));
+        let folder = match cell(folder_column) {
+            "" => "Imported",
+            folder => folder,
+        };
+        // Newer exports write the tags as a JSON array
+        let tags = cell(tags_column);
+        let tags = serde_json::from_str::<Vec<String>>(tags)
+            .unwrap_or_else(|_| tags.split(',').map(str::to_string).collect());
+        push_to_folder(
+            &mut folders,
+            folder,
+            Bookmark {
+                name: if title.is_empty() { url } else { title }.to_string(),
+                url: url.to_string(),
+                created: cell(time_column).parse().ok().map(format_utc),
+                tags: clean_tags(tags),
+                ..Default::default()
+            },
+        );
+    }
+    Ok((folders, failures))
+}
+
+/// Index of the `>` closing the tag at the start of `s`, ignoring any inside
+/// quoted attribute values.
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses `name="value"` pairs from the inside of a tag, decoding entities.
+fn xml_attrs(mut s: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    while let Some(eq) = s.find('=') {
+        let name = s[..eq].trim().to_string();
+        let after = s[eq + 1..].trim_start();
+        let Some(quote) = after.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            break;
+        };
+        let Some(len) = after[1..].find(quote) else {
+            break;
+        };
+        attrs.push((name, decode_entities(&after[1..1 + len])));
+        s = &after[len + 2..];
+    }
+    attrs
+}
+
+/// Decodes the predefined XML entities and numeric character references.
+/// Anything unrecognised is left as written.
+fn decode_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').and_then(|semi| {
+            let ch = match &rest[1..semi] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                entity => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            ch.map(|c| (c, semi))
+        });
+        match decoded {
+            Some((c, semi)) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The palette to render: the explicit setting, or the system's under "auto".
+fn resolved_theme(setting: &str, system: Theme) -> &'static str {
+    match setting {
+        "dark" => "dark",
+        "light" => "light",
+        "high_contrast" => "high_contrast",
+        _ if system == Theme::Light => "light",
+        _ => "dark",
+    }
+}
+
+fn sidebar_html(
+    store: &BookmarkStore,
+    settings: &Settings,
+    theme: &str,
+    health: &HealthMap,
+) -> String {
+    let folders_json = script_json(&store.folders, "[]");
+    let health_json = health_json(health);
+    let experiments_json = script_json(enabled_experiments(), "[]");
+    let has_token = !settings.github_token.is_empty();
+    let repo = settings.github_repo.replace('\'', "\\'");
+    let gitea_base_url = settings.gitea_base_url.replace('\'', "\\'");
+    let collapsed_class = if settings.sidebar_collapsed {
+        " collapsed"
+    } else {
+        ""
+    };
+    let density = settings.density();
+    let scrollbars = settings.scrollbars();
+    let dirty_class = if has_unsynced_changes(store, settings) {
+        " dirty"
+    } else {
+        ""
+    };
+    // A non-default profile gets its own accent, whatever the theme
+    let profile_css = settings
+        .profile()
+        .map(|name| {
+            format!(
+                ":root, :root[data-theme=\"light\"] {{ --accent: {}; }}",
+                settings.profile_color(&name)
+            )
+        })
+        .unwrap_or_default();
+    format!(
+        r#"<!DOCTYPE html>
+<html data-theme="{theme}">
 <head>
 <style>
   :root {{
+    --font-scale: {font_scale};
     --base: #1e1e2e;
     --mantle: #181825;
     --surface0: #313244;
@@ -206,1314 +4016,10206 @@ fn sidebar_html(store: &BookmarkStore, settings: &Settings) -> String {
     --accent: #cba6f7;
     --red: #f38ba8;
     --green: #a6e3a1;
+    --yellow: #f9e2af;
     --overlay: rgba(0, 0, 0, 0.5);
   }}
-  * {{ margin: 0; padding: 0; box-sizing: border-box; }}
-  body {{
-    background: var(--mantle);
-    color: var(--text);
-    font-family: system-ui, -apple-system, sans-serif;
-    font-size: 14px;
-    height: 100vh;
-    overflow: hidden;
-    display: flex;
-    flex-direction: column;
-    border-right: 1px solid var(--surface0);
+  :root[data-theme="light"] {{
+    --base: #eff1f5;
+    --mantle: #e6e9ef;
+    --surface0: #ccd0da;
+    --surface1: #bcc0cc;
+    --surface2: #acb0be;
+    --text: #4c4f69;
+    --subtext: #6c6f85;
+    --accent: #8839ef;
+    --red: #d20f39;
+    --green: #40a02b;
+    --yellow: #df8e1d;
+    --overlay: rgba(0, 0, 0, 0.3);
+  }}
+  :root[data-theme="high_contrast"] {{
+    --base: #000000;
+    --mantle: #000000;
+    --surface0: #262626;
+    --surface1: #5a5a5a;
+    --surface2: #9a9a9a;
+    --text: #ffffff;
+    --subtext: #e6e6e6;
+    --accent: #ffd400;
+    --red: #ff7b7b;
+    --green: #6cff6c;
+    --yellow: #ffff4d;
+    --overlay: rgba(0, 0, 0, 0.85);
+  }}
+  {profile_css}
+  * {{ margin: 0; padding: 0; box-sizing: border-box; }}
+  body {{
+    background: var(--mantle);
+    color: var(--text);
+    font-family: system-ui, -apple-system, sans-serif;
+    font-size: calc(14px * var(--font-scale));
+    height: 100vh;
+    overflow: hidden;
+    display: flex;
+    flex-direction: column;
+    border-right: 1px solid var(--surface0);
+  }}
+  #tree {{
+    padding: 8px 0;
+    flex: 1;
+    overflow-y: auto;
+  }}
+  /* Scrollbars in the theme's colours. Engines without the WebKit
+     pseudo-elements get a thin themed bar from scrollbar-color instead, and
+     anything older keeps its native one */
+  ::-webkit-scrollbar {{
+    width: 10px;
+    height: 10px;
+  }}
+  ::-webkit-scrollbar-track,
+  ::-webkit-scrollbar-corner {{
+    background: transparent;
+  }}
+  ::-webkit-scrollbar-thumb {{
+    background: var(--surface1);
+    background-clip: content-box;
+    border: 2px solid transparent;
+    border-radius: 5px;
+  }}
+  ::-webkit-scrollbar-thumb:hover {{
+    background: var(--surface2);
+    background-clip: content-box;
+  }}
+  body[data-scrollbars="overlay"] ::-webkit-scrollbar-thumb {{
+    background: transparent;
+  }}
+  body[data-scrollbars="overlay"] :hover::-webkit-scrollbar-thumb {{
+    background: var(--surface1);
+    background-clip: content-box;
+  }}
+  @supports not selector(::-webkit-scrollbar) {{
+    * {{
+      scrollbar-width: thin;
+      scrollbar-color: var(--surface1) transparent;
+    }}
+  }}
+  .folder-header {{
+    display: flex;
+    align-items: center;
+    padding: 6px 12px;
+    cursor: pointer;
+    user-select: none;
+    color: var(--subtext);
+    font-size: calc(12px * var(--font-scale));
+    font-weight: 600;
+    text-transform: uppercase;
+    letter-spacing: 0.05em;
+  }}
+  .inline-folder input {{
+    flex: 1;
+    min-width: 0;
+    padding: 2px 6px;
+    border: 1px solid var(--accent);
+    border-radius: 4px;
+    background: var(--surface0);
+    color: var(--text);
+    font: inherit;
+    text-transform: none;
+    letter-spacing: normal;
+  }}
+  .folder-header:hover {{
+    background: var(--surface0);
+  }}
+  .folder-header:focus,
+  .bookmark:focus {{
+    outline: none;
+    background: var(--surface0);
+    box-shadow: inset 2px 0 0 var(--accent);
+  }}
+  .folder-arrow {{
+    display: inline-block;
+    width: 16px;
+    font-size: calc(10px * var(--font-scale));
+    color: var(--subtext);
+  }}
+  .folder-name {{
+    flex: 1;
+    overflow: hidden;
+    text-overflow: ellipsis;
+    white-space: nowrap;
+  }}
+  .folder-actions {{
+    display: none;
+    align-items: center;
+    gap: 2px;
+    margin-left: auto;
+  }}
+  .folder-header:hover .folder-actions {{
+    display: flex;
+  }}
+  .icon-btn {{
+    background: none;
+    border: none;
+    color: var(--subtext);
+    cursor: pointer;
+    font-size: calc(14px * var(--font-scale));
+    padding: 0 4px;
+    line-height: 1;
+  }}
+  .icon-btn:hover {{
+    color: var(--text);
+  }}
+  .icon-btn.delete:hover {{
+    color: var(--red);
+  }}
+  .icon-btn:disabled {{
+    opacity: 0.3;
+    cursor: default;
+    color: var(--subtext);
+  }}
+  .folder-icon {{
+    margin-right: 6px;
+    font-size: calc(13px * var(--font-scale));
+    text-transform: none;
+  }}
+  .folder-lock {{
+    font-size: calc(10px * var(--font-scale));
+    margin-left: 6px;
+  }}
+  .folder-local {{
+    font-size: calc(9px * var(--font-scale));
+    color: var(--subtext);
+    border: 1px solid var(--surface2);
+    border-radius: 3px;
+    padding: 0 3px;
+    margin-left: 6px;
+  }}
+  .icon-grid {{
+    display: grid;
+    grid-template-columns: repeat(6, 1fr);
+    gap: 4px;
+    margin-bottom: 10px;
+  }}
+  .icon-choice {{
+    background: var(--surface0);
+    border: 1px solid var(--surface1);
+    border-radius: 4px;
+    color: var(--text);
+    cursor: pointer;
+    font-size: calc(15px * var(--font-scale));
+    padding: 3px 0;
+    font-family: inherit;
+  }}
+  .icon-choice:hover {{
+    border-color: var(--surface2);
+  }}
+  .icon-choice.selected {{
+    border-color: var(--accent);
+  }}
+  .bookmark {{
+    display: flex;
+    align-items: center;
+    padding: 6px 12px 6px 32px;
+    color: var(--text);
+    text-decoration: none;
+    cursor: pointer;
+  }}
+  .bookmark:hover {{
+    background: var(--surface0);
+  }}
+  .bookmark.active {{
+    background: var(--surface0);
+    color: var(--accent);
+  }}
+  .health-dot {{
+    width: 7px;
+    height: 7px;
+    border-radius: 50%;
+    margin-right: 6px;
+    flex-shrink: 0;
+    background: var(--surface2);
+  }}
+  .health-dot.ok {{
+    background: var(--green);
+  }}
+  .health-dot.redirect {{
+    background: var(--yellow);
+  }}
+  .health-dot.broken {{
+    background: var(--red);
+  }}
+  .search-bar {{
+    padding: 6px 8px;
+    border-bottom: 1px solid var(--surface0);
+    flex-shrink: 0;
+  }}
+  .search-bar input {{
+    width: 100%;
+    box-sizing: border-box;
+    padding: 4px 8px;
+    background: var(--surface0);
+    border: 1px solid var(--surface2);
+    border-radius: 4px;
+    color: var(--text);
+    font-size: calc(12px * var(--font-scale));
+    font-family: inherit;
+  }}
+  .health-bar {{
+    display: none;
+    align-items: center;
+    gap: 6px;
+    padding: 6px 8px;
+    font-size: calc(11px * var(--font-scale));
+    color: var(--subtext);
+    border-bottom: 1px solid var(--surface0);
+    flex-shrink: 0;
+  }}
+  .health-bar.active {{
+    display: flex;
+  }}
+  .health-bar select {{
+    background: var(--surface0);
+    border: 1px solid var(--surface2);
+    color: var(--text);
+    border-radius: 4px;
+    font-size: calc(11px * var(--font-scale));
+    font-family: inherit;
+  }}
+  .bookmark-text {{
+    flex: 1;
+    min-width: 0;
+    display: flex;
+    flex-direction: column;
+  }}
+  .bookmark-name {{
+    white-space: nowrap;
+    overflow: hidden;
+    text-overflow: ellipsis;
+  }}
+  .bookmark-folder {{
+    margin-left: 6px;
+    padding: 0 4px;
+    border-radius: 3px;
+    background: var(--surface0);
+    color: var(--subtext);
+    font-size: calc(10px * var(--font-scale));
+  }}
+  body.pane-focused {{
+    box-shadow: inset -2px 0 0 var(--accent);
+  }}
+  .streak-badge {{
+    flex-shrink: 0;
+    margin-left: 4px;
+    font-size: calc(10px * var(--font-scale));
+    color: var(--subtext);
+    opacity: 0.6;
+  }}
+  .streak-badge.today {{
+    color: var(--accent);
+    opacity: 1;
+  }}
+  .startup-mark {{
+    flex-shrink: 0;
+    margin-left: 4px;
+    font-size: calc(11px * var(--font-scale));
+    color: var(--accent);
+  }}
+  .context-menu {{
+    display: none;
+    position: fixed;
+    z-index: 200;
+    min-width: 160px;
+    padding: 4px;
+    background: var(--mantle);
+    border: 1px solid var(--surface1);
+    border-radius: 6px;
+    box-shadow: 0 4px 12px rgba(0, 0, 0, 0.3);
+  }}
+  .context-menu.active {{
+    display: block;
+  }}
+  .bookmark-url {{
+    font-size: calc(11px * var(--font-scale));
+    color: var(--subtext);
+    opacity: 0.7;
+    white-space: nowrap;
+    overflow: hidden;
+    text-overflow: ellipsis;
+  }}
+  .bookmark .delete-btn,
+  .bookmark .queue-btn {{
+    display: none;
+    background: none;
+    border: none;
+    color: var(--subtext);
+    cursor: pointer;
+    font-size: calc(14px * var(--font-scale));
+    padding: 0 4px;
+    line-height: 1;
+  }}
+  .bookmark:hover .delete-btn,
+  .bookmark:hover .queue-btn {{
+    display: inline;
+  }}
+  .bookmark .delete-btn:hover {{
+    color: var(--red);
+  }}
+  /* Keyboard users get the hover-only controls once focus is in the row */
+  .folder-header:focus-within .folder-actions {{
+    display: flex;
+  }}
+  .bookmark:focus-within .delete-btn,
+  .bookmark:focus-within .queue-btn {{
+    display: inline;
+  }}
+  .gallery-card:focus-within .thumb-btn {{
+    display: block;
+  }}
+  button:focus-visible,
+  select:focus-visible,
+  summary:focus-visible,
+  input[type="checkbox"]:focus-visible {{
+    outline: 2px solid var(--accent);
+    outline-offset: 1px;
+  }}
+  :root[data-theme="high_contrast"] .folder-header:focus,
+  :root[data-theme="high_contrast"] .bookmark:focus {{
+    outline: 2px solid var(--accent);
+    outline-offset: -2px;
+  }}
+  .bookmark .queue-btn:hover {{
+    color: var(--accent);
+  }}
+  .reading-queue {{
+    display: none;
+    align-items: center;
+    gap: 6px;
+    padding: 6px 8px;
+    font-size: calc(11px * var(--font-scale));
+    color: var(--subtext);
+    border-top: 1px solid var(--surface0);
+    flex-shrink: 0;
+  }}
+  .reading-queue.active {{
+    display: flex;
+  }}
+  .reading-queue-next {{
+    flex: 1;
+    white-space: nowrap;
+    overflow: hidden;
+    text-overflow: ellipsis;
+  }}
+  #selectionBar input {{
+    width: 70px;
+    padding: 2px 6px;
+    background: var(--surface0);
+    border: 1px solid var(--surface1);
+    border-radius: 4px;
+    color: var(--text);
+    font-size: calc(11px * var(--font-scale));
+    font-family: inherit;
+    outline: none;
+  }}
+  .bookmark.selected {{
+    background: var(--surface1);
+  }}
+  .bookmark.cut {{
+    opacity: 0.45;
+  }}
+  .bookmark-mnemonic {{
+    margin-left: 3px;
+    color: var(--accent);
+    font-family: monospace;
+    font-size: calc(9px * var(--font-scale));
+  }}
+  .bookmark-refresh {{
+    margin-left: 3px;
+    color: var(--subtext);
+    font-size: calc(10px * var(--font-scale));
+  }}
+  .bookmark-container {{
+    margin-left: 4px;
+    padding: 0 4px;
+    border: 1px solid var(--accent);
+    border-radius: 3px;
+    color: var(--accent);
+    font-size: calc(9px * var(--font-scale));
+  }}
+  .bookmarklet-badge {{
+    color: var(--yellow);
+    border-color: var(--yellow);
+  }}
+  .bookmark-tag {{
+    margin-left: 4px;
+    padding: 0 4px;
+    border-radius: 3px;
+    border: 1px solid var(--surface1);
+    color: var(--subtext);
+    font-size: calc(10px * var(--font-scale));
+  }}
+  .reading-queue button {{
+    background: var(--surface1);
+    border: 1px solid var(--surface2);
+    color: var(--text);
+    padding: 2px 8px;
+    border-radius: 4px;
+    cursor: pointer;
+    font-size: calc(11px * var(--font-scale));
+    font-family: inherit;
+  }}
+  .bottom-bar {{
+    display: flex;
+    background: var(--mantle);
+    border-top: 1px solid var(--surface0);
+    padding: 8px;
+    gap: 8px;
+    flex-shrink: 0;
+  }}
+  .bar-btn {{
+    flex: 1;
+    background: var(--surface1);
+    border: 1px solid var(--surface2);
+    color: var(--text);
+    padding: 6px 8px;
+    border-radius: 4px;
+    cursor: pointer;
+    font-size: calc(12px * var(--font-scale));
+    font-family: inherit;
+  }}
+  .bar-btn:hover {{
+    background: var(--surface2);
+    color: var(--text);
+  }}
+  .unread-badge {{
+    display: none;
+  }}
+  .unread-badge.visible {{
+    display: block;
+  }}
+  .unread-badge.filtering {{
+    border-color: var(--accent);
+    color: var(--accent);
+  }}
+  .bar-btn.dirty::after {{
+    content: ' \2022';
+    color: var(--accent);
+  }}
+  .modal-overlay {{
+    display: none;
+    position: fixed;
+    top: 0; left: 0; right: 0; bottom: 0;
+    background: var(--overlay);
+    z-index: 100;
+    align-items: center;
+    justify-content: center;
+  }}
+  .modal-overlay.active {{
+    display: flex;
+  }}
+  .modal {{
+    background: var(--base);
+    border: 1px solid var(--surface1);
+    border-radius: 8px;
+    padding: 20px;
+    width: 240px;
+  }}
+  .modal h3 {{
+    font-size: calc(14px * var(--font-scale));
+    margin-bottom: 12px;
+    color: var(--text);
+  }}
+  .modal label {{
+    display: block;
+    font-size: calc(12px * var(--font-scale));
+    color: var(--subtext);
+    margin-bottom: 4px;
+  }}
+  .modal input, .modal select {{
+    width: 100%;
+    padding: 6px 8px;
+    margin-bottom: 10px;
+    background: var(--surface0);
+    border: 1px solid var(--surface1);
+    border-radius: 4px;
+    color: var(--text);
+    font-size: calc(13px * var(--font-scale));
+    font-family: inherit;
+    outline: none;
+  }}
+  .modal select {{
+    appearance: none;
+    -webkit-appearance: none;
+    overflow: hidden;
+    text-overflow: ellipsis;
+    background-image: url("data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' width='12' height='12' viewBox='0 0 12 12'%3E%3Cpath fill='%23a6adc8' d='M6 8L1 3h10z'/%3E%3C/svg%3E");
+    background-repeat: no-repeat;
+    background-position: right 8px center;
+    padding-right: 28px;
+  }}
+  .modal select option {{
+    background: var(--surface0);
+    color: var(--text);
+  }}
+  .modal textarea {{
+    width: 100%;
+    padding: 6px 8px;
+    margin-bottom: 10px;
+    background: var(--surface0);
+    border: 1px solid var(--surface1);
+    border-radius: 4px;
+    color: var(--text);
+    font-family: monospace;
+    font-size: calc(12px * var(--font-scale));
+    resize: vertical;
+    outline: none;
+  }}
+  .modal details.advanced summary {{
+    font-size: calc(12px * var(--font-scale));
+    color: var(--subtext);
+    margin-bottom: 8px;
+    cursor: pointer;
+  }}
+  .modal input:focus, .modal select:focus, .modal textarea:focus {{
+    border-color: var(--accent);
+  }}
+  .token-test {{
+    display: none;
+    font-size: calc(12px * var(--font-scale));
+    margin-bottom: 10px;
+  }}
+  .token-test.active {{
+    display: block;
+  }}
+  .token-test.ok {{
+    color: var(--green);
+  }}
+  .token-test.fail {{
+    color: var(--red);
+  }}
+  .modal label.check-row {{
+    display: flex;
+    align-items: center;
+    gap: 6px;
+    margin-bottom: 8px;
+    cursor: pointer;
+  }}
+  .modal .check-row input {{
+    width: auto;
+    margin: 0;
+  }}
+  .settings-link {{
+    width: 100%;
+    margin-bottom: 10px;
+  }}
+  .settings-link.danger {{
+    color: var(--red);
+  }}
+  .btn-primary.danger {{
+    background: var(--red);
+  }}
+  .modal-note {{
+    font-size: calc(12px * var(--font-scale));
+    color: var(--subtext);
+    margin-bottom: 10px;
+    word-break: break-all;
+  }}
+  .modal-buttons {{
+    display: flex;
+    gap: 8px;
+    margin-top: 4px;
+  }}
+  .modal-buttons button {{
+    flex: 1;
+    padding: 6px 8px;
+    border: none;
+    border-radius: 4px;
+    cursor: pointer;
+    font-size: calc(13px * var(--font-scale));
+    font-family: inherit;
+  }}
+  .btn-cancel {{
+    background: var(--surface0);
+    color: var(--subtext);
+  }}
+  .btn-cancel:hover {{
+    background: var(--surface1);
+    color: var(--text);
+  }}
+  .btn-primary {{
+    background: var(--accent);
+    color: var(--base);
+    font-weight: 600;
+  }}
+  .btn-primary:hover {{
+    opacity: 0.9;
+  }}
+  .help-table {{
+    width: 100%;
+    margin-bottom: 12px;
+  }}
+  .help-table td {{
+    padding: 4px 0;
+    font-size: calc(13px * var(--font-scale));
+  }}
+  .help-key {{
+    color: var(--accent);
+    font-family: monospace;
+    padding-right: 12px;
+    white-space: nowrap;
+  }}
+  .sync-status {{
+    display: none;
+    padding: 6px 8px;
+    font-size: calc(11px * var(--font-scale));
+    border-top: 1px solid var(--surface0);
+    flex-shrink: 0;
+    text-align: center;
+    cursor: pointer;
+  }}
+  .sync-status.active {{
+    display: block;
+  }}
+  .sync-status.status-progress {{
+    color: var(--subtext);
+  }}
+  .sync-status.status-success {{
+    color: var(--green);
+  }}
+  .sync-status.status-error {{
+    color: var(--red);
+  }}
+  .recent-list {{
+    max-height: 260px;
+    overflow-y: auto;
+    margin-bottom: 12px;
+  }}
+  .dupe-entry {{
+    padding: 6px 8px;
+    border-bottom: 1px solid var(--surface1);
+  }}
+  .dupe-url {{
+    font-size: calc(13px * var(--font-scale));
+    margin-bottom: 4px;
+    overflow: hidden;
+    text-overflow: ellipsis;
+    white-space: nowrap;
+  }}
+  .title-change {{
+    display: flex;
+    gap: 8px;
+    align-items: flex-start;
+    padding: 6px 8px;
+    font-size: calc(12px * var(--font-scale));
+    border-bottom: 1px solid var(--surface1);
+  }}
+  .title-change .title-old {{
+    color: var(--overlay0);
+    text-decoration: line-through;
+  }}
+  .dupe-keep {{
+    margin: 0 4px 4px 0;
+    padding: 2px 8px;
+    font-size: calc(11px * var(--font-scale));
+  }}
+  .recent-item {{
+    padding: 6px 8px;
+    font-size: calc(13px * var(--font-scale));
+    border-radius: 4px;
+    cursor: pointer;
+    white-space: nowrap;
+    overflow: hidden;
+    text-overflow: ellipsis;
+  }}
+  .recent-item:hover,
+  .recent-item.selected {{
+    background: var(--surface0);
+    color: var(--accent);
+  }}
+  .recent-empty {{
+    font-size: calc(13px * var(--font-scale));
+    color: var(--subtext);
+    padding: 6px 0;
+  }}
+  .gallery-folder {{
+    padding: 8px 8px 4px;
+    font-size: calc(12px * var(--font-scale));
+    font-weight: 600;
+    color: var(--subtext);
+  }}
+  .gallery-grid {{
+    display: grid;
+    grid-template-columns: repeat(2, 1fr);
+    gap: 8px;
+    padding: 0 8px 8px;
+  }}
+  .gallery-card {{
+    position: relative;
+    background: var(--surface0);
+    border: 1px solid transparent;
+    border-radius: 6px;
+    overflow: hidden;
+    cursor: pointer;
+  }}
+  .gallery-card:hover,
+  .gallery-card.active {{
+    border-color: var(--accent);
+  }}
+  .gallery-card img,
+  .gallery-placeholder {{
+    display: block;
+    width: 100%;
+    aspect-ratio: 16 / 10;
+    object-fit: cover;
+  }}
+  .gallery-placeholder {{
+    display: flex;
+    align-items: center;
+    justify-content: center;
+    font-size: calc(28px * var(--font-scale));
+    color: var(--surface2);
+    background: var(--mantle);
+  }}
+  .gallery-name {{
+    display: block;
+    padding: 4px 6px;
+    font-size: calc(11px * var(--font-scale));
+    white-space: nowrap;
+    overflow: hidden;
+    text-overflow: ellipsis;
+  }}
+  .gallery-card .thumb-btn {{
+    display: none;
+    position: absolute;
+    top: 4px;
+    right: 4px;
+    background: var(--overlay);
+    border: none;
+    border-radius: 4px;
+    color: var(--text);
+    cursor: pointer;
+    font-size: calc(11px * var(--font-scale));
+    padding: 2px 5px;
+  }}
+  .gallery-card:hover .thumb-btn {{
+    display: block;
+  }}
+  .thumb-drop {{
+    border: 1px dashed var(--surface2);
+    border-radius: 6px;
+    padding: 16px;
+    margin-bottom: 12px;
+    text-align: center;
+    font-size: calc(12px * var(--font-scale));
+    color: var(--subtext);
+  }}
+  .loading-bar {{
+    position: fixed;
+    top: 0;
+    left: 0;
+    height: 2px;
+    width: 0;
+    background: var(--accent);
+    opacity: 0;
+    z-index: 50;
+    transition: opacity 0.3s;
+  }}
+  .loading-bar.active {{
+    opacity: 1;
+    animation: loading 1.2s ease-in-out infinite;
+  }}
+  @keyframes loading {{
+    0% {{ left: 0; width: 0; }}
+    50% {{ left: 20%; width: 60%; }}
+    100% {{ left: 100%; width: 0; }}
+  }}
+  /* Compact density */
+  body[data-density="compact"] #tree {{
+    padding: 4px 0;
+  }}
+  body[data-density="compact"] .folder-header {{
+    padding: 3px 10px;
+    font-size: calc(11px * var(--font-scale));
+  }}
+  body[data-density="compact"] .bookmark {{
+    padding: 3px 10px 3px 28px;
+    font-size: calc(13px * var(--font-scale));
+  }}
+  /* Collapsed sidebar mode */
+  #expandBtn {{
+    display: none;
+    background: none;
+    border: none;
+    color: var(--text);
+    font-size: calc(16px * var(--font-scale));
+    cursor: pointer;
+    width: 100%;
+    flex: 1;
+  }}
+  #expandBtn:hover {{
+    background: var(--surface0);
+    color: var(--accent);
+  }}
+  body.collapsed #tree,
+  body.collapsed .search-bar,
+  body.collapsed .bottom-bar,
+  body.collapsed .sync-status,
+  body.collapsed .modal-overlay {{
+    display: none !important;
+  }}
+  body.collapsed #expandBtn {{
+    display: block;
+  }}
+  body.collapsed {{
+    border-right: 1px solid var(--surface0);
+  }}
+</style>
+</head>
+<body class="{collapsed_class}" data-density="{density}" data-scrollbars="{scrollbars}">
+<div id="loadingBar" class="loading-bar"></div>
+<button id="expandBtn" onclick="expandSidebar()" title="Expand sidebar (Ctrl+B)">&raquo;</button>
+<div class="search-bar">
+  <input type="search" id="searchBox" placeholder="Filter bookmarks (Ctrl+F)" spellcheck="false" autocomplete="off">
+</div>
+<div id="healthBar" class="health-bar">
+  <select id="healthFilter" onchange="setHealthView()">
+    <option value="all">All links</option>
+    <option value="broken">Broken</option>
+    <option value="redirect">Redirected</option>
+    <option value="unchecked">Unchecked</option>
+    <option value="ok">OK</option>
+  </select>
+  <label><input type="checkbox" id="healthSort" onchange="setHealthView()"> Broken first</label>
+</div>
+<div id="tree"></div>
+<div id="selectionBar" class="reading-queue">
+  <span id="selectionCount" class="reading-queue-next"></span>
+  <input type="text" id="selectionTag" placeholder="Tag" spellcheck="false">
+  <button onclick="tagSelected(true)" title="Add the tag to every selected bookmark">Add tag</button>
+  <button onclick="tagSelected(false)" title="Remove the tag from every selected bookmark">Remove</button>
+  <button onclick="clearSelection()" title="Clear the selection (Escape)">&times;</button>
+</div>
+<div id="readingQueue" class="reading-queue">
+  <span id="readingQueueNext" class="reading-queue-next"></span>
+  <button onclick="openNextQueued()" title="Open next in queue (Ctrl+J)">Open next</button>
+</div>
+<div id="syncStatus" class="sync-status"></div>
+<div class="bottom-bar" style="flex-wrap:wrap;">
+  <button id="pushBtn" class="bar-btn{dirty_class}" onclick="pushToGitHub()" title="Push to GitHub (Ctrl+U)">&#x2191; Push</button>
+  <button class="bar-btn" onclick="pullFromGitHub()" title="Pull from GitHub (Ctrl+I)">&#x2193; Pull</button>
+  <button class="bar-btn" onclick="syncBoth()" title="Pull, merge and push (Ctrl+S)">&#x21C5; Sync</button>
+  <button class="bar-btn" onclick="showAddFolderModal()">+ Folder</button>
+  <button class="bar-btn" onclick="showSettingsModal()" title="Settings">&#x2699; Settings</button>
+  <button class="bar-btn" onclick="goHome()" title="Home page (Alt+Home)">&#x2302; Home</button>
+  <button class="bar-btn" onclick="showRecentModal()" title="Recently visited (Ctrl+Shift+T reopens the last one)">&#x21BA; Recent</button>
+  <button id="viewBtn" class="bar-btn" onclick="cycleViewMode()"></button>
+  <button id="unreadBadge" class="bar-btn unread-badge" onclick="toggleUnreadOnly()" title="Show only unopened bookmarks"></button>
+  <button class="bar-btn" onclick="showSortMenu(event)" title="Sort folders">&#x21C5; Sort</button>
+  <button class="bar-btn" onclick="showHelpModal()">? Help</button>
+  <button class="bar-btn" onclick="collapseSidebar()" title="Collapse sidebar (Ctrl+B)">&laquo;</button>
+</div>
+
+<div id="sortMenu" class="context-menu">
+  <div class="recent-item" onclick="sortFolders('name_asc')">Folders A&ndash;Z</div>
+  <div class="recent-item" onclick="sortFolders('name_desc')">Folders Z&ndash;A</div>
+  <div class="recent-item" onclick="sortFolders('bookmark_count')">Most bookmarks first</div>
+</div>
+
+<div id="bookmarkMenu" class="context-menu">
+  <div class="recent-item" onclick="showEditBookmarkModal()">Edit&hellip;</div>
+  <div id="startupToggle" class="recent-item" onclick="toggleOpenOnStartup()"></div>
+  <div class="recent-item" onclick="pinReference()" title="Keep this page open in a small frame over the content pane">Pin as reference</div>
+  <div id="closeReference" class="recent-item" onclick="closeReference()">Close reference</div>
+  <div class="recent-item" onclick="archiveBookmark()" title="Ask the Internet Archive to save a public copy of this page">Archive to Wayback Machine</div>
+  <div id="openArchived" class="recent-item" onclick="openArchived()">Open archived copy</div>
+  <div class="recent-item" onclick="copyBookmarkDebug()" title="Copy this bookmark's full record and local data as JSON, for a bug report">Copy debug info</div>
+</div>
+
+<div id="thumbnailOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Thumbnail</h3>
+    <div class="thumb-drop">Paste an image or screenshot (Ctrl+V), or choose a file</div>
+    <input type="file" id="thumbFile" accept="image/*" style="display:none" onchange="if (this.files[0]) sendThumbnail(this.files[0]); this.value = '';">
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="removeThumbnail()">Remove</button>
+      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
+      <button class="btn-primary" onclick="document.getElementById('thumbFile').click()">Choose file</button>
+    </div>
+  </div>
+</div>
+
+<div id="addBookmarkOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Add Bookmark</h3>
+    <label for="bmName">Name</label>
+    <input type="text" id="bmName" placeholder="Bookmark name">
+    <label for="bmUrl">URL</label>
+    <input type="text" id="bmUrl" placeholder="https://...">
+    <label for="bmFolder">Folder</label>
+    <select id="bmFolder"></select>
+    <label for="bmNotes">Notes</label>
+    <textarea id="bmNotes" rows="3" maxlength="{max_notes_chars}" placeholder="Optional"></textarea>
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
+      <button class="btn-primary" onclick="submitAddBookmark()">Add</button>
+    </div>
+  </div>
+</div>
+
+<div id="editBookmarkOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Edit Bookmark</h3>
+    <label for="editName">Name</label>
+    <input type="text" id="editName">
+    <label for="editUrl">URL</label>
+    <input type="text" id="editUrl">
+    <label for="editMnemonic">Mnemonic (Alt+G, then this key)</label>
+    <input type="text" id="editMnemonic" maxlength="1" placeholder="None" spellcheck="false" autocomplete="off">
+    <label for="editContainer">Container (own cookies and logins)</label>
+    <input type="text" id="editContainer" maxlength="32" placeholder="None" spellcheck="false" autocomplete="off">
+    <label for="editRefresh">Reload every (seconds, empty for never)</label>
+    <input type="number" id="editRefresh" min="{min_refresh_secs}" step="1" placeholder="Never">
+    <label for="editNotes">Notes</label>
+    <textarea id="editNotes" rows="3" maxlength="{max_notes_chars}" placeholder="None"></textarea>
+    <details id="editAdvanced" class="advanced">
+      <summary>Advanced: inject CSS / JavaScript</summary>
+      <p class="modal-note">Applied each time this bookmark's page finishes loading. A script runs with the site's own access: it can read and change anything on the page, including what you type there. Only paste code you understand. Both stay on this computer and are never synced.</p>
+      <label for="editCss">CSS</label>
+      <textarea id="editCss" rows="4" spellcheck="false" placeholder="body {{ background: #1e1e2e; }}"></textarea>
+      <label for="editJs">JavaScript</label>
+      <textarea id="editJs" rows="4" spellcheck="false" placeholder="document.querySelector('.banner')?.remove();"></textarea>
+    </details>
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
+      <button class="btn-primary" onclick="submitEditBookmark()">Save</button>
+    </div>
+  </div>
+</div>
+
+<div id="addFolderOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3 id="folderModalTitle">Add Folder</h3>
+    <div id="folderNameField">
+      <label for="folderName">Name</label>
+      <input type="text" id="folderName" placeholder="Folder name">
+    </div>
+    <label>Icon</label>
+    <div id="iconGrid" class="icon-grid"></div>
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
+      <button class="btn-primary" id="folderSubmitBtn" onclick="submitAddFolder()">Create</button>
+    </div>
+  </div>
+</div>
+
+<div id="scratchpadOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Scratchpad</h3>
+    <textarea id="scratchpadText" rows="14" spellcheck="true" placeholder="Notes for this computer; saved as you type, never synced"></textarea>
+    <div class="modal-buttons">
+      <button class="btn-primary" onclick="closeModals()" style="flex:1">Close</button>
+    </div>
+  </div>
+</div>
+
+<div id="helpOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Keyboard Shortcuts</h3>
+    <table class="help-table">
+{help_rows}
+    </table>
+    <div class="modal-buttons">
+      <button class="btn-primary" onclick="closeModals()" style="flex:1">Close</button>
+    </div>
+  </div>
+</div>
+
+<div id="authOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Sign In</h3>
+    <p id="authOrigin" class="modal-note"></p>
+    <label for="authUser">Username</label>
+    <input type="text" id="authUser" autocomplete="username">
+    <label for="authPass">Password</label>
+    <input type="password" id="authPass" autocomplete="current-password">
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
+      <button class="btn-primary" onclick="submitAuth()">Sign In</button>
+    </div>
+  </div>
+</div>
+
+<div id="backupsOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Restore from Backup</h3>
+    <div id="backupList" class="recent-list"></div>
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="closeModals()" style="flex:1">Close</button>
+    </div>
+  </div>
+</div>
+
+<div id="validationOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Library Check</h3>
+    <p id="validationNote" class="modal-note"></p>
+    <div id="validationList" class="recent-list"></div>
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="closeModals()">Close</button>
+      <button id="validationFix" class="btn-primary" onclick="fixLibrary()">Fix safe issues</button>
+    </div>
+  </div>
+</div>
+
+<div id="titlesOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Refresh Titles</h3>
+    <p id="titlesNote" class="modal-note"></p>
+    <div id="titlesList" class="recent-list"></div>
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
+      <button id="titlesApply" class="btn-primary" onclick="applyTitles()">Rename</button>
+    </div>
+  </div>
+</div>
+
+<div id="syncHealthOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Sync Health</h3>
+    <table id="syncHealthTable" class="help-table"></table>
+    <div id="syncHealthTest" class="token-test"></div>
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="testSyncHealth()" title="Look up bookmarks.json in the repository with the saved settings">Run test</button>
+      <button class="btn-primary" onclick="closeModals()">Close</button>
+    </div>
+  </div>
+</div>
+
+<div id="crossDupesOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Duplicates Across Folders</h3>
+    <p class="modal-note">Each of these is saved in more than one folder. Pick the folder to keep it in; the copies elsewhere are deleted.</p>
+    <div id="crossDupesList" class="recent-list"></div>
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="closeModals()" style="flex:1">Close</button>
+    </div>
+  </div>
+</div>
+
+<div id="recentOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Recently Visited</h3>
+    <div id="recentList" class="recent-list"></div>
+    <div class="modal-buttons">
+      <button class="btn-primary" onclick="closeModals()" style="flex:1">Close</button>
+    </div>
+  </div>
+</div>
+
+<div id="omnibarOverlay" class="modal-overlay">
+  <div class="modal">
+    <input type="text" id="omnibarInput" placeholder="Search bookmarks, enter a URL, or search the web" oninput="updateOmnibar()" onkeydown="omnibarKey(event)">
+    <div id="omnibarList" class="recent-list"></div>
+  </div>
+</div>
+
+<div id="replaceOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Find &amp; Replace URLs</h3>
+    <label for="replaceFind">Find</label>
+    <input type="text" id="replaceFind" placeholder="old.example.com" oninput="requestReplacePreview()">
+    <label for="replaceWith">Replace with</label>
+    <input type="text" id="replaceWith" placeholder="new.example.com" oninput="requestReplacePreview()">
+    <label class="check-row"><input type="checkbox" id="replaceRegex" onchange="requestReplacePreview()"> Regular expression ($1 refers to groups)</label>
+    <div id="replacePreview" class="recent-list"></div>
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
+      <button id="replaceApply" class="btn-primary" onclick="submitReplaceUrls()" disabled>Replace</button>
+    </div>
+  </div>
+</div>
+
+<div id="resetOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Reset Library</h3>
+    <p class="modal-note">This removes every folder and bookmark, local-only ones included. A backup is saved first; bring it back with Settings &rarr; Import bookmarks&hellip;</p>
+    <label for="resetConfirm">Type DELETE to confirm</label>
+    <input type="text" id="resetConfirm" autocomplete="off" oninput="updateResetButton()">
+    <label class="check-row"><input type="checkbox" id="resetPush"> Also push the empty library to GitHub</label>
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
+      <button id="resetApply" class="btn-primary danger" onclick="submitResetLibrary()" disabled>Reset</button>
+    </div>
+  </div>
+</div>
+
+<div id="pinOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3 id="pinTitle">Unlock Folder</h3>
+    <p id="pinNote" class="modal-note"></p>
+    <label for="pinInput">PIN</label>
+    <input type="password" id="pinInput" autocomplete="off">
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
+      <button id="pinSubmit" class="btn-primary" onclick="submitPin()">Unlock</button>
+    </div>
+  </div>
+</div>
+
+<div id="settingsOverlay" class="modal-overlay">
+  <div class="modal">
+    <h3>Settings</h3>
+    <label for="syncProvider">Sync with</label>
+    <select id="syncProvider" onchange="updateProviderFields()">
+      <option value="github">GitHub</option>
+      <option value="gitea">Gitea / Forgejo (self-hosted)</option>
+    </select>
+    <div id="giteaFields" style="display:none">
+      <label for="giteaBaseUrl">Server address</label>
+      <input type="text" id="giteaBaseUrl" placeholder="https://git.example.com">
+    </div>
+    <label for="ghToken" id="ghTokenLabel">GitHub Personal Access Token</label>
+    <input type="password" id="ghToken" placeholder="ghp_...">
+    <label for="ghRepo">Repository (owner/repo)</label>
+    <input type="text" id="ghRepo" placeholder="username/my-bookmarks">
+    <label for="density">Sidebar density</label>
+    <select id="density">
+      <option value="comfortable">Comfortable</option>
+      <option value="compact">Compact</option>
+    </select>
+    <label for="scrollbars">Scrollbars</label>
+    <select id="scrollbars">
+      <option value="always">Always visible</option>
+      <option value="overlay">Only while hovering the list</option>
+    </select>
+    <label for="theme">Theme</label>
+    <select id="theme">
+      <option value="auto">Match system</option>
+      <option value="dark">Dark</option>
+      <option value="light">Light</option>
+      <option value="high_contrast">High contrast</option>
+    </select>
+    <label for="fontScale">Text size</label>
+    <select id="fontScale">
+      <option value="1">100%</option>
+      <option value="1.15">115%</option>
+      <option value="1.3">130%</option>
+      <option value="1.5">150%</option>
+      <option value="1.75">175%</option>
+      <option value="2">200%</option>
+    </select>
+    <label for="recentlyAdded">Recently added, above the tree</label>
+    <select id="recentlyAdded">
+      <option value="0">Don't show</option>
+      <option value="3">3 bookmarks</option>
+      <option value="5">5 bookmarks</option>
+      <option value="10">10 bookmarks</option>
+    </select>
+    <label for="newFolderPosition">New folders</label>
+    <select id="newFolderPosition">
+      <option value="bottom">Add at the bottom</option>
+      <option value="top">Add at the top</option>
+    </select>
+    <label for="newBookmarkPosition">New bookmarks</label>
+    <select id="newBookmarkPosition">
+      <option value="bottom">Add at the end of the folder</option>
+      <option value="top">Add at the start of the folder</option>
+    </select>
+    <label for="duplicateStrategy">Importing a URL you already have</label>
+    <select id="duplicateStrategy">
+      <option value="skip">Skip it</option>
+      <option value="keep_both">Add it anyway</option>
+      <option value="update_name">Keep one, with the imported name</option>
+    </select>
+    <label for="closeAction">Closing the window</label>
+    <select id="closeAction">
+      <option value="quit">Quits the app</option>
+      <option value="minimize">Minimizes it (Ctrl+Q still quits)</option>
+    </select>
+    <label for="onDuplicateFolder">New folder with a name already in use</label>
+    <select id="onDuplicateFolder">
+      <option value="suffix">Number it, e.g. News (2)</option>
+      <option value="reject">Don't create it</option>
+    </select>
+    <label for="autoSyncMode">Auto-sync</label>
+    <select id="autoSyncMode">
+      <option value="off">Off</option>
+      <option value="push">Push after each change</option>
+      <option value="push_pull">Pull on startup, push after each change</option>
+    </select>
+    <label class="check-row"><input type="checkbox" id="showPullChanges"> After a pull, say what changed</label>
+    <label class="check-row"><input type="checkbox" id="prettyJson"> Push bookmarks.json indented (off: minified)</label>
+    <label class="check-row"><input type="checkbox" id="syncOnFocus"> Pull when the window regains focus (at most every 2 minutes)</label>
+    <label class="check-row"><input type="checkbox" id="showUrls"> Show URLs under bookmark names</label>
+    <label class="check-row"><input type="checkbox" id="showUnreadBadge"> Show how many bookmarks haven't been opened yet</label>
+    <label class="check-row"><input type="checkbox" id="autoExpandActive"> Expand the folder of the bookmark being shown</label>
+    <label class="check-row"><input type="checkbox" id="decorations"> Window title bar and borders</label>
+    <label class="check-row"><input type="checkbox" id="alwaysOnTop"> Keep window on top</label>
+    <label class="check-row"><input type="checkbox" id="confirmFormLeave"> Ask before leaving a page with typed-in form input</label>
+    <label class="check-row"><input type="checkbox" id="runBookmarklets"> Run bookmarklets (javascript: bookmarks) on the current page</label>
+    <label class="check-row"><input type="checkbox" id="blockTrackers"> Block ads and trackers on the blocklist (after a restart)</label>
+    <label for="homeUrl">Home page</label>
+    <input type="text" id="homeUrl" placeholder="Empty for the welcome page">
+    <div id="tokenTestResult" class="token-test"></div>
+    <button class="bar-btn settings-link" onclick="listBackups()">Restore from backup&hellip;</button>
+    <button class="bar-btn settings-link" onclick="chooseImportFile()" title="Bookmarks JSON, a flat bookmark list or OPML">Import bookmarks&hellip;</button>
+    <button class="bar-btn settings-link" onclick="exportFlatJson()" title="A flat list of {{title, url, folder, tags}} for other tools">Export bookmarks&hellip;</button>
+    <button class="bar-btn settings-link" onclick="exportHtml()" title="One self-contained page of links for reading or sharing">Export as web page&hellip;</button>
+    <input type="file" id="importFile" accept=".json,.opml,.xml,.html,.htm,.csv" style="display:none" onchange="importSelectedFile(this)">
+    <button class="bar-btn settings-link" onclick="checkLinks()" title="Request every bookmark and mark the ones that fail">Check links</button>
+    <button class="bar-btn settings-link" onclick="refreshTitles()" title="Fetch each page's current title and review the renames; names you typed are kept">Refresh titles&hellip;</button>
+    <button class="bar-btn settings-link" onclick="showReplaceModal()">Find &amp; replace URLs&hellip;</button>
+    <button class="bar-btn settings-link" onclick="removeDuplicates()" title="Keep the first bookmark for each URL">Remove duplicates&hellip;</button>
+    <label class="check-row"><input type="checkbox" id="dedupeGlobal"> Across all folders, not just within each</label>
+    <button class="bar-btn settings-link" onclick="validateLibrary()" title="Look for empty names, broken or repeated URLs and leftover data">Validate library&hellip;</button>
+    <button class="bar-btn settings-link" onclick="findCrossDuplicates()" title="URLs saved in more than one folder, to keep in one place">Duplicates across folders&hellip;</button>
+    <button class="bar-btn settings-link" onclick="exportDiagnostics()" title="Write a report for bug reports; the token is never included">Export diagnostics&hellip;</button>
+    <button class="bar-btn settings-link" onclick="showSyncHealthModal()" title="Last sync, last error and a live check of the repository">Sync health&hellip;</button>
+    <label class="check-row"><input type="checkbox" id="diagIncludeRepo"> Include repository name in diagnostics and sync health</label>
+    <button class="bar-btn settings-link danger" onclick="showResetModal()" title="Remove every folder and bookmark, after saving a backup">Reset library&hellip;</button>
+    <button class="bar-btn settings-link" onclick="openConfigDir()" title="bookmarks.json, settings.json and backups live here">Open config folder</button>
+    <div class="modal-buttons">
+      <button class="btn-cancel" onclick="testToken()" title="Check the token and repository without saving">Test connection</button>
+      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
+      <button class="btn-primary" onclick="submitSaveSettings()">Save</button>
+    </div>
+  </div>
+</div>
+
+<script>
+  let folders = {folders_json};
+  let activeUrl = null;
+  let activeModal = null;
+  let showUrls = {show_urls};
+  let showUnreadBadge = {show_unread_badge};
+  // False when history_retention_days is 0: with no visit log nothing counts as read
+  const visitsTracked = {visits_tracked};
+
+  // Link-check results keyed by URL: {{ class, label }}
+  let linkHealth = {health_json};
+  let healthFilter = 'all';
+  let healthSort = false;
+  const HEALTH_RANK = {{ broken: 0, redirect: 1, unchecked: 2, ok: 3 }};
+
+  function healthOf(url) {{
+    return linkHealth[url] || {{ class: 'unchecked', label: 'Not checked' }};
+  }}
+
+  // Visit streaks keyed by URL: {{ today, streak }}; sent by the host
+  let visits = {{}};
+
+  function updateVisits(data) {{
+    visits = data;
+    renderBookmarks(folders);
+  }}
+
+  // Unread means not opened within the visit log's retention window
+  let unreadOnly = false;
+
+  function isUnread(bm) {{
+    return !visits[bm.url];
+  }}
+
+  function toggleUnreadOnly() {{
+    unreadOnly = !unreadOnly;
+    renderBookmarks(folders);
+  }}
+
+  function updateUnreadBadge() {{
+    const badge = document.getElementById('unreadBadge');
+    const enabled = showUnreadBadge && visitsTracked;
+    badge.classList.toggle('visible', enabled);
+    if (!enabled) {{
+      unreadOnly = false;
+      return;
+    }}
+    let count = 0;
+    folders.forEach(function(folder) {{
+      if (!isHidden(folder)) count += folder.bookmarks.filter(isUnread).length;
+    }});
+    badge.textContent = count === 0 ? '\u2713 All read' : '\u25CF ' + count + ' unread';
+    badge.classList.toggle('filtering', unreadOnly);
+    badge.title = unreadOnly ? 'Show all bookmarks' : 'Show only unopened bookmarks';
+  }}
+
+  function hasHealth() {{
+    return Object.keys(linkHealth).length > 0;
+  }}
+
+  function setHealthView() {{
+    healthFilter = document.getElementById('healthFilter').value;
+    healthSort = document.getElementById('healthSort').checked;
+    renderBookmarks(folders);
+  }}
+
+  function updateHealth(health) {{
+    linkHealth = health;
+    document.getElementById('healthBar').classList.toggle('active', hasHealth());
+    renderBookmarks(folders);
+  }}
+
+  function checkLinks() {{
+    closeModals();
+    window.ipc.postMessage(JSON.stringify({{ action: 'check_links' }}));
+  }}
+
+  const THUMBNAIL_BASE = '{thumbnail_base}';
+  // Experimental UI is switched on with BOOKMARKS_EXPERIMENTS or "experiments" in settings.json
+  const EXPERIMENTS = {experiments_json};
+  function experimentEnabled(name) {{
+    return EXPERIMENTS.includes(name);
+  }}
+
+  const VIEW_MODES = ['tree', 'flat', 'changed', 'gallery'];
+  const VIEW_LABELS = {{ tree: '&#x2630; Tree', flat: '&#x2261; List', changed: '&#x270E; Changed', gallery: '&#x25A6; Gallery' }};
+  let viewMode = '{view_mode}';
+  let thumbTarget = null;
+
+  // Cycles tree -> flat list -> gallery; the host remembers the choice
+  function cycleViewMode() {{
+    viewMode = VIEW_MODES[(VIEW_MODES.indexOf(viewMode) + 1) % VIEW_MODES.length];
+    updateViewButton();
+    window.ipc.postMessage(JSON.stringify({{ action: 'set_view_mode', mode: viewMode }}));
+    renderBookmarks(folders);
+  }}
+
+  function updateViewButton() {{
+    const next = VIEW_MODES[(VIEW_MODES.indexOf(viewMode) + 1) % VIEW_MODES.length];
+    const btn = document.getElementById('viewBtn');
+    btn.innerHTML = VIEW_LABELS[viewMode];
+    btn.title = 'Switch to ' + next + ' view';
+  }}
+
+  // Cards with thumbnails, grouped by folder; an alternative to the tree
+  function renderGallery(tree) {{
+    folders.forEach(function(folder, fi) {{
+      if (isHidden(folder) || !folder.bookmarks.some(matchesSearch)) return;
+      const heading = document.createElement('div');
+      heading.className = 'gallery-folder';
+      heading.textContent = (folder.icon ? folder.icon + ' ' : '') + folder.name;
+      tree.appendChild(heading);
+
+      const grid = document.createElement('div');
+      grid.className = 'gallery-grid';
+      folder.bookmarks.forEach(function(bm, bi) {{
+        if (!matchesSearch(bm)) return;
+        const card = document.createElement('div');
+        card.className = 'gallery-card' + (bm.url === activeUrl ? ' active' : '');
+        card.title = bm.url;
+        card.onclick = function(e) {{
+          if (e.ctrlKey) queueBookmark(bm.url);
+          else navigate(bm.url);
+        }};
+        card.onmousedown = function(e) {{ if (e.button === 1) e.preventDefault(); }};
+        card.onauxclick = function(e) {{
+          if (e.button !== 1) return;
+          e.preventDefault();
+          openExternal(bm.url);
+        }};
+
+        let preview;
+        if (bm.thumbnail) {{
+          preview = document.createElement('img');
+          preview.src = THUMBNAIL_BASE + bm.thumbnail;
+          preview.alt = '';
+        }} else {{
+          preview = document.createElement('div');
+          preview.className = 'gallery-placeholder';
+          preview.textContent = (bm.name.trim()[0] || '?').toUpperCase();
+        }}
+
+        const name = document.createElement('span');
+        name.className = 'gallery-name';
+        name.textContent = bm.name;
+
+        const thumbBtn = document.createElement('button');
+        thumbBtn.className = 'thumb-btn';
+        thumbBtn.textContent = '\u{{1F5BC}}';
+        thumbBtn.title = 'Set thumbnail';
+        thumbBtn.onclick = function(e) {{ e.stopPropagation(); showThumbnailModal(fi, bi); }};
+
+        card.appendChild(preview);
+        card.appendChild(name);
+        card.appendChild(thumbBtn);
+        grid.appendChild(card);
+      }});
+      tree.appendChild(grid);
+    }});
+  }}
+
+  function showThumbnailModal(fi, bi) {{
+    closeModals();
+    thumbTarget = {{ folder_index: fi, bookmark_index: bi }};
+    document.getElementById('thumbnailOverlay').classList.add('active');
+    activeModal = 'thumbnail';
+  }}
+
+  // Scales the image down before handing it to the host, which stores it as is
+  function sendThumbnail(blob) {{
+    const target = thumbTarget;
+    const img = new Image();
+    img.onload = function() {{
+      const scale = Math.min(1, 480 / img.width);
+      const canvas = document.createElement('canvas');
+      canvas.width = Math.round(img.width * scale);
+      canvas.height = Math.round(img.height * scale);
+      canvas.getContext('2d').drawImage(img, 0, 0, canvas.width, canvas.height);
+      URL.revokeObjectURL(img.src);
+      window.ipc.postMessage(JSON.stringify({{
+        action: 'set_thumbnail',
+        folder_index: target.folder_index,
+        bookmark_index: target.bookmark_index,
+        image: canvas.toDataURL('image/jpeg', 0.85)
+      }}));
+    }};
+    img.src = URL.createObjectURL(blob);
+    closeModals();
+  }}
+
+  function removeThumbnail() {{
+    window.ipc.postMessage(JSON.stringify({{
+      action: 'set_thumbnail',
+      folder_index: thumbTarget.folder_index,
+      bookmark_index: thumbTarget.bookmark_index,
+      image: null
+    }}));
+    closeModals();
+  }}
+
+  document.addEventListener('paste', function(e) {{
+    if (activeModal !== 'thumbnail') return;
+    const items = e.clipboardData ? e.clipboardData.items : [];
+    for (const item of items) {{
+      if (item.type.startsWith('image/')) {{
+        e.preventDefault();
+        sendThumbnail(item.getAsFile());
+        return;
+      }}
+    }}
+  }});
+
+  // One sidebar row; folderTag names the folder in the flat view, else null
+  // Shift+Click selects bookmarks for tagging. Keyed by folder and URL, not
+  // position, so a move or delete elsewhere can't shift the selection onto
+  // a different bookmark
+  const selection = new Set();
+
+  function selectionKey(fi, bm) {{
+    return fi + ' ' + bm.url;
+  }}
+
+  function toggleSelected(fi, bm, row) {{
+    const key = selectionKey(fi, bm);
+    if (selection.has(key)) selection.delete(key);
+    else selection.add(key);
+    row.classList.toggle('selected', selection.has(key));
+    updateSelectionBar();
+  }}
+
+  function clearSelection() {{
+    selection.clear();
+    document.querySelectorAll('#tree .bookmark.selected').forEach(function(row) {{
+      row.classList.remove('selected');
+    }});
+    updateSelectionBar();
+  }}
+
+  function updateSelectionBar() {{
+    document.getElementById('selectionBar').classList.toggle('active', selection.size > 0);
+    document.getElementById('selectionCount').textContent = selection.size + ' selected';
+  }}
+
+  function tagSelected(add) {{
+    const tag = document.getElementById('selectionTag').value.trim();
+    if (!tag) {{
+      document.getElementById('selectionTag').focus();
+      return;
+    }}
+    const targets = [];
+    folders.forEach(function(folder, fi) {{
+      folder.bookmarks.forEach(function(bm, bi) {{
+        if (selection.has(selectionKey(fi, bm))) targets.push([fi, bi]);
+      }});
+    }});
+    window.ipc.postMessage(JSON.stringify({{ action: 'tag_bookmarks', targets: targets, tag: tag, add: add }}));
+    clearSelection();
+  }}
+
+  function bookmarkRow(bm, fi, bi, folderTag) {{
+    const link = document.createElement('div');
+    link.className = 'bookmark' + (bm.url === activeUrl ? ' active' : '');
+    link.title = bm.notes ? bm.url + '\n\n' + bm.notes : bm.url;
+    link.tabIndex = 0;
+    link.dataset.key = 'b' + fi + ':' + bi;
+    if (selection.has(selectionKey(fi, bm))) link.classList.add('selected');
+    if (clipboardItem && clipboardItem[0] === fi && clipboardItem[1] === bi) link.classList.add('cut');
+    link.onclick = function(e) {{
+      if (e.shiftKey) toggleSelected(fi, bm, link);
+      else if (e.ctrlKey) queueBookmark(bm.url);
+      else navigate(bm.url);
+    }};
+    link.onfocus = function() {{ focusKey = link.dataset.key; }};
+    // Middle click opens in the system browser, like a new tab would
+    link.onmousedown = function(e) {{ if (e.button === 1) e.preventDefault(); }};
+    link.onauxclick = function(e) {{
+      if (e.button !== 1) return;
+      e.preventDefault();
+      openExternal(bm.url);
+    }};
+    link.oncontextmenu = function(e) {{
+      e.preventDefault();
+      showBookmarkMenu(fi, bi, e.clientX, e.clientY);
+    }};
+
+    const health = healthOf(bm.url);
+    const dot = document.createElement('span');
+    dot.className = 'health-dot ' + health.class;
+    dot.title = health.label;
+
+    const bmText = document.createElement('span');
+    bmText.className = 'bookmark-text';
+
+    const bmName = document.createElement('span');
+    bmName.className = 'bookmark-name';
+    bmName.textContent = bm.name;
+    bmText.appendChild(bmName);
+
+    if (showUrls) {{
+      const bmUrl = document.createElement('span');
+      bmUrl.className = 'bookmark-url';
+      bmUrl.textContent = bm.url;
+      bmText.appendChild(bmUrl);
+    }}
+
+    const bmDel = document.createElement('button');
+    bmDel.className = 'delete-btn';
+    bmDel.textContent = '\u00D7';
+    bmDel.title = 'Delete bookmark';
+    bmDel.onclick = function(e) {{ e.stopPropagation(); deleteBookmark(fi, bi); }};
+
+    const bmQueue = document.createElement('button');
+    bmQueue.className = 'queue-btn';
+    bmQueue.textContent = '+';
+    bmQueue.title = 'Read later (Ctrl+Click)';
+    bmQueue.onclick = function(e) {{ e.stopPropagation(); queueBookmark(bm.url); }};
+
+    if (folderTag !== null) {{
+      const tag = document.createElement('span');
+      tag.className = 'bookmark-folder';
+      tag.textContent = folderTag;
+      bmName.appendChild(tag);
+    }}
+    if (bm.mnemonic) {{
+      const key = document.createElement('sup');
+      key.className = 'bookmark-mnemonic';
+      key.textContent = bm.mnemonic;
+      key.title = 'Alt+G, ' + bm.mnemonic;
+      bmName.appendChild(key);
+    }}
+    if (bm.refresh_secs) {{
+      const timer = document.createElement('span');
+      timer.className = 'bookmark-refresh';
+      timer.textContent = '\u27F3';
+      timer.title = 'Reloads every ' + bm.refresh_secs + ' s while open';
+      bmName.appendChild(timer);
+    }}
+    if (bm.container) {{
+      const box = document.createElement('span');
+      box.className = 'bookmark-container';
+      box.textContent = bm.container;
+      box.title = 'Opens in the ' + bm.container + ' container';
+      bmName.appendChild(box);
+    }}
+    (bm.tags || []).forEach(function(tagName) {{
+      const tag = document.createElement('span');
+      tag.className = 'bookmark-tag';
+      tag.textContent = tagName;
+      bmName.appendChild(tag);
+    }});
+
+    if (isBookmarklet(bm.url)) {{
+      link.classList.add('bookmarklet');
+      link.title = 'Bookmarklet \u2014 runs on the current page';
+      const badge = document.createElement('span');
+      badge.className = 'bookmark-tag bookmarklet-badge';
+      badge.textContent = 'JS';
+      bmName.appendChild(badge);
+    }}
+
+    if (hasHealth()) link.appendChild(dot);
+    link.appendChild(bmText);
+    const visit = visits[bm.url];
+    if (visit && (visit.streak >= 2 || visit.today)) {{
+      const badge = document.createElement('span');
+      badge.className = 'streak-badge' + (visit.today ? ' today' : '');
+      badge.textContent = visit.streak >= 2 ? '\u{{1F525}}' + visit.streak : '\u2022';
+      badge.title = (visit.today ? 'Opened today' : 'Not opened yet today') +
+        (visit.streak >= 2 ? ' \u2014 ' + visit.streak + '-day streak' : '');
+      link.appendChild(badge);
+    }}
+    if (bm.open_on_startup) {{
+      const mark = document.createElement('span');
+      mark.className = 'startup-mark';
+      mark.textContent = '\u2605';
+      mark.title = 'Opens on startup';
+      link.appendChild(mark);
+    }}
+    link.appendChild(bmQueue);
+    link.appendChild(bmDel);
+    return link;
+  }}
+
+  // Every bookmark in one list sorted by name, folder shown as a tag
+  function renderFlat(tree) {{
+    let entries = [];
+    folders.forEach(function(folder, fi) {{
+      if (isHidden(folder)) return;
+      folder.bookmarks.forEach(function(bm, bi) {{
+        if (matchesSearch(bm)) entries.push([bm, fi, bi]);
+      }});
+    }});
+    if (healthFilter !== 'all') {{
+      entries = entries.filter(function(entry) {{ return healthOf(entry[0].url).class === healthFilter; }});
+    }}
+    if (unreadOnly) entries = entries.filter(function(entry) {{ return isUnread(entry[0]); }});
+    entries.sort(function(a, b) {{
+      if (healthSort) {{
+        const rank = HEALTH_RANK[healthOf(a[0].url).class] - HEALTH_RANK[healthOf(b[0].url).class];
+        if (rank !== 0) return rank;
+      }}
+      return a[0].name.localeCompare(b[0].name, undefined, {{ sensitivity: 'base' }});
+    }});
+    entries.forEach(function(entry) {{
+      const folder = folders[entry[1]];
+      const tag = (folder.icon ? folder.icon + ' ' : '') + folder.name;
+      tree.appendChild(bookmarkRow(entry[0], entry[1], entry[2], tag));
+    }});
+  }}
+
+  // Bookmarks whose name or URL was edited, most recent first, each tagged
+  // with its folder and the day it changed
+  function renderChanged(tree) {{
+    const entries = [];
+    folders.forEach(function(folder, fi) {{
+      if (isHidden(folder)) return;
+      folder.bookmarks.forEach(function(bm, bi) {{
+        if (bm.modified && matchesSearch(bm)) entries.push([bm, fi, bi]);
+      }});
+    }});
+    // "YYYY-MM-DD HH:MM UTC" sorts correctly as text
+    entries.sort(function(a, b) {{ return b[0].modified.localeCompare(a[0].modified); }});
+    if (entries.length === 0) {{
+      const empty = document.createElement('div');
+      empty.className = 'modal-note';
+      empty.style.padding = '12px';
+      empty.textContent = 'No edited bookmarks yet. Right-click a bookmark and choose Edit to change it.';
+      tree.appendChild(empty);
+      return;
+    }}
+    entries.forEach(function(entry) {{
+      const folder = folders[entry[1]];
+      const tag = folder.name + ' \u00B7 ' + entry[0].modified.slice(0, 10);
+      tree.appendChild(bookmarkRow(entry[0], entry[1], entry[2], tag));
+    }});
+  }}
+
+  // Sidebar filter: every word has to appear in the name, URL or a tag
+  let searchQuery = '';
+
+  function matchesSearch(bm) {{
+    if (!searchQuery) return true;
+    const text = (bm.name + ' ' + bm.url + ' ' + (bm.tags || []).join(' ') + ' ' + (bm.notes || '')).toLowerCase();
+    return searchQuery.split(/\s+/).every(function(word) {{ return text.indexOf(word) !== -1; }});
+  }}
+
+  function focusSearch() {{
+    if (activeModal) return;
+    if (document.body.classList.contains('collapsed')) expandSidebar();
+    closeModals();
+    const box = document.getElementById('searchBox');
+    box.focus();
+    box.select();
+  }}
+
+  function clearSearch() {{
+    const box = document.getElementById('searchBox');
+    box.value = '';
+    box.blur();
+    if (searchQuery) {{
+      searchQuery = '';
+      renderBookmarks(folders);
+    }}
+  }}
+
+  document.getElementById('searchBox').addEventListener('input', function(e) {{
+    searchQuery = e.target.value.trim().toLowerCase();
+    renderBookmarks(folders);
+  }});
+
+  // The newest bookmarks across all folders, above the tree. Rows are keyed
+  // 'r…' so focus and jumps still find the copy inside its folder.
+  let recentlyAddedOpen = true;
+
+  function renderRecentlyAdded(tree) {{
+    if (savedRecentlyAdded <= 0) return;
+    const entries = [];
+    folders.forEach(function(folder, fi) {{
+      if (isHidden(folder)) return;
+      folder.bookmarks.forEach(function(bm, bi) {{
+        if (bm.created) entries.push([bm, fi, bi]);
+      }});
+    }});
+    if (entries.length === 0) return;
+    // "YYYY-MM-DD HH:MM UTC" sorts correctly as text
+    entries.sort(function(a, b) {{ return b[0].created.localeCompare(a[0].created); }});
+
+    const header = document.createElement('div');
+    header.className = 'folder-header recently-added';
+    header.tabIndex = 0;
+    header.dataset.key = 'r';
+    header.onclick = function() {{
+      recentlyAddedOpen = !recentlyAddedOpen;
+      renderBookmarks(folders);
+    }};
+    header.onfocus = function() {{ focusKey = header.dataset.key; }};
+    const arrow = document.createElement('span');
+    arrow.className = 'folder-arrow';
+    arrow.textContent = recentlyAddedOpen ? '\u25BC' : '\u25B6';
+    const name = document.createElement('span');
+    name.className = 'folder-name';
+    name.textContent = 'Recently added';
+    header.appendChild(arrow);
+    header.appendChild(name);
+    tree.appendChild(header);
+    if (!recentlyAddedOpen) return;
+
+    entries.slice(0, savedRecentlyAdded).forEach(function(entry) {{
+      const fi = entry[1], bi = entry[2];
+      const row = bookmarkRow(entry[0], fi, bi, folders[fi].name);
+      row.dataset.key = 'r' + fi + ':' + bi;
+      const open = row.onclick;
+      row.onclick = function(e) {{
+        open(e);
+        if (!e.shiftKey && !e.ctrlKey) jumpTo(fi, bi);
+      }};
+      tree.appendChild(row);
+    }});
+  }}
+
+  function renderBookmarks(data) {{
+    folders = data;
+    // Only collapsed folders carry the flag
+    folders.forEach(function(folder) {{
+      if (folder.expanded === undefined) folder.expanded = true;
+    }});
+    updateUnreadBadge();
+    const tree = document.getElementById('tree');
+    tree.innerHTML = '';
+    if (viewMode === 'gallery') {{
+      renderGallery(tree);
+      return;
+    }}
+    if (viewMode === 'flat') {{
+      renderFlat(tree);
+      restoreTreeFocus();
+      return;
+    }}
+    if (viewMode === 'changed') {{
+      renderChanged(tree);
+      restoreTreeFocus();
+      return;
+    }}
+    if (!searchQuery) renderRecentlyAdded(tree);
+    folders.forEach(function(folder, fi) {{
+      const hidden = isHidden(folder);
+      // While filtering, only folders with a match show up, opened
+      if (searchQuery && (hidden || !folder.bookmarks.some(matchesSearch))) return;
+      const open = (folder.expanded || searchQuery !== '') && !hidden;
+      const header = document.createElement('div');
+      header.className = 'folder-header';
+      header.tabIndex = 0;
+      header.dataset.key = 'f' + fi;
+      header.onclick = function() {{ toggleFolder(fi); }};
+      header.onfocus = function() {{ focusKey = header.dataset.key; }};
+
+      const arrow = document.createElement('span');
+      arrow.className = 'folder-arrow';
+      arrow.textContent = open ? '\u25BC' : '\u25B6';
+
+      const name = document.createElement('span');
+      name.className = 'folder-name';
+      name.textContent = folder.name;
+
+      const icon = document.createElement('span');
+      icon.className = 'folder-icon';
+      icon.textContent = folder.icon || '';
+
+      const actions = document.createElement('span');
+      actions.className = 'folder-actions';
+
+      const addBtn = document.createElement('button');
+      addBtn.className = 'icon-btn';
+      addBtn.textContent = '+';
+      addBtn.title = 'Add bookmark to this folder';
+      addBtn.onclick = function(e) {{ e.stopPropagation(); showAddBookmarkModal(fi); }};
+
+      const iconBtn = document.createElement('button');
+      iconBtn.className = 'icon-btn';
+      iconBtn.textContent = '\u263A';
+      iconBtn.title = 'Set folder icon';
+      iconBtn.onclick = function(e) {{ e.stopPropagation(); showFolderIconModal(fi); }};
+
+      const syncBtn = document.createElement('button');
+      syncBtn.className = 'icon-btn';
+      syncBtn.textContent = folder.exclude_from_sync ? '\u21C5' : '\u2298';
+      syncBtn.title = folder.exclude_from_sync ? 'Include folder in sync' : 'Exclude folder from sync';
+      syncBtn.onclick = function(e) {{
+        e.stopPropagation();
+        window.ipc.postMessage(JSON.stringify({{ action: 'toggle_sync_exclusion', folder_index: fi }}));
+      }};
+
+      const upBtn = document.createElement('button');
+      upBtn.className = 'icon-btn';
+      upBtn.textContent = '\u25B2';
+      upBtn.title = 'Move folder up (Alt+\u2191)';
+      upBtn.disabled = fi === 0;
+      upBtn.onclick = function(e) {{ e.stopPropagation(); moveFolder(fi, fi - 1); }};
+
+      const downBtn = document.createElement('button');
+      downBtn.className = 'icon-btn';
+      downBtn.textContent = '\u25BC';
+      downBtn.title = 'Move folder down (Alt+\u2193)';
+      downBtn.disabled = fi === folders.length - 1;
+      downBtn.onclick = function(e) {{ e.stopPropagation(); moveFolder(fi, fi + 1); }};
+
+      const lockBtn = document.createElement('button');
+      lockBtn.className = 'icon-btn';
+      lockBtn.textContent = isLocked(folder) ? '\uD83D\uDD13' : '\uD83D\uDD12';
+      lockBtn.title = isLocked(folder) ? 'Remove PIN lock' : 'Lock with a PIN';
+      lockBtn.onclick = function(e) {{
+        e.stopPropagation();
+        showPinModal(fi, isLocked(folder) ? 'remove' : 'set');
+      }};
+
+      const copyBtn = document.createElement('button');
+      copyBtn.className = 'icon-btn';
+      copyBtn.textContent = '\u29C9';
+      copyBtn.title = 'Copy as Markdown';
+      copyBtn.onclick = function(e) {{
+        e.stopPropagation();
+        if (hidden) {{
+          showPinModal(fi, 'unlock');
+          return;
+        }}
+        window.ipc.postMessage(JSON.stringify({{ action: 'copy_folder_markdown', folder_index: fi }}));
+      }};
+
+      const delBtn = document.createElement('button');
+      delBtn.className = 'icon-btn delete';
+      delBtn.textContent = '\u00D7';
+      delBtn.title = 'Delete folder';
+      delBtn.onclick = function(e) {{
+        e.stopPropagation();
+        if (hidden) showPinModal(fi, 'unlock');
+        else deleteFolder(fi);
+      }};
+
+      actions.appendChild(addBtn);
+      actions.appendChild(upBtn);
+      actions.appendChild(downBtn);
+      actions.appendChild(iconBtn);
+      actions.appendChild(syncBtn);
+      actions.appendChild(lockBtn);
+      actions.appendChild(copyBtn);
+      actions.appendChild(delBtn);
+      header.appendChild(arrow);
+      if (folder.icon) header.appendChild(icon);
+      header.appendChild(name);
+      if (hidden) {{
+        const lock = document.createElement('span');
+        lock.className = 'folder-lock';
+        lock.textContent = '\uD83D\uDD12';
+        lock.title = 'Locked \u2014 click to enter the PIN';
+        header.appendChild(lock);
+      }}
+      if (folder.exclude_from_sync) {{
+        const local = document.createElement('span');
+        local.className = 'folder-local';
+        local.textContent = 'local';
+        local.title = 'Not synced to GitHub';
+        header.appendChild(local);
+      }}
+      header.appendChild(actions);
+      tree.appendChild(header);
+
+      if (open) {{
+        let entries = folder.bookmarks.map(function(bm, bi) {{ return [bm, bi]; }});
+        entries = entries.filter(function(entry) {{ return matchesSearch(entry[0]); }});
+        if (healthFilter !== 'all') {{
+          entries = entries.filter(function(entry) {{ return healthOf(entry[0].url).class === healthFilter; }});
+        }}
+        if (unreadOnly) entries = entries.filter(function(entry) {{ return isUnread(entry[0]); }});
+        if (healthSort) {{
+          entries.sort(function(a, b) {{
+            return HEALTH_RANK[healthOf(a[0].url).class] - HEALTH_RANK[healthOf(b[0].url).class];
+          }});
+        }}
+        entries.forEach(function(entry) {{
+          tree.appendChild(bookmarkRow(entry[0], fi, entry[1], null));
+        }});
+      }}
+    }});
+    restoreTreeFocus();
+  }}
+
+  // Tree keyboard navigation. Rows are re-created on every render, so focus is
+  // tracked by a key ('f<folder>' or 'b<folder>:<bookmark>') and re-applied.
+  let focusKey = null;
+
+  function treeRows() {{
+    return Array.prototype.slice.call(document.querySelectorAll('#tree .folder-header, #tree .bookmark'));
+  }}
+
+  function focusRow(row) {{
+    if (!row) return;
+    focusKey = row.dataset.key;
+    row.focus();
+    row.scrollIntoView({{ block: 'nearest' }});
+  }}
+
+  function restoreTreeFocus() {{
+    if (!focusKey || document.activeElement !== document.body) return;
+    const row = document.querySelector('#tree [data-key="' + focusKey + '"]');
+    if (row) row.focus();
+  }}
+
+  function handleTreeKey(e) {{
+    const row = document.activeElement;
+    if (!row || !row.dataset || !row.dataset.key) return false;
+    const isFolder = row.classList.contains('folder-header');
+    const parts = row.dataset.key.slice(1).split(':');
+    const fi = parseInt(parts[0], 10);
+    const rows = treeRows();
+    const pos = rows.indexOf(row);
+    if (row.dataset.key.charAt(0) === 'r' && (e.key === 'ArrowLeft' || e.key === 'ArrowRight' || (e.altKey && isFolder))) {{
+      // "Recently added" isn't a folder: its header only opens and closes it
+      if (isFolder && e.altKey) return true;
+      if (isFolder && (e.key === 'ArrowLeft') === recentlyAddedOpen) row.click();
+      else if (!isFolder && e.key === 'ArrowLeft') focusRow(document.querySelector('#tree [data-key="r"]'));
+      return true;
+    }}
+    if (e.ctrlKey && (e.key === 'x' || e.key === 'v')) {{
+      if (isNaN(fi)) return true;
+      const bi = isFolder ? null : parseInt(parts[1], 10);
+      if (e.key === 'v') pasteBookmark(fi, bi);
+      else if (bi !== null) cutBookmark(fi, bi);
+      return true;
+    }}
+    if (e.altKey && isFolder && (e.key === 'ArrowUp' || e.key === 'ArrowDown')) {{
+      moveFolder(fi, e.key === 'ArrowUp' ? fi - 1 : fi + 1);
+    }} else if (e.key === 'ArrowDown') {{
+      focusRow(rows[pos + 1]);
+    }} else if (e.key === 'ArrowUp') {{
+      focusRow(rows[pos - 1]);
+    }} else if (e.key === 'ArrowRight' && isFolder) {{
+      if (!folders[fi].expanded || isHidden(folders[fi])) {{
+        toggleFolder(fi);
+      }} else if (folders[fi].bookmarks.length > 0) {{
+        focusRow(rows[pos + 1]);
+      }}
+    }} else if (e.key === 'ArrowLeft') {{
+      if (isFolder) {{
+        if (folders[fi].expanded && !isHidden(folders[fi])) toggleFolder(fi);
+      }} else {{
+        focusRow(document.querySelector('#tree [data-key="f' + fi + '"]'));
+      }}
+    }} else if (e.key === 'Enter') {{
+      row.click();
+    }} else {{
+      return false;
+    }}
+    return true;
+  }}
+
+  // Keyboard reordering: Ctrl+X marks a bookmark [folder, index] to move,
+  // Ctrl+V puts it before the focused bookmark, or at the end of the focused
+  // folder. Escape cancels.
+  let clipboardItem = null;
+
+  function cutBookmark(fi, bi) {{
+    clipboardItem = [fi, bi, folders[fi].bookmarks[bi].url];
+    renderBookmarks(folders);
+  }}
+
+  function cancelCut() {{
+    clipboardItem = null;
+    renderBookmarks(folders);
+  }}
+
+  function pasteBookmark(fi, bi) {{
+    if (!clipboardItem || isHidden(folders[fi])) return;
+    const from = clipboardItem;
+    clipboardItem = null;
+    let to = bi === null ? folders[fi].bookmarks.length : bi;
+    // Taking it out first shifts the later ones in its own folder up
+    if (from[0] === fi && from[1] < to) to -= 1;
+    focusKey = 'b' + fi + ':' + to;
+    if (from[0] === fi && from[1] === to) {{
+      renderBookmarks(folders);
+      return;
+    }}
+    window.ipc.postMessage(JSON.stringify({{
+      action: 'move_bookmark',
+      from_folder: from[0],
+      from_index: from[1],
+      url: from[2],
+      to_folder: fi,
+      to_index: to
+    }}));
+  }}
+
+  // Swaps a folder with its neighbour; focus follows it to its new place
+  function moveFolder(from, to) {{
+    if (to < 0 || to >= folders.length) return;
+    focusKey = 'f' + to;
+    window.ipc.postMessage(JSON.stringify({{ action: 'move_folder', from: from, to: to }}));
+  }}
+
+  function isBookmarklet(url) {{
+    return /^\s*javascript:/i.test(url);
+  }}
+
+  function navigate(url) {{
+    // A bookmarklet runs on the page already showing, which stays current
+    if (isBookmarklet(url)) {{
+      window.ipc.postMessage(JSON.stringify({{ action: 'navigate', url: url }}));
+      return;
+    }}
+    activeUrl = url;
+    window.ipc.postMessage(JSON.stringify({{ action: 'navigate', url: url }}));
+    renderBookmarks(folders);
+  }}
+
+  // Called by the host when the page shown matches a different bookmark, or none
+  function setActiveUrl(url) {{
+    activeUrl = url;
+    renderBookmarks(folders);
+  }}
+
+  // Asked by the host when a navigation would drop edited form fields;
+  // previous is the page still shown, to re-highlight if the user stays
+  function confirmLeavePage(previous) {{
+    if (confirm('This page has form input that hasn\'t been submitted. Leave anyway?')) {{
+      window.ipc.postMessage(JSON.stringify({{ action: 'discard_form_changes' }}));
+    }} else {{
+      activeUrl = previous;
+      renderBookmarks(folders);
+    }}
+  }}
+
+  function toggleFolder(index) {{
+    const folder = folders[index];
+    if (isHidden(folder)) {{
+      showPinModal(index, 'unlock');
+      return;
+    }}
+    // Collapsing an unlocked folder locks it again
+    if (isLocked(folder) && folder.expanded) unlockedFolders.delete(folder.name);
+    window.ipc.postMessage(JSON.stringify({{ action: 'toggle_folder', folder_index: index }}));
+  }}
+
+  // Locked folders opened with their PIN this session, by name so the
+  // unlock survives reordering; nothing here is saved
+  const unlockedFolders = new Set();
+  let pinTarget = null;
+  let pinMode = null;
+
+  function isLocked(folder) {{
+    return folder.locked && !!folder.pin;
+  }}
+
+  function isHidden(folder) {{
+    return isLocked(folder) && !unlockedFolders.has(folder.name);
+  }}
+
+  const PIN_MODES = {{
+    set: ['Lock Folder', 'Lock', 'Choose a PIN of at least 4 characters. The folder stays collapsed until it is entered. This only hides it in the sidebar; bookmarks.json is not encrypted.'],
+    unlock: ['Unlock Folder', 'Unlock', 'Enter the PIN for this folder.'],
+    remove: ['Remove Lock', 'Remove lock', 'Enter the PIN to take the lock off this folder.']
+  }};
+
+  // mode is 'set' (new lock), 'unlock' (open for this session) or 'remove'
+  function showPinModal(fi, mode) {{
+    closeModals();
+    pinTarget = fi;
+    pinMode = mode;
+    document.getElementById('pinTitle').textContent = PIN_MODES[mode][0] + ' \u201C' + folders[fi].name + '\u201D';
+    document.getElementById('pinSubmit').textContent = PIN_MODES[mode][1];
+    document.getElementById('pinNote').textContent = PIN_MODES[mode][2];
+    const input = document.getElementById('pinInput');
+    input.value = '';
+    document.getElementById('pinOverlay').classList.add('active');
+    activeModal = 'pin';
+    input.focus();
+  }}
+
+  function submitPin() {{
+    const pin = document.getElementById('pinInput').value;
+    if (pinTarget === null || !pin) return;
+    if (pinMode === 'set') {{
+      if (pin.length < 4) {{
+        document.getElementById('pinNote').textContent = 'The PIN needs at least 4 characters.';
+        return;
+      }}
+      window.ipc.postMessage(JSON.stringify({{ action: 'lock_folder', folder_index: pinTarget, pin: pin }}));
+      closeModals();
+      return;
+    }}
+    window.ipc.postMessage(JSON.stringify({{
+      action: 'unlock_folder',
+      folder_index: pinTarget,
+      pin: pin,
+      remove: pinMode === 'remove'
+    }}));
+  }}
+
+  // Called by the host once it has checked a PIN
+  function folderUnlocked(fi, ok) {{
+    if (!ok) {{
+      document.getElementById('pinNote').textContent = 'Wrong PIN, try again.';
+      const input = document.getElementById('pinInput');
+      input.value = '';
+      input.focus();
+      return;
+    }}
+    const mode = pinMode;
+    closeModals();
+    if (mode !== 'unlock') return;
+    unlockedFolders.add(folders[fi].name);
+    if (folders[fi].expanded) renderBookmarks(folders);
+    else toggleFolder(fi);
+  }}
+
+  function deleteFolder(fi) {{
+    if (confirm('Delete folder "' + folders[fi].name + '" and all its bookmarks?')) {{
+      window.ipc.postMessage(JSON.stringify({{ action: 'delete_folder', folder_index: fi }}));
+    }}
+  }}
+
+  function deleteBookmark(fi, bi) {{
+    if (confirm('Delete bookmark "' + folders[fi].bookmarks[bi].name + '"?')) {{
+      window.ipc.postMessage(JSON.stringify({{ action: 'delete_bookmark', folder_index: fi, bookmark_index: bi }}));
+    }}
+  }}
+
+  let menuTarget = null;
+
+  function openExternal(url) {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'open_external', url: url }}));
+  }}
+
+  function showBookmarkMenu(fi, bi, x, y) {{
+    menuTarget = {{ folder_index: fi, bookmark_index: bi }};
+    const menu = document.getElementById('bookmarkMenu');
+    document.getElementById('startupToggle').textContent =
+      (folders[fi].bookmarks[bi].open_on_startup ? '\u2713 ' : '') + 'Open on startup';
+    document.getElementById('closeReference').style.display = pinnedReference ? '' : 'none';
+    document.getElementById('openArchived').style.display =
+      folders[fi].bookmarks[bi].wayback_url ? '' : 'none';
+    menu.style.left = Math.min(x, window.innerWidth - 170) + 'px';
+    menu.style.top = Math.min(y, window.innerHeight - menu.offsetHeight - 10) + 'px';
+    menu.classList.add('active');
+  }}
+
+  function hideBookmarkMenu() {{
+    document.getElementById('bookmarkMenu').classList.remove('active');
+    document.getElementById('sortMenu').classList.remove('active');
+  }}
+
+  function showSortMenu(e) {{
+    e.stopPropagation();
+    hideBookmarkMenu();
+    const menu = document.getElementById('sortMenu');
+    const rect = e.currentTarget.getBoundingClientRect();
+    menu.style.left = Math.min(rect.left, window.innerWidth - 170) + 'px';
+    menu.style.top = Math.max(rect.top - 100, 0) + 'px';
+    menu.classList.add('active');
+  }}
+
+  function sortFolders(mode) {{
+    hideBookmarkMenu();
+    window.ipc.postMessage(JSON.stringify({{ action: 'sort_folders', mode: mode }}));
+  }}
+
+  function toggleOpenOnStartup() {{
+    hideBookmarkMenu();
+    if (!menuTarget) return;
+    window.ipc.postMessage(JSON.stringify(Object.assign({{ action: 'toggle_open_on_startup' }}, menuTarget)));
+  }}
+
+  // URL in the floating reference frame, or '' when none is pinned
+  let pinnedReference = {pip_url_json};
+
+  function setReference(url) {{
+    pinnedReference = url;
+  }}
+
+  function pinReference() {{
+    hideBookmarkMenu();
+    if (!menuTarget) return;
+    window.ipc.postMessage(JSON.stringify(Object.assign({{ action: 'pin_reference' }}, menuTarget)));
+  }}
+
+  function archiveBookmark() {{
+    hideBookmarkMenu();
+    if (!menuTarget) return;
+    window.ipc.postMessage(JSON.stringify(Object.assign({{ action: 'archive_bookmark' }}, menuTarget)));
+  }}
+
+  function copyBookmarkDebug() {{
+    hideBookmarkMenu();
+    if (!menuTarget) return;
+    window.ipc.postMessage(JSON.stringify(Object.assign({{ action: 'copy_bookmark_debug' }}, menuTarget)));
+  }}
+
+  function openArchived() {{
+    hideBookmarkMenu();
+    if (!menuTarget) return;
+    const bm = folders[menuTarget.folder_index].bookmarks[menuTarget.bookmark_index];
+    if (bm.wayback_url) navigate(bm.wayback_url);
+  }}
+
+  function toggleReference() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'toggle_reference' }}));
+  }}
+
+  function closeReference() {{
+    hideBookmarkMenu();
+    window.ipc.postMessage(JSON.stringify({{ action: 'close_reference' }}));
+  }}
+
+  document.addEventListener('click', hideBookmarkMenu);
+
+  function showAddBookmarkModal(fi) {{
+    if (folders.length === 0) {{
+      alert('Create a folder first before adding bookmarks.');
+      return;
+    }}
+    const select = document.getElementById('bmFolder');
+    select.innerHTML = '';
+    folderChosen = fi !== undefined;
+    folders.forEach(function(folder, i) {{
+      const opt = document.createElement('option');
+      opt.value = i;
+      opt.textContent = folder.name;
+      if (fi !== undefined && fi === i) opt.selected = true;
+      select.appendChild(opt);
+    }});
+    document.getElementById('bmName').value = '';
+    document.getElementById('bmUrl').value = '';
+    document.getElementById('bmNotes').value = '';
+    document.getElementById('addBookmarkOverlay').classList.add('active');
+    activeModal = 'addBookmark';
+    document.getElementById('bmName').focus();
+  }}
+
+  // Ctrl+Shift+S on the page: the add dialog, filled in from it
+  function showClipModal(url, title, text) {{
+    if (activeModal) closeModals();
+    showAddBookmarkModal();
+    if (activeModal !== 'addBookmark') return;
+    document.getElementById('bmName').value = title || url;
+    document.getElementById('bmUrl').value = url;
+    document.getElementById('bmNotes').value = text;
+    requestFolderSuggestion();
+    document.getElementById('bmName').select();
+  }}
+
+  // Set once the folder is picked by hand (or the dialog was opened from a
+  // folder), after which typed URLs no longer move the selection
+  let folderChosen = false;
+  let suggestTimer = null;
+
+  function requestFolderSuggestion() {{
+    clearTimeout(suggestTimer);
+    if (folderChosen) return;
+    suggestTimer = setTimeout(function() {{
+      const url = document.getElementById('bmUrl').value.trim();
+      if (url) window.ipc.postMessage(JSON.stringify({{ action: 'suggest_folder', url: url }}));
+    }}, 250);
+  }}
+
+  function applyFolderSuggestion(index) {{
+    if (folderChosen || activeModal !== 'addBookmark') return;
+    document.getElementById('bmFolder').value = index;
+  }}
+
+  const FOLDER_ICONS = ['\u{{1F4C1}}', '\u{{1F4DA}}', '\u{{1F4F0}}', '\u{{1F4BC}}', '\u{{1F6E0}}', '\u{{1F3AE}}',
+    '\u{{1F3B5}}', '\u{{1F3AC}}', '\u{{1F3E0}}', '\u2B50', '\u{{1F512}}', '\u{{1F4A1}}',
+    '\u{{1F9EA}}', '\u{{1F310}}', '\u{{1F4DD}}', '\u{{1F6D2}}', '\u2764', '\u{{1F680}}'];
+  let selectedIcon = null;
+  let editingFolder = null;
+
+  function renderIconGrid() {{
+    const grid = document.getElementById('iconGrid');
+    grid.innerHTML = '';
+    [null].concat(FOLDER_ICONS).forEach(function(ic) {{
+      const btn = document.createElement('button');
+      btn.className = 'icon-choice' + (ic === selectedIcon ? ' selected' : '');
+      btn.textContent = ic === null ? '\u2014' : ic;
+      btn.title = ic === null ? 'No icon' : ic;
+      btn.onclick = function() {{ selectedIcon = ic; renderIconGrid(); }};
+      grid.appendChild(btn);
+    }});
+  }}
+
+  function showAddFolderModal() {{
+    editingFolder = null;
+    selectedIcon = null;
+    document.getElementById('folderModalTitle').textContent = 'Add Folder';
+    document.getElementById('folderSubmitBtn').textContent = 'Create';
+    document.getElementById('folderNameField').style.display = '';
+    document.getElementById('folderName').value = '';
+    renderIconGrid();
+    document.getElementById('addFolderOverlay').classList.add('active');
+    activeModal = 'addFolder';
+    document.getElementById('folderName').focus();
+  }}
+
+  function showFolderIconModal(fi) {{
+    editingFolder = fi;
+    selectedIcon = folders[fi].icon || null;
+    document.getElementById('folderModalTitle').textContent = 'Icon for "' + folders[fi].name + '"';
+    document.getElementById('folderSubmitBtn').textContent = 'Save';
+    document.getElementById('folderNameField').style.display = 'none';
+    renderIconGrid();
+    document.getElementById('addFolderOverlay').classList.add('active');
+    activeModal = 'addFolder';
+  }}
+
+  // Typing is saved once it pauses, and straight away when the pad closes
+  let scratchpadTimer = null;
+  let scratchpadDirty = false;
+
+  function toggleScratchpad() {{
+    if (activeModal === 'scratchpad') {{
+      closeModals();
+      restoreTreeFocus();
+      return;
+    }}
+    window.ipc.postMessage(JSON.stringify({{ action: 'load_scratchpad' }}));
+  }}
+
+  function showScratchpad(text) {{
+    closeModals();
+    const pad = document.getElementById('scratchpadText');
+    pad.value = text;
+    document.getElementById('scratchpadOverlay').classList.add('active');
+    activeModal = 'scratchpad';
+    pad.focus();
+  }}
+
+  function flushScratchpad() {{
+    clearTimeout(scratchpadTimer);
+    scratchpadTimer = null;
+    if (!scratchpadDirty) return;
+    scratchpadDirty = false;
+    const text = document.getElementById('scratchpadText').value;
+    window.ipc.postMessage(JSON.stringify({{ action: 'save_scratchpad', text: text }}));
+  }}
+
+  document.getElementById('scratchpadText').addEventListener('input', function() {{
+    scratchpadDirty = true;
+    clearTimeout(scratchpadTimer);
+    scratchpadTimer = setTimeout(flushScratchpad, 500);
+  }});
+
+  function showHelpModal() {{
+    document.getElementById('helpOverlay').classList.add('active');
+    activeModal = 'help';
+  }}
+
+  let loadingTimer = null;
+
+  // Hidden again on load finished, or after 30s in case the page never settles
+  function setPageLoading(loading) {{
+    const bar = document.getElementById('loadingBar');
+    if (loadingTimer) {{ clearTimeout(loadingTimer); loadingTimer = null; }}
+    if (loading) {{
+      bar.classList.add('active');
+      loadingTimer = setTimeout(function() {{ bar.classList.remove('active'); }}, 30000);
+    }} else {{
+      bar.classList.remove('active');
+    }}
+  }}
+
+  let recentUrls = [];
+
+  function updateRecent(urls) {{
+    recentUrls = urls;
+  }}
+
+  // Adds a bookmark to the session reading queue without leaving the current page
+  function queueBookmark(url) {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'queue_bookmark', url: url }}));
+  }}
+
+  function openNextQueued() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'open_next_queued' }}));
+  }}
+
+  function updateQueue(urls) {{
+    const panel = document.getElementById('readingQueue');
+    if (urls.length === 0) {{
+      panel.classList.remove('active');
+      return;
+    }}
+    const next = document.getElementById('readingQueueNext');
+    next.textContent = urls.length + ' queued \u00B7 ' + bookmarkNameFor(urls[0]);
+    next.title = urls.map(bookmarkNameFor).join('\n');
+    panel.classList.add('active');
+  }}
+
+  // Called by the host after the sidebar HTML is (re)loaded
+  function restoreSidebarState(url, urls, queued) {{
+    activeUrl = url;
+    recentUrls = urls;
+    renderBookmarks(folders);
+    updateQueue(queued);
+  }}
+
+  function bookmarkNameFor(url) {{
+    for (const folder of folders) {{
+      for (const bm of folder.bookmarks) {{
+        if (bm.url === url) return bm.name;
+      }}
+    }}
+    return url;
+  }}
+
+  function showRecentModal() {{
+    const list = document.getElementById('recentList');
+    list.innerHTML = '';
+    if (recentUrls.length === 0) {{
+      const empty = document.createElement('div');
+      empty.className = 'recent-empty';
+      empty.textContent = 'No pages visited yet.';
+      list.appendChild(empty);
+    }}
+    recentUrls.forEach(function(url) {{
+      const item = document.createElement('div');
+      item.className = 'recent-item';
+      item.textContent = bookmarkNameFor(url);
+      item.title = url;
+      item.onclick = function() {{ closeModals(); navigate(url); }};
+      list.appendChild(item);
+    }});
+    document.getElementById('recentOverlay').classList.add('active');
+    activeModal = 'recent';
+  }}
+
+  let omnibarMatches = [];
+  let omnibarIndex = -1;
+
+  function showOmnibar() {{
+    closeModals();
+    const input = document.getElementById('omnibarInput');
+    input.value = '';
+    updateOmnibar();
+    document.getElementById('omnibarOverlay').classList.add('active');
+    activeModal = 'omnibar';
+    input.focus();
+  }}
+
+  // Bookmarks whose name or URL contains the typed text become suggestions
+  function updateOmnibar() {{
+    const query = document.getElementById('omnibarInput').value.trim().toLowerCase();
+    omnibarMatches = [];
+    if (query) {{
+      for (const folder of folders) {{
+        if (isHidden(folder)) continue;
+        for (const bm of folder.bookmarks) {{
+          if (bm.name.toLowerCase().includes(query) || bm.url.toLowerCase().includes(query)) {{
+            omnibarMatches.push(bm);
+          }}
+        }}
+      }}
+      omnibarMatches = omnibarMatches.slice(0, 8);
+    }}
+    omnibarIndex = -1;
+    renderOmnibar();
+  }}
+
+  function renderOmnibar() {{
+    const list = document.getElementById('omnibarList');
+    list.innerHTML = '';
+    omnibarMatches.forEach(function(bm, i) {{
+      const item = document.createElement('div');
+      item.className = 'recent-item' + (i === omnibarIndex ? ' selected' : '');
+      item.textContent = bm.name;
+      item.title = bm.url;
+      item.onclick = function() {{ closeModals(); navigate(bm.url); }};
+      list.appendChild(item);
+    }});
+  }}
+
+  function omnibarKey(e) {{
+    if (isComposing(e) || (e.key !== 'ArrowDown' && e.key !== 'ArrowUp')) return;
+    e.preventDefault();
+    if (omnibarMatches.length === 0) return;
+    const step = e.key === 'ArrowDown' ? 1 : -1;
+    // -1 means no suggestion selected, so Enter searches the typed text
+    omnibarIndex = Math.max(-1, Math.min(omnibarMatches.length - 1, omnibarIndex + step));
+    renderOmnibar();
+  }}
+
+  function submitOmnibar() {{
+    const text = document.getElementById('omnibarInput').value.trim();
+    if (omnibarIndex >= 0) {{
+      const url = omnibarMatches[omnibarIndex].url;
+      closeModals();
+      navigate(url);
+    }} else if (text) {{
+      closeModals();
+      window.ipc.postMessage(JSON.stringify({{ action: 'omnibar', text: text }}));
+    }}
+  }}
+
+  function replaceFields() {{
+    return {{
+      find: document.getElementById('replaceFind').value,
+      replace: document.getElementById('replaceWith').value,
+      regex: document.getElementById('replaceRegex').checked
+    }};
+  }}
+
+  function showResetModal() {{
+    closeModals();
+    document.getElementById('resetConfirm').value = '';
+    document.getElementById('resetPush').checked = false;
+    updateResetButton();
+    document.getElementById('resetOverlay').classList.add('active');
+    document.getElementById('resetConfirm').focus();
+    activeModal = 'reset';
+  }}
+
+  function updateResetButton() {{
+    document.getElementById('resetApply').disabled =
+      document.getElementById('resetConfirm').value !== 'DELETE';
+  }}
+
+  function submitResetLibrary() {{
+    const confirmText = document.getElementById('resetConfirm').value;
+    if (confirmText !== 'DELETE') return;
+    window.ipc.postMessage(JSON.stringify({{
+      action: 'reset_library',
+      confirm: confirmText,
+      push: document.getElementById('resetPush').checked
+    }}));
+    closeModals();
+  }}
+
+  function showReplaceModal() {{
+    closeModals();
+    document.getElementById('replaceFind').value = '';
+    document.getElementById('replaceWith').value = '';
+    document.getElementById('replaceRegex').checked = false;
+    showReplacePreview([], null);
+    document.getElementById('replaceOverlay').classList.add('active');
+    document.getElementById('replaceFind').focus();
+    activeModal = 'replace';
   }}
-  #tree {{
-    padding: 8px 0;
-    flex: 1;
-    overflow-y: auto;
+
+  function requestReplacePreview() {{
+    const msg = replaceFields();
+    msg.action = 'preview_replace_urls';
+    window.ipc.postMessage(JSON.stringify(msg));
   }}
-  .folder-header {{
-    display: flex;
-    align-items: center;
-    padding: 6px 12px;
-    cursor: pointer;
-    user-select: none;
-    color: var(--subtext);
-    font-size: 12px;
-    font-weight: 600;
-    text-transform: uppercase;
-    letter-spacing: 0.05em;
+
+  // changes are [name, old URL, new URL] triples; error explains a bad pattern
+  function showReplacePreview(changes, error) {{
+    const list = document.getElementById('replacePreview');
+    list.innerHTML = '';
+    const fields = replaceFields();
+    if (error || changes.length === 0) {{
+      const empty = document.createElement('div');
+      empty.className = 'recent-empty';
+      empty.textContent = error || (fields.find ? 'No bookmarks match.' : '');
+      list.appendChild(empty);
+    }}
+    changes.forEach(function(change) {{
+      const item = document.createElement('div');
+      item.className = 'recent-item';
+      item.textContent = change[0] + ': ' + change[2];
+      item.title = change[1] + '\n\u2192 ' + change[2];
+      list.appendChild(item);
+    }});
+    const apply = document.getElementById('replaceApply');
+    apply.disabled = changes.length === 0;
+    apply.textContent = changes.length > 0 ? 'Replace ' + changes.length : 'Replace';
   }}
-  .folder-header:hover {{
-    background: var(--surface0);
+
+  function submitReplaceUrls() {{
+    if (document.getElementById('replaceApply').disabled) return;
+    const msg = replaceFields();
+    msg.action = 'replace_urls';
+    window.ipc.postMessage(JSON.stringify(msg));
+    closeModals();
   }}
-  .folder-arrow {{
-    display: inline-block;
-    width: 16px;
-    font-size: 10px;
-    color: var(--subtext);
+
+  function listBackups() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'list_backups' }}));
   }}
-  .folder-name {{
-    flex: 1;
-    overflow: hidden;
-    text-overflow: ellipsis;
-    white-space: nowrap;
+
+  function removeDuplicates() {{
+    const global = document.getElementById('dedupeGlobal').checked;
+    const scope = global ? 'across all folders' : 'within each folder';
+    if (!confirm('Remove bookmarks whose URL appears earlier ' + scope + '?')) return;
+    window.ipc.postMessage(JSON.stringify({{ action: 'remove_duplicates', global: global }}));
+    closeModals();
   }}
-  .folder-actions {{
-    display: none;
-    align-items: center;
-    gap: 2px;
-    margin-left: auto;
+
+  function goHome() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'go_home' }}));
   }}
-  .folder-header:hover .folder-actions {{
-    display: flex;
+
+  function validateLibrary() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'validate_library' }}));
   }}
-  .icon-btn {{
-    background: none;
-    border: none;
-    color: var(--subtext);
-    cursor: pointer;
-    font-size: 14px;
-    padding: 0 4px;
-    line-height: 1;
+
+  function fixLibrary() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'fix_library' }}));
   }}
-  .icon-btn:hover {{
-    color: var(--text);
+
+  // issues: {{ kind, folder_index, bookmark_index, detail, fixable }}; each
+  // row jumps to what it's about. Locked folders are only named.
+  function showValidation(issues) {{
+    closeModals();
+    const list = document.getElementById('validationList');
+    list.innerHTML = '';
+    const fixable = issues.filter(function(issue) {{ return issue.fixable; }}).length;
+    document.getElementById('validationNote').textContent = issues.length === 0
+      ? 'No problems found.'
+      : issues.length + (issues.length === 1 ? ' issue' : ' issues') + ' found' +
+        (fixable ? ', ' + fixable + ' of them safe to fix automatically (trimming spaces, clearing leftovers).' : '.');
+    document.getElementById('validationFix').disabled = fixable === 0;
+    issues.forEach(function(issue) {{
+      const folder = folders[issue.folder_index];
+      const hidden = isHidden(folder);
+      let place = folder.name.trim() || 'Unnamed folder';
+      if (issue.bookmark_index !== null && !hidden) {{
+        place += ' \u203A ' + (folder.bookmarks[issue.bookmark_index].name.trim() || 'Unnamed');
+      }}
+      const item = document.createElement('div');
+      item.className = 'recent-item';
+      item.textContent = place + ' \u2014 ' + (hidden ? 'in a locked folder' : issue.detail);
+      item.title = issue.fixable ? 'Fixable automatically' : 'Click to go there';
+      item.onclick = function() {{ jumpTo(issue.folder_index, issue.bookmark_index); }};
+      list.appendChild(item);
+    }});
+    document.getElementById('validationOverlay').classList.add('active');
+    activeModal = 'validation';
   }}
-  .icon-btn.delete:hover {{
-    color: var(--red);
+
+  // Focuses a folder or bookmark row in the tree, opening its folder first
+  function jumpTo(fi, bi) {{
+    closeModals();
+    if (isHidden(folders[fi])) {{
+      showPinModal(fi, 'unlock');
+      return;
+    }}
+    if (viewMode !== 'tree') {{
+      viewMode = 'tree';
+      window.ipc.postMessage(JSON.stringify({{ action: 'set_view_mode', mode: viewMode }}));
+      updateViewButton();
+    }}
+    focusKey = bi === null ? 'f' + fi : 'b' + fi + ':' + bi;
+    if (bi !== null && !folders[fi].expanded) {{
+      toggleFolder(fi);
+      return;
+    }}
+    renderBookmarks(folders);
+    focusRow(document.querySelector('#tree [data-key="' + focusKey + '"]'));
   }}
-  .bookmark {{
-    display: flex;
-    align-items: center;
-    padding: 6px 12px 6px 32px;
-    color: var(--text);
-    text-decoration: none;
-    cursor: pointer;
+
+  function refreshTitles() {{
+    closeModals();
+    window.ipc.postMessage(JSON.stringify({{ action: 'refresh_titles' }}));
   }}
-  .bookmark:hover {{
-    background: var(--surface0);
+
+  let titleChanges = [];
+
+  // Each change is {{folder_index, bookmark_index, url, old, new}}; all start
+  // ticked, and only the ticked ones are sent back
+  function showTitleChanges(changes) {{
+    closeModals();
+    titleChanges = changes.filter(function(c) {{ return !isHidden(folders[c.folder_index]); }});
+    const list = document.getElementById('titlesList');
+    list.innerHTML = '';
+    document.getElementById('titlesNote').textContent = titleChanges.length === 0
+      ? 'Every bookmark already matches its page title.'
+      : titleChanges.length + (titleChanges.length === 1 ? ' bookmark has' : ' bookmarks have') +
+        ' a different page title now. Untick any to keep the current name.';
+    document.getElementById('titlesApply').disabled = titleChanges.length === 0;
+    titleChanges.forEach(function(change, i) {{
+      const row = document.createElement('label');
+      row.className = 'title-change';
+      row.title = change.url;
+      const check = document.createElement('input');
+      check.type = 'checkbox';
+      check.checked = true;
+      check.dataset.index = i;
+      const text = document.createElement('div');
+      const oldName = document.createElement('div');
+      oldName.className = 'title-old';
+      oldName.textContent = change.old;
+      const newName = document.createElement('div');
+      newName.textContent = change.new;
+      text.appendChild(oldName);
+      text.appendChild(newName);
+      row.appendChild(check);
+      row.appendChild(text);
+      list.appendChild(row);
+    }});
+    document.getElementById('titlesOverlay').classList.add('active');
+    activeModal = 'titles';
   }}
-  .bookmark.active {{
-    background: var(--surface0);
-    color: var(--accent);
+
+  function applyTitles() {{
+    const picked = [];
+    document.querySelectorAll('#titlesList input:checked').forEach(function(check) {{
+      picked.push(titleChanges[Number(check.dataset.index)]);
+    }});
+    closeModals();
+    if (picked.length > 0) {{
+      window.ipc.postMessage(JSON.stringify({{ action: 'apply_titles', changes: picked }}));
+    }}
   }}
-  .bookmark-name {{
-    flex: 1;
-    white-space: nowrap;
-    overflow: hidden;
-    text-overflow: ellipsis;
+
+  function findCrossDuplicates() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'find_cross_duplicates' }}));
   }}
-  .bookmark .delete-btn {{
-    display: none;
-    background: none;
-    border: none;
-    color: var(--subtext);
-    cursor: pointer;
-    font-size: 14px;
-    padding: 0 4px;
-    line-height: 1;
+
+  // entries are [url, [folder indices]]; ones touching a locked folder are
+  // left out so the list doesn't reveal what's inside it
+  function showCrossDuplicates(entries) {{
+    closeModals();
+    const list = document.getElementById('crossDupesList');
+    list.innerHTML = '';
+    entries = entries.filter(function(entry) {{
+      return !entry[1].some(function(fi) {{ return isHidden(folders[fi]); }});
+    }});
+    if (entries.length === 0) {{
+      const empty = document.createElement('div');
+      empty.className = 'recent-empty';
+      empty.textContent = 'No bookmark is in more than one folder.';
+      list.appendChild(empty);
+    }}
+    entries.forEach(function(entry) {{
+      const url = entry[0];
+      const row = document.createElement('div');
+      row.className = 'dupe-entry';
+      const label = document.createElement('div');
+      label.className = 'dupe-url';
+      label.textContent = bookmarkNameFor(url);
+      label.title = url;
+      row.appendChild(label);
+      entry[1].forEach(function(fi) {{
+        const keep = document.createElement('button');
+        keep.className = 'btn-cancel dupe-keep';
+        keep.textContent = 'Keep in ' + (folders[fi].icon ? folders[fi].icon + ' ' : '') + folders[fi].name;
+        keep.onclick = function() {{
+          window.ipc.postMessage(JSON.stringify({{ action: 'keep_duplicate_in', url: url, folder_index: fi }}));
+        }};
+        row.appendChild(keep);
+      }});
+      list.appendChild(row);
+    }});
+    document.getElementById('crossDupesOverlay').classList.add('active');
+    activeModal = 'crossDupes';
   }}
-  .bookmark:hover .delete-btn {{
-    display: inline;
+
+  function exportHtml() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'export_html' }}));
+    closeModals();
   }}
-  .bookmark .delete-btn:hover {{
-    color: var(--red);
+
+  function exportFlatJson() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'export_flat_json' }}));
+    closeModals();
   }}
-  .bottom-bar {{
-    display: flex;
-    background: var(--mantle);
-    border-top: 1px solid var(--surface0);
-    padding: 8px;
-    gap: 8px;
-    flex-shrink: 0;
+
+  function openConfigDir() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'open_config_dir' }}));
+    closeModals();
   }}
-  .bar-btn {{
-    flex: 1;
-    background: var(--surface1);
-    border: 1px solid var(--surface2);
-    color: var(--text);
-    padding: 6px 8px;
-    border-radius: 4px;
-    cursor: pointer;
-    font-size: 12px;
-    font-family: inherit;
+
+  function exportDiagnostics() {{
+    window.ipc.postMessage(JSON.stringify({{
+      action: 'export_diagnostics',
+      include_repo: document.getElementById('diagIncludeRepo').checked
+    }}));
+    closeModals();
   }}
-  .bar-btn:hover {{
-    background: var(--surface2);
-    color: var(--text);
+
+  function showSyncHealthModal() {{
+    window.ipc.postMessage(JSON.stringify({{
+      action: 'sync_health',
+      include_repo: document.getElementById('diagIncludeRepo').checked
+    }}));
   }}
-  .modal-overlay {{
-    display: none;
-    position: fixed;
-    top: 0; left: 0; right: 0; bottom: 0;
-    background: var(--overlay);
-    z-index: 100;
-    align-items: center;
-    justify-content: center;
+
+  // Rows are [label, value] pairs from the host
+  function showSyncHealth(rows) {{
+    closeModals();
+    const table = document.getElementById('syncHealthTable');
+    table.innerHTML = '';
+    rows.forEach(function(row) {{
+      const tr = document.createElement('tr');
+      const label = document.createElement('td');
+      label.className = 'help-key';
+      label.textContent = row[0];
+      const value = document.createElement('td');
+      value.textContent = row[1];
+      tr.appendChild(label);
+      tr.appendChild(value);
+      table.appendChild(tr);
+    }});
+    document.getElementById('syncHealthTest').className = 'token-test';
+    document.getElementById('syncHealthOverlay').classList.add('active');
   }}
-  .modal-overlay.active {{
-    display: flex;
+
+  function testSyncHealth() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'test_sync_health' }}));
   }}
-  .modal {{
-    background: var(--base);
-    border: 1px solid var(--surface1);
-    border-radius: 8px;
-    padding: 20px;
-    width: 240px;
+
+  function showSyncHealthTest(msg, ok) {{
+    const el = document.getElementById('syncHealthTest');
+    el.textContent = msg;
+    el.className = 'token-test active' + (ok === true ? ' ok' : ok === false ? ' fail' : '');
+  }}
+
+  function chooseImportFile() {{
+    document.getElementById('importFile').click();
+  }}
+
+  // The host detects the format, so just hand over the file's text
+  function importSelectedFile(input) {{
+    const file = input.files[0];
+    input.value = '';
+    if (!file) return;
+    file.text().then(function(content) {{
+      closeModals();
+      window.ipc.postMessage(JSON.stringify({{ action: 'import_bookmarks', content: content }}));
+    }});
+  }}
+
+  // names are newest first, e.g. bookmarks-001700000000.json
+  function showBackupsModal(names) {{
+    closeModals();
+    const list = document.getElementById('backupList');
+    list.innerHTML = '';
+    if (names.length === 0) {{
+      const empty = document.createElement('div');
+      empty.className = 'recent-empty';
+      empty.textContent = 'No backups yet.';
+      list.appendChild(empty);
+    }}
+    names.forEach(function(name) {{
+      const secs = parseInt(name.replace(/\D/g, ''), 10);
+      const item = document.createElement('div');
+      item.className = 'recent-item';
+      item.textContent = new Date(secs * 1000).toLocaleString();
+      item.title = name;
+      item.onclick = function() {{
+        if (confirm('Replace all bookmarks with the backup from ' + item.textContent + '?')) {{
+          window.ipc.postMessage(JSON.stringify({{ action: 'restore_backup', name: name }}));
+          closeModals();
+        }}
+      }};
+      list.appendChild(item);
+    }});
+    document.getElementById('backupsOverlay').classList.add('active');
+    activeModal = 'backups';
+  }}
+
+  let authUrl = null;
+
+  function showAuthModal(url) {{
+    authUrl = url;
+    document.getElementById('authOrigin').textContent = url + ' requires a username and password.';
+    document.getElementById('authUser').value = '';
+    document.getElementById('authPass').value = '';
+    document.getElementById('authOverlay').classList.add('active');
+    activeModal = 'auth';
+    document.getElementById('authUser').focus();
+  }}
+
+  function submitAuth() {{
+    const username = document.getElementById('authUser').value;
+    const password = document.getElementById('authPass').value;
+    if (!authUrl || !username) return;
+    window.ipc.postMessage(JSON.stringify({{ action: 'submit_auth', url: authUrl, username: username, password: password }}));
+    document.getElementById('authPass').value = '';
+    closeModals();
+  }}
+
+  function closeModals() {{
+    document.getElementById('addBookmarkOverlay').classList.remove('active');
+    document.getElementById('editBookmarkOverlay').classList.remove('active');
+    document.getElementById('addFolderOverlay').classList.remove('active');
+    document.getElementById('helpOverlay').classList.remove('active');
+    document.getElementById('settingsOverlay').classList.remove('active');
+    document.getElementById('recentOverlay').classList.remove('active');
+    document.getElementById('authOverlay').classList.remove('active');
+    document.getElementById('backupsOverlay').classList.remove('active');
+    document.getElementById('crossDupesOverlay').classList.remove('active');
+    document.getElementById('titlesOverlay').classList.remove('active');
+    document.getElementById('syncHealthOverlay').classList.remove('active');
+    document.getElementById('validationOverlay').classList.remove('active');
+    document.getElementById('replaceOverlay').classList.remove('active');
+    document.getElementById('resetOverlay').classList.remove('active');
+    document.getElementById('pinOverlay').classList.remove('active');
+    document.getElementById('omnibarOverlay').classList.remove('active');
+    document.getElementById('thumbnailOverlay').classList.remove('active');
+    if (activeModal === 'scratchpad') flushScratchpad();
+    document.getElementById('scratchpadOverlay').classList.remove('active');
+    hideBookmarkMenu();
+    activeModal = null;
+  }}
+
+  let editTarget = null;
+
+  function showEditBookmarkModal() {{
+    if (!menuTarget) return;
+    const target = menuTarget;
+    const bm = folders[target.folder_index].bookmarks[target.bookmark_index];
+    closeModals();
+    editTarget = target;
+    document.getElementById('editName').value = bm.name;
+    document.getElementById('editUrl').value = bm.url;
+    document.getElementById('editMnemonic').value = bm.mnemonic || '';
+    document.getElementById('editContainer').value = bm.container || '';
+    document.getElementById('editNotes').value = bm.notes || '';
+    document.getElementById('editRefresh').value = bm.refresh_secs || '';
+    document.getElementById('editCss').value = bm.inject_css || '';
+    document.getElementById('editJs').value = bm.inject_js || '';
+    document.getElementById('editAdvanced').open = !!(bm.inject_css || bm.inject_js);
+    document.getElementById('editBookmarkOverlay').classList.add('active');
+    activeModal = 'editBookmark';
+    document.getElementById('editName').focus();
+  }}
+
+  function submitEditBookmark() {{
+    const name = document.getElementById('editName').value.trim();
+    const url = document.getElementById('editUrl').value.trim();
+    if (!name || !url || !editTarget) return;
+    window.ipc.postMessage(JSON.stringify({{
+      action: 'edit_bookmark',
+      folder_index: editTarget.folder_index,
+      bookmark_index: editTarget.bookmark_index,
+      name: name,
+      url: url,
+      inject_css: document.getElementById('editCss').value,
+      inject_js: document.getElementById('editJs').value,
+      mnemonic: document.getElementById('editMnemonic').value.trim(),
+      container: document.getElementById('editContainer').value.trim(),
+      notes: document.getElementById('editNotes').value,
+      refresh_secs: parseInt(document.getElementById('editRefresh').value, 10) || 0
+    }}));
+    closeModals();
+  }}
+
+  function submitAddBookmark() {{
+    const name = document.getElementById('bmName').value.trim();
+    const url = document.getElementById('bmUrl').value.trim();
+    const fi = parseInt(document.getElementById('bmFolder').value, 10);
+    const notes = document.getElementById('bmNotes').value;
+    if (!name || !url) return;
+    window.ipc.postMessage(JSON.stringify({{ action: 'add_bookmark', folder_index: fi, name: name, url: url, notes: notes }}));
+    closeModals();
+  }}
+
+  // Keyboard-only folder creation: an editable row in the tree, committed
+  // with Enter and dropped with Escape. Its keys never reach the document
+  // handler, so modal Enter handling is unaffected
+  function showInlineFolder() {{
+    const existing = document.getElementById('inlineFolderInput');
+    if (existing) {{
+      existing.focus();
+      return;
+    }}
+    try {{
+      if (viewMode !== 'tree') throw new Error('inline folders need the tree view');
+      closeModals();
+      const tree = document.getElementById('tree');
+      const row = document.createElement('div');
+      row.className = 'folder-header inline-folder';
+      const arrow = document.createElement('span');
+      arrow.className = 'folder-arrow';
+      arrow.textContent = '\u25B6';
+      const input = document.createElement('input');
+      input.type = 'text';
+      input.id = 'inlineFolderInput';
+      input.placeholder = 'Folder name';
+      row.appendChild(arrow);
+      row.appendChild(input);
+      if (savedNewFolderPosition === 'top') tree.insertBefore(row, tree.firstChild);
+      else tree.appendChild(row);
+
+      let done = false;
+      const finish = function(commit) {{
+        if (done) return;
+        done = true;
+        const name = input.value.trim();
+        row.remove();
+        if (commit && name) {{
+          window.ipc.postMessage(JSON.stringify({{ action: 'add_folder', name: name, icon: '' }}));
+        }} else {{
+          restoreTreeFocus();
+        }}
+      }};
+      input.addEventListener('keydown', function(e) {{
+        e.stopPropagation();
+        if (isComposing(e)) return;
+        if (e.key === 'Enter') {{
+          e.preventDefault();
+          finish(true);
+        }} else if (e.key === 'Escape') {{
+          e.preventDefault();
+          finish(false);
+        }}
+      }});
+      input.addEventListener('blur', function() {{ finish(false); }});
+      row.scrollIntoView({{ block: 'nearest' }});
+      input.focus();
+    }} catch (err) {{
+      const row = document.querySelector('.inline-folder');
+      if (row) row.remove();
+      showAddFolderModal();
+    }}
+  }}
+
+  function submitAddFolder() {{
+    if (editingFolder !== null) {{
+      window.ipc.postMessage(JSON.stringify({{ action: 'set_folder_icon', folder_index: editingFolder, icon: selectedIcon || '' }}));
+      closeModals();
+      return;
+    }}
+    const name = document.getElementById('folderName').value.trim();
+    if (!name) return;
+    window.ipc.postMessage(JSON.stringify({{ action: 'add_folder', name: name, icon: selectedIcon || '' }}));
+    closeModals();
+  }}
+
+  function collapseSidebar() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'toggle_sidebar' }}));
+  }}
+
+  function expandSidebar() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'toggle_sidebar' }}));
+  }}
+
+  function setSidebarCollapsed(collapsed) {{
+    if (collapsed) {{
+      document.body.classList.add('collapsed');
+    }} else {{
+      document.body.classList.remove('collapsed');
+    }}
+  }}
+
+  let savedHasToken = {has_token};
+  let savedRepo = '{repo}';
+  let savedDecorations = {decorations};
+  let savedAutoSyncMode = '{auto_sync_mode}';
+  let savedAlwaysOnTop = {always_on_top};
+  let savedTheme = '{theme_setting}';
+  let savedNewFolderPosition = '{new_folder_position}';
+  let savedNewBookmarkPosition = '{new_bookmark_position}';
+  let savedDuplicateStrategy = '{duplicate_strategy}';
+  let savedCloseAction = '{close_action}';
+  let savedOnDuplicateFolder = '{on_duplicate_folder}';
+  let savedConfirmFormLeave = {confirm_form_leave};
+  let savedSyncOnFocus = {sync_on_focus};
+  let savedRunBookmarklets = {run_bookmarklets};
+  let savedBlockTrackers = {block_trackers};
+  let savedShowPullChanges = {show_pull_changes};
+  let savedAutoExpandActive = {auto_expand_active};
+  let savedPrettyJson = {pretty_json};
+  let savedRecentlyAdded = {recently_added};
+  let savedSyncProvider = '{sync_provider}';
+  let savedGiteaBaseUrl = '{gitea_base_url}';
+  // Updated by the host after a save, since an invalid address isn't kept
+  let savedHomeUrl = {home_url_json};
+
+  function updateProviderFields() {{
+    const gitea = document.getElementById('syncProvider').value === 'gitea';
+    document.getElementById('giteaFields').style.display = gitea ? '' : 'none';
+    document.getElementById('ghTokenLabel').textContent = gitea ? 'Access token' : 'GitHub Personal Access Token';
   }}
-  .modal h3 {{
-    font-size: 14px;
-    margin-bottom: 12px;
-    color: var(--text);
+
+  function providerFields() {{
+    return {{
+      sync_provider: document.getElementById('syncProvider').value,
+      gitea_base_url: document.getElementById('giteaBaseUrl').value.trim()
+    }};
   }}
-  .modal label {{
-    display: block;
-    font-size: 12px;
-    color: var(--subtext);
-    margin-bottom: 4px;
+
+  function showSettingsModal() {{
+    document.getElementById('ghToken').value = '';
+    document.getElementById('ghToken').placeholder = savedHasToken ? '(token saved - enter new to change)' : 'ghp_...';
+    document.getElementById('ghRepo').value = savedRepo;
+    document.getElementById('syncProvider').value = savedSyncProvider;
+    document.getElementById('giteaBaseUrl').value = savedGiteaBaseUrl;
+    document.getElementById('homeUrl').value = savedHomeUrl;
+    updateProviderFields();
+    document.getElementById('density').value = document.body.dataset.density;
+    document.getElementById('scrollbars').value = document.body.dataset.scrollbars;
+    document.getElementById('fontScale').value =
+      getComputedStyle(document.documentElement).getPropertyValue('--font-scale').trim();
+    document.getElementById('autoSyncMode').value = savedAutoSyncMode;
+    document.getElementById('theme').value = savedTheme;
+    document.getElementById('newFolderPosition').value = savedNewFolderPosition;
+    document.getElementById('newBookmarkPosition').value = savedNewBookmarkPosition;
+    document.getElementById('duplicateStrategy').value = savedDuplicateStrategy;
+    document.getElementById('closeAction').value = savedCloseAction;
+    document.getElementById('onDuplicateFolder').value = savedOnDuplicateFolder;
+    document.getElementById('showUrls').checked = showUrls;
+    document.getElementById('showUnreadBadge').checked = showUnreadBadge;
+    document.getElementById('decorations').checked = savedDecorations;
+    document.getElementById('alwaysOnTop').checked = savedAlwaysOnTop;
+    document.getElementById('confirmFormLeave').checked = savedConfirmFormLeave;
+    document.getElementById('syncOnFocus').checked = savedSyncOnFocus;
+    document.getElementById('runBookmarklets').checked = savedRunBookmarklets;
+    document.getElementById('blockTrackers').checked = savedBlockTrackers;
+    document.getElementById('showPullChanges').checked = savedShowPullChanges;
+    document.getElementById('autoExpandActive').checked = savedAutoExpandActive;
+    document.getElementById('prettyJson').checked = savedPrettyJson;
+    document.getElementById('recentlyAdded').value = String(savedRecentlyAdded);
+    document.getElementById('tokenTestResult').className = 'token-test';
+    document.getElementById('settingsOverlay').classList.add('active');
+    activeModal = 'settings';
+    document.getElementById('ghToken').focus();
   }}
-  .modal input, .modal select {{
-    width: 100%;
-    padding: 6px 8px;
-    margin-bottom: 10px;
-    background: var(--surface0);
-    border: 1px solid var(--surface1);
-    border-radius: 4px;
-    color: var(--text);
-    font-size: 13px;
-    font-family: inherit;
-    outline: none;
+
+  function testToken() {{
+    window.ipc.postMessage(JSON.stringify(Object.assign({{
+      action: 'test_token',
+      github_token: document.getElementById('ghToken').value.trim(),
+      github_repo: document.getElementById('ghRepo').value.trim()
+    }}, providerFields())));
   }}
-  .modal select {{
-    appearance: none;
-    -webkit-appearance: none;
-    overflow: hidden;
-    text-overflow: ellipsis;
-    background-image: url("data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' width='12' height='12' viewBox='0 0 12 12'%3E%3Cpath fill='%23a6adc8' d='M6 8L1 3h10z'/%3E%3C/svg%3E");
-    background-repeat: no-repeat;
-    background-position: right 8px center;
-    padding-right: 28px;
+
+  // ok is true/false for a finished test, null while it is still running
+  function showTokenTestResult(msg, ok) {{
+    const el = document.getElementById('tokenTestResult');
+    el.textContent = msg;
+    el.className = 'token-test active' + (ok === true ? ' ok' : ok === false ? ' fail' : '');
   }}
-  .modal select option {{
-    background: var(--surface0);
-    color: var(--text);
+
+  function submitSaveSettings() {{
+    const token = document.getElementById('ghToken').value.trim();
+    const repo = document.getElementById('ghRepo').value.trim();
+    const density = document.getElementById('density').value;
+    savedAutoSyncMode = document.getElementById('autoSyncMode').value;
+    savedDecorations = document.getElementById('decorations').checked;
+    savedAlwaysOnTop = document.getElementById('alwaysOnTop').checked;
+    savedTheme = document.getElementById('theme').value;
+    savedNewFolderPosition = document.getElementById('newFolderPosition').value;
+    savedNewBookmarkPosition = document.getElementById('newBookmarkPosition').value;
+    savedDuplicateStrategy = document.getElementById('duplicateStrategy').value;
+    savedCloseAction = document.getElementById('closeAction').value;
+    savedOnDuplicateFolder = document.getElementById('onDuplicateFolder').value;
+    savedConfirmFormLeave = document.getElementById('confirmFormLeave').checked;
+    savedSyncOnFocus = document.getElementById('syncOnFocus').checked;
+    savedRunBookmarklets = document.getElementById('runBookmarklets').checked;
+    savedBlockTrackers = document.getElementById('blockTrackers').checked;
+    savedShowPullChanges = document.getElementById('showPullChanges').checked;
+    savedAutoExpandActive = document.getElementById('autoExpandActive').checked;
+    savedPrettyJson = document.getElementById('prettyJson').checked;
+    savedRecentlyAdded = parseInt(document.getElementById('recentlyAdded').value, 10);
+    const provider = providerFields();
+    if (provider.sync_provider === 'gitea' && !provider.gitea_base_url) {{
+      showTokenTestResult('Enter the server address', false);
+      return;
+    }}
+    savedSyncProvider = provider.sync_provider;
+    savedGiteaBaseUrl = provider.gitea_base_url;
+    window.ipc.postMessage(JSON.stringify(Object.assign({{
+      action: 'save_settings',
+      github_token: token,
+      github_repo: repo,
+      density: density,
+      scrollbars: document.getElementById('scrollbars').value,
+      ui_font_scale: parseFloat(document.getElementById('fontScale').value),
+      decorations: savedDecorations,
+      always_on_top: savedAlwaysOnTop,
+      auto_sync_mode: savedAutoSyncMode,
+      show_urls: document.getElementById('showUrls').checked,
+      show_unread_badge: document.getElementById('showUnreadBadge').checked,
+      theme: savedTheme,
+      new_folder_position: savedNewFolderPosition,
+      new_bookmark_position: savedNewBookmarkPosition,
+      duplicate_strategy: savedDuplicateStrategy,
+      close_action: savedCloseAction,
+      on_duplicate_folder: savedOnDuplicateFolder,
+      confirm_form_leave: savedConfirmFormLeave,
+      sync_on_focus: savedSyncOnFocus,
+      run_bookmarklets: savedRunBookmarklets,
+      block_trackers: savedBlockTrackers,
+      show_pull_changes: savedShowPullChanges,
+      auto_expand_active: savedAutoExpandActive,
+      pretty_json: savedPrettyJson,
+      recently_added: savedRecentlyAdded,
+      home_url: document.getElementById('homeUrl').value.trim()
+    }}, provider)));
+    if (token) savedHasToken = true;
+    savedRepo = repo;
+    closeModals();
+    renderBookmarks(folders);
   }}
-  .modal input:focus, .modal select:focus {{
-    border-color: var(--accent);
+
+  // Called by the host when the resolved theme changes (e.g. the system switched)
+  function setTheme(theme) {{
+    document.documentElement.dataset.theme = theme;
   }}
-  .modal-buttons {{
-    display: flex;
-    gap: 8px;
-    margin-top: 4px;
+
+  function updateSettings(hasToken, repo) {{
+    savedHasToken = hasToken;
+    savedRepo = repo;
   }}
-  .modal-buttons button {{
-    flex: 1;
-    padding: 6px 8px;
-    border: none;
-    border-radius: 4px;
-    cursor: pointer;
-    font-size: 13px;
-    font-family: inherit;
+
+  // Marks the Push button while local bookmarks differ from the last sync
+  function setDirty(dirty) {{
+    const btn = document.getElementById('pushBtn');
+    btn.classList.toggle('dirty', dirty);
+    btn.title = dirty
+      ? 'Push to GitHub (Ctrl+U) \u2014 unpushed changes'
+      : 'Push to GitHub (Ctrl+U)';
   }}
-  .btn-cancel {{
-    background: var(--surface0);
-    color: var(--subtext);
+
+  function pushToGitHub() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'push_to_github' }}));
   }}
-  .btn-cancel:hover {{
-    background: var(--surface1);
-    color: var(--text);
+
+  // Called by the host when a push looks like it would wipe the remote
+  function confirmRiskyPush(msg) {{
+    if (confirm(msg + '.\n\nPush anyway?')) {{
+      window.ipc.postMessage(JSON.stringify({{ action: 'confirm_push' }}));
+    }} else {{
+      updateSyncStatus('Push cancelled', 'error');
+    }}
   }}
-  .btn-primary {{
-    background: var(--accent);
-    color: var(--base);
-    font-weight: 600;
+
+  function syncBoth() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'sync_both' }}));
   }}
-  .btn-primary:hover {{
-    opacity: 0.9;
+
+  function pullFromGitHub() {{
+    window.ipc.postMessage(JSON.stringify({{ action: 'pull_from_github' }}));
   }}
-  .help-table {{
-    width: 100%;
-    margin-bottom: 12px;
+
+  let syncTimer = null;
+
+  function updateSyncStatus(msg, type) {{
+    var el = document.getElementById('syncStatus');
+    if (syncTimer) {{ clearTimeout(syncTimer); syncTimer = null; }}
+    el.className = 'sync-status';
+    if (msg) {{
+      el.textContent = msg;
+      if (!type) {{
+        if (msg.indexOf('successfully') !== -1 || msg.indexOf('Last synced') !== -1) type = 'success';
+        else if (msg.indexOf('failed') !== -1 || msg.indexOf('error') !== -1 || msg.indexOf('No token') !== -1 || msg.indexOf('No repo') !== -1 || msg.indexOf('not found') !== -1) type = 'error';
+        else type = 'progress';
+      }}
+      el.classList.add('active', 'status-' + type);
+      if (type === 'success') {{
+        syncTimer = setTimeout(function() {{ el.classList.remove('active'); }}, 5000);
+      }} else if (type === 'error') {{
+        syncTimer = setTimeout(function() {{ el.classList.remove('active'); }}, 8000);
+      }}
+    }} else {{
+      el.classList.remove('active');
+    }}
   }}
-  .help-table td {{
-    padding: 4px 0;
-    font-size: 13px;
+
+  // Called by the host when F6 moves focus here: back to the last focused row
+  function focusSidebar() {{
+    window.focus();
+    const rows = treeRows();
+    const row = rows.find(function(r) {{ return r.dataset.key === focusKey; }}) || rows[0];
+    if (row) focusRow(row);
   }}
-  .help-key {{
-    color: var(--accent);
-    font-family: monospace;
-    padding-right: 12px;
-    white-space: nowrap;
+
+  window.addEventListener('focus', function() {{
+    document.body.classList.add('pane-focused');
+    window.ipc.postMessage(JSON.stringify({{ action: 'sidebar_focused', focused: true }}));
+  }});
+  window.addEventListener('blur', function() {{
+    document.body.classList.remove('pane-focused');
+    window.ipc.postMessage(JSON.stringify({{ action: 'sidebar_focused', focused: false }}));
+  }});
+  // While a text field has focus, Ctrl+Z is its own undo, not the app's
+  function reportTextFocus(e) {{
+    const t = e.type === 'focusin' ? e.target : null;
+    const editing = !!t && (t.isContentEditable || t.tagName === 'TEXTAREA' ||
+      (t.tagName === 'INPUT' && !/^(checkbox|radio|button|submit|range|color|file)$/.test(t.type)));
+    window.ipc.postMessage(JSON.stringify({{ action: 'text_focus', editing: editing }}));
   }}
-  .sync-status {{
-    display: none;
-    padding: 6px 8px;
-    font-size: 11px;
-    border-top: 1px solid var(--surface0);
-    flex-shrink: 0;
-    text-align: center;
-    cursor: pointer;
+  document.addEventListener('focusin', reportTextFocus, true);
+  document.addEventListener('focusout', reportTextFocus, true);
+
+  // With an input method (CJK and the like) Enter and Escape first belong to
+  // the composition: they commit or cancel it and must not submit or close
+  // the dialog. WebKit sends the committing keydown after compositionend,
+  // marked only by keyCode 229, so that is checked as well.
+  let composing = false;
+  document.addEventListener('compositionstart', function() {{ composing = true; }}, true);
+  document.addEventListener('compositionend', function() {{ composing = false; }}, true);
+
+  function isComposing(e) {{
+    return composing || e.isComposing || e.keyCode === 229;
   }}
-  .sync-status.active {{
-    display: block;
+
+  // Escape undoes one thing at a time, innermost first: an open dialog, then
+  // a filter being typed, then the selection and a pending cut. Menus and
+  // the inline folder field aren't dialogs, so closeModals() still runs.
+  function handleEscape(e) {{
+    if (activeModal) {{
+      closeModals();
+    }} else if (e.target.id === 'searchBox') {{
+      e.preventDefault();
+      clearSearch();
+      restoreTreeFocus();
+    }} else {{
+      if (selection.size > 0) clearSelection();
+      if (clipboardItem) cancelCut();
+      closeModals();
+    }}
   }}
-  .sync-status.status-progress {{
-    color: var(--subtext);
+
+  function openMnemonic(fi, bi) {{
+    if (isHidden(folders[fi])) {{
+      showPinModal(fi, 'unlock');
+      return;
+    }}
+    navigate(folders[fi].bookmarks[bi].url);
   }}
-  .sync-status.status-success {{
-    color: var(--green);
+
+  document.addEventListener('keydown', function(e) {{
+    if (isComposing(e)) return;
+    // Application shortcuts (Ctrl+D, Ctrl+L and the rest) belong to the
+    // window's key handler, which sees every key press first; handling them
+    // here as well would run them twice
+    if (e.key === 'F6') {{
+      e.preventDefault();
+      closeModals();
+      window.ipc.postMessage(JSON.stringify({{ action: 'focus_content' }}));
+    }} else if (e.key === 'Escape') {{
+      handleEscape(e);
+    }} else if (e.key === 'Enter' && activeModal && e.target.tagName !== 'TEXTAREA') {{
+      e.preventDefault();
+      if (activeModal === 'addBookmark') submitAddBookmark();
+      else if (activeModal === 'editBookmark') submitEditBookmark();
+      else if (activeModal === 'addFolder') submitAddFolder();
+      else if (activeModal === 'auth') submitAuth();
+      else if (activeModal === 'replace') submitReplaceUrls();
+      else if (activeModal === 'reset') submitResetLibrary();
+      else if (activeModal === 'pin') submitPin();
+      else if (activeModal === 'omnibar') submitOmnibar();
+    }} else if (!activeModal && handleTreeKey(e)) {{
+      e.preventDefault();
+    }}
+  }});
+
+  document.getElementById('bmUrl').addEventListener('input', requestFolderSuggestion);
+  document.getElementById('bmFolder').addEventListener('change', function() {{
+    folderChosen = true;
+  }});
+
+  document.getElementById('syncStatus').addEventListener('click', function() {{
+    updateSyncStatus(null);
+  }});
+
+  updateViewButton();
+  updateHealth(linkHealth);
+  window.ipc.postMessage(JSON.stringify({{ action: 'sidebar_ready' }}));
+</script>
+</body>
+</html>"#,
+        folders_json = folders_json,
+        has_token = has_token,
+        density = density,
+        scrollbars = scrollbars,
+        font_scale = settings.ui_font_scale(),
+        decorations = settings.decorations,
+        show_urls = settings.show_urls,
+        show_unread_badge = settings.show_unread_badge,
+        visits_tracked = settings.history_retention_days() > 0,
+        auto_sync_mode = settings.auto_sync_mode(),
+        always_on_top = settings.always_on_top,
+        theme_setting = settings.theme(),
+        new_folder_position = settings.new_folder_position(),
+        new_bookmark_position = settings.new_bookmark_position(),
+        duplicate_strategy = settings.duplicate_strategy().as_str(),
+        close_action = settings.close_action(),
+        on_duplicate_folder = settings.on_duplicate_folder(),
+        confirm_form_leave = settings.confirm_form_leave,
+        sync_on_focus = settings.sync_on_focus,
+        run_bookmarklets = settings.run_bookmarklets,
+        block_trackers = settings.block_trackers,
+        show_pull_changes = settings.show_pull_changes,
+        auto_expand_active = settings.auto_expand_active,
+        pretty_json = settings.pretty_json,
+        recently_added = settings.recently_added,
+        max_notes_chars = MAX_NOTES_CHARS,
+        min_refresh_secs = MIN_REFRESH_SECS,
+        sync_provider = settings.sync_api().map_or("github", |api| api.provider()),
+        gitea_base_url = gitea_base_url,
+        home_url_json = script_json(&settings.home_url, "''"),
+        pip_url_json = script_json(&settings.pip_url, "''"),
+        health_json = health_json,
+        thumbnail_base = THUMBNAIL_BASE_URL,
+        view_mode = settings.view_mode(),
+        experiments_json = experiments_json,
+        help_rows = help_rows(SHORTCUTS),
+        repo = repo
+    )
+}
+
+/// Stand-in for a sidebar whose page never reported ready, most likely
+/// broken markup or script from `sidebar_html`. Static, so the same bug
+/// can't break it too.
+fn sidebar_failed_html(theme: &str) -> String {
+    let (base, text, subtext, accent) = match theme {
+        "light" => ("#eff1f5", "#4c4f69", "#6c6f85", "#8839ef"),
+        "high_contrast" => ("#000000", "#ffffff", "#e6e6e6", "#ffd400"),
+        _ => ("#1e1e2e", "#cdd6f4", "#a6adc8", "#cba6f7"),
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<body style="margin: 0; padding: 16px; background: {base}; color: {text}; font: 13px/1.5 system-ui, sans-serif;">
+<h3 style="margin: 0 0 8px;">Sidebar failed to initialize</h3>
+<p style="margin: 0 0 12px; color: {subtext};">The bookmark list didn't finish loading. Your bookmarks are safe; run the app from a terminal to see the error.</p>
+<button style="padding: 6px 12px; border: 0; border-radius: 4px; background: {accent}; color: {base}; cursor: pointer;" onclick="window.ipc.postMessage(JSON.stringify({{ action: 'reload_sidebar' }}))">Try again</button>
+</body>
+</html>"#
+    )
+}
+
+/// Sends `SidebarCheck(generation)` once the ready timeout has passed.
+fn watch_sidebar(proxy: EventLoopProxy<UserEvent>, generation: u64) {
+    std::thread::spawn(move || {
+        std::thread::sleep(SIDEBAR_READY_TIMEOUT);
+        let _ = proxy.send_event(UserEvent::SidebarCheck(generation));
+    });
+}
+
+fn welcome_html(theme: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html data-theme="{theme}">
+<head>
+<style>
+  :root {{
+    --base: #1e1e2e;
+    --mantle: #181825;
+    --text: #cdd6f4;
+    --subtext: #a6adc8;
+    --accent: #cba6f7;
   }}
-  .sync-status.status-error {{
-    color: var(--red);
+  :root[data-theme="light"] {{
+    --base: #eff1f5;
+    --mantle: #e6e9ef;
+    --text: #4c4f69;
+    --subtext: #6c6f85;
+    --accent: #8839ef;
   }}
-  /* Collapsed sidebar mode */
-  #expandBtn {{
-    display: none;
-    background: none;
-    border: none;
-    color: var(--text);
-    font-size: 16px;
-    cursor: pointer;
-    width: 100%;
-    flex: 1;
+  :root[data-theme="high_contrast"] {{
+    --base: #000000;
+    --mantle: #000000;
+    --text: #ffffff;
+    --subtext: #e6e6e6;
+    --accent: #ffd400;
   }}
-  #expandBtn:hover {{
-    background: var(--surface0);
-    color: var(--accent);
+  * {{ margin: 0; padding: 0; box-sizing: border-box; }}
+  body {{
+    background: var(--base);
+    color: var(--text);
+    font-family: system-ui, -apple-system, sans-serif;
+    display: flex;
+    align-items: center;
+    justify-content: center;
+    height: 100vh;
   }}
-  body.collapsed #tree,
-  body.collapsed .bottom-bar,
-  body.collapsed .sync-status,
-  body.collapsed .modal-overlay {{
-    display: none !important;
+  .welcome {{
+    text-align: center;
   }}
-  body.collapsed #expandBtn {{
-    display: block;
+  .welcome h1 {{
+    font-size: 24px;
+    font-weight: 600;
+    color: var(--text);
+    margin-bottom: 8px;
   }}
-  body.collapsed {{
-    border-right: 1px solid var(--surface0);
+  .welcome p {{
+    font-size: 14px;
+    color: var(--subtext);
   }}
 </style>
 </head>
-<body class="{collapsed_class}">
-<button id="expandBtn" onclick="expandSidebar()" title="Expand sidebar (Ctrl+B)">&raquo;</button>
-<div id="tree"></div>
-<div id="syncStatus" class="sync-status"></div>
-<div class="bottom-bar" style="flex-wrap:wrap;">
-  <button class="bar-btn" onclick="pushToGitHub()" title="Push to GitHub (Ctrl+U)">&#x2191; Push</button>
-  <button class="bar-btn" onclick="pullFromGitHub()" title="Pull from GitHub (Ctrl+I)">&#x2193; Pull</button>
-  <button class="bar-btn" onclick="showAddFolderModal()">+ Folder</button>
-  <button class="bar-btn" onclick="showSettingsModal()" title="Settings">&#x2699; Settings</button>
-  <button class="bar-btn" onclick="showHelpModal()">? Help</button>
-  <button class="bar-btn" onclick="collapseSidebar()" title="Collapse sidebar (Ctrl+B)">&laquo;</button>
-</div>
+<body>
+  <div class="welcome">
+    <h1>Select a bookmark</h1>
+    <p>Choose a bookmark from the sidebar to get started.</p>
+  </div>
+</body>
+</html>"#
+    )
+}
+
+const BLOCKER_SCRIPT: &str = r#"
+(function() {
+  var blocked = new Set(__BLOCKLIST__);
+  function hostBlocked(host) {
+    host = host.toLowerCase().replace(/\.$/, '');
+    while (host) {
+      if (blocked.has(host)) return true;
+      var dot = host.indexOf('.');
+      if (dot < 0) return false;
+      host = host.slice(dot + 1);
+    }
+    return false;
+  }
+  function urlBlocked(url) {
+    try {
+      return hostBlocked(new URL(String(url), location.href).hostname);
+    } catch (e) {
+      return false;
+    }
+  }
+  var origFetch = window.fetch;
+  if (origFetch) {
+    window.fetch = function(input) {
+      var url = typeof input === 'string' ? input : (input && input.url);
+      if (url && urlBlocked(url)) return Promise.reject(new TypeError('Blocked'));
+      return origFetch.apply(this, arguments);
+    };
+  }
+  var origOpen = XMLHttpRequest.prototype.open;
+  var origSend = XMLHttpRequest.prototype.send;
+  XMLHttpRequest.prototype.open = function(method, url) {
+    this.__bbBlocked = urlBlocked(url);
+    return origOpen.apply(this, arguments);
+  };
+  XMLHttpRequest.prototype.send = function() {
+    if (this.__bbBlocked) {
+      this.abort();
+      return;
+    }
+    return origSend.apply(this, arguments);
+  };
+  if (navigator.sendBeacon) {
+    var origBeacon = navigator.sendBeacon.bind(navigator);
+    navigator.sendBeacon = function(url, data) {
+      return urlBlocked(url) ? false : origBeacon(url, data);
+    };
+  }
+  function strip(el) {
+    if (el.nodeType !== 1 || !/^(SCRIPT|IMG|IFRAME)$/.test(el.tagName)) return;
+    var src = el.getAttribute('src');
+    if (!src || !urlBlocked(src)) return;
+    // A changed type keeps a parser-inserted script from running
+    if (el.tagName === 'SCRIPT') el.type = 'text/blocked';
+    el.removeAttribute('src');
+    el.remove();
+  }
+  new MutationObserver(function(records) {
+    records.forEach(function(record) {
+      record.addedNodes.forEach(function(node) {
+        strip(node);
+        if (node.querySelectorAll) {
+          node.querySelectorAll('script[src], img[src], iframe[src]').forEach(strip);
+        }
+      });
+    });
+  }).observe(document, { childList: true, subtree: true });
+})();
+"#;
+
+/// Injected into every page of the content webview. After a top-level page
+/// finishes loading it re-requests the same URL with `HEAD`; a 401 means the
+/// server wants HTTP Basic credentials, which WebKit would otherwise fail
+/// silently, so the host is asked to show a login prompt. The probe cannot
+/// see credentials the page was loaded with, so when the host already holds
+/// some for this origin it re-checks them itself (`probe_auth`); the header
+/// is never handed to the page.
+///
+/// It also reports the scroll offset (debounced, and once more on `pagehide`)
+/// and announces `page_ready` after `load` so the host can restore a saved
+/// offset via `__bbRestoreScroll`.
+const CONTENT_INIT_SCRIPT: &str = r#"
+(function() {
+  if (window.top !== window.self) return;
+  if (!/^https?:$/.test(location.protocol)) return;
+  function post(msg) {
+    window.ipc.postMessage(JSON.stringify(msg));
+  }
+  var scrollTimer = null;
+  function reportScroll() {
+    post({ action: 'scroll', url: location.href, y: window.scrollY });
+  }
+  window.addEventListener('scroll', function() {
+    if (scrollTimer) clearTimeout(scrollTimer);
+    scrollTimer = setTimeout(reportScroll, 300);
+  }, { passive: true });
+  window.addEventListener('pagehide', reportScroll);
+  window.__bbRestoreScroll = function(y) {
+    window.scrollTo(0, y);
+    // Late-loading images and fonts can shift layout; try once more
+    setTimeout(function() { window.scrollTo(0, y); }, 400);
+  };
+  function probe() {
+    fetch(location.href, { method: 'HEAD', credentials: 'include', cache: 'no-store' })
+      .then(function(res) {
+        if (res.status === 401) {
+          post({ action: 'auth_required' });
+        }
+      })
+      .catch(function() {});
+  }
+  // Edited form fields, reported once per change of state so the host can
+  // warn before a bookmark click throws the input away
+  var formDirty = false;
+  function setFormDirty(dirty) {
+    if (dirty === formDirty) return;
+    formDirty = dirty;
+    post({ action: 'form_dirty', dirty: dirty });
+  }
+  document.addEventListener('input', function(e) {
+    var t = e.target;
+    if (t && (t.form || t.isContentEditable || /^(INPUT|TEXTAREA|SELECT)$/.test(t.tagName))) {
+      setFormDirty(true);
+    }
+  }, true);
+  document.addEventListener('submit', function() { setFormDirty(false); }, true);
+  // While a text field has focus, Ctrl+Z is the page's undo, not the app's
+  function reportTextFocus(e) {
+    var t = e.type === 'focusin' ? e.target : null;
+    var editing = !!t && (t.isContentEditable || t.tagName === 'TEXTAREA' ||
+      (t.tagName === 'INPUT' && !/^(checkbox|radio|button|submit|range|color|file)$/.test(t.type)));
+    post({ action: 'text_focus', editing: editing });
+  }
+  document.addEventListener('focusin', reportTextFocus, true);
+  document.addEventListener('focusout', reportTextFocus, true);
+  document.addEventListener('keydown', function(e) {
+    if (e.key === 'F6') {
+      e.preventDefault();
+      post({ action: 'focus_sidebar' });
+    } else if (e.ctrlKey && e.shiftKey && e.key.toLowerCase() === 's') {
+      var text = String(window.getSelection() || '').trim();
+      if (!text) return;
+      e.preventDefault();
+      post({ action: 'clip_selection', title: document.title, text: text });
+    }
+  }, true);
+  window.addEventListener('load', function() {
+    post({ action: 'page_ready', title: document.title });
+    probe();
+  });
+})();
+"#;
+
+/// Briefly overlays the content page with a reminder of how to leave focus mode.
+const FOCUS_HINT_SCRIPT: &str = r#"
+(function() {
+  var hint = document.createElement('div');
+  hint.textContent = 'Focus mode — press F11 or Ctrl+B to show the sidebar';
+  hint.style.cssText = 'position:fixed;top:12px;left:50%;transform:translateX(-50%);z-index:2147483647;' +
+    'background:rgba(30,30,46,0.9);color:#cdd6f4;font:13px system-ui,sans-serif;padding:6px 12px;' +
+    'border-radius:6px;pointer-events:none;transition:opacity 0.4s;';
+  (document.body || document.documentElement).appendChild(hint);
+  setTimeout(function() { hint.style.opacity = '0'; }, 2000);
+  setTimeout(function() { hint.remove(); }, 2500);
+})();
+"#;
+
+/// Returns `scheme://host[:port]` for an http(s) URL, without any userinfo.
+fn url_origin(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or("");
+    if host.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{}://{}",
+        scheme.to_ascii_lowercase(),
+        host.to_ascii_lowercase()
+    ))
+}
+
+fn basic_auth_header(username: &str, password: &str) -> String {
+    format!("Basic {}", BASE64.encode(format!("{username}:{password}")))
+}
+
+/// How long the host-side check of stored credentials may take
+const AUTH_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Re-requests `url` with `HEAD` and the stored `Authorization` header, from
+/// this process rather than the page so page scripts never see the header.
+/// A 401 means the credentials were refused and is reported as `AuthFailed`;
+/// anything else, network errors included, leaves them in place.
+fn probe_auth(proxy: EventLoopProxy<UserEvent>, url: String, authorization: String) {
+    std::thread::spawn(move || {
+        let response = sync_agent(AUTH_PROBE_TIMEOUT)
+            .head(&url)
+            .header("Authorization", &authorization)
+            .call();
+        if let Err(ureq::Error::StatusCode(401)) = response {
+            let _ = proxy.send_event(UserEvent::AuthFailed(url));
+        }
+    });
+}
+
+/// Loads `url` in the content pane, attaching the session's Basic auth
+/// credentials for that origin if the user has supplied any.
+fn load_with_auth(content: &wry::WebView, url: &str, auth_headers: &HashMap<String, String>) {
+    let header = url_origin(url).and_then(|origin| auth_headers.get(&origin));
+    match header.and_then(|h| wry::http::HeaderValue::from_str(h).ok()) {
+        Some(value) => {
+            let mut headers = wry::http::HeaderMap::new();
+            headers.insert(wry::http::header::AUTHORIZATION, value);
+            let _ = content.load_url_with_headers(url, headers);
+        }
+        None => {
+            let _ = content.load_url(url);
+        }
+    }
+}
+
+/// Saves the store, warning in the sidebar if bookmarks.json had been edited
+/// outside the app since we last touched it, and redraws the tree.
+fn save_and_render(
+    store: &BookmarkStore,
+    stamp: &mut Option<FileStamp>,
+    keep: usize,
+    sidebar: &wry::WebView,
+) {
+    if store.save_tracked(stamp, keep).unwrap_or(false) {
+        let _ = sidebar.evaluate_script(EXTERNAL_EDIT_SCRIPT);
+    }
+    if let Ok(json) = serde_json::to_string(&store.folders) {
+        let _ = sidebar.evaluate_script(&format!("renderBookmarks({json})"));
+    }
+}
+
+/// The end of every edit to the library: save with a rolling backup,
+/// redraw, and let auto-sync push it if that is turned on.
+fn commit_edit(
+    store: &BookmarkStore,
+    stamp: &mut Option<FileStamp>,
+    settings: &Settings,
+    sidebar: &wry::WebView,
+    proxy: &EventLoopProxy<UserEvent>,
+) {
+    save_and_render(store, stamp, settings.backup_keep(), sidebar);
+    let _ = proxy.send_event(UserEvent::AutoSync);
+}
+
+/// A mirror repository that receives every push, configured in settings.json.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+struct SyncTarget {
+    /// "github" (default) or "gitea"
+    #[serde(default)]
+    provider: String,
+    /// Gitea/Forgejo address; unused for GitHub
+    #[serde(default)]
+    base_url: String,
+    #[serde(default)]
+    repo: String,
+    #[serde(default)]
+    token: String,
+}
+
+impl SyncTarget {
+    fn api(&self) -> Result<SyncApi, String> {
+        SyncApi::new(&self.provider, &self.base_url)
+    }
+
+    /// "owner/repo (provider)", as reports name the mirror
+    fn label(&self) -> String {
+        let provider = match self.provider.as_str() {
+            "" => "github",
+            other => other,
+        };
+        format!("{} ({provider})", self.repo)
+    }
+
+    /// Overwrites the mirror's `bookmarks.json`. Mirrors aren't pulled from,
+    /// so their SHA is looked up fresh every time.
+    fn push(&self, bookmarks_json: &str, timeout: Duration) -> Result<String, String> {
+        self.api()
+            .and_then(|api| do_push(&api, &self.token, &self.repo, bookmarks_json, None, timeout))
+    }
+}
+
+/// Pushes to each mirror, overwriting whatever is there, and returns a
+/// message per failure.
+fn push_mirrors(mirrors: &[SyncTarget], bookmarks_json: &str, timeout: Duration) -> Vec<String> {
+    mirrors
+        .iter()
+        .filter_map(|mirror| {
+            mirror
+                .push(bookmarks_json, timeout)
+                .err()
+                .map(|e| format!("{}: {e}", mirror.label()))
+        })
+        .collect()
+}
+
+/// Status line once a push has been tried on all `targets`.
+fn push_summary(targets: usize, failures: &[String]) -> String {
+    let pushed = targets.saturating_sub(failures.len());
+    if failures.is_empty() {
+        format!("Pushed to {pushed}/{targets} targets")
+    } else {
+        format!(
+            "Pushed to {pushed}/{targets} targets — {}",
+            failures.join("; ")
+        )
+    }
+}
+
+/// Runs the mirror pushes after the primary one, whatever its outcome, then
+/// reports both: the primary result as usual, followed by a `PushReport`
+/// when there are mirrors. The sync stays busy until the mirrors are done.
+fn finish_push(
+    proxy: &EventLoopProxy<UserEvent>,
+    primary: Result<String, String>,
+    mirrors: &[SyncTarget],
+    bookmarks_json: &str,
+    timeout: Duration,
+    failure: &str,
+) {
+    let mut failures = Vec::new();
+    if !mirrors.is_empty() {
+        if let Err(e) = &primary {
+            failures.push(format!("primary: {e}"));
+        }
+        failures.extend(push_mirrors(mirrors, bookmarks_json, timeout));
+    }
+    match primary {
+        Ok(new_sha) => {
+            let _ = proxy.send_event(UserEvent::PushComplete(Some(new_sha)));
+        }
+        Err(e) => {
+            let _ = proxy.send_event(UserEvent::SyncStatus(format!("{failure}: {e}")));
+        }
+    }
+    if !mirrors.is_empty() {
+        let _ = proxy.send_event(UserEvent::PushReport {
+            message: push_summary(mirrors.len() + 1, &failures),
+            ok: failures.is_empty(),
+        });
+    }
+}
+
+/// The host whose contents API holds `bookmarks.json`. Gitea and Forgejo
+/// mirror GitHub's API under `/api/v1`, except that creating a file is a POST.
+#[derive(Debug, Clone, PartialEq)]
+enum SyncApi {
+    GitHub,
+    Gitea(String),
+}
+
+impl SyncApi {
+    /// An empty provider means GitHub; the base URL matters only for Gitea.
+    /// Anything else is refused rather than guessed, so a token meant for
+    /// another host is never sent to GitHub.
+    fn new(provider: &str, base_url: &str) -> Result<SyncApi, String> {
+        match provider {
+            "" | "github" => Ok(SyncApi::GitHub),
+            "gitea" => Ok(SyncApi::Gitea(
+                base_url.trim().trim_end_matches('/').to_string(),
+            )),
+            other => Err(format!(
+                "Unknown sync provider \"{other}\" — use \"github\" or \"gitea\""
+            )),
+        }
+    }
+
+    fn provider(&self) -> &'static str {
+        match self {
+            SyncApi::GitHub => "github",
+            SyncApi::Gitea(_) => "gitea",
+        }
+    }
+
+    /// How error messages name the host
+    fn display_name(&self) -> &'static str {
+        match self {
+            SyncApi::GitHub => "GitHub",
+            SyncApi::Gitea(_) => "Gitea",
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        match self {
+            SyncApi::GitHub => format!("https://api.github.com{path}"),
+            SyncApi::Gitea(base) => format!("{base}/api/v1{path}"),
+        }
+    }
+
+    fn contents_url(&self, repo: &str) -> String {
+        self.url(&format!("/repos/{repo}/contents/bookmarks.json"))
+    }
+
+    fn request<B>(&self, builder: ureq::RequestBuilder<B>, token: &str) -> ureq::RequestBuilder<B> {
+        let builder = builder
+            .header("Authorization", &format!("token {token}"))
+            .header("User-Agent", "bookmarks-browser");
+        match self {
+            SyncApi::GitHub => builder.header("Accept", "application/vnd.github+json"),
+            SyncApi::Gitea(_) => builder.header("Accept", "application/json"),
+        }
+    }
+}
+
+fn format_ureq_error(api: &SyncApi, e: ureq::Error) -> String {
+    let host = api.display_name();
+    match e {
+        ureq::Error::StatusCode(401) => format!("Invalid or expired {host} token"),
+        ureq::Error::StatusCode(404) => {
+            "Repository or file not found — check owner/repo".to_string()
+        }
+        ureq::Error::StatusCode(409) => "SHA conflict — pull first, then push again".to_string(),
+        ureq::Error::StatusCode(422) => "Validation error — check repo name format".to_string(),
+        ureq::Error::StatusCode(code) => format!("{host} API error (HTTP {code})"),
+        ureq::Error::Timeout(_) => "Request timed out — try again".to_string(),
+        ureq::Error::HostNotFound => format!("Could not reach {host} — check your connection"),
+        ureq::Error::ConnectionFailed => "Connection failed — check your connection".to_string(),
+        _ => format!("{e}"),
+    }
+}
+
+/// Builds an agent whose requests give up after `timeout` in total, so a
+/// stalled connection surfaces as an error instead of hanging the sync.
+fn sync_agent(timeout: Duration) -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .build()
+        .into()
+}
+
+/// Checks that a token authenticates and, when a repo is given, that the repo
+/// is reachable and writable with it.
+fn do_test_token(
+    api: &SyncApi,
+    token: &str,
+    repo: &str,
+    timeout: Duration,
+) -> Result<String, String> {
+    if matches!(api, SyncApi::Gitea(base) if base.is_empty()) {
+        return Err("No server address entered".to_string());
+    }
+    let url = if repo.is_empty() {
+        api.url("/user")
+    } else {
+        api.url(&format!("/repos/{repo}"))
+    };
+    let agent = sync_agent(timeout);
+
+    let mut response = api
+        .request(agent.get(&url), token)
+        .call()
+        .map_err(|e| format_ureq_error(api, e))?;
+
+    if repo.is_empty() {
+        return Ok("Token OK (no repository set)".to_string());
+    }
+
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| format!("Failed to read response: {e}"))?;
+    let parsed: serde_json::Value =
+        serde_json::from_str(&body).map_err(|_| "Malformed response from GitHub".to_string())?;
+    let can_push = parsed
+        .get("permissions")
+        .and_then(|p| p.get("push"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if can_push {
+        Ok("Token OK, repo accessible".to_string())
+    } else {
+        Err("Token OK, but it cannot push to this repo — check its scopes".to_string())
+    }
+}
+
+fn get_file_sha(
+    api: &SyncApi,
+    token: &str,
+    repo: &str,
+    timeout: Duration,
+) -> Result<Option<String>, String> {
+    let url = api.contents_url(repo);
+    let agent = sync_agent(timeout);
+
+    match api.request(agent.get(&url), token).call() {
+        Ok(mut response) => {
+            let body = response
+                .body_mut()
+                .read_to_string()
+                .map_err(|e| format!("Failed to read response: {e}"))?;
+            let parsed: serde_json::Value = serde_json::from_str(&body)
+                .map_err(|_| "Malformed response from GitHub".to_string())?;
+            Ok(parsed.get("sha").and_then(|v| v.as_str()).map(String::from))
+        }
+        Err(ureq::Error::StatusCode(404)) => Ok(None),
+        Err(e) => Err(format_ureq_error(api, e)),
+    }
+}
+
+fn do_push(
+    api: &SyncApi,
+    token: &str,
+    repo: &str,
+    bookmarks_json: &str,
+    sha: Option<&str>,
+    timeout: Duration,
+) -> Result<String, String> {
+    let encoded = BASE64.encode(bookmarks_json.as_bytes());
+
+    let sha = match sha {
+        Some(s) => Some(s.to_string()),
+        None => get_file_sha(api, token, repo, timeout)?,
+    };
+
+    let mut payload = serde_json::json!({
+        "message": "Update bookmarks",
+        "content": encoded,
+    });
+    if let Some(ref sha_val) = sha {
+        payload["sha"] = serde_json::json!(sha_val);
+    }
+
+    let url = api.contents_url(repo);
+    let agent = sync_agent(timeout);
+
+    // GitHub creates and updates with PUT; Gitea only updates with it
+    let request = match (api, &sha) {
+        (SyncApi::Gitea(_), None) => api.request(agent.post(&url), token),
+        _ => api.request(agent.put(&url), token),
+    };
+    let mut response = request
+        .send_json(&payload)
+        .map_err(|e| format_ureq_error(api, e))?;
+
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| format!("Failed to read response: {e}"))?;
+    let parsed: serde_json::Value =
+        serde_json::from_str(&body).map_err(|_| "Malformed response from GitHub".to_string())?;
+
+    parsed
+        .get("content")
+        .and_then(|c| c.get("sha"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| "Malformed response from GitHub".to_string())
+}
+
+fn do_pull(
+    api: &SyncApi,
+    token: &str,
+    repo: &str,
+    timeout: Duration,
+) -> Result<(BookmarkStore, String), String> {
+    fetch_remote(api, token, repo, timeout)?
+        .ok_or_else(|| format_ureq_error(api, ureq::Error::StatusCode(404)))
+}
+
+/// Where a contents API response keeps the file's text.
+#[derive(Debug, PartialEq)]
+enum RemoteContent {
+    Inline(String),
+    /// Too large to inline (`encoding: "none"`); fetch it from this URL
+    Download(String),
+}
+
+/// Reads the file out of a contents API response according to its
+/// `encoding`, so an unexpected one gets a clear error rather than a base64
+/// decoding failure.
+fn remote_content(parsed: &serde_json::Value) -> Result<RemoteContent, String> {
+    let content = parsed.get("content").and_then(|c| c.as_str());
+    match parsed
+        .get("encoding")
+        .and_then(|e| e.as_str())
+        .unwrap_or("base64")
+    {
+        "base64" => {
+            let encoded =
+                content.ok_or_else(|| "bookmarks.json not found in repository".to_string())?;
+            // GitHub returns base64 with newlines — strip them before decoding
+            let cleaned: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
+            let decoded = BASE64
+                .decode(&cleaned)
+                .map_err(|e| format!("Failed to decode content: {e}"))?;
+            String::from_utf8(decoded)
+                .map(RemoteContent::Inline)
+                .map_err(|e| format!("Invalid UTF-8 content: {e}"))
+        }
+        "utf-8" => content
+            .map(|text| RemoteContent::Inline(text.to_string()))
+            .ok_or_else(|| "bookmarks.json not found in repository".to_string()),
+        "none" => parsed
+            .get("download_url")
+            .and_then(|u| u.as_str())
+            .filter(|u| !u.is_empty())
+            .map(|u| RemoteContent::Download(u.to_string()))
+            .ok_or_else(|| {
+                "bookmarks.json is too large to be sent inline and no download URL was given"
+                    .to_string()
+            }),
+        other => Err(format!(
+            "Unsupported content encoding \"{other}\" for bookmarks.json"
+        )),
+    }
+}
+
+/// Like `do_pull`, but a missing file is `Ok(None)` rather than an error, so
+/// the combined sync can create it.
+fn fetch_remote(
+    api: &SyncApi,
+    token: &str,
+    repo: &str,
+    timeout: Duration,
+) -> Result<Option<(BookmarkStore, String)>, String> {
+    let url = api.contents_url(repo);
+    let agent = sync_agent(timeout);
+
+    let mut response = match api.request(agent.get(&url), token).call() {
+        Ok(response) => response,
+        Err(ureq::Error::StatusCode(404)) => return Ok(None),
+        Err(e) => return Err(format_ureq_error(api, e)),
+    };
+
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| format!("Failed to read response: {e}"))?;
+    let parsed: serde_json::Value =
+        serde_json::from_str(&body).map_err(|_| "Malformed response from GitHub".to_string())?;
+
+    let sha = parsed
+        .get("sha")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "Missing SHA in response".to_string())?
+        .to_string();
+
+    let content = match remote_content(&parsed)? {
+        RemoteContent::Inline(content) => content,
+        RemoteContent::Download(download_url) => api
+            .request(agent.get(&download_url), token)
+            .call()
+            .map_err(|e| format_ureq_error(api, e))?
+            .body_mut()
+            .read_to_string()
+            .map_err(|e| format!("Failed to read response: {e}"))?,
+    };
+
+    let store = serde_json::from_str::<BookmarkStore>(&content)
+        .map_err(|e| format!("Failed to parse bookmarks: {e}"))?;
+
+    Ok(Some((store, sha)))
+}
+
+/// Percent-encodes every byte outside the URL unreserved set, apart from
+/// those in `keep`.
+fn percent_encode(text: &str, keep: &[u8]) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric()
+            || matches!(byte, b'-' | b'_' | b'.' | b'~')
+            || keep.contains(&byte)
+        {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Fills a search engine template with the percent-encoded query. The query
+/// replaces every `%s`, or is appended when the template has none.
+fn build_search_url(template: &str, query: &str) -> String {
+    let encoded = percent_encode(query, b"");
+    if template.contains("%s") {
+        template.replace("%s", &encoded)
+    } else {
+        format!("{template}{encoded}")
+    }
+}
+
+/// Turns an `@docs/intro.md` bookmark into a `file://` URL under `base`, so
+/// the same bookmark works wherever the project lives. Anything else, and
+/// `@` URLs while no base path is set, comes back unchanged.
+fn expand_bookmark_url(url: &str, base: &str) -> String {
+    let base = base.trim().replace('\\', "/");
+    let Some(relative) = url.strip_prefix('@').filter(|_| !base.is_empty()) else {
+        return url.to_string();
+    };
+    let base = base.trim_end_matches('/');
+    // Windows paths (C:/...) need the extra slash of an empty host too
+    let root = if base.is_empty() || base.starts_with('/') {
+        ""
+    } else {
+        "/"
+    };
+    let path = format!("{root}{base}/{}", relative.trim_start_matches('/'));
+    // Only the separators and a drive letter's colon stay as typed
+    format!("file://{}", percent_encode(&path, b"/:"))
+}
+
+/// Whether `url` is a `javascript:` bookmarklet rather than a page.
+fn is_bookmarklet(url: &str) -> bool {
+    url.trim_start()
+        .get(..11)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("javascript:"))
+}
+
+/// The script a bookmarklet runs: everything after `javascript:`, with
+/// `%XX` escapes decoded the way browsers do before running it.
+fn bookmarklet_code(url: &str) -> Option<String> {
+    if !is_bookmarklet(url) {
+        return None;
+    }
+    let payload = url.trim_start()[11..].as_bytes();
+    let mut bytes = Vec::with_capacity(payload.len());
+    let mut i = 0;
+    while i < payload.len() {
+        let hex = payload
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (payload[i], hex) {
+            (b'%', Some(byte)) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                bytes.push(byte);
+                i += 1;
+            }
+        }
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Where omnibar text should go: URLs as typed, bare host names over
+/// https, and anything else to the search engine.
+fn omnibar_target(text: &str, search_template: &str) -> String {
+    let text = text.trim();
+    let lower = text.to_ascii_lowercase();
+    if lower.starts_with("http://") || lower.starts_with("https://") {
+        return text.to_string();
+    }
+    let host = text.split(['/', ':']).next().unwrap_or("");
+    let looks_like_host = !text.contains(char::is_whitespace)
+        && (host == "localhost"
+            || (host.contains('.') && !host.starts_with('.') && !host.ends_with('.')));
+    if looks_like_host {
+        format!("https://{text}")
+    } else {
+        build_search_url(search_template, text)
+    }
+}
+
+/// URL of the bookmark named exactly `text` (ignoring case), for the
+/// `omnibar_bookmarks` experiment. Locked folders are never searched.
+fn omnibar_bookmark(store: &BookmarkStore, text: &str) -> Option<String> {
+    let text = text.trim().to_lowercase();
+    store
+        .folders
+        .iter()
+        .filter(|f| !f.locked)
+        .flat_map(|f| &f.bookmarks)
+        .find(|bm| bm.name.trim().to_lowercase() == text)
+        .map(|bm| bm.url.clone())
+}
+
+/// Records a visit at the front of the session's recent list, skipping
+/// consecutive repeats and dropping the oldest entry past `RECENT_LIMIT`.
+fn push_recent(recent: &mut VecDeque<String>, url: &str) {
+    if recent.front().map(String::as_str) == Some(url) {
+        return;
+    }
+    recent.push_front(url.to_string());
+    recent.truncate(RECENT_LIMIT);
+}
+
+/// Opens a web URL in the default browser. Other schemes are refused, so a
+/// bookmark can't be used to launch a local file or program.
+fn open_external(url: &str) -> std::io::Result<()> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "only http and https links can be opened externally",
+        ));
+    }
+    system_open(std::ffi::OsStr::new(url))
+}
+
+/// Creates the config directory if needed and opens it in the file manager.
+fn open_config_dir() -> std::io::Result<PathBuf> {
+    let dir = config_dir();
+    fs::create_dir_all(&dir)?;
+    system_open(dir.as_os_str())?;
+    Ok(dir)
+}
+
+/// Hands a URL or path to the desktop's default handler.
+fn system_open(target: &std::ffi::OsStr) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = std::process::Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler").arg(target);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = std::process::Command::new("open");
+        command.arg(target);
+        command
+    };
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = {
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(target);
+        command
+    };
+    let mut child = command.spawn()?;
+    // Reap the launcher once it hands off to the browser or file manager
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Orders for the one-shot folder sort
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortMode {
+    NameAsc,
+    NameDesc,
+    /// Most bookmarks first
+    BookmarkCount,
+}
+
+impl SortMode {
+    fn parse(mode: &str) -> Option<SortMode> {
+        match mode {
+            "name_asc" => Some(SortMode::NameAsc),
+            "name_desc" => Some(SortMode::NameDesc),
+            "bookmark_count" => Some(SortMode::BookmarkCount),
+            _ => None,
+        }
+    }
+}
+
+/// Reorders the folders in place. The sort is stable, so folders that
+/// compare equal (same name, or same count) keep their relative order, and
+/// each folder moves whole, `expanded` flag included.
+fn sort_folders(folders: &mut [Folder], mode: SortMode) {
+    match mode {
+        SortMode::NameAsc => folders.sort_by_cached_key(|f| f.name.to_lowercase()),
+        SortMode::NameDesc => {
+            folders.sort_by(|a, b| b.name.to_lowercase().cmp(&a.name.to_lowercase()))
+        }
+        SortMode::BookmarkCount => folders.sort_by_key(|f| std::cmp::Reverse(f.bookmarks.len())),
+    }
+}
+
+/// Checks a home page typed into Settings. Empty is fine (the welcome page);
+/// otherwise it must be an http(s) URL with a host, where a bare
+/// `example.com` gets `https://` added.
+fn validate_home_url(input: &str) -> Result<String, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(String::new());
+    }
+    let url = if input.contains("://") {
+        input.to_string()
+    } else {
+        // Only a port may follow a colon here, as in `localhost:3000`
+        if let Some((scheme, rest)) = input.split_once(':') {
+            let port = rest.split('/').next().unwrap_or("");
+            if port.is_empty() || !port.chars().all(|c| c.is_ascii_digit()) {
+                return Err(format!("Home page must be a web address, not {scheme}:"));
+            }
+        }
+        format!("https://{input}")
+    };
+    let scheme = url.split("://").next().unwrap_or("").to_ascii_lowercase();
+    if scheme != "http" && scheme != "https" {
+        return Err(format!("Home page must be a web address, not {scheme}:"));
+    }
+    if url.chars().any(char::is_whitespace) || host_of(&url).is_none() {
+        return Err(format!("\"{input}\" isn't a valid home page address"));
+    }
+    Ok(url)
+}
+
+/// URLs of the bookmarks flagged to open on startup, in sidebar order and
+/// without repeats. The first is loaded; the rest wait in the reading queue.
+fn startup_urls(store: &BookmarkStore) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for bm in store.folders.iter().flat_map(|f| &f.bookmarks) {
+        if bm.open_on_startup && !is_bookmarklet(&bm.url) && !urls.contains(&bm.url) {
+            urls.push(bm.url.clone());
+        }
+    }
+    urls
+}
+
+/// Appends a URL to the session reading queue unless it is already waiting
+/// there. Returns whether the queue changed.
+fn push_queue(queue: &mut VecDeque<String>, url: &str) -> bool {
+    if queue.iter().any(|queued| queued == url) {
+        return false;
+    }
+    queue.push_back(url.to_string());
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SyncKind {
+    Pull,
+    AutoPush,
+    Push,
+    Both,
+}
+
+impl SyncKind {
+    fn event(self) -> UserEvent {
+        match self {
+            SyncKind::Pull => UserEvent::PullFromGitHub,
+            SyncKind::AutoPush => UserEvent::AutoSync,
+            SyncKind::Push => UserEvent::PushToGitHub,
+            SyncKind::Both => UserEvent::SyncBoth,
+        }
+    }
+}
+
+/// Serializes GitHub syncs. While one is running, further requests collapse
+/// into a single pending one instead of being dropped, so the last edit
+/// always gets pushed.
+///
+/// When requests of different kinds pile up, a push wins over a pull: a
+/// push makes the remote match local anyway, while a pull queued behind
+/// unpushed edits would discard them. A manual push wins over an auto-push
+/// so its progress is still reported, and a combined sync wins over both
+/// since it ends in a push of everything local.
+#[derive(Debug, Default)]
+struct SyncQueue {
+    in_progress: bool,
+    pending: Option<SyncKind>,
+}
+
+impl SyncQueue {
+    /// Returns `true` if `kind` may start now; otherwise it is queued.
+    fn request(&mut self, kind: SyncKind) -> bool {
+        if !self.in_progress {
+            self.in_progress = true;
+            return true;
+        }
+        self.pending = self.pending.max(Some(kind));
+        false
+    }
+
+    /// Marks the running sync as finished and hands back the queued one, if
+    /// any, for the caller to start.
+    fn finish(&mut self) -> Option<SyncKind> {
+        self.in_progress = false;
+        self.pending.take()
+    }
+}
+
+fn make_bounds(x: f64, y: f64, width: f64, height: f64) -> Rect {
+    Rect {
+        position: LogicalPosition::new(x, y).into(),
+        size: WryLogicalSize::new(width, height).into(),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn logical_inner_size(window: &tao::window::Window) -> (f64, f64) {
+    let scale = window.scale_factor();
+    let inner = window.inner_size();
+    (inner.width as f64 / scale, inner.height as f64 / scale)
+}
+
+/// Positions both webviews by bounds, giving the content pane the full
+/// window when the sidebar is not shown.
+fn layout_webviews(
+    sidebar: &wry::WebView,
+    content: &wry::WebView,
+    w: f64,
+    h: f64,
+    sidebar_shown: bool,
+) {
+    if sidebar_shown {
+        let _ = sidebar.set_bounds(make_bounds(0.0, 0.0, SIDEBAR_WIDTH, h));
+        let _ = content.set_bounds(make_bounds(SIDEBAR_WIDTH, 0.0, w - SIDEBAR_WIDTH, h));
+    } else {
+        let _ = sidebar.set_bounds(make_bounds(0.0, 0.0, 0.0, h));
+        let _ = content.set_bounds(make_bounds(0.0, 0.0, w, h));
+    }
+}
+
+/// Where the reference frame sits in a content pane starting at `content_x`:
+/// the bottom-right corner, never more than half the pane either way.
+/// Returns (x, y, width, height).
+fn pip_rect(content_x: f64, content_w: f64, h: f64) -> (f64, f64, f64, f64) {
+    let width = PIP_WIDTH.min(content_w / 2.0);
+    let height = PIP_HEIGHT.min(h / 2.0);
+    (
+        content_x + content_w - width - PIP_MARGIN,
+        h - height - PIP_MARGIN,
+        width,
+        height,
+    )
+}
+
+/// Sizes the reference frame's GTK container; the overlay keeps it in the
+/// corner, so only the size follows the window.
+#[cfg(target_os = "linux")]
+fn layout_pip(pip_box: &gtk::Box, content_w: f64, h: f64) {
+    use gtk::prelude::*;
+    let (_, _, width, height) = pip_rect(0.0, content_w, h);
+    pip_box.set_size_request(width as i32, height as i32);
+}
+
+#[cfg(not(target_os = "linux"))]
+fn layout_pip(pip: &wry::WebView, content_x: f64, content_w: f64, h: f64) {
+    let (x, y, width, height) = pip_rect(content_x, content_w, h);
+    let _ = pip.set_bounds(make_bounds(x, y, width, height));
+}
+
+/// Sizes the sidebar's GTK container: the expand strip when collapsed, and
+/// hidden outright in focus mode so the content pane fills the window.
+#[cfg(target_os = "linux")]
+fn resize_sidebar_box(sidebar_box: &gtk::Box, collapsed: bool, focus_mode: bool) {
+    use gtk::prelude::*;
+    let width = if collapsed {
+        STRIP_WIDTH
+    } else {
+        SIDEBAR_WIDTH
+    };
+    sidebar_box.set_visible(!focus_mode);
+    sidebar_box.set_size_request(width as i32, -1);
+    sidebar_box.queue_resize();
+}
+
+/// Loads the store for a GUI session along with the stamp that `save_tracked`
+/// compares against. The sample store is written only when there is no file
+/// at all (and `save_defaults` allows it). A file that exists but can't be
+/// read or parsed right now is left untouched, and its stamp is reported as
+/// unknown so the first in-app save backs it up before replacing it.
+fn startup_store(path: &Path, save_defaults: bool) -> (BookmarkStore, Option<FileStamp>) {
+    if let Some(store) = BookmarkStore::try_load_from(path) {
+        return (store, file_stamp(path));
+    }
+    let store = default_store();
+    if path.exists() {
+        eprintln!(
+            "Warning: could not read {}; starting with sample bookmarks without overwriting it",
+            path.display()
+        );
+        return (store, None);
+    }
+    if save_defaults {
+        if let Err(e) = store.save_to(path) {
+            eprintln!("Warning: could not save bookmarks: {e}");
+        }
+    }
+    (store, file_stamp(path))
+}
+
+/// What to do about GitHub at startup, before the user starts editing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StartupSync {
+    Nothing,
+    /// "push_pull" mode: fetch the latest remote store
+    Pull,
+    /// "push_pull" mode, but unpushed local edits would be overwritten
+    SkipDirty,
+    /// No local bookmarks.json yet: clone the remote store before anything
+    /// is written to disk or pushed
+    Clone,
+}
+
+fn plan_startup_sync(first_run: bool, store: &BookmarkStore, settings: &Settings) -> StartupSync {
+    if settings.github_token.is_empty()
+        || settings.github_repo.is_empty()
+        || settings.auto_sync_mode() == "off"
+    {
+        return StartupSync::Nothing;
+    }
+    if first_run {
+        return StartupSync::Clone;
+    }
+    if settings.auto_sync_mode() != "push_pull" {
+        return StartupSync::Nothing;
+    }
+    // Never synced counts as dirty too: there's nothing to say the local
+    // bookmarks are already on the remote
+    if has_unsynced_changes(store, settings) {
+        StartupSync::SkipDirty
+    } else {
+        StartupSync::Pull
+    }
+}
+
+/// Shortest gap between pulls triggered by the window regaining focus
+const FOCUS_PULL_INTERVAL: Duration = Duration::from_secs(120);
+
+/// Whether regaining focus should pull: the option is on, auto-sync isn't
+/// off, there are no unpushed edits a pull would overwrite, and the last
+/// focus pull (if any) was at least `FOCUS_PULL_INTERVAL` ago.
+fn should_pull_on_focus(
+    settings: &Settings,
+    store: &BookmarkStore,
+    last_pull: Option<Instant>,
+    now: Instant,
+) -> bool {
+    settings.sync_on_focus
+        && !settings.github_token.is_empty()
+        && !settings.github_repo.is_empty()
+        && settings.auto_sync_mode() != "off"
+        && !has_unsynced_changes(store, settings)
+        && !last_pull.is_some_and(|at| now.duration_since(at) < FOCUS_PULL_INTERVAL)
+}
+
+const SYNC_USAGE: &str =
+    "usage: bookmarks-browser [--config-dir DIR] sync (--push | --pull) [--force]";
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(dir) = take_config_dir_flag(&mut args) {
+        // Single-threaded at this point, and config_dir() reads it from here on
+        std::env::set_var(CONFIG_DIR_ENV, dir);
+    }
+    if args.first().map(String::as_str) == Some("sync") {
+        std::process::exit(run_sync_command(&args[1..]));
+    }
+    run_gui();
+}
+
+/// Removes `--config-dir DIR` / `--config-dir=DIR` from `args`, returning DIR.
+fn take_config_dir_flag(args: &mut Vec<String>) -> Option<String> {
+    let pos = args
+        .iter()
+        .position(|a| a == "--config-dir" || a.starts_with("--config-dir="))?;
+    let flag = args.remove(pos);
+    match flag.strip_prefix("--config-dir=") {
+        Some(dir) => Some(dir.to_string()),
+        None if pos < args.len() => Some(args.remove(pos)),
+        None => None,
+    }
+}
+
+/// Headless `sync` subcommand for cron/systemd timers: pushes or pulls
+/// using the saved settings without creating a window. Returns the process
+/// exit code.
+fn run_sync_command(args: &[String]) -> i32 {
+    let push = args.iter().any(|a| a == "--push");
+    let pull = args.iter().any(|a| a == "--pull");
+    let force = args.iter().any(|a| a == "--force");
+    if push == pull
+        || args
+            .iter()
+            .any(|a| !matches!(a.as_str(), "--push" | "--pull" | "--force"))
+    {
+        eprintln!("{SYNC_USAGE}");
+        return 2;
+    }
+
+    let mut settings = Settings::load();
+    if settings.github_token.is_empty() || settings.github_repo.is_empty() {
+        eprintln!("Sync is not configured — set a token and repository in the app's Settings");
+        return 1;
+    }
+    let api = match settings.sync_api() {
+        Ok(api) => api,
+        Err(e) => {
+            eprintln!("{e}");
+            return 1;
+        }
+    };
+    let path = config_path();
+    let local = BookmarkStore::try_load_from(&path);
+
+    if push {
+        // Never push the built-in sample store over a real remote
+        let Some(store) = local else {
+            eprintln!("Push failed: no readable bookmarks at {}", path.display());
+            return 1;
+        };
+        let count = synced_bookmark_count(&store);
+        if !force {
+            if let Err(e) = check_push(
+                count,
+                settings.last_synced_count,
+                settings.push_guard_percent(),
+            ) {
+                eprintln!("Push skipped: {e} (use --force to push anyway)");
+                return 1;
+            }
+        }
+        let json = sync_json(&store, settings.pretty_json);
+        let mirrors = settings.sync_mirrors();
+        let mut failures = Vec::new();
+        match do_push(
+            &api,
+            &settings.github_token,
+            &settings.github_repo,
+            &json,
+            None,
+            settings.sync_timeout(),
+        ) {
+            Ok(_) => {
+                settings.last_synced_digest = Some(store_digest(&store));
+                settings.last_synced_count = Some(count);
+                let _ = settings.save();
+                if mirrors.is_empty() {
+                    println!("Pushed successfully");
+                } else {
+                    println!("Pushed to {}", settings.github_repo);
+                }
+            }
+            Err(e) => {
+                eprintln!("Push failed: {e}");
+                failures.push(format!("primary: {e}"));
+            }
+        }
+        // Mirrors get the push whatever the primary's outcome, as in the app
+        for mirror in &mirrors {
+            match mirror.push(&json, settings.sync_timeout()) {
+                Ok(_) => println!("Pushed to {}", mirror.label()),
+                Err(e) => {
+                    eprintln!("Push to {} failed: {e}", mirror.label());
+                    failures.push(format!("{}: {e}", mirror.label()));
+                }
+            }
+        }
+        if !mirrors.is_empty() {
+            println!("{}", push_summary(mirrors.len() + 1, &failures));
+        }
+        i32::from(!failures.is_empty())
+    } else {
+        if let Some(store) = &local {
+            if !force && has_unsynced_changes(store, &settings) {
+                eprintln!(
+                    "Pull skipped: local bookmarks have changes that were never pushed (use --force to overwrite)"
+                );
+                return 1;
+            }
+        }
+        match do_pull(
+            &api,
+            &settings.github_token,
+            &settings.github_repo,
+            settings.sync_timeout(),
+        ) {
+            Ok((remote, _sha)) => {
+                let store = match &local {
+                    Some(local) => merge_excluded(remote, local),
+                    None => remote,
+                };
+                let mut stamp = file_stamp(&path);
+                if let Err(e) = store.save_tracked(&mut stamp, settings.backup_keep()) {
+                    eprintln!("Pull failed: could not save bookmarks: {e}");
+                    return 1;
+                }
+                settings.last_synced_digest = Some(store_digest(&store));
+                settings.last_synced_count = Some(synced_bookmark_count(&store));
+                let _ = settings.save();
+                println!("Pulled successfully");
+                0
+            }
+            Err(e) => {
+                eprintln!("Pull failed: {e}");
+                1
+            }
+        }
+    }
+}
+
+/// What to tell the user when no webview could be created, with the fix for
+/// this platform's web engine.
+fn webview_error_message(error: &str) -> String {
+    let hint = if cfg!(target_os = "linux") {
+        "Bookmarks Browser needs WebKitGTK 4.1. Install it with your package manager, e.g.\n  \
+         Debian/Ubuntu: sudo apt install libwebkit2gtk-4.1-0\n  \
+         Fedora: sudo dnf install webkit2gtk4.1\n  \
+         Arch: sudo pacman -S webkit2gtk-4.1"
+    } else if cfg!(target_os = "windows") {
+        "Bookmarks Browser needs the Microsoft Edge WebView2 Runtime. Download it from \
+         https://developer.microsoft.com/microsoft-edge/webview2/"
+    } else {
+        "Bookmarks Browser needs the system WebKit framework; make sure your OS is up to date."
+    };
+    format!("Could not start the web view: {error}\n\n{hint}")
+}
+
+/// The content pane, minus what it first shows. Rebuilt around another
+/// web context whenever a bookmark switches container.
+fn content_webview_builder<'a>(
+    builder: WebViewBuilder<'a>,
+    proxy: EventLoopProxy<UserEvent>,
+    blocklist: Rc<HashSet<String>>,
+    blocker: &str,
+) -> WebViewBuilder<'a> {
+    let load_proxy = proxy.clone();
+    let block_proxy = proxy.clone();
+    builder
+        .with_initialization_script(CONTENT_INIT_SCRIPT)
+        .with_initialization_script(blocker)
+        .with_navigation_handler(move |url| {
+            match host_of(&url).filter(|host| is_blocked_host(&blocklist, host)) {
+                Some(host) => {
+                    let _ = block_proxy.send_event(UserEvent::NavigationBlocked(host));
+                    false
+                }
+                None => true,
+            }
+        })
+        .with_on_page_load_handler(move |event, url| {
+            let loading = matches!(event, PageLoadEvent::Started);
+            if loading {
+                let _ = load_proxy.send_event(UserEvent::PageStarted(url));
+            }
+            let _ = load_proxy.send_event(UserEvent::PageLoading(loading));
+        })
+        .with_ipc_handler(move |req: wry::http::Request<String>| {
+            // Arbitrary web pages can post here, so only accept a narrow set of
+            // actions and take the page URL from the webview rather than the message
+            let Ok(msg) = serde_json::from_str::<serde_json::Value>(req.body()) else {
+                return;
+            };
+            let url = req.uri().to_string();
+            match msg.get("action").and_then(|a| a.as_str()) {
+                Some("auth_required") => {
+                    let _ = proxy.send_event(UserEvent::AuthRequired(url));
+                }
+                Some("focus_sidebar") => {
+                    let _ = proxy.send_event(UserEvent::FocusPane(true));
+                }
+                Some("clip_selection") => {
+                    let text = |key: &str| msg.get(key).and_then(|t| t.as_str()).unwrap_or("");
+                    if let Some(selection) = clean_notes(text("text")) {
+                        let _ = proxy.send_event(UserEvent::ClipSelection {
+                            url,
+                            title: text("title").trim().chars().take(200).collect(),
+                            text: selection,
+                        });
+                    }
+                }
+                Some("form_dirty") => {
+                    let dirty = msg.get("dirty").and_then(|d| d.as_bool()).unwrap_or(false);
+                    let _ = proxy.send_event(UserEvent::FormDirty(dirty));
+                }
+                Some("text_focus") => {
+                    let editing = msg
+                        .get("editing")
+                        .and_then(|e| e.as_bool())
+                        .unwrap_or(false);
+                    let _ = proxy.send_event(UserEvent::TextFocus {
+                        sidebar: false,
+                        editing,
+                    });
+                }
+                Some("page_ready") => {
+                    let title: String = msg
+                        .get("title")
+                        .and_then(|t| t.as_str())
+                        .unwrap_or("")
+                        .trim()
+                        .chars()
+                        .take(200)
+                        .collect();
+                    let _ = proxy.send_event(UserEvent::PageReady { url, title });
+                }
+                Some("scroll") => {
+                    // The webview URI may already point at the next page during
+                    // pagehide, so trust the page's own location for this one
+                    if let (Some(page_url), Some(y)) = (
+                        msg.get("url").and_then(|u| u.as_str()),
+                        msg.get("y").and_then(|y| y.as_f64()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::ScrollPosition {
+                            url: page_url.to_string(),
+                            y,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        })
+        .with_user_agent(USER_AGENT)
+}
+
+/// Reports a webview that failed to build and exits, instead of panicking.
+fn webview_failed(error: &wry::Error) -> ! {
+    let message = webview_error_message(&error.to_string());
+    eprintln!("{message}");
+    #[cfg(target_os = "linux")]
+    {
+        use gtk::prelude::*;
+
+        let dialog = gtk::MessageDialog::new(
+            None::<&gtk::Window>,
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Error,
+            gtk::ButtonsType::Close,
+            &message,
+        );
+        dialog.set_title("Bookmarks Browser");
+        dialog.run();
+    }
+    std::process::exit(1);
+}
+
+fn run_gui() {
+    let path = config_path();
+    let first_run = !path.exists();
+    let mut settings = Settings::load();
+    init_experiments(&settings);
+    // On a first run that will clone from GitHub, writing the sample store
+    // now would only be overwritten (or, worse, pushed) — leave the file absent
+    let cloning = plan_startup_sync(first_run, &default_store(), &settings) == StartupSync::Clone;
+    let (mut store, mut store_stamp) = startup_store(&path, !cloning);
+    let startup_sync = plan_startup_sync(first_run, &store, &settings);
+
+    let initial_collapsed = settings.sidebar_collapsed;
+    let initial_focus_mode = settings.focus_mode;
+
+    let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+    let proxy = event_loop.create_proxy();
+
+    let window = WindowBuilder::new()
+        .with_title(window_title(settings.profile().as_deref()))
+        .with_inner_size(LogicalSize::new(1200.0, 800.0))
+        .with_decorations(settings.decorations)
+        .with_always_on_top(settings.always_on_top)
+        .build(&event_loop)
+        .expect("Failed to create window");
+
+    let initial_theme = resolved_theme(settings.theme(), window.theme());
+
+    let inner = window.inner_size();
+    let scale = window.scale_factor();
+    let w = inner.width as f64 / scale;
+    let h = inner.height as f64 / scale;
+
+    let sidebar_builder = WebViewBuilder::new()
+        .with_html(sidebar_html(
+            &store,
+            &settings,
+            initial_theme,
+            &load_health(&health_path()),
+        ))
+        .with_bounds(make_bounds(0.0, 0.0, SIDEBAR_WIDTH, h))
+        .with_custom_protocol(THUMBNAIL_PROTOCOL.into(), |_id, request| {
+            serve_thumbnail(request.uri().path())
+        })
+        .with_ipc_handler(move |req: wry::http::Request<String>| {
+            let body = req.body();
+            let Ok(msg) = serde_json::from_str::<serde_json::Value>(body) else {
+                return;
+            };
+            let Some(action) = msg.get("action").and_then(|a| a.as_str()) else {
+                return;
+            };
+            match action {
+                "navigate" => {
+                    if let Some(url) = msg.get("url").and_then(|u| u.as_str()) {
+                        let _ = proxy.send_event(UserEvent::Navigate(url.to_string()));
+                    }
+                }
+                "sidebar_ready" => {
+                    let _ = proxy.send_event(UserEvent::SidebarReady);
+                }
+                "reload_sidebar" => {
+                    let _ = proxy.send_event(UserEvent::ReloadSidebar);
+                }
+                "omnibar" => {
+                    if let Some(text) = msg.get("text").and_then(|t| t.as_str()) {
+                        let _ = proxy.send_event(UserEvent::OmnibarSubmit(text.to_string()));
+                    }
+                }
+                "load_scratchpad" => {
+                    let _ = proxy.send_event(UserEvent::LoadScratchpad);
+                }
+                "save_scratchpad" => {
+                    if let Some(text) = msg.get("text").and_then(|t| t.as_str()) {
+                        let _ = proxy.send_event(UserEvent::SaveScratchpad(text.to_string()));
+                    }
+                }
+                "tag_bookmarks" => {
+                    if let (Some(targets), Some(tag)) = (
+                        msg.get("targets").and_then(|t| t.as_array()),
+                        msg.get("tag").and_then(|t| t.as_str()),
+                    ) {
+                        let targets = targets
+                            .iter()
+                            .filter_map(|t| {
+                                let fi = t.get(0)?.as_u64()? as usize;
+                                let bi = t.get(1)?.as_u64()? as usize;
+                                Some((fi, bi))
+                            })
+                            .collect();
+                        let _ = proxy.send_event(UserEvent::TagBookmarks {
+                            targets,
+                            tag: tag.to_string(),
+                            add: msg.get("add").and_then(|a| a.as_bool()).unwrap_or(true),
+                        });
+                    }
+                }
+                "copy_bookmark_debug" => {
+                    if let (Some(fi), Some(bi)) = (
+                        msg.get("folder_index").and_then(|i| i.as_u64()),
+                        msg.get("bookmark_index").and_then(|i| i.as_u64()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::CopyBookmarkDebug {
+                            folder_index: fi as usize,
+                            bookmark_index: bi as usize,
+                        });
+                    }
+                }
+                "archive_bookmark" => {
+                    if let (Some(fi), Some(bi)) = (
+                        msg.get("folder_index").and_then(|i| i.as_u64()),
+                        msg.get("bookmark_index").and_then(|i| i.as_u64()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::ArchiveBookmark {
+                            folder_index: fi as usize,
+                            bookmark_index: bi as usize,
+                        });
+                    }
+                }
+                "pin_reference" => {
+                    if let (Some(fi), Some(bi)) = (
+                        msg.get("folder_index").and_then(|i| i.as_u64()),
+                        msg.get("bookmark_index").and_then(|i| i.as_u64()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::PinReference {
+                            folder_index: fi as usize,
+                            bookmark_index: bi as usize,
+                        });
+                    }
+                }
+                "toggle_reference" => {
+                    let _ = proxy.send_event(UserEvent::ToggleReference);
+                }
+                "close_reference" => {
+                    let _ = proxy.send_event(UserEvent::CloseReference);
+                }
+                "toggle_open_on_startup" => {
+                    if let (Some(fi), Some(bi)) = (
+                        msg.get("folder_index").and_then(|i| i.as_u64()),
+                        msg.get("bookmark_index").and_then(|i| i.as_u64()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::ToggleOpenOnStartup {
+                            folder_index: fi as usize,
+                            bookmark_index: bi as usize,
+                        });
+                    }
+                }
+                "set_thumbnail" => {
+                    if let (Some(fi), Some(bi)) = (
+                        msg.get("folder_index").and_then(|i| i.as_u64()),
+                        msg.get("bookmark_index").and_then(|i| i.as_u64()),
+                    ) {
+                        let image = msg.get("image").and_then(|i| i.as_str()).map(String::from);
+                        let _ = proxy.send_event(UserEvent::SetThumbnail {
+                            folder_index: fi as usize,
+                            bookmark_index: bi as usize,
+                            image,
+                        });
+                    }
+                }
+                "reset_library" => {
+                    // The typed confirmation is checked here too, not just in the dialog
+                    if msg.get("confirm").and_then(|c| c.as_str()) == Some("DELETE") {
+                        let push = msg.get("push").and_then(|p| p.as_bool()).unwrap_or(false);
+                        let _ = proxy.send_event(UserEvent::ResetLibrary { push });
+                    }
+                }
+                "open_config_dir" => {
+                    let _ = proxy.send_event(UserEvent::OpenConfigDir);
+                }
+                "open_external" => {
+                    if let Some(url) = msg.get("url").and_then(|u| u.as_str()) {
+                        let _ = proxy.send_event(UserEvent::OpenExternal(url.to_string()));
+                    }
+                }
+                "queue_bookmark" => {
+                    if let Some(url) = msg.get("url").and_then(|u| u.as_str()) {
+                        let _ = proxy.send_event(UserEvent::QueueBookmark(url.to_string()));
+                    }
+                }
+                "open_next_queued" => {
+                    let _ = proxy.send_event(UserEvent::OpenNextQueued);
+                }
+                "toggle_folder" => {
+                    if let Some(index) = msg.get("folder_index").and_then(|i| i.as_u64()) {
+                        let _ = proxy.send_event(UserEvent::ToggleFolder(index as usize));
+                    }
+                }
+                "move_bookmark" => {
+                    let index = |key: &str| msg.get(key).and_then(|i| i.as_u64());
+                    if let (Some(ff), Some(fb), Some(url), Some(tf), Some(tb)) = (
+                        index("from_folder"),
+                        index("from_index"),
+                        msg.get("url").and_then(|u| u.as_str()),
+                        index("to_folder"),
+                        index("to_index"),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::MoveBookmark {
+                            from_folder: ff as usize,
+                            from_index: fb as usize,
+                            url: url.to_string(),
+                            to_folder: tf as usize,
+                            to_index: tb as usize,
+                        });
+                    }
+                }
+                "move_folder" => {
+                    if let (Some(from), Some(to)) = (
+                        msg.get("from").and_then(|i| i.as_u64()),
+                        msg.get("to").and_then(|i| i.as_u64()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::MoveFolder {
+                            from: from as usize,
+                            to: to as usize,
+                        });
+                    }
+                }
+                "edit_bookmark" => {
+                    if let (Some(fi), Some(bi), Some(name), Some(url)) = (
+                        msg.get("folder_index").and_then(|i| i.as_u64()),
+                        msg.get("bookmark_index").and_then(|i| i.as_u64()),
+                        msg.get("name").and_then(|n| n.as_str()),
+                        msg.get("url").and_then(|u| u.as_str()),
+                    ) {
+                        let text =
+                            |key: &str| msg.get(key).and_then(|t| t.as_str()).map(str::to_string);
+                        let _ = proxy.send_event(UserEvent::EditBookmark {
+                            folder_index: fi as usize,
+                            bookmark_index: bi as usize,
+                            name: name.to_string(),
+                            url: url.to_string(),
+                            inject_css: text("inject_css"),
+                            inject_js: text("inject_js"),
+                            mnemonic: text("mnemonic").and_then(|m| m.trim().chars().next()),
+                            container: text("container"),
+                            notes: text("notes").as_deref().and_then(clean_notes),
+                            refresh_secs: msg
+                                .get("refresh_secs")
+                                .and_then(|r| r.as_u64())
+                                .and_then(refresh_interval),
+                        });
+                    }
+                }
+                "sort_folders" => {
+                    if let Some(mode) = msg
+                        .get("mode")
+                        .and_then(|m| m.as_str())
+                        .and_then(SortMode::parse)
+                    {
+                        let _ = proxy.send_event(UserEvent::SortFolders(mode));
+                    }
+                }
+                "set_view_mode" => {
+                    if let Some(mode) = msg.get("mode").and_then(|m| m.as_str()) {
+                        let _ = proxy.send_event(UserEvent::SetViewMode(mode.to_string()));
+                    }
+                }
+                "focus_content" => {
+                    let _ = proxy.send_event(UserEvent::FocusPane(false));
+                }
+                "text_focus" => {
+                    let editing = msg
+                        .get("editing")
+                        .and_then(|e| e.as_bool())
+                        .unwrap_or(false);
+                    let _ = proxy.send_event(UserEvent::TextFocus {
+                        sidebar: true,
+                        editing,
+                    });
+                }
+                "sidebar_focused" => {
+                    let focused = msg
+                        .get("focused")
+                        .and_then(|f| f.as_bool())
+                        .unwrap_or(false);
+                    let _ = proxy.send_event(UserEvent::SidebarFocused(focused));
+                }
+                "discard_form_changes" => {
+                    let _ = proxy.send_event(UserEvent::DiscardFormChanges);
+                }
+                "toggle_sidebar" => {
+                    let _ = proxy.send_event(UserEvent::ToggleSidebar);
+                }
+                "add_folder" => {
+                    if let Some(name) = msg.get("name").and_then(|n| n.as_str()) {
+                        let icon = msg
+                            .get("icon")
+                            .and_then(|i| i.as_str())
+                            .and_then(sanitize_icon);
+                        let _ = proxy.send_event(UserEvent::AddFolder {
+                            name: name.to_string(),
+                            icon,
+                        });
+                    }
+                }
+                "set_folder_icon" => {
+                    if let Some(index) = msg.get("folder_index").and_then(|i| i.as_u64()) {
+                        let icon = msg
+                            .get("icon")
+                            .and_then(|i| i.as_str())
+                            .and_then(sanitize_icon);
+                        let _ = proxy.send_event(UserEvent::SetFolderIcon {
+                            folder_index: index as usize,
+                            icon,
+                        });
+                    }
+                }
+                "add_bookmark" => {
+                    if let (Some(fi), Some(name), Some(url)) = (
+                        msg.get("folder_index").and_then(|i| i.as_u64()),
+                        msg.get("name").and_then(|n| n.as_str()),
+                        msg.get("url").and_then(|u| u.as_str()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::AddBookmark {
+                            folder_index: fi as usize,
+                            name: name.to_string(),
+                            url: url.to_string(),
+                            notes: msg
+                                .get("notes")
+                                .and_then(|n| n.as_str())
+                                .and_then(clean_notes),
+                        });
+                    }
+                }
+                "delete_bookmark" => {
+                    if let (Some(fi), Some(bi)) = (
+                        msg.get("folder_index").and_then(|i| i.as_u64()),
+                        msg.get("bookmark_index").and_then(|i| i.as_u64()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::DeleteBookmark {
+                            folder_index: fi as usize,
+                            bookmark_index: bi as usize,
+                        });
+                    }
+                }
+                "copy_folder_markdown" => {
+                    if let Some(index) = msg.get("folder_index").and_then(|i| i.as_u64()) {
+                        let _ = proxy.send_event(UserEvent::CopyFolderMarkdown(index as usize));
+                    }
+                }
+                "suggest_folder" => {
+                    if let Some(url) = msg.get("url").and_then(|u| u.as_str()) {
+                        let _ = proxy.send_event(UserEvent::SuggestFolder(url.to_string()));
+                    }
+                }
+                "toggle_sync_exclusion" => {
+                    if let Some(index) = msg.get("folder_index").and_then(|i| i.as_u64()) {
+                        let _ = proxy.send_event(UserEvent::ToggleSyncExclusion(index as usize));
+                    }
+                }
+                "lock_folder" => {
+                    if let (Some(fi), Some(pin)) = (
+                        msg.get("folder_index").and_then(|i| i.as_u64()),
+                        msg.get("pin").and_then(|p| p.as_str()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::LockFolder {
+                            folder_index: fi as usize,
+                            pin: pin.to_string(),
+                        });
+                    }
+                }
+                "unlock_folder" => {
+                    if let (Some(fi), Some(pin)) = (
+                        msg.get("folder_index").and_then(|i| i.as_u64()),
+                        msg.get("pin").and_then(|p| p.as_str()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::UnlockFolder {
+                            folder_index: fi as usize,
+                            pin: pin.to_string(),
+                            remove: msg.get("remove").and_then(|r| r.as_bool()).unwrap_or(false),
+                        });
+                    }
+                }
+                "delete_folder" => {
+                    if let Some(index) = msg.get("folder_index").and_then(|i| i.as_u64()) {
+                        let _ = proxy.send_event(UserEvent::DeleteFolder(index as usize));
+                    }
+                }
+                "push_to_github" => {
+                    let _ = proxy.send_event(UserEvent::PushToGitHub);
+                }
+                "confirm_push" => {
+                    let _ = proxy.send_event(UserEvent::ConfirmPush);
+                }
+                "pull_from_github" => {
+                    let _ = proxy.send_event(UserEvent::PullFromGitHub);
+                }
+                "sync_both" => {
+                    let _ = proxy.send_event(UserEvent::SyncBoth);
+                }
+                "submit_auth" => {
+                    if let (Some(url), Some(username), Some(password)) = (
+                        msg.get("url").and_then(|u| u.as_str()),
+                        msg.get("username").and_then(|u| u.as_str()),
+                        msg.get("password").and_then(|p| p.as_str()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::SubmitAuth {
+                            url: url.to_string(),
+                            username: username.to_string(),
+                            password: password.to_string(),
+                        });
+                    }
+                }
+                "list_backups" => {
+                    let _ = proxy.send_event(UserEvent::ListBackups);
+                }
+                "preview_replace_urls" | "replace_urls" => {
+                    let field = |key: &str| {
+                        msg.get(key)
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("")
+                            .to_string()
+                    };
+                    let find = field("find");
+                    let replace = field("replace");
+                    let regex = msg.get("regex").and_then(|v| v.as_bool()).unwrap_or(false);
+                    let _ = proxy.send_event(if action == "replace_urls" {
+                        UserEvent::ReplaceUrls {
+                            find,
+                            replace,
+                            regex,
+                        }
+                    } else {
+                        UserEvent::PreviewReplaceUrls {
+                            find,
+                            replace,
+                            regex,
+                        }
+                    });
+                }
+                "check_links" => {
+                    let _ = proxy.send_event(UserEvent::CheckLinks);
+                }
+                "refresh_titles" => {
+                    let _ = proxy.send_event(UserEvent::RefreshTitles);
+                }
+                "apply_titles" => {
+                    if let Some(changes) = msg
+                        .get("changes")
+                        .and_then(|c| serde_json::from_value(c.clone()).ok())
+                    {
+                        let _ = proxy.send_event(UserEvent::ApplyTitles(changes));
+                    }
+                }
+                "remove_duplicates" => {
+                    let global = msg.get("global").and_then(|g| g.as_bool()).unwrap_or(false);
+                    let _ = proxy.send_event(UserEvent::RemoveDuplicates { global });
+                }
+                "find_cross_duplicates" => {
+                    let _ = proxy.send_event(UserEvent::FindCrossDuplicates);
+                }
+                "validate_library" => {
+                    let _ = proxy.send_event(UserEvent::ValidateLibrary);
+                }
+                "fix_library" => {
+                    let _ = proxy.send_event(UserEvent::FixLibrary);
+                }
+                "keep_duplicate_in" => {
+                    if let (Some(url), Some(fi)) = (
+                        msg.get("url").and_then(|u| u.as_str()),
+                        msg.get("folder_index").and_then(|i| i.as_u64()),
+                    ) {
+                        let _ = proxy.send_event(UserEvent::KeepDuplicateIn {
+                            url: url.to_string(),
+                            folder_index: fi as usize,
+                        });
+                    }
+                }
+                "export_flat_json" => {
+                    let _ = proxy.send_event(UserEvent::ExportFlatJson);
+                }
+                "export_html" => {
+                    let _ = proxy.send_event(UserEvent::ExportHtml);
+                }
+                "export_diagnostics" => {
+                    let include_repo = msg
+                        .get("include_repo")
+                        .and_then(|i| i.as_bool())
+                        .unwrap_or(false);
+                    let _ = proxy.send_event(UserEvent::ExportDiagnostics { include_repo });
+                }
+                "sync_health" => {
+                    let include_repo = msg
+                        .get("include_repo")
+                        .and_then(|i| i.as_bool())
+                        .unwrap_or(false);
+                    let _ = proxy.send_event(UserEvent::ShowSyncHealth { include_repo });
+                }
+                "test_sync_health" => {
+                    let _ = proxy.send_event(UserEvent::TestSyncHealth);
+                }
+                "import_bookmarks" => {
+                    if let Some(content) = msg.get("content").and_then(|c| c.as_str()) {
+                        let _ = proxy.send_event(UserEvent::ImportBookmarks(content.to_string()));
+                    }
+                }
+                "restore_backup" => {
+                    if let Some(name) = msg.get("name").and_then(|n| n.as_str()) {
+                        let _ = proxy.send_event(UserEvent::RestoreBackup(name.to_string()));
+                    }
+                }
+                "test_token" => {
+                    let token = msg
+                        .get("github_token")
+                        .and_then(|t| t.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let repo = msg
+                        .get("github_repo")
+                        .and_then(|g| g.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let api = SyncApi::new(
+                        msg.get("sync_provider")
+                            .and_then(|p| p.as_str())
+                            .unwrap_or(""),
+                        msg.get("gitea_base_url")
+                            .and_then(|u| u.as_str())
+                            .unwrap_or(""),
+                    );
+                    let _ = proxy.send_event(UserEvent::TestToken {
+                        github_token: token,
+                        github_repo: repo,
+                        api,
+                    });
+                }
+                "save_settings" => {
+                    let token = msg
+                        .get("github_token")
+                        .and_then(|t| t.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let repo = msg
+                        .get("github_repo")
+                        .and_then(|g| g.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let density = msg
+                        .get("density")
+                        .and_then(|d| d.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let scrollbars = msg
+                        .get("scrollbars")
+                        .and_then(|s| s.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let ui_font_scale = msg
+                        .get("ui_font_scale")
+                        .and_then(|f| f.as_f64())
+                        .unwrap_or(1.0);
+                    let decorations = msg
+                        .get("decorations")
+                        .and_then(|d| d.as_bool())
+                        .unwrap_or(true);
+                    let always_on_top = msg
+                        .get("always_on_top")
+                        .and_then(|a| a.as_bool())
+                        .unwrap_or(false);
+                    let auto_sync_mode = msg
+                        .get("auto_sync_mode")
+                        .and_then(|m| m.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let show_urls = msg
+                        .get("show_urls")
+                        .and_then(|s| s.as_bool())
+                        .unwrap_or(false);
+                    let show_unread_badge = msg
+                        .get("show_unread_badge")
+                        .and_then(|s| s.as_bool())
+                        .unwrap_or(false);
+                    let theme = msg
+                        .get("theme")
+                        .and_then(|t| t.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let new_folder_position = msg
+                        .get("new_folder_position")
+                        .and_then(|p| p.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let new_bookmark_position = msg
+                        .get("new_bookmark_position")
+                        .and_then(|p| p.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let duplicate_strategy = msg
+                        .get("duplicate_strategy")
+                        .and_then(|d| d.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let close_action = msg
+                        .get("close_action")
+                        .and_then(|c| c.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let on_duplicate_folder = msg
+                        .get("on_duplicate_folder")
+                        .and_then(|d| d.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let confirm_form_leave = msg
+                        .get("confirm_form_leave")
+                        .and_then(|c| c.as_bool())
+                        .unwrap_or(false);
+                    let sync_on_focus = msg
+                        .get("sync_on_focus")
+                        .and_then(|s| s.as_bool())
+                        .unwrap_or(false);
+                    let run_bookmarklets = msg
+                        .get("run_bookmarklets")
+                        .and_then(|s| s.as_bool())
+                        .unwrap_or(false);
+                    let block_trackers = msg
+                        .get("block_trackers")
+                        .and_then(|s| s.as_bool())
+                        .unwrap_or(false);
+                    let show_pull_changes = msg
+                        .get("show_pull_changes")
+                        .and_then(|s| s.as_bool())
+                        .unwrap_or(true);
+                    let auto_expand_active = msg
+                        .get("auto_expand_active")
+                        .and_then(|s| s.as_bool())
+                        .unwrap_or(true);
+                    let pretty_json = msg
+                        .get("pretty_json")
+                        .and_then(|s| s.as_bool())
+                        .unwrap_or(true);
+                    let recently_added = msg
+                        .get("recently_added")
+                        .and_then(|n| n.as_u64())
+                        .map_or(DEFAULT_RECENTLY_ADDED, |n| n as usize);
+                    let sync_provider = msg
+                        .get("sync_provider")
+                        .and_then(|p| p.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let gitea_base_url = msg
+                        .get("gitea_base_url")
+                        .and_then(|u| u.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let home_url = msg
+                        .get("home_url")
+                        .and_then(|u| u.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let _ = proxy.send_event(UserEvent::SaveSettings {
+                        github_token: token,
+                        github_repo: repo,
+                        density,
+                        scrollbars,
+                        ui_font_scale,
+                        decorations,
+                        always_on_top,
+                        auto_sync_mode,
+                        show_urls,
+                        show_unread_badge,
+                        theme,
+                        new_folder_position,
+                        new_bookmark_position,
+                        duplicate_strategy,
+                        close_action,
+                        on_duplicate_folder,
+                        confirm_form_leave,
+                        sync_on_focus,
+                        run_bookmarklets,
+                        block_trackers,
+                        show_pull_changes,
+                        auto_expand_active,
+                        pretty_json,
+                        recently_added,
+                        sync_provider,
+                        gitea_base_url,
+                        home_url,
+                    });
+                }
+                "go_home" => {
+                    let _ = proxy.send_event(UserEvent::GoHome);
+                }
+                _ => {}
+            }
+        });
+
+    let blocklist = Rc::new(if settings.block_trackers {
+        load_blocklist(&settings.blocklist_path())
+    } else {
+        HashSet::new()
+    });
+    let blocker = blocker_script(&blocklist);
+    let content_builder = content_webview_builder(
+        WebViewBuilder::new(),
+        event_loop.create_proxy(),
+        blocklist.clone(),
+        &blocker,
+    )
+    .with_html(welcome_html(initial_theme))
+    .with_bounds(make_bounds(SIDEBAR_WIDTH, 0.0, w - SIDEBAR_WIDTH, h));
+
+    // A second, independent page for reference, floating over the content pane
+    let pip_shown = !settings.pip_url.is_empty();
+    let pip_builder = WebViewBuilder::new()
+        .with_url(if pip_shown {
+            settings.pip_url.as_str()
+        } else {
+            "about:blank"
+        })
+        .with_visible(pip_shown)
+        .with_user_agent(USER_AGENT);
+    #[cfg(not(target_os = "linux"))]
+    let pip_builder = {
+        let (x, y, width, height) = pip_rect(SIDEBAR_WIDTH, w - SIDEBAR_WIDTH, h);
+        pip_builder.with_bounds(make_bounds(x, y, width, height))
+    };
+
+    #[cfg(target_os = "linux")]
+    let (sidebar, mut content, pip, sidebar_gtk_box, content_gtk_box, pip_gtk_box) = {
+        use gtk::prelude::*;
+
+        let vbox = window.default_vbox().expect("Failed to get default vbox");
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        vbox.pack_start(&hbox, true, true, 0);
+
+        let sidebar_width = if initial_collapsed {
+            STRIP_WIDTH
+        } else {
+            SIDEBAR_WIDTH
+        };
+        let sidebar_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        sidebar_box.set_size_request(sidebar_width as i32, -1);
+        hbox.pack_start(&sidebar_box, false, false, 0);
+
+        let content_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let content_overlay = gtk::Overlay::new();
+        content_overlay.add(&content_box);
+        hbox.pack_start(&content_overlay, true, true, 0);
+
+        let pip_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        pip_box.set_halign(gtk::Align::End);
+        pip_box.set_valign(gtk::Align::End);
+        pip_box.set_margin_end(PIP_MARGIN as i32);
+        pip_box.set_margin_bottom(PIP_MARGIN as i32);
+        layout_pip(&pip_box, w - SIDEBAR_WIDTH, h);
+        content_overlay.add_overlay(&pip_box);
+
+        hbox.show_all();
+
+        let sidebar = sidebar_builder
+            .build_gtk(&sidebar_box)
+            .unwrap_or_else(|e| webview_failed(&e));
+        let content = content_builder
+            .build_gtk(&content_box)
+            .unwrap_or_else(|e| webview_failed(&e));
+        let pip = pip_builder
+            .build_gtk(&pip_box)
+            .unwrap_or_else(|e| webview_failed(&e));
+        pip_box.set_visible(pip_shown);
+
+        if initial_focus_mode {
+            sidebar_box.set_visible(false);
+        }
+
+        // GTK has no theme-changed window event; watch the theme name instead
+        if let Some(gtk_settings) = gtk::Settings::default() {
+            let theme_proxy = event_loop.create_proxy();
+            gtk_settings.connect_gtk_theme_name_notify(move |_| {
+                let _ = theme_proxy.send_event(UserEvent::ApplyTheme);
+            });
+        }
+
+        (sidebar, content, pip, sidebar_box, content_box, pip_box)
+    };
+
+    #[cfg(not(target_os = "linux"))]
+    let (sidebar, mut content, pip) = {
+        let sidebar = sidebar_builder
+            .build_as_child(&window)
+            .unwrap_or_else(|e| webview_failed(&e));
+        let content = content_builder
+            .build_as_child(&window)
+            .unwrap_or_else(|e| webview_failed(&e));
+        let pip = pip_builder
+            .build_as_child(&window)
+            .unwrap_or_else(|e| webview_failed(&e));
+        (sidebar, content, pip)
+    };
+
+    let sync_proxy = event_loop.create_proxy();
+
+    // Bumped on every sidebar load; a load is settled once it reports ready
+    let mut sidebar_generation: u64 = 0;
+    let mut sidebar_ready = false;
+    watch_sidebar(sync_proxy.clone(), sidebar_generation);
+
+    let mut modifiers = ModifiersState::empty();
+    let mut mnemonic_leader = MnemonicLeader::default();
+    // Container the content pane was built in; contexts are kept for the
+    // session so switching back to one doesn't set it up again
+    let mut content_container: Option<String> = None;
+    let mut content_contexts: HashMap<String, WebContext> = HashMap::new();
+    let mut sidebar_collapsed = initial_collapsed;
+    let mut focus_mode = initial_focus_mode;
+    let mut remote_sha: Option<String> = None;
+    let mut sync = SyncQueue::default();
+    let mut recent: VecDeque<String> = VecDeque::with_capacity(RECENT_LIMIT);
+    // Bookmarks set aside to read later; session-only, never persisted
+    let mut reading_queue: VecDeque<String> = VecDeque::new();
+    let mut active_url: Option<String> = None;
+    // Bookmark lit in the sidebar: whichever matches the page actually shown.
+    // The first load after a click keeps the clicked one, redirects and all
+    let mut highlight_url: Option<String> = None;
+    let mut highlight_clicked = false;
+    // Basic auth headers keyed by origin; held for this session only, never persisted
+    let mut auth_headers: HashMap<String, String> = HashMap::new();
+    // Last scroll offset per page URL for this session, restored when a bookmark is reopened
+    let mut scroll_positions: HashMap<String, f64> = HashMap::new();
+    let mut pending_scroll: Option<f64> = None;
+    // Whether the content page has edited form fields, and the bookmark
+    // waiting on the user's answer about discarding them
+    let mut form_dirty = false;
+    // Which webview has keyboard focus, as reported by the sidebar, for F6
+    let mut sidebar_focused = false;
+    // Whether a text field has focus in the sidebar and in the content page,
+    // so Ctrl+Z there stays the field's own undo
+    let mut sidebar_editing = false;
+    let mut content_editing = false;
+    let mut pip_shown = pip_shown;
+    let mut pending_navigation: Option<String> = None;
+    // URL and title of the loaded web page, for Ctrl+D
+    let mut current_page: Option<(String, String)> = None;
+    // Auto-refresh of the bookmark last opened, as (its URL, interval). Each
+    // navigation bumps the generation, so ticks from older timers are ignored
+    // and only one keeps going
+    let mut refresh: Option<(String, u64)> = None;
+    let mut refresh_generation: u64 = 0;
+    // Folder of the last bookmark added, where Ctrl+D puts the next one
+    let mut last_folder: Option<usize> = None;
+    // Folder and bookmark index of the last Ctrl+D bookmark, until undone
+    let mut last_quick_add: Option<(usize, usize, String)> = None;
+    // Only the latest delete is kept; a second delete replaces it
+    let mut last_deleted: Option<Deleted> = None;
+    // When regaining focus last triggered a pull, for the sync-on-focus throttle
+    let mut last_focus_pull: Option<Instant> = None;
+    // Digest and bookmark count of the store being pushed, recorded as synced
+    // once the push lands
+    let mut pushing: Option<(u64, usize)> = None;
+    // Digest of the store when the running pull was requested; a pull that
+    // lands after an edit leaves the store alone
+    let mut pulling: Option<u64> = None;
+    // Set when the user confirms a push the safety check refused; used up by
+    // the next push that starts
+    let mut push_confirmed = false;
+    // Set while a combined sync is between its merge and its push landing
+    let mut syncing_both = false;
+    let mut link_health = load_health(&health_path());
+    // Local only, like link health; never part of the synced store
+    let mut zoom_levels = load_zoom(&zoom_path());
+    let mut visits = load_visits(&visits_path());
+    match settings.history_retention_days() {
+        0 => {
+            visits.clear();
+            let _ = fs::remove_file(visits_path());
+        }
+        days => {
+            if prune_history(&mut visits, utc_today().0 - i64::from(days)) {
+                let _ = save_visits(&visits_path(), &visits);
+            }
+        }
+    }
+    let mut current_host: Option<String> = None;
+    // Number of links still being checked, or None when no check is running
+    let mut links_pending: Option<usize> = None;
+    // Page titles are being fetched for "Refresh titles"
+    let mut titles_pending = false;
+    // An import is being read on a background thread
+    let mut importing = false;
+    // Most recent sync failure this session, for the diagnostics report
+    let mut last_sync_error: Option<String> = None;
+    // When this session last pushed or pulled successfully, for sync health
+    let mut last_sync_at: Option<String> = None;
+
+    // Auto-push stays off until the first-run clone has landed, so the sample
+    // bookmarks can never be pushed over a real remote store
+    let mut awaiting_clone = startup_sync == StartupSync::Clone;
+    match startup_sync {
+        StartupSync::Pull | StartupSync::Clone => {
+            let _ = sync_proxy.send_event(UserEvent::PullFromGitHub);
+        }
+        StartupSync::SkipDirty => {
+            let _ = sidebar.evaluate_script(
+                "updateSyncStatus('Local changes not yet pushed — skipped startup pull', 'progress')",
+            );
+        }
+        StartupSync::Nothing => {}
+    }
+    let mut startup_pages = startup_urls(&store).into_iter();
+    if let Some(url) = startup_pages
+        .next()
+        .or_else(|| settings.home_url().map(str::to_string))
+    {
+        let _ = sync_proxy.send_event(UserEvent::Navigate(url));
+    }
+    for url in startup_pages {
+        push_queue(&mut reading_queue, &url);
+    }
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(new_modifiers),
+                ..
+            } => {
+                modifiers = new_modifiers;
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        event: ref key_event,
+                        ..
+                    },
+                ..
+            } if key_event.state == ElementState::Pressed => {
+                let ctrl = modifiers.control_key();
+                let shift = modifiers.shift_key();
+                let alt = modifiers.alt_key();
+                let key = &key_event.logical_key;
+                let editing = if sidebar_focused {
+                    sidebar_editing
+                } else {
+                    content_editing
+                };
+
+                if let Key::Character(c) = key {
+                    if !ctrl && !alt {
+                        if let Some(m) = mnemonic_leader.finish(c, Instant::now()) {
+                            let _ = sync_proxy.send_event(UserEvent::OpenMnemonic(m));
+                            return;
+                        }
+                    }
+                }
+                if alt && matches!(key, Key::Character(c) if c.eq_ignore_ascii_case("g")) {
+                    mnemonic_leader.start(Instant::now());
+                } else if alt && *key == Key::Home {
+                    let _ = sync_proxy.send_event(UserEvent::GoHome);
+                } else if ctrl && shift && matches!(key, Key::Character(c) if c.eq_ignore_ascii_case("t"))
+                {
+                    let _ = sync_proxy.send_event(UserEvent::ReopenRecent);
+                } else if ctrl && *key == Key::Character("l") {
+                    let _ = sync_proxy.send_event(UserEvent::ShowOmnibar);
+                } else if ctrl && *key == Key::Character("f") {
+                    let _ = sidebar.focus();
+                    let _ = sidebar.evaluate_script("focusSearch()");
+                } else if ctrl && *key == Key::Character("j") {
+                    let _ = sync_proxy.send_event(UserEvent::OpenNextQueued);
+                } else if ctrl && *key == Key::Character("b") {
+                    let _ = sync_proxy.send_event(UserEvent::ToggleSidebar);
+                } else if ctrl && shift && matches!(key, Key::Character(c) if c.eq_ignore_ascii_case("p"))
+                {
+                    let _ = sync_proxy.send_event(UserEvent::ToggleReference);
+                } else if ctrl && *key == Key::Character("e") {
+                    let _ = sync_proxy.send_event(UserEvent::ToggleScratchpad);
+                } else if *key == Key::F6 {
+                    let _ = sync_proxy.send_event(UserEvent::FocusPane(!sidebar_focused));
+                } else if *key == Key::F11 {
+                    let _ = sync_proxy.send_event(UserEvent::FocusMode(!focus_mode));
+                } else if ctrl && *key == Key::Character("u") {
+                    let _ = sync_proxy.send_event(UserEvent::PushToGitHub);
+                } else if ctrl && *key == Key::Character("i") {
+                    let _ = sync_proxy.send_event(UserEvent::PullFromGitHub);
+                } else if ctrl && *key == Key::Character("s") {
+                    let _ = sync_proxy.send_event(UserEvent::SyncBoth);
+                } else if ctrl && *key == Key::Character("g") {
+                    let _ = sidebar.evaluate_script("showAddFolderModal()");
+                } else if ctrl && *key == Key::Character("d") {
+                    let _ = sync_proxy.send_event(UserEvent::QuickAdd);
+                } else if ctrl && shift && !editing && matches!(key, Key::Character(c) if c.eq_ignore_ascii_case("z"))
+                {
+                    let _ = sync_proxy.send_event(UserEvent::RestoreDeleted);
+                } else if ctrl && !editing && *key == Key::Character("z") {
+                    let _ = sync_proxy.send_event(UserEvent::UndoQuickAdd);
+                } else if ctrl && shift && matches!(key, Key::Character(c) if c.eq_ignore_ascii_case("n"))
+                {
+                    let _ = sidebar.focus();
+                    let _ = sidebar.evaluate_script("showInlineFolder()");
+                } else if ctrl && *key == Key::Character("n") {
+                    let _ = sidebar.evaluate_script("showAddBookmarkModal()");
+                } else if *key == Key::F1 || (ctrl && *key == Key::Character("/")) {
+                    let _ = sidebar.evaluate_script("showHelpModal()");
+                } else if ctrl && *key == Key::Character("q") {
+                    *control_flow = ControlFlow::Exit;
+                } else if ctrl && matches!(key, Key::Character(c) if c == "=" || c == "+") {
+                    let _ = sync_proxy.send_event(UserEvent::Zoom(1));
+                } else if ctrl && *key == Key::Character("-") {
+                    let _ = sync_proxy.send_event(UserEvent::Zoom(-1));
+                } else if ctrl && *key == Key::Character("0") {
+                    let _ = sync_proxy.send_event(UserEvent::Zoom(0));
+                } else if *key == Key::F5 {
+                    let _ = content.evaluate_script("location.reload()");
+                } else if ctrl && *key == Key::Character("[") {
+                    let _ = content.evaluate_script("history.back()");
+                } else if ctrl && *key == Key::Character("]") {
+                    let _ = content.evaluate_script("history.forward()");
+                } else if *key == Key::Escape {
+                    let _ = sidebar.evaluate_script("closeModals()");
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Resized(new_size),
+                ..
+            } => {
+                let scale = window.scale_factor();
+                let w = new_size.width as f64 / scale;
+                let h = new_size.height as f64 / scale;
+                let sidebar_shown = !sidebar_collapsed && !focus_mode;
+                layout_webviews(&sidebar, &content, w, h, sidebar_shown);
+                let content_x = if sidebar_shown { SIDEBAR_WIDTH } else { 0.0 };
+                #[cfg(target_os = "linux")]
+                layout_pip(&pip_gtk_box, w - content_x, h);
+                #[cfg(not(target_os = "linux"))]
+                layout_pip(&pip, content_x, w - content_x, h);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ThemeChanged(_),
+                ..
+            } => {
+                let _ = sync_proxy.send_event(UserEvent::ApplyTheme);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Focused(true),
+                ..
+            } => {
+                // Pick up hand edits made while the app was in the background
+                let path = config_path();
+                if file_stamp(&path) != store_stamp {
+                    match BookmarkStore::try_load_from(&path) {
+                        Some(reloaded) => {
+                            store = reloaded;
+                            store_stamp = file_stamp(&path);
+                            if let Ok(json) = serde_json::to_string(&store.folders) {
+                                let _ =
+                                    sidebar.evaluate_script(&format!("renderBookmarks({json})"));
+                            }
+                            let _ = sidebar.evaluate_script(
+                                "updateSyncStatus('Reloaded bookmarks.json after an external edit', 'success')",
+                            );
+                        }
+                        None => {
+                            let _ = sidebar.evaluate_script(
+                                "updateSyncStatus('bookmarks.json was edited but is not valid JSON — keeping the loaded copy', 'error')",
+                            );
+                        }
+                    }
+                }
+                let now = Instant::now();
+                if !awaiting_clone && should_pull_on_focus(&settings, &store, last_focus_pull, now) {
+                    last_focus_pull = Some(now);
+                    let _ = sync_proxy.send_event(UserEvent::PullFromGitHub);
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                // There's no tray icon to come back from, so "minimize" keeps
+                // the window in the taskbar rather than hiding it
+                if settings.close_action() == "minimize" {
+                    window.set_minimized(true);
+                } else {
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            Event::UserEvent(UserEvent::Navigate(url)) if is_bookmarklet(&url) => {
+                // Runs on whatever page is showing; nothing is loaded or recorded
+                if !settings.run_bookmarklets {
+                    let _ = sidebar.evaluate_script(
+                        "updateSyncStatus('Bookmarklets are off \u{2014} turn them on in Settings', 'error')",
+                    );
+                } else if let Some(code) = bookmarklet_code(&url) {
+                    let _ = content.evaluate_script(&code);
+                }
+            }
+            Event::UserEvent(UserEvent::Navigate(url)) => {
+                if settings.confirm_form_leave && form_dirty {
+                    let previous =
+                        serde_json::to_string(&highlight_url).unwrap_or_else(|_| "null".into());
+                    pending_navigation = Some(url);
+                    let _ = sidebar.evaluate_script(&format!("confirmLeavePage({previous})"));
+                    return;
+                }
+                if let Some(container) = bookmark_container(&store, &url) {
+                    if container != content_container {
+                        // WebKit fixes a view's storage when it is made, so switching
+                        // container means a new view: a fresh web process, and the
+                        // pane's back/forward history starts over
+                        let builder = match container.as_deref() {
+                            Some(name) => WebViewBuilder::with_web_context(
+                                content_contexts
+                                    .entry(name.to_string())
+                                    .or_insert_with(|| WebContext::new(Some(container_dir(name)))),
+                            ),
+                            None => WebViewBuilder::new(),
+                        };
+                        let builder = content_webview_builder(
+                            builder,
+                            sync_proxy.clone(),
+                            blocklist.clone(),
+                            &blocker,
+                        );
+                        let builder = match content.bounds() {
+                            Ok(bounds) => builder.with_bounds(bounds),
+                            Err(_) => builder,
+                        };
+                        #[cfg(target_os = "linux")]
+                        let rebuilt = builder.build_gtk(&content_gtk_box);
+                        #[cfg(not(target_os = "linux"))]
+                        let rebuilt = builder.build_as_child(&window);
+                        match rebuilt {
+                            Ok(view) => {
+                                content = view;
+                                content_container = container;
+                            }
+                            Err(e) => {
+                                let json = serde_json::to_string(&format!(
+                                    "Couldn't open the container: {e}"
+                                ))
+                                .unwrap_or_else(|_| "''".into());
+                                let _ = sidebar.evaluate_script(&format!(
+                                    "updateSyncStatus({json}, 'error')"
+                                ));
+                                return;
+                            }
+                        }
+                    }
+                }
+                refresh_generation += 1;
+                refresh = store
+                    .folders
+                    .iter()
+                    .flat_map(|f| &f.bookmarks)
+                    .find(|bm| bm.url == url)
+                    .and_then(|bm| Some((bm.url.clone(), bm.refresh_secs?)));
+                if let Some((_, secs)) = refresh {
+                    schedule_refresh(sync_proxy.clone(), refresh_generation, secs);
+                }
+                active_url = Some(url.clone());
+                highlight_url = Some(url.clone());
+                highlight_clicked = true;
+                if settings.auto_expand_active && expand_folder_of(&mut store, &url) {
+                    save_and_render(&store, &mut store_stamp, 0, &sidebar);
+                }
+                pending_scroll = scroll_positions.get(&url).copied().filter(|y| *y > 0.0);
+                load_with_auth(
+                    &content,
+                    &expand_bookmark_url(&url, &settings.base_path),
+                    &auth_headers,
+                );
+                push_recent(&mut recent, &url);
+                if let Ok(json) = serde_json::to_string(&recent) {
+                    let _ = sidebar.evaluate_script(&format!("updateRecent({json})"));
+                }
+                let is_bookmark = store
+                    .folders
+                    .iter()
+                    .flat_map(|f| &f.bookmarks)
+                    .any(|bm| bm.url == url);
+                let (today, date) = utc_today();
+                let tracking = settings.history_retention_days() > 0;
+                if tracking && is_bookmark && record_visit(&mut visits, &url, &date) {
+                    let _ = save_visits(&visits_path(), &visits);
+                    let json = visits_json(&visits, today);
+                    let _ = sidebar.evaluate_script(&format!("updateVisits({json})"));
+                }
+            }
+            Event::UserEvent(UserEvent::GoHome) => {
+                if let Some(url) = settings.home_url() {
+                    let _ = sync_proxy.send_event(UserEvent::Navigate(url.to_string()));
+                    return;
+                }
+                let theme = resolved_theme(settings.theme(), window.theme());
+                let _ = content.load_html(&welcome_html(theme));
+                active_url = None;
+                highlight_url = None;
+                highlight_clicked = false;
+                current_page = None;
+                let _ = sidebar.evaluate_script("setActiveUrl(null)");
+            }
+            Event::UserEvent(UserEvent::AuthRequired(url)) => {
+                let stored = url_origin(&url).and_then(|origin| auth_headers.get(&origin));
+                if let Some(header) = stored {
+                    probe_auth(sync_proxy.clone(), url, header.clone());
+                    return;
+                }
+                let escaped = url.replace('\\', "\\\\").replace('\'', "\\'");
+                let _ = sidebar.evaluate_script(&format!("showAuthModal('{escaped}')"));
+            }
+            Event::UserEvent(UserEvent::NavigationBlocked(host)) => {
+                let msg = format!("Blocked {host} (on the blocklist)");
+                let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json}, 'error')"));
+            }
+            Event::UserEvent(UserEvent::PageStarted(url)) => {
+                // Covers redirects and links followed inside the page, not just bookmarks
+                let host = host_of(&url);
+                if host != current_host {
+                    let zoom = host
+                        .as_ref()
+                        .and_then(|h| zoom_levels.get(h))
+                        .copied()
+                        .unwrap_or(1.0);
+                    let _ = content.zoom(zoom);
+                    current_host = host;
+                }
+            }
+            Event::UserEvent(UserEvent::Zoom(direction)) => {
+                let Some(host) = current_host.clone() else {
+                    return;
+                };
+                let current = zoom_levels.get(&host).copied().unwrap_or(1.0);
+                let zoom = step_zoom(current, direction);
+                if zoom == 1.0 {
+                    zoom_levels.remove(&host);
+                } else {
+                    zoom_levels.insert(host.clone(), zoom);
+                }
+                let _ = content.zoom(zoom);
+                let _ = save_zoom(&zoom_path(), &zoom_levels);
+                let percent = (zoom * 100.0).round();
+                let _ = sidebar.evaluate_script(&format!(
+                    "updateSyncStatus('Zoom {percent}% for {host}')"
+                ));
+            }
+            Event::UserEvent(UserEvent::FormDirty(dirty)) => {
+                form_dirty = dirty;
+            }
+            Event::UserEvent(UserEvent::DiscardFormChanges) => {
+                form_dirty = false;
+                if let Some(url) = pending_navigation.take() {
+                    let _ = sync_proxy.send_event(UserEvent::Navigate(url));
+                }
+            }
+            Event::UserEvent(UserEvent::PageLoading(loading)) => {
+                // A new document starts with untouched forms and nothing focused
+                if loading {
+                    form_dirty = false;
+                    content_editing = false;
+                }
+                let _ = sidebar.evaluate_script(&format!("setPageLoading({loading})"));
+            }
+            Event::UserEvent(UserEvent::ScrollPosition { url, y }) => {
+                scroll_positions.insert(url, y);
+            }
+            Event::UserEvent(UserEvent::PageReady { url, title }) => {
+                let clicked = std::mem::take(&mut highlight_clicked);
+                let matched = bookmark_url_for(&store, &url)
+                    .or_else(|| highlight_url.clone().filter(|_| clicked));
+                if matched != highlight_url {
+                    highlight_url = matched;
+                    // Reached without a click, by a link or the address bar
+                    if let Some(url) = highlight_url.as_deref().filter(|_| settings.auto_expand_active) {
+                        if expand_folder_of(&mut store, url) {
+                            save_and_render(&store, &mut store_stamp, 0, &sidebar);
+                        }
+                    }
+                    let json =
+                        serde_json::to_string(&highlight_url).unwrap_or_else(|_| "null".into());
+                    let _ = sidebar.evaluate_script(&format!("setActiveUrl({json})"));
+                }
+                // Opt-in per bookmark, and only on the page opened as that bookmark
+                if let Some(bm) = highlight_url.as_ref().and_then(|u| {
+                    store
+                        .folders
+                        .iter()
+                        .flat_map(|f| &f.bookmarks)
+                        .find(|bm| bm.url == *u)
+                }) {
+                    for script in injection_scripts(bm, &url) {
+                        let _ = content.evaluate_script(&script);
+                    }
+                }
+                current_page = Some((url, title));
+                // Applied to the first load after a bookmark click, even if the
+                // server redirected; scrollTo clamps if the page got shorter
+                if let Some(y) = pending_scroll.take() {
+                    let _ = content.evaluate_script(&format!("window.__bbRestoreScroll({y})"));
+                }
+            }
+            Event::UserEvent(UserEvent::AuthFailed(url)) => {
+                if let Some(origin) = url_origin(&url) {
+                    auth_headers.remove(&origin);
+                }
+                let escaped = url.replace('\\', "\\\\").replace('\'', "\\'");
+                let _ = sidebar.evaluate_script(&format!("showAuthModal('{escaped}')"));
+            }
+            Event::UserEvent(UserEvent::SubmitAuth {
+                url,
+                username,
+                password,
+            }) => {
+                if let Some(origin) = url_origin(&url) {
+                    auth_headers.insert(origin, basic_auth_header(&username, &password));
+                }
+                load_with_auth(&content, &url, &auth_headers);
+            }
+            Event::UserEvent(UserEvent::ReopenRecent) => {
+                // The front entry is the page currently shown, so jump to the one before it
+                if let Some(url) = recent.get(1).cloned() {
+                    let _ = sync_proxy.send_event(UserEvent::Navigate(url));
+                }
+            }
+            Event::UserEvent(UserEvent::SidebarFocused(focused)) => {
+                sidebar_focused = focused;
+            }
+            Event::UserEvent(UserEvent::TextFocus {
+                sidebar: in_sidebar,
+                editing,
+            }) => {
+                if in_sidebar {
+                    sidebar_editing = editing;
+                } else {
+                    content_editing = editing;
+                }
+            }
+            Event::UserEvent(UserEvent::FocusPane(to_sidebar)) => {
+                if to_sidebar {
+                    // Focusing a hidden sidebar would strand the keyboard, so show it
+                    if focus_mode {
+                        let _ = sync_proxy.send_event(UserEvent::FocusMode(false));
+                    }
+                    if sidebar_collapsed {
+                        let _ = sync_proxy.send_event(UserEvent::ToggleSidebar);
+                    }
+                    let _ = sidebar.focus();
+                    let _ = sidebar.evaluate_script("focusSidebar()");
+                } else {
+                    let _ = content.focus();
+                    let _ = content.evaluate_script("window.focus()");
+                }
+            }
+            Event::UserEvent(UserEvent::ShowOmnibar) => {
+                // The omnibar lives in the sidebar, so make sure it can be seen
+                if focus_mode {
+                    let _ = sync_proxy.send_event(UserEvent::FocusMode(false));
+                }
+                if sidebar_collapsed {
+                    let _ = sync_proxy.send_event(UserEvent::ToggleSidebar);
+                }
+                let _ = sidebar.focus();
+                let _ = sidebar.evaluate_script("showOmnibar()");
+            }
+            Event::UserEvent(UserEvent::OmnibarSubmit(text)) => {
+                if !text.trim().is_empty() {
+                    let url = experiment_enabled(OMNIBAR_BOOKMARKS_EXPERIMENT)
+                        .then(|| omnibar_bookmark(&store, &text))
+                        .flatten()
+                        .unwrap_or_else(|| omnibar_target(&text, settings.search_engine_url()));
+                    let _ = sync_proxy.send_event(UserEvent::Navigate(url));
+                }
+            }
+            Event::UserEvent(UserEvent::QueueBookmark(url)) => {
+                if push_queue(&mut reading_queue, &url) {
+                    if let Ok(json) = serde_json::to_string(&reading_queue) {
+                        let _ = sidebar.evaluate_script(&format!("updateQueue({json})"));
+                    }
+                }
+            }
+            Event::UserEvent(UserEvent::OpenNextQueued) => {
+                if let Some(url) = reading_queue.pop_front() {
+                    let _ = sync_proxy.send_event(UserEvent::Navigate(url));
+                    if let Ok(json) = serde_json::to_string(&reading_queue) {
+                        let _ = sidebar.evaluate_script(&format!("updateQueue({json})"));
+                    }
+                }
+            }
+            Event::UserEvent(UserEvent::TagBookmarks { targets, tag, add }) => {
+                let changed = tag_bookmarks(&mut store, &targets, &tag, add, &utc_now());
+                let noun = if changed == 1 { "bookmark" } else { "bookmarks" };
+                let msg = match (add, changed) {
+                    (_, 0) => "No bookmarks changed".to_string(),
+                    (true, _) => format!("Tagged {changed} {noun}"),
+                    (false, _) => format!("Removed the tag from {changed} {noun}"),
+                };
+                let msg = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({msg}, 'success')"));
+                if changed == 0 {
+                    return;
+                }
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+            }
+            Event::UserEvent(UserEvent::PinReference {
+                folder_index,
+                bookmark_index,
+            }) => {
+                let Some(url) = store
+                    .folders
+                    .get(folder_index)
+                    .and_then(|f| f.bookmarks.get(bookmark_index))
+                    .map(|bm| bm.url.clone())
+                    .filter(|url| !is_bookmarklet(url))
+                else {
+                    return;
+                };
+                let _ = pip.load_url(&url);
+                let _ = sidebar.evaluate_script(&format!(
+                    "setReference({})",
+                    serde_json::to_string(&url).unwrap_or_else(|_| "''".into())
+                ));
+                settings.pip_url = url;
+                let _ = settings.save();
+                let _ = sync_proxy.send_event(UserEvent::ShowReference(true));
+            }
+            Event::UserEvent(UserEvent::ShowReference(shown)) => {
+                if shown && settings.pip_url.is_empty() {
+                    let _ = sidebar.evaluate_script(
+                        "updateSyncStatus('No reference page; right-click a bookmark and choose Pin as reference')",
+                    );
+                    return;
+                }
+                pip_shown = shown;
+                #[cfg(target_os = "linux")]
+                {
+                    use gtk::prelude::*;
+                    pip_gtk_box.set_visible(shown);
+                }
+                #[cfg(not(target_os = "linux"))]
+                let _ = pip.set_visible(shown);
+            }
+            Event::UserEvent(UserEvent::ArchiveBookmark {
+                folder_index,
+                bookmark_index,
+            }) => {
+                let Some(url) = store
+                    .folders
+                    .get(folder_index)
+                    .and_then(|f| f.bookmarks.get(bookmark_index))
+                    .map(|bm| bm.url.clone())
+                else {
+                    return;
+                };
+                let _ = sidebar.evaluate_script(
+                    "updateSyncStatus('Saving to the Wayback Machine (this can take a minute)...')",
+                );
+                let proxy = sync_proxy.clone();
+                std::thread::spawn(move || {
+                    let result = archive_to_wayback(&url);
+                    let _ = proxy.send_event(UserEvent::Archived { url, result });
+                });
+            }
+            Event::UserEvent(UserEvent::Archived { url, result }) => {
+                let (msg, class) = match result {
+                    Ok(snapshot) => {
+                        if set_wayback_url(&mut store, &url, &snapshot) > 0 {
+                            commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                        }
+                        (format!("Archived: {snapshot}"), "success")
+                    }
+                    Err(e) => (format!("Archiving failed: {e}"), "error"),
+                };
+                let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json}, '{class}')"));
+            }
+            Event::UserEvent(UserEvent::ToggleReference) => {
+                let _ = sync_proxy.send_event(UserEvent::ShowReference(!pip_shown));
+            }
+            Event::UserEvent(UserEvent::ToggleScratchpad) => {
+                let _ = sidebar.focus();
+                let _ = sidebar.evaluate_script("toggleScratchpad()");
+            }
+            Event::UserEvent(UserEvent::LoadScratchpad) => {
+                let text = load_scratchpad(&scratchpad_path());
+                let json = serde_json::to_string(&text).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("showScratchpad({json})"));
+            }
+            Event::UserEvent(UserEvent::SaveScratchpad(text)) => {
+                if let Err(e) = save_scratchpad(&scratchpad_path(), &text) {
+                    let json = serde_json::to_string(&format!("Scratchpad not saved: {e}"))
+                        .unwrap_or_else(|_| "''".into());
+                    let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json}, 'error')"));
+                }
+            }
+            Event::UserEvent(UserEvent::CloseReference) => {
+                settings.pip_url.clear();
+                let _ = settings.save();
+                let _ = pip.load_url("about:blank");
+                let _ = sidebar.evaluate_script("setReference('')");
+                let _ = sync_proxy.send_event(UserEvent::ShowReference(false));
+            }
+            Event::UserEvent(UserEvent::ToggleSidebar) => {
+                // Asking for the sidebar while it is hidden by focus mode brings it back
+                if focus_mode {
+                    let _ = sync_proxy.send_event(UserEvent::FocusMode(false));
+                    return;
+                }
+                sidebar_collapsed = !sidebar_collapsed;
+                settings.sidebar_collapsed = sidebar_collapsed;
+                let _ = settings.save();
+                let _ =
+                    sidebar.evaluate_script(&format!("setSidebarCollapsed({})", sidebar_collapsed));
+                #[cfg(target_os = "linux")]
+                resize_sidebar_box(&sidebar_gtk_box, sidebar_collapsed, focus_mode);
+                #[cfg(not(target_os = "linux"))]
+                {
+                    let (w, h) = logical_inner_size(&window);
+                    layout_webviews(&sidebar, &content, w, h, !sidebar_collapsed);
+                    let content_x = if sidebar_collapsed { 0.0 } else { SIDEBAR_WIDTH };
+                    layout_pip(&pip, content_x, w - content_x, h);
+                }
+            }
+            Event::UserEvent(UserEvent::FocusMode(enabled)) => {
+                focus_mode = enabled;
+                settings.focus_mode = enabled;
+                let _ = settings.save();
+                #[cfg(target_os = "linux")]
+                resize_sidebar_box(&sidebar_gtk_box, sidebar_collapsed, focus_mode);
+                #[cfg(not(target_os = "linux"))]
+                {
+                    let (w, h) = logical_inner_size(&window);
+                    let sidebar_shown = !sidebar_collapsed && !focus_mode;
+                    layout_webviews(&sidebar, &content, w, h, sidebar_shown);
+                    let content_x = if sidebar_shown { SIDEBAR_WIDTH } else { 0.0 };
+                    layout_pip(&pip, content_x, w - content_x, h);
+                }
+                if enabled {
+                    let _ = content.evaluate_script(FOCUS_HINT_SCRIPT);
+                }
+            }
+            Event::UserEvent(UserEvent::ToggleFolder(index)) => {
+                if let Some(folder) = store.folders.get_mut(index) {
+                    folder.expanded = !folder.expanded;
+                    save_and_render(&store, &mut store_stamp, 0, &sidebar);
+                    let _ = sidebar.evaluate_script(&dirty_script(&store, &settings));
+                }
+            }
+            Event::UserEvent(UserEvent::AddFolder { name, icon }) => {
+                let existing: Vec<String> =
+                    store.folders.iter().map(|f| f.name.clone()).collect();
+                let unique = unique_folder_name(&existing, &name);
+                if unique != name && settings.on_duplicate_folder() == "reject" {
+                    let msg = format!("There's already a folder called \u{201c}{name}\u{201d}");
+                    let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                    let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json}, 'error')"));
+                    return;
+                }
+                let folder = Folder {
+                    name: unique,
+                    expanded: true,
+                    icon,
+                    exclude_from_sync: false,
+                    locked: false,
+                    pin: None,
+                    bookmarks: vec![],
+                };
+                insert_at_position(&mut store.folders, folder, settings.new_folder_position());
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+            }
+            Event::UserEvent(UserEvent::SetFolderIcon { folder_index, icon }) => {
+                if let Some(folder) = store.folders.get_mut(folder_index) {
+                    folder.icon = icon;
+                    commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                }
+            }
+            Event::UserEvent(UserEvent::ResetLibrary { push }) => {
+                // No backup, no reset: there must always be a way back
+                let backup = match write_reset_backup(&backups_dir(), &store) {
+                    Ok(path) => path,
+                    Err(e) => {
+                        let msg = format!("Reset cancelled — could not back up: {e}");
+                        let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                        let _ = sidebar
+                            .evaluate_script(&format!("updateSyncStatus({json}, 'error')"));
+                        return;
+                    }
+                };
+                store = BookmarkStore { folders: Vec::new() };
+                last_folder = None;
+                last_quick_add = None;
+                save_and_render(&store, &mut store_stamp, settings.backup_keep(), &sidebar);
+                let msg = format!("Library reset — backup saved to {}", backup.display());
+                let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json}, 'success')"));
+                if push {
+                    // Typing DELETE already confirmed removing everything remotely
+                    push_confirmed = true;
+                    let _ = sync_proxy.send_event(UserEvent::PushToGitHub);
+                } else {
+                    // Skip auto-sync, which would push the empty library
+                    let _ = sidebar.evaluate_script(&dirty_script(&store, &settings));
+                }
+            }
+            Event::UserEvent(UserEvent::OpenConfigDir) => {
+                let msg = match open_config_dir() {
+                    Ok(dir) => format!("Opened {}", dir.display()),
+                    Err(e) => format!("Could not open the config folder: {e}"),
+                };
+                let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json})"));
+            }
+            Event::UserEvent(UserEvent::OpenExternal(url)) => {
+                if let Err(e) = open_external(&url) {
+                    let json = serde_json::to_string(&format!("Could not open browser: {e}"))
+                        .unwrap_or_else(|_| "''".into());
+                    let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json}, 'error')"));
+                }
+            }
+            Event::UserEvent(UserEvent::SetViewMode(mode)) => {
+                settings.view_mode = mode;
+                let _ = settings.save();
+            }
+            Event::UserEvent(UserEvent::EditBookmark {
+                folder_index,
+                bookmark_index,
+                name,
+                url,
+                inject_css,
+                inject_js,
+                mnemonic,
+                container,
+                notes,
+                refresh_secs,
+            }) => {
+                // Checked first so a refused container or mnemonic leaves the rest unsaved too
+                let container = match normalize_container(container.as_deref().unwrap_or("")) {
+                    Ok(container) => container,
+                    Err(msg) => {
+                        let json = serde_json::to_string(&format!("Not saved: {msg}"))
+                            .unwrap_or_else(|_| "''".into());
+                        let _ =
+                            sidebar.evaluate_script(&format!("updateSyncStatus({json}, 'error')"));
+                        return;
+                    }
+                };
+                let relabeled =
+                    match set_mnemonic(&mut store, folder_index, bookmark_index, mnemonic) {
+                        Ok(changed) => changed,
+                        Err(msg) => {
+                            let json = serde_json::to_string(&format!("Not saved: {msg}"))
+                                .unwrap_or_else(|_| "''".into());
+                            let _ = sidebar
+                                .evaluate_script(&format!("updateSyncStatus({json}, 'error')"));
+                            return;
+                        }
+                    };
+                let Some(bm) = store
+                    .folders
+                    .get_mut(folder_index)
+                    .and_then(|f| f.bookmarks.get_mut(bookmark_index))
+                else {
+                    return;
+                };
+                let now = utc_now();
+                let edited = edit_bookmark(bm, &name, &url, &now);
+                let moved = bm.container != container;
+                bm.container = container;
+                let noted = bm.notes != notes;
+                if noted {
+                    bm.modified = Some(now);
+                }
+                bm.notes = notes;
+                let retimed = bm.refresh_secs != refresh_secs;
+                bm.refresh_secs = refresh_secs;
+                if !set_injection(bm, inject_css.as_deref(), inject_js.as_deref())
+                    && !edited
+                    && !relabeled
+                    && !moved
+                    && !noted
+                    && !retimed
+                {
+                    return;
+                }
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+            }
+            Event::UserEvent(UserEvent::OpenMnemonic(c)) => {
+                match mnemonic_owner(&store, c) {
+                    // The sidebar knows which locked folders are open
+                    Some((fi, bi)) => {
+                        let _ = sidebar.evaluate_script(&format!("openMnemonic({fi}, {bi})"));
+                    }
+                    None => {
+                        let msg = format!("No bookmark has the mnemonic \u{201c}{c}\u{201d}");
+                        let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                        let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json}, 'error')"));
+                    }
+                }
+            }
+            Event::UserEvent(UserEvent::MoveBookmark {
+                from_folder,
+                from_index,
+                url,
+                to_folder,
+                to_index,
+            }) => {
+                let still_there = store
+                    .folders
+                    .get(from_folder)
+                    .and_then(|f| f.bookmarks.get(from_index))
+                    .is_some_and(|bm| bm.url == url);
+                if !still_there
+                    || !move_bookmark(
+                        &mut store.folders,
+                        (from_folder, from_index),
+                        (to_folder, to_index),
+                    )
+                {
+                    return;
+                }
+                // Its index moved, so Ctrl+Z can no longer find it
+                last_quick_add = None;
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+            }
+            Event::UserEvent(UserEvent::MoveFolder { from, to }) => {
+                if !move_item(&mut store.folders, from, to) {
+                    return;
+                }
+                last_folder = None;
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+            }
+            Event::UserEvent(UserEvent::SortFolders(mode)) => {
+                // Folder indices change, so the quick-add folder can't be trusted
+                last_folder = None;
+                sort_folders(&mut store.folders, mode);
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+            }
+            Event::UserEvent(UserEvent::ToggleOpenOnStartup {
+                folder_index,
+                bookmark_index,
+            }) => {
+                let Some(bm) = store
+                    .folders
+                    .get_mut(folder_index)
+                    .and_then(|f| f.bookmarks.get_mut(bookmark_index))
+                else {
+                    return;
+                };
+                bm.open_on_startup = !bm.open_on_startup;
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+            }
+            Event::UserEvent(UserEvent::ToggleSyncExclusion(index)) => {
+                if let Some(folder) = store.folders.get_mut(index) {
+                    folder.exclude_from_sync = !folder.exclude_from_sync;
+                    commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                }
+            }
+            Event::UserEvent(UserEvent::LockFolder { folder_index, pin }) => {
+                if let Some(folder) = store.folders.get_mut(folder_index) {
+                    let salt = new_pin_salt();
+                    folder.pin = Some(PinHash {
+                        hash: hash_pin(&salt, &pin),
+                        salt,
+                    });
+                    folder.locked = true;
+                    folder.expanded = false;
+                    commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                }
+            }
+            Event::UserEvent(UserEvent::UnlockFolder {
+                folder_index,
+                pin,
+                remove,
+            }) => {
+                if let Some(folder) = store.folders.get_mut(folder_index) {
+                    let ok = folder
+                        .pin
+                        .as_ref()
+                        .is_some_and(|p| verify_pin(&p.hash, &p.salt, &pin));
+                    if ok && remove {
+                        folder.locked = false;
+                        folder.pin = None;
+                        commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                    }
+                    let _ = sidebar.evaluate_script(&format!("folderUnlocked({folder_index}, {ok})"));
+                }
+            }
+            Event::UserEvent(UserEvent::SuggestFolder(url)) => {
+                if let Some(index) = suggest_folder(&store, &url) {
+                    let _ = sidebar.evaluate_script(&format!("applyFolderSuggestion({index})"));
+                }
+            }
+            Event::UserEvent(UserEvent::CopyBookmarkDebug {
+                folder_index,
+                bookmark_index,
+            }) => {
+                let Some(folder) = store.folders.get(folder_index) else {
+                    return;
+                };
+                let Some(bm) = folder.bookmarks.get(bookmark_index) else {
+                    return;
+                };
+                let report = bookmark_debug_json(folder, bm, &link_health, &visits, &zoom_levels);
+                #[cfg(target_os = "linux")]
+                gtk::Clipboard::get(&gtk::gdk::SELECTION_CLIPBOARD).set_text(&report);
+                #[cfg(not(target_os = "linux"))]
+                if let Ok(json) = serde_json::to_string(&report) {
+                    let _ =
+                        sidebar.evaluate_script(&format!("navigator.clipboard.writeText({json})"));
+                }
+                let msg = format!("Copied debug info for \u{201c}{}\u{201d}", bm.name);
+                let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json}, 'success')"));
+            }
+            Event::UserEvent(UserEvent::CopyFolderMarkdown(index)) => {
+                if let Some(folder) = store.folders.get(index) {
+                    let markdown = folder_to_markdown(folder);
+                    #[cfg(target_os = "linux")]
+                    gtk::Clipboard::get(&gtk::gdk::SELECTION_CLIPBOARD).set_text(&markdown);
+                    #[cfg(not(target_os = "linux"))]
+                    if let Ok(json) = serde_json::to_string(&markdown) {
+                        let _ = sidebar
+                            .evaluate_script(&format!("navigator.clipboard.writeText({json})"));
+                    }
+                    let count = folder.bookmarks.len();
+                    let noun = if count == 1 { "link" } else { "links" };
+                    let _ = sidebar.evaluate_script(&format!(
+                        "updateSyncStatus('Copied {count} {noun}', 'success')"
+                    ));
+                }
+            }
+            Event::UserEvent(UserEvent::AddBookmark {
+                folder_index,
+                name,
+                url,
+                notes,
+            }) => {
+                if let Some(folder) = store.folders.get_mut(folder_index) {
+                    last_folder = Some(folder_index);
+                    let bookmark = Bookmark {
+                        name,
+                        url,
+                        created: Some(utc_now()),
+                        notes,
+                        ..Default::default()
+                    };
+                    insert_at_position(
+                        &mut folder.bookmarks,
+                        bookmark,
+                        settings.new_bookmark_position(),
+                    );
+                    commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                }
+            }
+            Event::UserEvent(UserEvent::ClipSelection { url, title, text }) => {
+                let args = serde_json::to_string(&[&url, &title, &text])
+                    .unwrap_or_else(|_| "[]".into());
+                let _ = sidebar.focus();
+                let _ = sidebar.evaluate_script(&format!("showClipModal(...{args})"));
+            }
+            Event::UserEvent(UserEvent::QuickAdd) => {
+                // Inside the welcome page or with nowhere to put it, ask instead
+                let Some((url, title)) = current_page.clone().filter(|_| !store.folders.is_empty())
+                else {
+                    let _ = sidebar.evaluate_script("showAddBookmarkModal()");
+                    return;
+                };
+                let folder_index = last_folder
+                    .filter(|fi| *fi < store.folders.len())
+                    .unwrap_or(0);
+                let name = if title.is_empty() { url.clone() } else { title };
+                let folder = &mut store.folders[folder_index];
+                let position = settings.new_bookmark_position();
+                let bookmark = Bookmark {
+                    name,
+                    url: url.clone(),
+                    created: Some(utc_now()),
+                    ..Default::default()
+                };
+                insert_at_position(&mut folder.bookmarks, bookmark, position);
+                let bookmark_index = if position == "top" {
+                    0
+                } else {
+                    folder.bookmarks.len() - 1
+                };
+                let folder_name = folder.name.clone();
+                last_folder = Some(folder_index);
+                last_quick_add = Some((folder_index, bookmark_index, url));
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                let msg = serde_json::to_string(&format!("Added to {folder_name} — Ctrl+Z to undo"))
+                    .unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({msg}, 'success')"));
+            }
+            Event::UserEvent(UserEvent::UndoQuickAdd) => {
+                let Some((folder_index, bookmark_index, url)) = last_quick_add.take() else {
+                    return;
+                };
+                // Only if that bookmark is still where it was put
+                let Some(folder) = store
+                    .folders
+                    .get_mut(folder_index)
+                    .filter(|f| f.bookmarks.get(bookmark_index).is_some_and(|bm| bm.url == url))
+                else {
+                    return;
+                };
+                folder.bookmarks.remove(bookmark_index);
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                let _ = sidebar.evaluate_script("updateSyncStatus('Bookmark removed', 'success')");
+            }
+            Event::UserEvent(UserEvent::DeleteBookmark {
+                folder_index,
+                bookmark_index,
+            }) => {
+                if let Some(folder) = store.folders.get_mut(folder_index) {
+                    if bookmark_index < folder.bookmarks.len() {
+                        let bookmark = folder.bookmarks.remove(bookmark_index);
+                        let msg = deleted_status(&bookmark.name);
+                        last_deleted = Some(Deleted::Bookmark {
+                            folder_index,
+                            folder_name: folder.name.clone(),
+                            index: bookmark_index,
+                            bookmark,
+                        });
+                        commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                        let _ = sidebar
+                            .evaluate_script(&format!("updateSyncStatus({msg}, 'success')"));
+                    }
+                }
+            }
+            Event::UserEvent(UserEvent::DeleteFolder(index)) => {
+                if index < store.folders.len() {
+                    let folder = store.folders.remove(index);
+                    let msg = deleted_status(&folder.name);
+                    last_deleted = Some(Deleted::Folder { index, folder });
+                    commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                    let _ =
+                        sidebar.evaluate_script(&format!("updateSyncStatus({msg}, 'success')"));
+                }
+            }
+            Event::UserEvent(UserEvent::RestoreDeleted) => {
+                let Some(deleted) = last_deleted.take() else {
+                    let _ = sidebar
+                        .evaluate_script("updateSyncStatus('Nothing to restore', 'error')");
+                    return;
+                };
+                let label = restore_deleted(&mut store, deleted);
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                let msg = serde_json::to_string(&format!("Restored {label}"))
+                    .unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({msg}, 'success')"));
+            }
+            Event::UserEvent(UserEvent::SaveSettings {
+                github_token,
+                github_repo,
+                density,
+                scrollbars,
+                ui_font_scale,
+                decorations,
+                always_on_top,
+                auto_sync_mode,
+                show_urls,
+                show_unread_badge,
+                theme,
+                new_folder_position,
+                new_bookmark_position,
+                duplicate_strategy,
+                close_action,
+                on_duplicate_folder,
+                confirm_form_leave,
+                sync_on_focus,
+                run_bookmarklets,
+                block_trackers,
+                show_pull_changes,
+                auto_expand_active,
+                pretty_json,
+                recently_added,
+                sync_provider,
+                gitea_base_url,
+                home_url,
+            }) => {
+                if !github_token.is_empty() {
+                    settings.github_token = github_token;
+                }
+                if settings.github_repo != github_repo {
+                    remote_sha = None;
+                }
+                settings.github_repo = github_repo;
+                // An unknown provider keeps the previous one, like a bad home page
+                match SyncApi::new(&sync_provider, &gitea_base_url) {
+                    Ok(api) => {
+                        if settings.sync_api().as_ref() != Ok(&api) {
+                            remote_sha = None;
+                        }
+                        settings.sync_provider = api.provider().to_string();
+                        settings.gitea_base_url = match api {
+                            SyncApi::Gitea(base) => base,
+                            SyncApi::GitHub => gitea_base_url.trim().to_string(),
+                        };
+                    }
+                    Err(e) => {
+                        let msg = serde_json::to_string(&e).unwrap_or_else(|_| "''".into());
+                        let _ =
+                            sidebar.evaluate_script(&format!("updateSyncStatus({msg}, 'error')"));
+                    }
+                }
+                // Appearance settings are baked into the sidebar HTML, so rebuild it
+                let appearance_changed = settings.density != density
+                    || settings.scrollbars != scrollbars
+                    || settings.ui_font_scale != ui_font_scale
+                    || settings.show_urls != show_urls
+                    || settings.show_unread_badge != show_unread_badge;
+                settings.density = density;
+                settings.scrollbars = scrollbars;
+                settings.ui_font_scale = ui_font_scale;
+                // Some window managers ignore decoration changes on a mapped
+                // window; the saved value is applied at the next launch regardless
+                if settings.decorations != decorations {
+                    window.set_decorations(decorations);
+                }
+                if settings.always_on_top != always_on_top {
+                    window.set_always_on_top(always_on_top);
+                }
+                settings.decorations = decorations;
+                settings.always_on_top = always_on_top;
+                settings.auto_sync_mode = auto_sync_mode;
+                settings.show_urls = show_urls;
+                settings.show_unread_badge = show_unread_badge;
+                settings.new_folder_position = new_folder_position;
+                settings.new_bookmark_position = new_bookmark_position;
+                settings.duplicate_strategy = duplicate_strategy;
+                settings.close_action = close_action;
+                settings.on_duplicate_folder = on_duplicate_folder;
+                settings.confirm_form_leave = confirm_form_leave;
+                settings.sync_on_focus = sync_on_focus;
+                settings.run_bookmarklets = run_bookmarklets;
+                settings.block_trackers = block_trackers;
+                settings.show_pull_changes = show_pull_changes;
+                settings.auto_expand_active = auto_expand_active;
+                settings.pretty_json = pretty_json;
+                settings.recently_added = recently_added;
+                // A bad address keeps the previous one; everything else still saves
+                match validate_home_url(&home_url) {
+                    Ok(url) => settings.home_url = url,
+                    Err(e) => {
+                        let msg = serde_json::to_string(&e).unwrap_or_else(|_| "''".into());
+                        let _ =
+                            sidebar.evaluate_script(&format!("updateSyncStatus({msg}, 'error')"));
+                    }
+                }
+                let _ = sidebar.evaluate_script(&format!(
+                    "savedHomeUrl = {}",
+                    serde_json::to_string(&settings.home_url).unwrap_or_else(|_| "''".into())
+                ));
+                let theme_changed = settings.theme != theme;
+                settings.theme = theme;
+                let _ = settings.save();
+                let has_token = !settings.github_token.is_empty();
+                let repo = settings.github_repo.replace('\'', "\\'");
+                let _ = sidebar.evaluate_script(&format!("updateSettings({has_token}, '{repo}')"));
+                if appearance_changed {
+                    let _ = sync_proxy.send_event(UserEvent::ReloadSidebar);
+                }
+                if theme_changed {
+                    let _ = sync_proxy.send_event(UserEvent::ApplyTheme);
+                }
+            }
+            Event::UserEvent(UserEvent::ApplyTheme) => {
+                let theme = resolved_theme(settings.theme(), window.theme());
+                let _ = sidebar.evaluate_script(&format!("setTheme('{theme}')"));
+                // Web pages pick their own colours; only the built-in welcome page follows
+                if active_url.is_none() {
+                    let _ = content.evaluate_script(&format!(
+                        "document.documentElement.dataset.theme = '{theme}'"
+                    ));
+                }
+            }
+            Event::UserEvent(UserEvent::ReloadSidebar) => {
+                let theme = resolved_theme(settings.theme(), window.theme());
+                let _ = sidebar.load_html(&sidebar_html(&store, &settings, theme, &link_health));
+                sidebar_generation += 1;
+                sidebar_ready = false;
+                watch_sidebar(sync_proxy.clone(), sidebar_generation);
+            }
+            Event::UserEvent(UserEvent::SidebarCheck(generation)) => {
+                if generation == sidebar_generation && !sidebar_ready {
+                    eprintln!(
+                        "Sidebar failed to initialize: no ready message after {}s",
+                        SIDEBAR_READY_TIMEOUT.as_secs()
+                    );
+                    let theme = resolved_theme(settings.theme(), window.theme());
+                    let _ = sidebar.load_html(&sidebar_failed_html(theme));
+                }
+            }
+            Event::UserEvent(UserEvent::RefreshTick(generation)) => {
+                if generation != refresh_generation {
+                    return;
+                }
+                let Some((url, secs)) = refresh.clone() else {
+                    return;
+                };
+                // Following a link away from the dashboard ends the refreshing
+                let showing = current_page
+                    .as_ref()
+                    .and_then(|(page, _)| bookmark_url_for(&store, page));
+                if showing.as_deref() == Some(url.as_str()) {
+                    let _ = content.evaluate_script("location.reload()");
+                    schedule_refresh(sync_proxy.clone(), generation, secs);
+                } else {
+                    refresh = None;
+                }
+            }
+            Event::UserEvent(UserEvent::SidebarReady) => {
+                sidebar_ready = true;
+                // Session state that isn't part of the generated HTML
+                let active =
+                    serde_json::to_string(&highlight_url).unwrap_or_else(|_| "null".into());
+                let recent_json = serde_json::to_string(&recent).unwrap_or_else(|_| "[]".into());
+                let queue_json =
+                    serde_json::to_string(&reading_queue).unwrap_or_else(|_| "[]".into());
+                let _ = sidebar.evaluate_script(&format!(
+                    "restoreSidebarState({active}, {recent_json}, {queue_json})"
+                ));
+                let json = visits_json(&visits, utc_today().0);
+                let _ = sidebar.evaluate_script(&format!("updateVisits({json})"));
+            }
+            Event::UserEvent(UserEvent::ListBackups) => {
+                let mut names = list_backups(&backups_dir());
+                names.reverse();
+                if let Ok(json) = serde_json::to_string(&names) {
+                    let _ = sidebar.evaluate_script(&format!("showBackupsModal({json})"));
+                }
+            }
+            Event::UserEvent(UserEvent::PreviewReplaceUrls {
+                find,
+                replace,
+                regex,
+            }) => {
+                let script = match preview_replace_urls(&store, &find, &replace, regex) {
+                    Ok(changes) => {
+                        let json = serde_json::to_string(&changes).unwrap_or_else(|_| "[]".into());
+                        format!("showReplacePreview({json}, null)")
+                    }
+                    // An empty find box isn't worth an error message
+                    Err(_) if find.is_empty() => "showReplacePreview([], null)".to_string(),
+                    Err(e) => {
+                        let json = serde_json::to_string(&e).unwrap_or_else(|_| "null".into());
+                        format!("showReplacePreview([], {json})")
+                    }
+                };
+                let _ = sidebar.evaluate_script(&script);
+            }
+            Event::UserEvent(UserEvent::ReplaceUrls {
+                find,
+                replace,
+                regex,
+            }) => {
+                let changed = replace_urls(&mut store, &find, &replace, regex, &utc_now());
+                if changed == 0 {
+                    return;
+                }
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                let _ = sidebar.evaluate_script(&format!(
+                    "updateSyncStatus('Updated {changed} bookmark URLs', 'success')"
+                ));
+            }
+            Event::UserEvent(UserEvent::ValidateLibrary) => {
+                let json = serde_json::to_string(&validate_store(&store))
+                    .unwrap_or_else(|_| "[]".into());
+                let _ = sidebar.evaluate_script(&format!("showValidation({json})"));
+            }
+            Event::UserEvent(UserEvent::FixLibrary) => {
+                let fixed = fix_store(&mut store);
+                if fixed > 0 {
+                    commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                }
+                let json = serde_json::to_string(&validate_store(&store))
+                    .unwrap_or_else(|_| "[]".into());
+                let _ = sidebar.evaluate_script(&format!("showValidation({json})"));
+                let noun = if fixed == 1 { "issue" } else { "issues" };
+                let _ = sidebar
+                    .evaluate_script(&format!("updateSyncStatus('Fixed {fixed} {noun}', 'success')"));
+            }
+            Event::UserEvent(UserEvent::FindCrossDuplicates) => {
+                let json = serde_json::to_string(&cross_folder_duplicates(&store))
+                    .unwrap_or_else(|_| "[]".into());
+                let _ = sidebar.evaluate_script(&format!("showCrossDuplicates({json})"));
+            }
+            Event::UserEvent(UserEvent::KeepDuplicateIn { url, folder_index }) => {
+                let removed = keep_only_in(&mut store, &url, folder_index);
+                if removed > 0 {
+                    commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                }
+                // Refresh the list so the resolved URL drops out
+                let json = serde_json::to_string(&cross_folder_duplicates(&store))
+                    .unwrap_or_else(|_| "[]".into());
+                let _ = sidebar.evaluate_script(&format!("showCrossDuplicates({json})"));
+            }
+            Event::UserEvent(UserEvent::RemoveDuplicates { global }) => {
+                let removed = dedupe_bookmarks(&mut store, global);
+                if removed == 0 {
+                    let _ = sidebar
+                        .evaluate_script("updateSyncStatus('No duplicate bookmarks found')");
+                    return;
+                }
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                let noun = if removed == 1 { "bookmark" } else { "bookmarks" };
+                let _ = sidebar.evaluate_script(&format!(
+                    "updateSyncStatus('Removed {removed} duplicate {noun}', 'success')"
+                ));
+            }
+            Event::UserEvent(UserEvent::CheckLinks) => {
+                if links_pending.is_some() {
+                    return;
+                }
+                let mut urls: Vec<String> = store
+                    .folders
+                    .iter()
+                    .flat_map(|f| &f.bookmarks)
+                    .map(|bm| bm.url.clone())
+                    .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+                    .collect();
+                urls.sort();
+                urls.dedup();
+                if urls.is_empty() {
+                    return;
+                }
+                links_pending = Some(urls.len());
+                let _ = sidebar.evaluate_script(&format!(
+                    "updateSyncStatus('Checking {} links...')",
+                    urls.len()
+                ));
+                let agent: ureq::Agent = ureq::Agent::config_builder()
+                    .timeout_global(Some(settings.sync_timeout()))
+                    .max_redirects(0)
+                    .http_status_as_error(false)
+                    .build()
+                    .into();
+                let proxy = sync_proxy.clone();
+                std::thread::spawn(move || {
+                    for url in urls {
+                        let status = check_link(&agent, &url);
+                        let _ = proxy.send_event(UserEvent::LinkChecked { url, status });
+                    }
+                    let _ = proxy.send_event(UserEvent::LinkCheckDone);
+                });
+            }
+            Event::UserEvent(UserEvent::LinkChecked { url, status }) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                link_health.insert(
+                    url,
+                    LinkHealth {
+                        last_status: status,
+                        last_checked: Some(format_utc(now)),
+                    },
+                );
+                if let Some(pending) = links_pending.as_mut() {
+                    *pending = pending.saturating_sub(1);
+                    let _ = sidebar.evaluate_script(&format!(
+                        "updateSyncStatus('Checking links... {pending} left')"
+                    ));
+                }
+            }
+            Event::UserEvent(UserEvent::LinkCheckDone) => {
+                links_pending = None;
+                // Drop results for bookmarks deleted since they were checked
+                link_health.retain(|url, _| {
+                    store
+                        .folders
+                        .iter()
+                        .any(|f| f.bookmarks.iter().any(|bm| bm.url == *url))
+                });
+                let _ = save_health(&health_path(), &link_health);
+                let broken = link_health
+                    .values()
+                    .filter(|h| health_class(Some(h)) == "broken")
+                    .count();
+                let _ = sidebar.evaluate_script(&format!(
+                    "updateHealth({})",
+                    health_json(&link_health)
+                ));
+                let kind = if broken > 0 { "error" } else { "success" };
+                let _ = sidebar.evaluate_script(&format!(
+                    "updateSyncStatus('Link check done: {broken} broken', '{kind}')"
+                ));
+            }
+            Event::UserEvent(UserEvent::RefreshTitles) => {
+                if titles_pending {
+                    return;
+                }
+                let mut urls: Vec<String> = store
+                    .folders
+                    .iter()
+                    .flat_map(|f| &f.bookmarks)
+                    .filter(|bm| !bm.name_is_custom)
+                    .map(|bm| bm.url.clone())
+                    .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+                    .collect();
+                urls.sort();
+                urls.dedup();
+                if urls.is_empty() {
+                    let _ = sidebar.evaluate_script(
+                        "updateSyncStatus('No bookmarks to refresh; every name was set by hand')",
+                    );
+                    return;
+                }
+                titles_pending = true;
+                let _ = sidebar.evaluate_script(&format!(
+                    "updateSyncStatus('Fetching {} titles...')",
+                    urls.len()
+                ));
+                let agent: ureq::Agent = ureq::Agent::config_builder()
+                    .timeout_global(Some(settings.sync_timeout()))
+                    .build()
+                    .into();
+                let proxy = sync_proxy.clone();
+                std::thread::spawn(move || {
+                    let total = urls.len();
+                    let mut titles = HashMap::new();
+                    for (done, url) in urls.into_iter().enumerate() {
+                        if let Some(title) = fetch_title(&agent, &url) {
+                            titles.insert(url, title);
+                        }
+                        let _ = proxy.send_event(UserEvent::TitlesProgress {
+                            done: done + 1,
+                            total,
+                        });
+                    }
+                    let _ = proxy.send_event(UserEvent::TitlesFetched(titles));
+                });
+            }
+            Event::UserEvent(UserEvent::TitlesProgress { done, total }) => {
+                let _ = sidebar.evaluate_script(&format!(
+                    "updateSyncStatus('Fetching titles... {done}/{total}')"
+                ));
+            }
+            Event::UserEvent(UserEvent::TitlesFetched(titles)) => {
+                titles_pending = false;
+                let json = serde_json::to_string(&title_changes(&store, &titles))
+                    .unwrap_or_else(|_| "[]".into());
+                let _ = sidebar.evaluate_script("updateSyncStatus('')");
+                let _ = sidebar.evaluate_script(&format!("showTitleChanges({json})"));
+            }
+            Event::UserEvent(UserEvent::ApplyTitles(changes)) => {
+                let applied = apply_title_changes(&mut store, &changes, &utc_now());
+                if applied > 0 {
+                    commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                }
+                let noun = if applied == 1 { "bookmark" } else { "bookmarks" };
+                let _ = sidebar.evaluate_script(&format!(
+                    "updateSyncStatus('Renamed {applied} {noun}', 'success')"
+                ));
+            }
+            Event::UserEvent(UserEvent::ExportFlatJson) => {
+                let stamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                let dir = dirs::download_dir().unwrap_or_else(config_dir);
+                let path = dir.join(format!("bookmarks-export-{stamp}.json"));
+                let msg = match fs::write(&path, to_flat_json(&store)) {
+                    Ok(()) => format!("Bookmarks exported to {}", path.display()),
+                    Err(e) => format!("Could not export bookmarks: {e}"),
+                };
+                let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json})"));
+            }
+            Event::UserEvent(UserEvent::ExportHtml) => {
+                let stamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                let dir = dirs::download_dir().unwrap_or_else(config_dir);
+                let path = dir.join(format!("bookmarks-{stamp}.html"));
+                let theme = resolved_theme(settings.theme(), window.theme());
+                let msg = match fs::write(&path, to_standalone_html(&store, theme)) {
+                    Ok(()) => format!("Bookmarks page saved to {}", path.display()),
+                    Err(e) => format!("Could not export bookmarks: {e}"),
+                };
+                let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json})"));
+            }
+            Event::UserEvent(UserEvent::ExportDiagnostics { include_repo }) => {
+                let report =
+                    diagnostics_report(&store, &settings, include_repo, last_sync_error.as_deref());
+                let stamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                let dir = dirs::download_dir().unwrap_or_else(config_dir);
+                let path = dir.join(format!("bookmarks-browser-diagnostics-{stamp}.txt"));
+                let msg = match fs::write(&path, report) {
+                    Ok(()) => format!("Diagnostics saved to {}", path.display()),
+                    Err(e) => format!("Could not write diagnostics: {e}"),
+                };
+                let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json})"));
+            }
+            Event::UserEvent(UserEvent::ShowSyncHealth { include_repo }) => {
+                let rows = sync_health_rows(
+                    &store,
+                    &settings,
+                    include_repo,
+                    remote_sha.as_deref(),
+                    last_sync_at.as_deref(),
+                    last_sync_error.as_deref(),
+                );
+                if let Ok(json) = serde_json::to_string(&rows) {
+                    let _ = sidebar.evaluate_script(&format!("showSyncHealth({json})"));
+                }
+            }
+            Event::UserEvent(UserEvent::TestSyncHealth) => {
+                if settings.github_token.is_empty() || settings.github_repo.is_empty() {
+                    let _ = sidebar.evaluate_script(
+                        "showSyncHealthTest('Set a token and repository in Settings first', false)",
+                    );
+                    return;
+                }
+                let api = settings.sync_api();
+                let token = settings.github_token.clone();
+                let repo = settings.github_repo.clone();
+                let timeout = settings.sync_timeout();
+                let proxy = sync_proxy.clone();
+                let _ = sidebar.evaluate_script("showSyncHealthTest('Testing...', null)");
+                std::thread::spawn(move || {
+                    let result = api.and_then(|api| get_file_sha(&api, &token, &repo, timeout));
+                    let _ = proxy.send_event(UserEvent::SyncHealthTested(result));
+                });
+            }
+            Event::UserEvent(UserEvent::SyncHealthTested(result)) => {
+                let (msg, ok) = sync_test_message(&result, remote_sha.as_deref());
+                let json = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("showSyncHealthTest({json}, {ok})"));
+            }
+            Event::UserEvent(UserEvent::SetThumbnail {
+                folder_index,
+                bookmark_index,
+                image,
+            }) => {
+                let dir = thumbnails_dir();
+                let thumbnail = match image {
+                    Some(data_url) => {
+                        let Some((bytes, ext)) = decode_image_data_url(&data_url) else {
+                            let _ = sidebar.evaluate_script(
+                                "updateSyncStatus('Unsupported or too large image', 'error')",
+                            );
+                            return;
+                        };
+                        let name = format!("{:016x}.{ext}", fnv1a(&bytes));
+                        if let Err(e) =
+                            fs::create_dir_all(&dir).and_then(|_| fs::write(dir.join(&name), &bytes))
+                        {
+                            let json = serde_json::to_string(&format!(
+                                "Could not save thumbnail: {e}"
+                            ))
+                            .unwrap_or_else(|_| "''".into());
+                            let _ = sidebar
+                                .evaluate_script(&format!("updateSyncStatus({json}, 'error')"));
+                            return;
+                        }
+                        Some(name)
+                    }
+                    None => None,
+                };
+                let Some(bm) = store
+                    .folders
+                    .get_mut(folder_index)
+                    .and_then(|f| f.bookmarks.get_mut(bookmark_index))
+                else {
+                    return;
+                };
+                let previous = std::mem::replace(&mut bm.thumbnail, thumbnail);
+                // Remove the old image unless another bookmark still shows it
+                if let Some(previous) = previous {
+                    let in_use = store
+                        .folders
+                        .iter()
+                        .flat_map(|f| &f.bookmarks)
+                        .any(|b| b.thumbnail.as_ref() == Some(&previous));
+                    if !in_use {
+                        let _ = fs::remove_file(dir.join(previous));
+                    }
+                }
+                save_and_render(&store, &mut store_stamp, settings.backup_keep(), &sidebar);
+            }
+            Event::UserEvent(UserEvent::ImportBookmarks(content)) => {
+                if importing {
+                    let _ = sidebar.evaluate_script(
+                        "updateSyncStatus('An import is already running', 'error')",
+                    );
+                    return;
+                }
+                importing = true;
+                let existing: HashMap<String, String> = store
+                    .folders
+                    .iter()
+                    .flat_map(|f| &f.bookmarks)
+                    .map(|bm| (normalize_url(&bm.url), bm.name.clone()))
+                    .collect();
+                let strategy = settings.duplicate_strategy();
+                let _ = sidebar.evaluate_script("updateSyncStatus('Reading import file...')");
+                let proxy = sync_proxy.clone();
+                std::thread::spawn(move || {
+                    let result = parse_import(&content).map(|(folders, failures)| {
+                        let mut last_update = Instant::now();
+                        let (folders, mut report) =
+                            filter_import(folders, &existing, strategy, |done, total| {
+                                if done == total
+                                    || last_update.elapsed() >= IMPORT_PROGRESS_INTERVAL
+                                {
+                                    last_update = Instant::now();
+                                    let _ = proxy
+                                        .send_event(UserEvent::ImportProgress { done, total });
+                                }
+                            });
+                        report.skipped = failures
+                            .into_iter()
+                            .chain(std::mem::take(&mut report.skipped))
+                            .collect();
+                        (folders, report)
+                    });
+                    let _ = proxy.send_event(UserEvent::ImportFinished(result));
+                });
+            }
+            Event::UserEvent(UserEvent::ImportProgress { done, total }) => {
+                let _ = sidebar.evaluate_script(&format!(
+                    "updateSyncStatus('Importing {done}/{total}\u{2026}')"
+                ));
+            }
+            Event::UserEvent(UserEvent::ImportFinished(result)) => {
+                importing = false;
+                let (imported, report) = match result {
+                    Ok(result) => result,
+                    Err(e) => {
+                        let escaped = e.replace('\\', "\\\\").replace('\'', "\\'");
+                        let _ = sidebar
+                            .evaluate_script(&format!("updateSyncStatus('{escaped}', 'error')"));
+                        return;
+                    }
+                };
+                let renamed = apply_import_renames(&mut store, &report.renames, &utc_now());
+                if !imported.is_empty() || renamed > 0 {
+                    store.folders.extend(imported);
+                    commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                }
+                // Skip reasons stay up longer as an error so they can be read
+                let kind = if report.skipped.is_empty() {
+                    "success"
+                } else {
+                    "error"
+                };
+                let msg = serde_json::to_string(&report.summary()).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({msg}, '{kind}')"));
+            }
+            Event::UserEvent(UserEvent::RestoreBackup(name)) => {
+                // Only accept names we listed, never a path from the page
+                if !is_backup_name(&name) {
+                    return;
+                }
+                let path = backups_dir().join(&name);
+                let Some(restored) = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|data| serde_json::from_str::<BookmarkStore>(&data).ok())
+                else {
+                    let _ = sidebar
+                        .evaluate_script("updateSyncStatus('Backup could not be read', 'error')");
+                    return;
+                };
+                store = restored;
+                commit_edit(&store, &mut store_stamp, &settings, &sidebar, &sync_proxy);
+                let _ =
+                    sidebar.evaluate_script("updateSyncStatus('Restored from backup', 'success')");
+            }
+            Event::UserEvent(UserEvent::TestToken {
+                github_token,
+                github_repo,
+                api,
+            }) => {
+                // An empty field means "use what's saved", matching the Save button
+                let token = if github_token.is_empty() {
+                    settings.github_token.clone()
+                } else {
+                    github_token
+                };
+                if token.is_empty() {
+                    let _ =
+                        sidebar.evaluate_script("showTokenTestResult('No token entered', false)");
+                    return;
+                }
+                let timeout = settings.sync_timeout();
+                let proxy = sync_proxy.clone();
+                let _ = sidebar.evaluate_script("showTokenTestResult('Testing...', null)");
+                std::thread::spawn(move || {
+                    let result =
+                        api.and_then(|api| do_test_token(&api, &token, &github_repo, timeout));
+                    let _ = proxy.send_event(UserEvent::TestTokenResult(result));
+                });
+            }
+            Event::UserEvent(UserEvent::TestTokenResult(result)) => {
+                let (msg, ok) = match result {
+                    Ok(msg) => (msg, true),
+                    Err(msg) => (msg, false),
+                };
+                let escaped = msg.replace('\\', "\\\\").replace('\'', "\\'");
+                let _ = sidebar.evaluate_script(&format!("showTokenTestResult('{escaped}', {ok})"));
+            }
+            Event::UserEvent(UserEvent::ConfirmPush) => {
+                push_confirmed = true;
+                let _ = sync_proxy.send_event(UserEvent::PushToGitHub);
+            }
+            Event::UserEvent(UserEvent::PushToGitHub) => {
+                if settings.github_token.is_empty() {
+                    let _ = sidebar
+                        .evaluate_script("updateSyncStatus('No token configured — open Settings')");
+                    return;
+                }
+                if settings.github_repo.is_empty() {
+                    let _ = sidebar
+                        .evaluate_script("updateSyncStatus('No repo configured — open Settings')");
+                    return;
+                }
+                let count = synced_bookmark_count(&store);
+                if !push_confirmed {
+                    if let Err(e) =
+                        check_push(count, settings.last_synced_count, settings.push_guard_percent())
+                    {
+                        let json = serde_json::to_string(&e).unwrap_or_else(|_| "''".into());
+                        let _ = sidebar.evaluate_script(&format!("confirmRiskyPush({json})"));
+                        return;
+                    }
+                }
+                if !sync.request(SyncKind::Push) {
+                    let _ = sidebar.evaluate_script("updateSyncStatus('Push queued...')");
+                    return;
+                }
+                push_confirmed = false;
+                let token = settings.github_token.clone();
+                let repo = settings.github_repo.clone();
+                let sha = remote_sha.clone();
+                let api = settings.sync_api();
+                let timeout = settings.sync_timeout();
+                let bookmarks_json = sync_json(&store, settings.pretty_json);
+                pushing = Some((store_digest(&store), count));
+                let proxy = sync_proxy.clone();
+                let _ = sidebar.evaluate_script("updateSyncStatus('Pushing...')");
+                let mirrors = settings.sync_mirrors();
+                std::thread::spawn(move || {
+                    let result = api.and_then(|api| {
+                        do_push(&api, &token, &repo, &bookmarks_json, sha.as_deref(), timeout)
+                    });
+                    finish_push(&proxy, result, &mirrors, &bookmarks_json, timeout, "Push failed");
+                });
+            }
+            Event::UserEvent(UserEvent::PullFromGitHub) => {
+                if settings.github_token.is_empty() {
+                    let _ = sidebar
+                        .evaluate_script("updateSyncStatus('No token configured — open Settings')");
+                    return;
+                }
+                if settings.github_repo.is_empty() {
+                    let _ = sidebar
+                        .evaluate_script("updateSyncStatus('No repo configured — open Settings')");
+                    return;
+                }
+                if !sync.request(SyncKind::Pull) {
+                    let _ = sidebar.evaluate_script("updateSyncStatus('Pull queued...')");
+                    return;
+                }
+                let token = settings.github_token.clone();
+                let repo = settings.github_repo.clone();
+                let api = settings.sync_api();
+                let timeout = settings.sync_timeout();
+                pulling = Some(store_digest(&store));
+                let proxy = sync_proxy.clone();
+                let _ = sidebar.evaluate_script("updateSyncStatus('Pulling...')");
+                let pull = move || api.and_then(|api| do_pull(&api, &token, &repo, timeout));
+                std::thread::spawn(move || match pull() {
+                    Ok((new_store, sha)) => {
+                        let _ = proxy.send_event(UserEvent::PullComplete(new_store, sha));
+                    }
+                    Err(e) => {
+                        let _ =
+                            proxy.send_event(UserEvent::SyncStatus(format!("Pull failed: {e}")));
+                    }
+                });
+            }
+            Event::UserEvent(UserEvent::SyncBoth) => {
+                if settings.github_token.is_empty() {
+                    let _ = sidebar
+                        .evaluate_script("updateSyncStatus('No token configured — open Settings')");
+                    return;
+                }
+                if settings.github_repo.is_empty() {
+                    let _ = sidebar
+                        .evaluate_script("updateSyncStatus('No repo configured — open Settings')");
+                    return;
+                }
+                if !sync.request(SyncKind::Both) {
+                    let _ = sidebar.evaluate_script("updateSyncStatus('Sync queued...')");
+                    return;
+                }
+                let token = settings.github_token.clone();
+                let repo = settings.github_repo.clone();
+                let api = settings.sync_api();
+                let timeout = settings.sync_timeout();
+                let proxy = sync_proxy.clone();
+                let _ = sidebar.evaluate_script("updateSyncStatus('Pulling...')");
+                let fetch = move || api.and_then(|api| fetch_remote(&api, &token, &repo, timeout));
+                std::thread::spawn(move || match fetch() {
+                    Ok(remote) => {
+                        let _ = proxy.send_event(UserEvent::SyncPulled(remote));
+                    }
+                    Err(e) => {
+                        let _ =
+                            proxy.send_event(UserEvent::SyncStatus(format!("Sync failed: {e}")));
+                    }
+                });
+            }
+            Event::UserEvent(UserEvent::SyncPulled(remote)) => {
+                // Still the same sync: the queue stays busy until the push lands
+                awaiting_clone = false;
+                let sha = match remote {
+                    Some((remote_store, sha)) => {
+                        let _ = sidebar.evaluate_script("updateSyncStatus('Merging...')");
+                        store = merge_stores(&store, remote_store);
+                        save_and_render(&store, &mut store_stamp, settings.backup_keep(), &sidebar);
+                        Some(sha)
+                    }
+                    // Nothing on the remote yet: local becomes the initial file
+                    None => None,
+                };
+                remote_sha = sha.clone();
+                syncing_both = true;
+                let token = settings.github_token.clone();
+                let repo = settings.github_repo.clone();
+                let api = settings.sync_api();
+                let timeout = settings.sync_timeout();
+                let bookmarks_json = sync_json(&store, settings.pretty_json);
+                pushing = Some((store_digest(&store), synced_bookmark_count(&store)));
+                let proxy = sync_proxy.clone();
+                let _ = sidebar.evaluate_script("updateSyncStatus('Pushing...')");
+                let mirrors = settings.sync_mirrors();
+                std::thread::spawn(move || {
+                    let result = api.and_then(|api| {
+                        do_push(&api, &token, &repo, &bookmarks_json, sha.as_deref(), timeout)
+                    });
+                    finish_push(&proxy, result, &mirrors, &bookmarks_json, timeout, "Sync failed");
+                });
+            }
+            Event::UserEvent(UserEvent::SyncStatus(msg)) if awaiting_clone => {
+                syncing_both = false;
+                if let Some(next) = sync.finish() {
+                    let _ = sync_proxy.send_event(next.event());
+                }
+                last_sync_error = Some(msg.clone());
+                let escaped = msg.replace('\\', "\\\\").replace('\'', "\\'");
+                let _ = sidebar.evaluate_script(&format!(
+                    "updateSyncStatus('{escaped} — auto-sync paused until a pull or push succeeds', 'error')"
+                ));
+            }
+            Event::UserEvent(UserEvent::SyncStatus(msg)) => {
+                syncing_both = false;
+                if let Some(next) = sync.finish() {
+                    let _ = sync_proxy.send_event(next.event());
+                }
+                last_sync_error = Some(msg.clone());
+                let escaped = msg.replace('\\', "\\\\").replace('\'', "\\'");
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus('{escaped}')"));
+            }
+            Event::UserEvent(UserEvent::PushComplete(new_sha)) => {
+                // An explicit push means the user chose local over remote
+                awaiting_clone = false;
+                if let Some(next) = sync.finish() {
+                    let _ = sync_proxy.send_event(next.event());
+                }
+                remote_sha = new_sha;
+                last_sync_at = Some(utc_now());
+                if let Some((digest, count)) = pushing.take() {
+                    settings.last_synced_digest = Some(digest);
+                    settings.last_synced_count = Some(count);
+                    let _ = settings.save();
+                }
+                let _ = sidebar.evaluate_script(&dirty_script(&store, &settings));
+                let _ = sidebar.evaluate_script(if std::mem::take(&mut syncing_both) {
+                    "updateSyncStatus('Synced')"
+                } else {
+                    "updateSyncStatus('Pushed successfully')"
+                });
+            }
+            Event::UserEvent(UserEvent::PushReport { message, ok }) => {
+                if !ok {
+                    last_sync_error = Some(message.clone());
+                }
+                let json = serde_json::to_string(&message).unwrap_or_else(|_| "''".into());
+                let class = if ok { "success" } else { "error" };
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({json}, '{class}')"));
+            }
+            Event::UserEvent(UserEvent::PullComplete(new_store, sha)) => {
+                awaiting_clone = false;
+                if let Some(next) = sync.finish() {
+                    let _ = sync_proxy.send_event(next.event());
+                }
+                remote_sha = Some(sha);
+                let requested = pulling.take().unwrap_or_else(|| store_digest(&store));
+                let Some(pulled) = apply_pull(&store, new_store, requested) else {
+                    let _ = sidebar.evaluate_script(&dirty_script(&store, &settings));
+                    let _ = sidebar.evaluate_script(
+                        "updateSyncStatus('Pull not applied: bookmarks changed while pulling — push to keep them', 'error')",
+                    );
+                    return;
+                };
+                last_sync_at = Some(utc_now());
+                let before = std::mem::replace(&mut store, pulled);
+                let changes = diff_stores(&before, &store);
+                settings.last_synced_digest = Some(store_digest(&store));
+                settings.last_synced_count = Some(synced_bookmark_count(&store));
+                let _ = settings.save();
+                save_and_render(&store, &mut store_stamp, settings.backup_keep(), &sidebar);
+                let _ = sidebar.evaluate_script(&dirty_script(&store, &settings));
+                let msg = if settings.show_pull_changes {
+                    format!("Pulled: {}", changes.summary())
+                } else {
+                    "Pulled successfully".to_string()
+                };
+                let msg = serde_json::to_string(&msg).unwrap_or_else(|_| "''".into());
+                let _ = sidebar.evaluate_script(&format!("updateSyncStatus({msg}, 'success')"));
+            }
+            Event::UserEvent(UserEvent::AutoSync) => {
+                // Every bookmark mutation lands here, synced or not
+                let _ = sidebar.evaluate_script(&dirty_script(&store, &settings));
+                if awaiting_clone
+                    || settings.auto_sync_mode() == "off"
+                    || settings.github_token.is_empty()
+                    || settings.github_repo.is_empty()
+                {
+                    return;
+                }
+                // Never auto-confirm: a risky push waits for an explicit Push
+                let count = synced_bookmark_count(&store);
+                if let Err(e) =
+                    check_push(count, settings.last_synced_count, settings.push_guard_percent())
+                {
+                    let msg = format!("Auto-sync paused: {e} — press Push to confirm");
+                    let escaped = msg.replace('\\', "\\\\").replace('\'', "\\'");
+                    let _ = sidebar
+                        .evaluate_script(&format!("updateSyncStatus('{escaped}', 'error')"));
+                    return;
+                }
+                if !sync.request(SyncKind::AutoPush) {
+                    return;
+                }
+                let token = settings.github_token.clone();
+                let repo = settings.github_repo.clone();
+                let sha = remote_sha.clone();
+                let api = settings.sync_api();
+                let timeout = settings.sync_timeout();
+                let bookmarks_json = sync_json(&store, settings.pretty_json);
+                pushing = Some((store_digest(&store), count));
+                let proxy = sync_proxy.clone();
+                let mirrors = settings.sync_mirrors();
+                std::thread::spawn(move || {
+                    let result = api.and_then(|api| {
+                        do_push(&api, &token, &repo, &bookmarks_json, sha.as_deref(), timeout)
+                    });
+                    finish_push(&proxy, result, &mirrors, &bookmarks_json, timeout, "Sync failed");
+                });
+            }
+            _ => {}
+        }
+    });
+}
 
-<div id="addBookmarkOverlay" class="modal-overlay">
-  <div class="modal">
-    <h3>Add Bookmark</h3>
-    <label for="bmName">Name</label>
-    <input type="text" id="bmName" placeholder="Bookmark name">
-    <label for="bmUrl">URL</label>
-    <input type="text" id="bmUrl" placeholder="https://...">
-    <label for="bmFolder">Folder</label>
-    <select id="bmFolder"></select>
-    <div class="modal-buttons">
-      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
-      <button class="btn-primary" onclick="submitAddBookmark()">Add</button>
-    </div>
-  </div>
-</div>
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
 
-<div id="addFolderOverlay" class="modal-overlay">
-  <div class="modal">
-    <h3>Add Folder</h3>
-    <label for="folderName">Name</label>
-    <input type="text" id="folderName" placeholder="Folder name">
-    <div class="modal-buttons">
-      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
-      <button class="btn-primary" onclick="submitAddFolder()">Create</button>
-    </div>
-  </div>
-</div>
+    fn bookmark(name: &str, url: &str) -> Bookmark {
+        Bookmark {
+            name: name.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
 
-<div id="helpOverlay" class="modal-overlay">
-  <div class="modal">
-    <h3>Keyboard Shortcuts</h3>
-    <table class="help-table">
-      <tr><td class="help-key">Ctrl+N</td><td>Add bookmark</td></tr>
-      <tr><td class="help-key">Ctrl+G</td><td>Add folder</td></tr>
-      <tr><td class="help-key">F5</td><td>Reload page</td></tr>
-      <tr><td class="help-key">Ctrl+[</td><td>Navigate back</td></tr>
-      <tr><td class="help-key">Ctrl+]</td><td>Navigate forward</td></tr>
-      <tr><td class="help-key">Ctrl+B</td><td>Toggle sidebar</td></tr>
-      <tr><td class="help-key">Ctrl+U</td><td>Push to GitHub</td></tr>
-      <tr><td class="help-key">Ctrl+I</td><td>Pull from GitHub</td></tr>
-      <tr><td class="help-key">Ctrl+Q</td><td>Quit</td></tr>
-      <tr><td class="help-key">Escape</td><td>Close dialog</td></tr>
-    </table>
-    <div class="modal-buttons">
-      <button class="btn-primary" onclick="closeModals()" style="flex:1">Close</button>
-    </div>
-  </div>
-</div>
+    #[test]
+    fn roundtrip_save_load() {
+        let dir = env::temp_dir().join("bookmarks-browser-test");
+        let path = dir.join("bookmarks.json");
 
-<div id="settingsOverlay" class="modal-overlay">
-  <div class="modal">
-    <h3>Settings</h3>
-    <label for="ghToken">GitHub Personal Access Token</label>
-    <input type="password" id="ghToken" placeholder="ghp_...">
-    <label for="ghRepo">Repository (owner/repo)</label>
-    <input type="text" id="ghRepo" placeholder="username/my-bookmarks">
-    <div class="modal-buttons">
-      <button class="btn-cancel" onclick="closeModals()">Cancel</button>
-      <button class="btn-primary" onclick="submitSaveSettings()">Save</button>
-    </div>
-  </div>
-</div>
+        // Clean up from any previous run
+        let _ = fs::remove_dir_all(&dir);
 
-<script>
-  let folders = {folders_json};
-  let activeUrl = null;
-  let activeModal = null;
+        let store = default_store();
+        store.save_to(&path).expect("save should succeed");
 
-  function renderBookmarks(data) {{
-    folders = data;
-    const tree = document.getElementById('tree');
-    tree.innerHTML = '';
-    folders.forEach(function(folder, fi) {{
-      const header = document.createElement('div');
-      header.className = 'folder-header';
-      header.onclick = function() {{ toggleFolder(fi); }};
+        let loaded = BookmarkStore::try_load_from(&path).expect("load should succeed");
+        assert_eq!(store, loaded);
 
-      const arrow = document.createElement('span');
-      arrow.className = 'folder-arrow';
-      arrow.textContent = folder.expanded ? '\u25BC' : '\u25B6';
+        // Clean up
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn recent_is_bounded_and_skips_consecutive_repeats() {
+        let mut recent = VecDeque::new();
+        push_recent(&mut recent, "https://a.example/");
+        push_recent(&mut recent, "https://a.example/");
+        push_recent(&mut recent, "https://b.example/");
+        push_recent(&mut recent, "https://a.example/");
+        assert_eq!(
+            recent,
+            [
+                "https://a.example/",
+                "https://b.example/",
+                "https://a.example/"
+            ]
+        );
+
+        for i in 0..RECENT_LIMIT * 2 {
+            push_recent(&mut recent, &format!("https://{i}.example/"));
+        }
+        assert_eq!(recent.len(), RECENT_LIMIT);
+        assert_eq!(
+            recent.front().unwrap(),
+            &format!("https://{}.example/", RECENT_LIMIT * 2 - 1)
+        );
+    }
+
+    #[test]
+    fn reading_queue_is_fifo_without_duplicates() {
+        let mut queue = VecDeque::new();
+        assert!(push_queue(&mut queue, "https://a.example/"));
+        assert!(push_queue(&mut queue, "https://b.example/"));
+        assert!(!push_queue(&mut queue, "https://a.example/"));
+        assert_eq!(queue, ["https://a.example/", "https://b.example/"]);
+        assert_eq!(queue.pop_front().as_deref(), Some("https://a.example/"));
+    }
+
+    #[test]
+    fn sanitize_icon_accepts_one_emoji_only() {
+        assert_eq!(sanitize_icon(" \u{1F4DA} "), Some("\u{1F4DA}".to_string()));
+        // Family emoji is a multi-codepoint ZWJ sequence but still one grapheme
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        assert_eq!(sanitize_icon(family), Some(family.to_string()));
+        assert_eq!(sanitize_icon(""), None);
+        assert_eq!(sanitize_icon("a b"), None);
+        assert_eq!(sanitize_icon(&"x".repeat(500)), None);
+        // Flags, skin tones and keycaps are one character each
+        for icon in [
+            "\u{1F1F3}\u{1F1F4}",
+            "\u{1F44D}\u{1F3FD}",
+            "1\u{FE0F}\u{20E3}",
+            "\u{2764}\u{FE0F}",
+        ] {
+            assert_eq!(sanitize_icon(icon), Some(icon.to_string()));
+        }
+        for icon in [
+            "ab",
+            "\u{1F4C1}\u{1F4C1}",
+            "\u{1F1F3}\u{1F1F4}\u{1F1F3}",
+            "\u{1F468}\u{200D}",
+        ] {
+            assert_eq!(sanitize_icon(icon), None, "{icon:?}");
+        }
+        // England's flag takes tag characters; endless ZWJ or tag runs don't fit
+        let england = "\u{1F3F4}\u{E0067}\u{E0062}\u{E0065}\u{E006E}\u{E0067}\u{E007F}";
+        assert_eq!(sanitize_icon(england), Some(england.to_string()));
+        let zwj_chain = vec!["\u{1F468}"; 200].join("\u{200D}");
+        assert_eq!(sanitize_icon(&zwj_chain), None);
+        let tag_run = format!("\u{1F3F4}{}", "\u{E0067}".repeat(1000));
+        assert_eq!(sanitize_icon(&tag_run), None);
+    }
+
+    #[test]
+    fn url_origin_strips_path_and_credentials() {
+        assert_eq!(
+            url_origin("https://Intranet.example:8443/wiki/page?x=1"),
+            Some("https://intranet.example:8443".to_string())
+        );
+        assert_eq!(
+            url_origin("http://user:pw@host.local/"),
+            Some("http://host.local".to_string())
+        );
+        assert_eq!(
+            url_origin("https://host.local"),
+            Some("https://host.local".to_string())
+        );
+        assert_eq!(url_origin("file:///etc/passwd"), None);
+        assert_eq!(url_origin("not a url"), None);
+    }
+
+    #[test]
+    fn basic_auth_header_encodes_credentials() {
+        assert_eq!(
+            basic_auth_header("aladdin", "opensesame"),
+            "Basic YWxhZGRpbjpvcGVuc2VzYW1l"
+        );
+    }
+
+    fn private_folder() -> Folder {
+        Folder {
+            name: "Private".to_string(),
+            expanded: true,
+            icon: None,
+            exclude_from_sync: true,
+            locked: false,
+            pin: None,
+            bookmarks: vec![bookmark("Bank", "https://bank.example/")],
+        }
+    }
+
+    #[test]
+    fn pushed_json_is_pretty_or_minified() {
+        let store = default_store();
+        let pretty = sync_json(&store, true);
+        let minified = sync_json(&store, false);
+        assert!(pretty.contains('\n'));
+        assert!(!minified.contains('\n'));
+        assert!(minified.len() < pretty.len());
+        for json in [pretty, minified] {
+            let pulled: BookmarkStore = serde_json::from_str(&json).unwrap();
+            assert_eq!(pulled, store_for_sync(&store));
+        }
+    }
 
-      const name = document.createElement('span');
-      name.className = 'folder-name';
-      name.textContent = folder.name;
+    #[test]
+    fn excluded_folders_never_pushed() {
+        let mut store = default_store();
+        store.folders.insert(1, private_folder());
 
-      const actions = document.createElement('span');
-      actions.className = 'folder-actions';
+        let pushed = serde_json::to_string_pretty(&store_for_sync(&store)).unwrap();
+        assert!(!pushed.contains("Private"));
+        assert!(!pushed.contains("bank.example"));
+        assert!(pushed.contains("Documentation"));
+    }
 
-      const addBtn = document.createElement('button');
-      addBtn.className = 'icon-btn';
-      addBtn.textContent = '+';
-      addBtn.title = 'Add bookmark to this folder';
-      addBtn.onclick = function(e) {{ e.stopPropagation(); showAddBookmarkModal(fi); }};
+    #[test]
+    fn excluded_folders_survive_pull() {
+        let mut local = default_store();
+        local.folders.insert(1, private_folder());
 
-      const delBtn = document.createElement('button');
-      delBtn.className = 'icon-btn delete';
-      delBtn.textContent = '\u00D7';
-      delBtn.title = 'Delete folder';
-      delBtn.onclick = function(e) {{ e.stopPropagation(); deleteFolder(fi); }};
+        // Remote has a stale copy of Private from before it was excluded
+        let mut remote = default_store();
+        remote.folders[0].bookmarks.clear();
+        remote.folders.push(Folder {
+            exclude_from_sync: false,
+            locked: false,
+            pin: None,
+            bookmarks: vec![],
+            ..private_folder()
+        });
 
-      actions.appendChild(addBtn);
-      actions.appendChild(delBtn);
-      header.appendChild(arrow);
-      header.appendChild(name);
-      header.appendChild(actions);
-      tree.appendChild(header);
+        let merged = merge_excluded(remote, &local);
+        let names: Vec<&str> = merged.folders.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["Documentation", "Private", "News"]);
+        assert_eq!(merged.folders[1], private_folder());
+        assert!(merged.folders[0].bookmarks.is_empty());
+    }
 
-      if (folder.expanded) {{
-        folder.bookmarks.forEach(function(bm, bi) {{
-          const link = document.createElement('div');
-          link.className = 'bookmark' + (bm.url === activeUrl ? ' active' : '');
-          link.title = bm.url;
-          link.onclick = function() {{ navigate(bm.url); }};
-
-          const bmName = document.createElement('span');
-          bmName.className = 'bookmark-name';
-          bmName.textContent = bm.name;
-
-          const bmDel = document.createElement('button');
-          bmDel.className = 'delete-btn';
-          bmDel.textContent = '\u00D7';
-          bmDel.title = 'Delete bookmark';
-          bmDel.onclick = function(e) {{ e.stopPropagation(); deleteBookmark(fi, bi); }};
-
-          link.appendChild(bmName);
-          link.appendChild(bmDel);
-          tree.appendChild(link);
-        }});
-      }}
-    }});
-  }}
+    #[test]
+    fn rotate_backups_keeps_newest() {
+        let dir = env::temp_dir().join("bookmarks-browser-backup-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create dir");
 
-  function navigate(url) {{
-    activeUrl = url;
-    window.ipc.postMessage(JSON.stringify({{ action: 'navigate', url: url }}));
-    renderBookmarks(folders);
-  }}
+        for stamp in 1..=15u64 {
+            fs::write(dir.join(format!("bookmarks-{stamp:012}.json")), "{}").expect("write");
+        }
+        fs::write(dir.join("notes.txt"), "unrelated").expect("write");
 
-  function toggleFolder(index) {{
-    window.ipc.postMessage(JSON.stringify({{ action: 'toggle_folder', folder_index: index }}));
-  }}
+        rotate_backups(&dir, 10).expect("rotate should succeed");
 
-  function deleteFolder(fi) {{
-    if (confirm('Delete folder "' + folders[fi].name + '" and all its bookmarks?')) {{
-      window.ipc.postMessage(JSON.stringify({{ action: 'delete_folder', folder_index: fi }}));
-    }}
-  }}
+        let remaining = list_backups(&dir);
+        assert_eq!(remaining.len(), 10);
+        assert_eq!(remaining.first().unwrap(), "bookmarks-000000000006.json");
+        assert_eq!(remaining.last().unwrap(), "bookmarks-000000000015.json");
+        assert!(dir.join("notes.txt").exists());
 
-  function deleteBookmark(fi, bi) {{
-    if (confirm('Delete bookmark "' + folders[fi].bookmarks[bi].name + '"?')) {{
-      window.ipc.postMessage(JSON.stringify({{ action: 'delete_bookmark', folder_index: fi, bookmark_index: bi }}));
-    }}
-  }}
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-  function showAddBookmarkModal(fi) {{
-    if (folders.length === 0) {{
-      alert('Create a folder first before adding bookmarks.');
-      return;
-    }}
-    const select = document.getElementById('bmFolder');
-    select.innerHTML = '';
-    folders.forEach(function(folder, i) {{
-      const opt = document.createElement('option');
-      opt.value = i;
-      opt.textContent = folder.name;
-      if (fi !== undefined && fi === i) opt.selected = true;
-      select.appendChild(opt);
-    }});
-    document.getElementById('bmName').value = '';
-    document.getElementById('bmUrl').value = '';
-    document.getElementById('addBookmarkOverlay').classList.add('active');
-    activeModal = 'addBookmark';
-    document.getElementById('bmName').focus();
-  }}
+    #[test]
+    fn write_backup_skips_unchanged_content() {
+        let dir = env::temp_dir().join("bookmarks-browser-backup-dedupe-test");
+        let _ = fs::remove_dir_all(&dir);
 
-  function showAddFolderModal() {{
-    document.getElementById('folderName').value = '';
-    document.getElementById('addFolderOverlay').classList.add('active');
-    activeModal = 'addFolder';
-    document.getElementById('folderName').focus();
-  }}
+        write_backup(&dir, "{\"folders\":[]}", 10).expect("first backup");
+        write_backup(&dir, "{\"folders\":[]}", 10).expect("second backup");
+        assert_eq!(list_backups(&dir).len(), 1);
+        // Copies of an outside edit never prune, and a second one in the
+        // same second doesn't overwrite the first
+        add_backup(&dir, "{\"folders\":[1]}").expect("outside edit");
+        write_backup(&dir, "{\"folders\":[2]}", 10).expect("own save");
+        assert_eq!(list_backups(&dir).len(), 3);
 
-  function showHelpModal() {{
-    document.getElementById('helpOverlay').classList.add('active');
-    activeModal = 'help';
-  }}
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-  function closeModals() {{
-    document.getElementById('addBookmarkOverlay').classList.remove('active');
-    document.getElementById('addFolderOverlay').classList.remove('active');
-    document.getElementById('helpOverlay').classList.remove('active');
-    document.getElementById('settingsOverlay').classList.remove('active');
-    activeModal = null;
-  }}
+    #[test]
+    fn try_load_rejects_invalid_json() {
+        let dir = env::temp_dir().join("bookmarks-browser-invalid-test");
+        let path = dir.join("bookmarks.json");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create dir");
 
-  function submitAddBookmark() {{
-    const name = document.getElementById('bmName').value.trim();
-    const url = document.getElementById('bmUrl').value.trim();
-    const fi = parseInt(document.getElementById('bmFolder').value, 10);
-    if (!name || !url) return;
-    window.ipc.postMessage(JSON.stringify({{ action: 'add_bookmark', folder_index: fi, name: name, url: url }}));
-    closeModals();
-  }}
+        assert!(BookmarkStore::try_load_from(&path).is_none());
+        fs::write(&path, "{ not json").expect("write");
+        assert!(BookmarkStore::try_load_from(&path).is_none());
 
-  function submitAddFolder() {{
-    const name = document.getElementById('folderName').value.trim();
-    if (!name) return;
-    window.ipc.postMessage(JSON.stringify({{ action: 'add_folder', name: name }}));
-    closeModals();
-  }}
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-  function collapseSidebar() {{
-    window.ipc.postMessage(JSON.stringify({{ action: 'toggle_sidebar' }}));
-  }}
+    #[test]
+    fn unsynced_changes_follow_digest() {
+        let mut store = default_store();
+        let mut settings = Settings::default();
+        assert!(has_unsynced_changes(&store, &settings));
 
-  function expandSidebar() {{
-    window.ipc.postMessage(JSON.stringify({{ action: 'toggle_sidebar' }}));
-  }}
+        settings.last_synced_digest = Some(store_digest(&store));
+        assert!(!has_unsynced_changes(&store, &settings));
 
-  function setSidebarCollapsed(collapsed) {{
-    if (collapsed) {{
-      document.body.classList.add('collapsed');
-    }} else {{
-      document.body.classList.remove('collapsed');
-    }}
-  }}
+        // Local-only folders never leave the machine, so they don't make the store dirty
+        store.folders.push(private_folder());
+        assert!(!has_unsynced_changes(&store, &settings));
 
-  let savedHasToken = {has_token};
-  let savedRepo = '{repo}';
+        store.folders[0].bookmarks.pop();
+        assert!(has_unsynced_changes(&store, &settings));
+    }
 
-  function showSettingsModal() {{
-    document.getElementById('ghToken').value = '';
-    document.getElementById('ghToken').placeholder = savedHasToken ? '(token saved - enter new to change)' : 'ghp_...';
-    document.getElementById('ghRepo').value = savedRepo;
-    document.getElementById('settingsOverlay').classList.add('active');
-    activeModal = 'settings';
-    document.getElementById('ghToken').focus();
-  }}
+    #[test]
+    fn auto_sync_mode_defaults_to_push() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.auto_sync_mode(), "push");
+        settings.auto_sync_mode = "push_pull".to_string();
+        assert_eq!(settings.auto_sync_mode(), "push_pull");
+        settings.auto_sync_mode = "bogus".to_string();
+        assert_eq!(settings.auto_sync_mode(), "push");
+    }
 
-  function submitSaveSettings() {{
-    const token = document.getElementById('ghToken').value.trim();
-    const repo = document.getElementById('ghRepo').value.trim();
-    window.ipc.postMessage(JSON.stringify({{
-      action: 'save_settings',
-      github_token: token,
-      github_repo: repo
-    }}));
-    if (token) savedHasToken = true;
-    savedRepo = repo;
-    closeModals();
-  }}
+    #[test]
+    fn close_button_quits_unless_set_to_minimize() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.close_action(), "quit");
+        settings.close_action = "minimize".to_string();
+        assert_eq!(settings.close_action(), "minimize");
+        settings.close_action = "hide".to_string();
+        assert_eq!(settings.close_action(), "quit");
+    }
 
-  function updateSettings(hasToken, repo) {{
-    savedHasToken = hasToken;
-    savedRepo = repo;
-  }}
+    #[test]
+    fn sync_queue_runs_immediately_when_idle() {
+        let mut sync = SyncQueue::default();
+        assert!(sync.request(SyncKind::AutoPush));
+        assert_eq!(sync.finish(), None);
+        assert!(sync.request(SyncKind::Pull));
+    }
 
-  function pushToGitHub() {{
-    window.ipc.postMessage(JSON.stringify({{ action: 'push_to_github' }}));
-  }}
+    #[test]
+    fn sync_queue_coalesces_requests_while_busy() {
+        let mut sync = SyncQueue::default();
+        assert!(sync.request(SyncKind::Push));
+        assert!(!sync.request(SyncKind::AutoPush));
+        assert!(!sync.request(SyncKind::AutoPush));
+        assert!(!sync.request(SyncKind::AutoPush));
 
-  function pullFromGitHub() {{
-    window.ipc.postMessage(JSON.stringify({{ action: 'pull_from_github' }}));
-  }}
+        // Three queued auto-pushes become one follow-up run
+        assert_eq!(sync.finish(), Some(SyncKind::AutoPush));
+        assert!(sync.request(SyncKind::AutoPush));
+        assert_eq!(sync.finish(), None);
+    }
 
-  let syncTimer = null;
+    #[test]
+    fn sync_queue_prefers_push_over_pull() {
+        let mut sync = SyncQueue::default();
+        assert!(sync.request(SyncKind::AutoPush));
+        assert!(!sync.request(SyncKind::Pull));
+        assert!(!sync.request(SyncKind::AutoPush));
+        assert_eq!(sync.finish(), Some(SyncKind::AutoPush));
 
-  function updateSyncStatus(msg, type) {{
-    var el = document.getElementById('syncStatus');
-    if (syncTimer) {{ clearTimeout(syncTimer); syncTimer = null; }}
-    el.className = 'sync-status';
-    if (msg) {{
-      el.textContent = msg;
-      if (!type) {{
-        if (msg.indexOf('successfully') !== -1 || msg.indexOf('Last synced') !== -1) type = 'success';
-        else if (msg.indexOf('failed') !== -1 || msg.indexOf('error') !== -1 || msg.indexOf('No token') !== -1 || msg.indexOf('No repo') !== -1 || msg.indexOf('not found') !== -1) type = 'error';
-        else type = 'progress';
-      }}
-      el.classList.add('active', 'status-' + type);
-      if (type === 'success') {{
-        syncTimer = setTimeout(function() {{ el.classList.remove('active'); }}, 5000);
-      }} else if (type === 'error') {{
-        syncTimer = setTimeout(function() {{ el.classList.remove('active'); }}, 8000);
-      }}
-    }} else {{
-      el.classList.remove('active');
-    }}
-  }}
+        assert!(sync.request(SyncKind::Pull));
+        assert!(!sync.request(SyncKind::Push));
+        assert!(!sync.request(SyncKind::Pull));
+        assert!(!sync.request(SyncKind::AutoPush));
+        assert_eq!(sync.finish(), Some(SyncKind::Push));
+    }
 
-  document.addEventListener('keydown', function(e) {{
-    if (e.key === 'Escape') {{
-      closeModals();
-    }} else if (e.key === 'Enter' && activeModal) {{
-      e.preventDefault();
-      if (activeModal === 'addBookmark') submitAddBookmark();
-      else if (activeModal === 'addFolder') submitAddFolder();
-    }}
-  }});
+    #[test]
+    fn config_dir_env_override() {
+        let platform = PathBuf::from("/home/me/.config");
+        let dir = PathBuf::from("/tmp/bookmarks-work");
+        assert_eq!(
+            config_dir_from(Some(dir.as_os_str()), platform.clone()),
+            dir
+        );
+        let default = platform.join("bookmarks-browser");
+        assert_eq!(
+            config_dir_from(Some(std::ffi::OsStr::new("")), platform.clone()),
+            default
+        );
+        assert_eq!(config_dir_from(None, platform), default);
+    }
 
-  document.getElementById('syncStatus').addEventListener('click', function() {{
-    updateSyncStatus(null);
-  }});
+    #[test]
+    fn config_dir_flag_is_removed_from_args() {
+        let mut args: Vec<String> = ["--config-dir", "/tmp/bb", "sync", "--push"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(take_config_dir_flag(&mut args), Some("/tmp/bb".to_string()));
+        assert_eq!(args, ["sync", "--push"]);
 
-  renderBookmarks(folders);
-</script>
-</body>
-</html>"#,
-        folders_json = folders_json,
-        has_token = has_token,
-        repo = repo
-    )
-}
+        let mut args = vec!["--config-dir=/tmp/x".to_string()];
+        assert_eq!(take_config_dir_flag(&mut args), Some("/tmp/x".to_string()));
+        assert!(args.is_empty());
 
-fn welcome_html() -> String {
-    r#"<!DOCTYPE html>
-<html>
-<head>
-<style>
-  :root {
-    --base: #1e1e2e;
-    --mantle: #181825;
-    --text: #cdd6f4;
-    --subtext: #a6adc8;
-    --accent: #cba6f7;
-  }
-  * { margin: 0; padding: 0; box-sizing: border-box; }
-  body {
-    background: var(--base);
-    color: var(--text);
-    font-family: system-ui, -apple-system, sans-serif;
-    display: flex;
-    align-items: center;
-    justify-content: center;
-    height: 100vh;
-  }
-  .welcome {
-    text-align: center;
-  }
-  .welcome h1 {
-    font-size: 24px;
-    font-weight: 600;
-    color: var(--text);
-    margin-bottom: 8px;
-  }
-  .welcome p {
-    font-size: 14px;
-    color: var(--subtext);
-  }
-</style>
-</head>
-<body>
-  <div class="welcome">
-    <h1>Select a bookmark</h1>
-    <p>Choose a bookmark from the sidebar to get started.</p>
-  </div>
-</body>
-</html>"#
-        .to_string()
-}
+        let mut args = vec!["sync".to_string()];
+        assert_eq!(take_config_dir_flag(&mut args), None);
+    }
 
-fn format_ureq_error(e: ureq::Error) -> String {
-    match e {
-        ureq::Error::StatusCode(401) => "Invalid or expired GitHub token".to_string(),
-        ureq::Error::StatusCode(404) => {
-            "Repository or file not found — check owner/repo".to_string()
+    fn sync_settings(mode: &str) -> Settings {
+        Settings {
+            github_token: "tok".to_string(),
+            github_repo: "user/bookmarks".to_string(),
+            auto_sync_mode: mode.to_string(),
+            ..Default::default()
         }
-        ureq::Error::StatusCode(409) => "SHA conflict — pull first, then push again".to_string(),
-        ureq::Error::StatusCode(422) => "Validation error — check repo name format".to_string(),
-        ureq::Error::StatusCode(code) => format!("GitHub API error (HTTP {code})"),
-        ureq::Error::Timeout(_) => "Request timed out — try again".to_string(),
-        ureq::Error::HostNotFound => "Could not reach GitHub — check your connection".to_string(),
-        ureq::Error::ConnectionFailed => "Connection failed — check your connection".to_string(),
-        _ => format!("{e}"),
     }
-}
 
-fn get_file_sha(token: &str, repo: &str) -> Result<Option<String>, String> {
-    let url = format!("https://api.github.com/repos/{repo}/contents/bookmarks.json");
-    let agent = ureq::Agent::new_with_defaults();
+    #[test]
+    fn first_run_clones_before_anything_else() {
+        let store = default_store();
+        // Even in plain push mode, a fresh install pulls first
+        assert_eq!(
+            plan_startup_sync(true, &store, &sync_settings("push")),
+            StartupSync::Clone
+        );
+        assert_eq!(
+            plan_startup_sync(true, &store, &sync_settings("push_pull")),
+            StartupSync::Clone
+        );
+        // Without sync configured there is nothing to clone from
+        assert_eq!(
+            plan_startup_sync(true, &store, &Settings::default()),
+            StartupSync::Nothing
+        );
+        assert_eq!(
+            plan_startup_sync(true, &store, &sync_settings("off")),
+            StartupSync::Nothing
+        );
+    }
 
-    match agent
-        .get(&url)
-        .header("Authorization", &format!("token {token}"))
-        .header("Accept", "application/vnd.github+json")
-        .header("User-Agent", "bookmarks-browser")
-        .call()
-    {
-        Ok(mut response) => {
-            let body = response
-                .body_mut()
-                .read_to_string()
-                .map_err(|e| format!("Failed to read response: {e}"))?;
-            let parsed: serde_json::Value = serde_json::from_str(&body)
-                .map_err(|_| "Malformed response from GitHub".to_string())?;
-            Ok(parsed.get("sha").and_then(|v| v.as_str()).map(String::from))
-        }
-        Err(ureq::Error::StatusCode(404)) => Ok(None),
-        Err(e) => Err(format_ureq_error(e)),
+    #[test]
+    fn focus_pulls_are_opt_in_clean_and_throttled() {
+        let store = default_store();
+        let now = Instant::now();
+        let mut settings = sync_settings("push");
+        assert!(!should_pull_on_focus(&settings, &store, None, now));
+
+        settings.sync_on_focus = true;
+        // Never synced: the local bookmarks may exist nowhere else
+        assert!(!should_pull_on_focus(&settings, &store, None, now));
+        settings.last_synced_digest = Some(store_digest(&store));
+        assert!(should_pull_on_focus(&settings, &store, None, now));
+        let recent = now - Duration::from_secs(30);
+        assert!(!should_pull_on_focus(&settings, &store, Some(recent), now));
+        let earlier = now - FOCUS_PULL_INTERVAL;
+        assert!(should_pull_on_focus(&settings, &store, Some(earlier), now));
+
+        // Unpushed edits would be overwritten
+        settings.last_synced_digest = Some(store_digest(&store) ^ 1);
+        assert!(!should_pull_on_focus(&settings, &store, None, now));
+
+        let mut off = sync_settings("off");
+        off.sync_on_focus = true;
+        assert!(!should_pull_on_focus(&off, &store, None, now));
     }
-}
 
-fn do_push(
-    token: &str,
-    repo: &str,
-    bookmarks_json: &str,
-    sha: Option<&str>,
-) -> Result<String, String> {
-    let encoded = BASE64.encode(bookmarks_json.as_bytes());
+    #[test]
+    fn later_runs_follow_auto_sync_mode() {
+        let store = default_store();
+        assert_eq!(
+            plan_startup_sync(false, &store, &sync_settings("push")),
+            StartupSync::Nothing
+        );
+        let mut settings = sync_settings("push_pull");
+        // No sync on record, so local edits can't be told apart from a clean store
+        assert_eq!(
+            plan_startup_sync(false, &store, &settings),
+            StartupSync::SkipDirty
+        );
 
-    let sha = match sha {
-        Some(s) => Some(s.to_string()),
-        None => get_file_sha(token, repo)?,
-    };
+        settings.last_synced_digest = Some(store_digest(&store));
+        assert_eq!(
+            plan_startup_sync(false, &store, &settings),
+            StartupSync::Pull
+        );
 
-    let mut payload = serde_json::json!({
-        "message": "Update bookmarks",
-        "content": encoded,
-    });
-    if let Some(ref sha_val) = sha {
-        payload["sha"] = serde_json::json!(sha_val);
+        settings.last_synced_digest = Some(store_digest(&store) ^ 1);
+        assert_eq!(
+            plan_startup_sync(false, &store, &settings),
+            StartupSync::SkipDirty
+        );
     }
 
-    let url = format!("https://api.github.com/repos/{repo}/contents/bookmarks.json");
-    let agent = ureq::Agent::new_with_defaults();
+    #[test]
+    fn startup_does_not_overwrite_unreadable_file() {
+        let dir = env::temp_dir().join("bookmarks-browser-startup-test");
+        let path = dir.join("bookmarks.json");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create dir");
 
-    let mut response = agent
-        .put(&url)
-        .header("Authorization", &format!("token {token}"))
-        .header("Accept", "application/vnd.github+json")
-        .header("User-Agent", "bookmarks-browser")
-        .send_json(&payload)
-        .map_err(format_ureq_error)?;
+        // e.g. caught mid-write by another process
+        let partial = r#"{"folders":[{"name":"Work","bookmarks":["#;
+        fs::write(&path, partial).expect("write");
 
-    let body = response
-        .body_mut()
-        .read_to_string()
-        .map_err(|e| format!("Failed to read response: {e}"))?;
-    let parsed: serde_json::Value =
-        serde_json::from_str(&body).map_err(|_| "Malformed response from GitHub".to_string())?;
+        let (store, stamp) = startup_store(&path, true);
+        assert_eq!(store, default_store());
+        assert_eq!(stamp, None);
+        assert_eq!(fs::read_to_string(&path).unwrap(), partial);
 
-    parsed
-        .get("content")
-        .and_then(|c| c.get("sha"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-        .ok_or_else(|| "Malformed response from GitHub".to_string())
-}
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-fn do_pull(token: &str, repo: &str) -> Result<(BookmarkStore, String), String> {
-    let url = format!("https://api.github.com/repos/{repo}/contents/bookmarks.json");
-    let agent = ureq::Agent::new_with_defaults();
+    #[test]
+    fn startup_writes_defaults_only_when_missing() {
+        let dir = env::temp_dir().join("bookmarks-browser-startup-missing-test");
+        let path = dir.join("bookmarks.json");
+        let _ = fs::remove_dir_all(&dir);
 
-    let mut response = agent
-        .get(&url)
-        .header("Authorization", &format!("token {token}"))
-        .header("Accept", "application/vnd.github+json")
-        .header("User-Agent", "bookmarks-browser")
-        .call()
-        .map_err(format_ureq_error)?;
+        let (_, stamp) = startup_store(&path, false);
+        assert!(!path.exists());
+        assert_eq!(stamp, None);
 
-    let body = response
-        .body_mut()
-        .read_to_string()
-        .map_err(|e| format!("Failed to read response: {e}"))?;
-    let parsed: serde_json::Value =
-        serde_json::from_str(&body).map_err(|_| "Malformed response from GitHub".to_string())?;
+        let (store, stamp) = startup_store(&path, true);
+        assert_eq!(BookmarkStore::try_load_from(&path), Some(store));
+        assert!(stamp.is_some());
 
-    let sha = parsed
-        .get("sha")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| "Missing SHA in response".to_string())?
-        .to_string();
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-    let encoded = parsed
-        .get("content")
-        .and_then(|c| c.as_str())
-        .ok_or_else(|| "bookmarks.json not found in repository".to_string())?;
+    #[test]
+    fn sync_timeout_defaults_and_clamps() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            settings.sync_timeout(),
+            Duration::from_secs(DEFAULT_SYNC_TIMEOUT_SECS)
+        );
+        settings.sync_timeout_secs = Some(1);
+        assert_eq!(
+            settings.sync_timeout(),
+            Duration::from_secs(MIN_SYNC_TIMEOUT_SECS)
+        );
+        settings.sync_timeout_secs = Some(90);
+        assert_eq!(settings.sync_timeout(), Duration::from_secs(90));
+    }
 
-    // GitHub returns base64 with newlines — strip them before decoding
-    let cleaned: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
-    let decoded = BASE64
-        .decode(&cleaned)
-        .map_err(|e| format!("Failed to decode content: {e}"))?;
-    let content = String::from_utf8(decoded).map_err(|e| format!("Invalid UTF-8 content: {e}"))?;
+    #[test]
+    fn normalize_url_edge_cases() {
+        assert_eq!(
+            normalize_url(" HTTPS://Example.COM:443/Docs/#intro"),
+            "https://example.com/Docs"
+        );
+        assert_eq!(normalize_url("http://example.com:80"), "http://example.com");
+        assert_eq!(
+            normalize_url("https://example.com/search/?q=A"),
+            "https://example.com/search?q=A"
+        );
+        // Non-default ports and schemes stay distinct
+        assert_ne!(
+            normalize_url("https://example.com:8443/"),
+            normalize_url("https://example.com/")
+        );
+        assert_ne!(
+            normalize_url("http://example.com/"),
+            normalize_url("https://example.com/")
+        );
+        assert_eq!(normalize_url("about:blank"), "about:blank");
+    }
 
-    let store = serde_json::from_str::<BookmarkStore>(&content)
-        .map_err(|e| format!("Failed to parse bookmarks: {e}"))?;
+    #[test]
+    fn dedupe_within_folders_or_globally() {
+        let mut store = default_store();
+        let copy = |url: &str| bookmark("Copy", url);
+        store.folders[0]
+            .bookmarks
+            .push(copy("https://DOC.rust-lang.org/book"));
+        store.folders[1]
+            .bookmarks
+            .push(copy("https://wiki.archlinux.org/#top"));
 
-    Ok((store, sha))
-}
+        let mut per_folder = store.clone();
+        assert_eq!(dedupe_bookmarks(&mut per_folder, false), 1);
+        assert_eq!(
+            per_folder.folders[0].bookmarks,
+            default_store().folders[0].bookmarks
+        );
+        assert_eq!(per_folder.folders[1].bookmarks.len(), 2);
 
-fn make_bounds(x: f64, y: f64, width: f64, height: f64) -> Rect {
-    Rect {
-        position: LogicalPosition::new(x, y).into(),
-        size: WryLogicalSize::new(width, height).into(),
+        assert_eq!(dedupe_bookmarks(&mut store, true), 2);
+        assert_eq!(store, default_store());
     }
-}
 
-fn main() {
-    let mut store = BookmarkStore::load();
-    if let Err(e) = store.save() {
-        eprintln!("Warning: could not save bookmarks: {e}");
+    #[test]
+    fn cross_folder_duplicates_list_each_url_once() {
+        let mut store = default_store();
+        let copy = |url: &str| bookmark("Copy", url);
+        // Deliberate: the same page filed under a second folder
+        store.folders[1]
+            .bookmarks
+            .push(copy("https://wiki.archlinux.org/"));
+        // Accidental: a near-identical URL, plus a repeat within one folder
+        store.folders[1]
+            .bookmarks
+            .push(copy("https://DOC.rust-lang.org/book/#intro"));
+        store.folders[0]
+            .bookmarks
+            .push(copy("https://news.ycombinator.com"));
+        store.folders[0]
+            .bookmarks
+            .push(copy("https://doc.rust-lang.org/book/"));
+
+        let dupes = cross_folder_duplicates(&store);
+        let urls: Vec<&str> = dupes.iter().map(|(url, _)| url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                store.folders[0].bookmarks[0].url.as_str(),
+                store.folders[0].bookmarks[1].url.as_str(),
+                "https://news.ycombinator.com",
+            ]
+        );
+        assert!(dupes.iter().all(|(_, folders)| folders == &[0, 1]));
+
+        assert_eq!(keep_only_in(&mut store, urls[0], 1), 2);
+        assert_eq!(cross_folder_duplicates(&store).len(), 2);
+        assert_eq!(
+            keep_only_in(&mut store, "https://news.ycombinator.com/", 9),
+            0
+        );
     }
 
-    let mut settings = Settings::load();
-    let initial_collapsed = settings.sidebar_collapsed;
+    #[test]
+    fn replace_urls_literal_and_regex() {
+        let mut store = default_store();
+        store.folders[0].bookmarks = vec![
+            bookmark("Old", "https://old.example.com/a"),
+            bookmark("Other", "https://other.example.org/"),
+        ];
 
-    let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
-    let proxy = event_loop.create_proxy();
+        let preview =
+            preview_replace_urls(&store, "old.example.com", "new.example.com", false).unwrap();
+        assert_eq!(preview.len(), 1);
+        assert_eq!(preview[0].2, "https://new.example.com/a");
 
-    let window = WindowBuilder::new()
-        .with_title("Bookmarks Browser")
-        .with_inner_size(LogicalSize::new(1200.0, 800.0))
-        .build(&event_loop)
-        .expect("Failed to create window");
+        let now = "2026-10-16 09:30 UTC";
+        assert_eq!(
+            replace_urls(&mut store, "old.example.com", "new.example.com", false, now),
+            1
+        );
+        assert_eq!(
+            store.folders[0].bookmarks[0].url,
+            "https://new.example.com/a"
+        );
+        assert_eq!(store.folders[0].bookmarks[0].modified.as_deref(), Some(now));
+        assert_eq!(store.folders[0].bookmarks[1].modified, None);
 
-    let inner = window.inner_size();
-    let scale = window.scale_factor();
-    let w = inner.width as f64 / scale;
-    let h = inner.height as f64 / scale;
+        let changed = replace_urls(
+            &mut store,
+            r"^https://(\w+)\.example",
+            "http://$1.test",
+            true,
+            now,
+        );
+        assert_eq!(changed, 2);
+        assert_eq!(store.folders[0].bookmarks[1].url, "http://other.test.org/");
 
-    let sidebar_builder = WebViewBuilder::new()
-        .with_html(sidebar_html(&store, &settings))
-        .with_bounds(make_bounds(0.0, 0.0, SIDEBAR_WIDTH, h))
-        .with_ipc_handler(move |req: wry::http::Request<String>| {
-            let body = req.body();
-            let Ok(msg) = serde_json::from_str::<serde_json::Value>(body) else {
-                return;
-            };
-            let Some(action) = msg.get("action").and_then(|a| a.as_str()) else {
-                return;
-            };
-            match action {
-                "navigate" => {
-                    if let Some(url) = msg.get("url").and_then(|u| u.as_str()) {
-                        let _ = proxy.send_event(UserEvent::Navigate(url.to_string()));
-                    }
-                }
-                "toggle_folder" => {
-                    if let Some(index) = msg.get("folder_index").and_then(|i| i.as_u64()) {
-                        let _ = proxy.send_event(UserEvent::ToggleFolder(index as usize));
-                    }
-                }
-                "toggle_sidebar" => {
-                    let _ = proxy.send_event(UserEvent::ToggleSidebar);
-                }
-                "add_folder" => {
-                    if let Some(name) = msg.get("name").and_then(|n| n.as_str()) {
-                        let _ = proxy.send_event(UserEvent::AddFolder(name.to_string()));
-                    }
-                }
-                "add_bookmark" => {
-                    if let (Some(fi), Some(name), Some(url)) = (
-                        msg.get("folder_index").and_then(|i| i.as_u64()),
-                        msg.get("name").and_then(|n| n.as_str()),
-                        msg.get("url").and_then(|u| u.as_str()),
-                    ) {
-                        let _ = proxy.send_event(UserEvent::AddBookmark {
-                            folder_index: fi as usize,
-                            name: name.to_string(),
-                            url: url.to_string(),
-                        });
-                    }
-                }
-                "delete_bookmark" => {
-                    if let (Some(fi), Some(bi)) = (
-                        msg.get("folder_index").and_then(|i| i.as_u64()),
-                        msg.get("bookmark_index").and_then(|i| i.as_u64()),
-                    ) {
-                        let _ = proxy.send_event(UserEvent::DeleteBookmark {
-                            folder_index: fi as usize,
-                            bookmark_index: bi as usize,
-                        });
-                    }
-                }
-                "delete_folder" => {
-                    if let Some(index) = msg.get("folder_index").and_then(|i| i.as_u64()) {
-                        let _ = proxy.send_event(UserEvent::DeleteFolder(index as usize));
-                    }
-                }
-                "push_to_github" => {
-                    let _ = proxy.send_event(UserEvent::PushToGitHub);
-                }
-                "pull_from_github" => {
-                    let _ = proxy.send_event(UserEvent::PullFromGitHub);
-                }
-                "save_settings" => {
-                    let token = msg
-                        .get("github_token")
-                        .and_then(|t| t.as_str())
-                        .unwrap_or("")
-                        .to_string();
-                    let repo = msg
-                        .get("github_repo")
-                        .and_then(|g| g.as_str())
-                        .unwrap_or("")
-                        .to_string();
-                    let _ = proxy.send_event(UserEvent::SaveSettings {
-                        github_token: token,
-                        github_repo: repo,
-                    });
-                }
-                _ => {}
-            }
-        });
+        // Invalid patterns are reported by the preview and change nothing
+        assert!(preview_replace_urls(&store, "(", "", true).is_err());
+        assert_eq!(replace_urls(&mut store, "(", "", true, now), 0);
+        assert_eq!(replace_urls(&mut store, "", "x", false, now), 0);
+    }
 
-    let content_builder = WebViewBuilder::new()
-        .with_html(welcome_html())
-        .with_bounds(make_bounds(SIDEBAR_WIDTH, 0.0, w - SIDEBAR_WIDTH, h))
-        .with_user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36");
+    const OPML_FIXTURE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<opml version="2.0">
+  <head><title>Reading &amp; Research</title></head>
+  <body>
+    <outline text="Loose link" htmlUrl="https://loose.example/"/>
+    <!-- <outline text="Commented out" htmlUrl="https://nope.example/"/> -->
+    <outline text="Rust &amp; Systems">
+      <outline text="This Week in Rust" type="rss" xmlUrl="https://this-week-in-rust.org/rss.xml" htmlUrl="https://this-week-in-rust.org/"/>
+      <outline title="Fasterthanli.me" xmlUrl="https://fasterthanli.me/index.xml"/>
+      <outline text="Deep">
+        <outline text="&lt;Tiny&gt; &#8220;Blog&#x201D;" url="https://tiny.example/?a=1&amp;b=2"/>
+      </outline>
+    </outline>
+    <outline text="Empty group"></outline>
+  </body>
+</opml>
+"#;
 
-    #[cfg(target_os = "linux")]
-    let (sidebar, content, sidebar_gtk_box) = {
-        use gtk::prelude::*;
+    #[test]
+    fn opml_outlines_map_to_folders() {
+        let folders = parse_opml(OPML_FIXTURE);
+        let names: Vec<&str> = folders.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Reading & Research",
+                "Rust & Systems",
+                "Rust & Systems / Deep"
+            ]
+        );
 
-        let vbox = window.default_vbox().expect("Failed to get default vbox");
-        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
-        vbox.pack_start(&hbox, true, true, 0);
+        assert_eq!(folders[0].bookmarks[0].url, "https://loose.example/");
+        assert_eq!(folders[0].bookmarks.len(), 1);
 
-        let sidebar_width = if initial_collapsed {
-            STRIP_WIDTH
-        } else {
-            SIDEBAR_WIDTH
+        let rust = &folders[1].bookmarks;
+        assert_eq!(rust.len(), 2);
+        assert_eq!(rust[0].name, "This Week in Rust");
+        // The page is preferred over the feed when both are given
+        assert_eq!(rust[0].url, "https://this-week-in-rust.org/");
+        assert_eq!(rust[1].name, "Fasterthanli.me");
+        assert_eq!(rust[1].url, "https://fasterthanli.me/index.xml");
+
+        let deep = &folders[2].bookmarks[0];
+        assert_eq!(deep.name, "<Tiny> \u{201c}Blog\u{201d}");
+        assert_eq!(deep.url, "https://tiny.example/?a=1&b=2");
+    }
+
+    #[test]
+    fn flat_json_round_trip() {
+        let store = default_store();
+        let json = to_flat_json(&store);
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[2]["title"], "Hacker News");
+        assert_eq!(parsed[2]["folder"], "News");
+        assert_eq!(parsed[2]["tags"], serde_json::json!([]));
+
+        let mut tagged = store.clone();
+        tagged.folders[1].bookmarks[0].tags = vec!["tech".into()];
+        let json = to_flat_json(&tagged);
+        assert_eq!(from_flat_json(&json).unwrap().0, tagged.folders);
+
+        assert_eq!(
+            from_flat_json(&json).unwrap(),
+            (store.folders.clone(), vec![])
+        );
+        assert_eq!(parse_import(&json).unwrap().0, store.folders);
+
+        let (loose, failures) =
+            from_flat_json(r#"[{"title": "A", "url": "https://a.example/"}, {"title": "B"}]"#)
+                .unwrap();
+        assert_eq!(loose[0].name, "Imported");
+        assert_eq!(loose[0].bookmarks.len(), 1);
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].starts_with("entry 2: missing field `url`"));
+    }
+
+    #[test]
+    fn import_detects_format() {
+        assert_eq!(parse_import(OPML_FIXTURE).unwrap().0.len(), 3);
+        let json = serde_json::to_string(&default_store()).unwrap();
+        assert_eq!(parse_import(&json).unwrap().0, default_store().folders);
+        assert!(parse_import("name,url\na,https://a.example/").is_err());
+        assert_eq!(parse_import(POCKET_FIXTURE).unwrap().0.len(), 2);
+        assert_eq!(parse_import(INSTAPAPER_FIXTURE).unwrap().0.len(), 3);
+        assert!(parse_import("<html><body>No links</body></html>").is_err());
+    }
+
+    const POCKET_FIXTURE: &str = r#"<!DOCTYPE html>
+<html>
+  <head><title>Pocket Export</title></head>
+  <body>
+    <h1>Unread</h1>
+    <ul>
+      <li><a href="https://rust-lang.org/" time_added="1700000000" tags="rust,Lang,rust">Rust &amp; friends</a></li>
+      <li><a href="https://untitled.example/" time_added="" tags=""></a></li>
+    </ul>
+    <h1>Read Archive</h1>
+    <ul>
+      <li><a href="https://old.example/" time_added="86400" tags="">Old one</a></li>
+    </ul>
+  </body>
+</html>
+"#;
+
+    #[test]
+    fn pocket_and_browser_html_keep_tags_and_dates() {
+        let folders = parse_netscape(POCKET_FIXTURE);
+        assert_eq!(folders.len(), 2);
+        assert_eq!(folders[0].name, "Unread");
+        let rust = &folders[0].bookmarks[0];
+        assert_eq!(rust.name, "Rust & friends");
+        assert_eq!(rust.tags, ["rust", "Lang"]);
+        assert_eq!(rust.created.as_deref(), Some("2023-11-14 22:13 UTC"));
+        let untitled = &folders[0].bookmarks[1];
+        assert_eq!(untitled.name, "https://untitled.example/");
+        assert!(untitled.created.is_none() && untitled.tags.is_empty());
+        assert_eq!(folders[1].name, "Read Archive");
+        assert_eq!(
+            folders[1].bookmarks[0].created.as_deref(),
+            Some("1970-01-02 00:00 UTC")
+        );
+
+        let browser = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><A HREF="https://top.example/" ADD_DATE="0">Top</A>
+    <DT><H3 ADD_DATE="1">Toolbar</H3>
+    <DL><p>
+        <DT><A HREF="https://bar.example/">Bar</A>
+        <DT><H3>Dev</H3>
+        <DL><p>
+            <DT><A HREF="https://docs.rs/">Docs</A>
+        </DL><p>
+    </DL><p>
+</DL><p>
+"#;
+        let names: Vec<(String, usize)> = parse_netscape(browser)
+            .into_iter()
+            .map(|f| (f.name, f.bookmarks.len()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("Bookmarks".to_string(), 1),
+                ("Toolbar".to_string(), 1),
+                ("Toolbar / Dev".to_string(), 1),
+            ]
+        );
+    }
+
+    const INSTAPAPER_FIXTURE: &str = "URL,Title,Selection,Folder,Timestamp,Tags\r
+https://a.example/,\"Commas, \"\"quotes\"\"\",,Unread,1700000000,\"[\"\"read\"\"]\"\r
+https://b.example/,,\"A selection\r
+over two lines\",Starred,,\r
+,No URL,,Unread,,\r
+https://c.example/,C,,Research,notatime,\"x, y\"\r
+";
+
+    #[test]
+    fn instapaper_csv_maps_columns_to_folders() {
+        let (folders, failures) = parse_instapaper_csv(INSTAPAPER_FIXTURE).unwrap();
+        let names: Vec<&str> = folders.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["Unread", "Starred", "Research"]);
+        let a = &folders[0].bookmarks[0];
+        assert_eq!(a.name, "Commas, \"quotes\"");
+        assert_eq!(a.created.as_deref(), Some("2023-11-14 22:13 UTC"));
+        assert_eq!(a.tags, ["read"]);
+        assert_eq!(folders[1].bookmarks[0].name, "https://b.example/");
+        assert_eq!(folders[2].bookmarks[0].tags, ["x", "y"]);
+        assert!(folders[2].bookmarks[0].created.is_none());
+        assert_eq!(failures, ["row 4: no URL"]);
+        assert!(parse_instapaper_csv("URL,Selection\nhttps://a.example/,x").is_err());
+    }
+
+    #[test]
+    fn explicit_theme_overrides_system() {
+        assert_eq!(resolved_theme("auto", Theme::Light), "light");
+        assert_eq!(resolved_theme("auto", Theme::Dark), "dark");
+        assert_eq!(resolved_theme("dark", Theme::Light), "dark");
+        assert_eq!(resolved_theme("light", Theme::Dark), "light");
+        assert_eq!(
+            resolved_theme("high_contrast", Theme::Light),
+            "high_contrast"
+        );
+
+        let mut settings = Settings::default();
+        assert_eq!(settings.theme(), "auto");
+        settings.theme = "sepia".into();
+        assert_eq!(settings.theme(), "auto");
+    }
+
+    #[test]
+    fn font_scale_defaults_and_stays_in_range() {
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.ui_font_scale(), 1.0);
+        let scaled = |scale| Settings {
+            ui_font_scale: scale,
+            ..Default::default()
         };
-        let sidebar_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
-        sidebar_box.set_size_request(sidebar_width as i32, -1);
-        hbox.pack_start(&sidebar_box, false, false, 0);
+        assert_eq!(scaled(1.5).ui_font_scale(), 1.5);
+        assert_eq!(scaled(10.0).ui_font_scale(), MAX_FONT_SCALE);
+        assert_eq!(scaled(0.0).ui_font_scale(), MIN_FONT_SCALE);
+        assert_eq!(scaled(f64::NAN).ui_font_scale(), 1.0);
+    }
+
+    #[test]
+    fn search_url_encodes_query() {
+        assert_eq!(
+            build_search_url("https://duckduckgo.com/?q=%s", "rust & wry/tao 100%"),
+            "https://duckduckgo.com/?q=rust%20%26%20wry%2Ftao%20100%25"
+        );
+        assert_eq!(
+            build_search_url("https://example.com/search?q=", "caf\u{e9}"),
+            "https://example.com/search?q=caf%C3%A9"
+        );
+    }
+
+    #[test]
+    fn at_bookmarks_expand_under_the_base_path() {
+        assert_eq!(
+            expand_bookmark_url("@docs/intro.md", "/home/me/project"),
+            "file:///home/me/project/docs/intro.md"
+        );
+        assert_eq!(
+            expand_bookmark_url("@/docs/my notes.md", "/home/me/project/"),
+            "file:///home/me/project/docs/my%20notes.md"
+        );
+        assert_eq!(
+            expand_bookmark_url("@notes/a#b.md", "/home/me"),
+            "file:///home/me/notes/a%23b.md"
+        );
+        assert_eq!(
+            expand_bookmark_url("@x&y=z?.md", "/home/me"),
+            "file:///home/me/x%26y%3Dz%3F.md"
+        );
+        assert_eq!(
+            expand_bookmark_url("@docs/intro.md", "C:\\Projects\\app"),
+            "file:///C:/Projects/app/docs/intro.md"
+        );
+        // Without a base path there's nothing to expand against
+        assert_eq!(
+            expand_bookmark_url("@docs/intro.md", "  "),
+            "@docs/intro.md"
+        );
+        for url in [
+            "https://example.com/@docs",
+            "file:///tmp/a.html",
+            "example.com",
+        ] {
+            assert_eq!(expand_bookmark_url(url, "/home/me/project"), url);
+        }
+    }
+
+    #[test]
+    fn omnibar_tells_urls_from_searches() {
+        let search = "https://s.example/?q=%s";
+        assert_eq!(
+            omnibar_target("https://a.example/x", search),
+            "https://a.example/x"
+        );
+        assert_eq!(omnibar_target("docs.rs/wry", search), "https://docs.rs/wry");
+        assert_eq!(
+            omnibar_target("localhost:8080", search),
+            "https://localhost:8080"
+        );
+        assert_eq!(
+            omnibar_target("how to use tao", search),
+            "https://s.example/?q=how%20to%20use%20tao"
+        );
+        assert_eq!(omnibar_target("rust", search), "https://s.example/?q=rust");
+    }
+
+    #[test]
+    fn push_guard_refuses_large_removals() {
+        // No sync on record, or an empty remote: nothing to protect
+        assert!(check_push(0, None, 50).is_ok());
+        assert!(check_push(0, Some(0), 50).is_ok());
+
+        assert!(check_push(0, Some(40), 50).is_err());
+        assert!(check_push(19, Some(40), 50).is_err());
+        assert!(check_push(20, Some(40), 50).is_ok());
+        assert!(check_push(60, Some(40), 50).is_ok());
+
+        // 100% only trips when more than everything would go, i.e. never
+        assert!(check_push(0, Some(40), 100).is_ok());
+        assert_eq!(
+            Settings::default().push_guard_percent(),
+            DEFAULT_PUSH_GUARD_PERCENT
+        );
+    }
+
+    #[test]
+    fn profile_title_and_color() {
+        assert_eq!(window_title(None), "Bookmarks Browser");
+        assert_eq!(
+            window_title(Some("work")),
+            "Bookmarks Browser \u{2014} work"
+        );
 
-        let content_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
-        hbox.pack_start(&content_box, true, true, 0);
+        let mut settings = Settings::default();
+        let derived = settings.profile_color("work").to_string();
+        assert!(PROFILE_COLORS.contains(&derived.as_str()));
+        assert_eq!(settings.profile_color("work"), derived);
 
-        hbox.show_all();
+        settings.profile_color = "#123abc".into();
+        assert_eq!(settings.profile_color("work"), "#123abc");
+        settings.profile_color = "red".into();
+        assert_eq!(settings.profile_color("work"), derived);
+    }
 
-        let sidebar = sidebar_builder
-            .build_gtk(&sidebar_box)
-            .expect("Failed to create sidebar webview");
-        let content = content_builder
-            .build_gtk(&content_box)
-            .expect("Failed to create content webview");
+    #[test]
+    fn help_rows_come_from_shortcut_list() {
+        let rows = help_rows(&[("Ctrl+<", "Zoom & pan")]);
+        assert_eq!(
+            rows,
+            "      <tr><td class=\"help-key\">Ctrl+&lt;</td><td>Zoom &amp; pan</td></tr>"
+        );
+        assert_eq!(
+            help_rows(SHORTCUTS).matches("<tr>").count(),
+            SHORTCUTS.len()
+        );
+    }
 
-        (sidebar, content, sidebar_box)
-    };
+    #[test]
+    fn diagnostics_never_include_the_token() {
+        let mut settings = Settings::default();
+        settings.github_token = "ghp_secret123".into();
+        settings.github_repo = "alice/my-bookmarks".into();
+        let store = default_store();
 
-    #[cfg(not(target_os = "linux"))]
-    let (sidebar, content) = {
-        let sidebar = sidebar_builder
-            .build_as_child(&window)
-            .expect("Failed to create sidebar webview");
-        let content = content_builder
-            .build_as_child(&window)
-            .expect("Failed to create content webview");
-        (sidebar, content)
-    };
+        let report = diagnostics_report(&store, &settings, false, Some("Push failed: timeout"));
+        assert!(!report.contains("ghp_secret123"));
+        assert!(!report.contains("alice"));
+        assert!(report.contains("GitHub repo: ***/my-bookmarks"));
+        assert!(report.contains("GitHub token: set"));
+        assert!(report.contains("Last sync error: Push failed: timeout"));
+        assert!(report.contains(env!("CARGO_PKG_VERSION")));
 
-    let sync_proxy = event_loop.create_proxy();
+        let report = diagnostics_report(&store, &settings, true, None);
+        assert!(report.contains("GitHub repo: alice/my-bookmarks"));
+        assert!(!report.contains("ghp_secret123"));
+    }
 
-    let mut modifiers = ModifiersState::empty();
-    let mut sidebar_collapsed = initial_collapsed;
-    let mut remote_sha: Option<String> = None;
-    let mut sync_in_progress = false;
+    #[test]
+    fn health_status_maps_to_indicator() {
+        let checked = |status| LinkHealth {
+            last_status: status,
+            last_checked: Some(format_utc(0)),
+        };
+        assert_eq!(health_class(None), "unchecked");
+        assert_eq!(health_class(Some(&LinkHealth::default())), "unchecked");
+        assert_eq!(health_class(Some(&checked(Some(200)))), "ok");
+        assert_eq!(health_class(Some(&checked(Some(204)))), "ok");
+        assert_eq!(health_class(Some(&checked(Some(301)))), "redirect");
+        assert_eq!(health_class(Some(&checked(Some(404)))), "broken");
+        assert_eq!(health_class(Some(&checked(Some(503)))), "broken");
+        assert_eq!(health_class(Some(&checked(None))), "broken");
+    }
 
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Wait;
+    #[test]
+    fn format_utc_dates() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_utc(951_782_400), "2000-02-29 00:00 UTC");
+        assert_eq!(format_utc(1_700_000_000), "2023-11-14 22:13 UTC");
+    }
 
-        match event {
-            Event::WindowEvent {
-                event: WindowEvent::ModifiersChanged(new_modifiers),
-                ..
-            } => {
-                modifiers = new_modifiers;
-            }
-            Event::WindowEvent {
-                event:
-                    WindowEvent::KeyboardInput {
-                        event: ref key_event,
-                        ..
-                    },
-                ..
-            } if key_event.state == ElementState::Pressed => {
-                let ctrl = modifiers.control_key();
-                let key = &key_event.logical_key;
+    #[test]
+    fn thumbnails_stay_local() {
+        let mut local = default_store();
+        local.folders[0].bookmarks[0].thumbnail = Some("00112233445566ff.png".into());
 
-                if ctrl && *key == Key::Character("b") {
-                    sidebar_collapsed = !sidebar_collapsed;
-                    settings.sidebar_collapsed = sidebar_collapsed;
-                    let _ = settings.save();
-                    let _ = sidebar
-                        .evaluate_script(&format!("setSidebarCollapsed({})", sidebar_collapsed));
-                    #[cfg(target_os = "linux")]
-                    {
-                        use gtk::prelude::*;
-                        let new_width = if sidebar_collapsed {
-                            STRIP_WIDTH
-                        } else {
-                            SIDEBAR_WIDTH
-                        };
-                        sidebar_gtk_box.set_size_request(new_width as i32, -1);
-                        sidebar_gtk_box.queue_resize();
-                    }
-                    #[cfg(not(target_os = "linux"))]
-                    {
-                        let scale = window.scale_factor();
-                        let inner = window.inner_size();
-                        let w = inner.width as f64 / scale;
-                        let h = inner.height as f64 / scale;
-                        if sidebar_collapsed {
-                            let _ = sidebar.set_bounds(make_bounds(0.0, 0.0, 0.0, h));
-                            let _ = content.set_bounds(make_bounds(0.0, 0.0, w, h));
-                        } else {
-                            let _ = sidebar.set_bounds(make_bounds(0.0, 0.0, SIDEBAR_WIDTH, h));
-                            let _ = content.set_bounds(make_bounds(
-                                SIDEBAR_WIDTH,
-                                0.0,
-                                w - SIDEBAR_WIDTH,
-                                h,
-                            ));
-                        }
-                    }
-                } else if ctrl && *key == Key::Character("u") {
-                    let _ = sync_proxy.send_event(UserEvent::PushToGitHub);
-                } else if ctrl && *key == Key::Character("i") {
-                    let _ = sync_proxy.send_event(UserEvent::PullFromGitHub);
-                } else if ctrl && *key == Key::Character("g") {
-                    let _ = sidebar.evaluate_script("showAddFolderModal()");
-                } else if ctrl && *key == Key::Character("n") {
-                    let _ = sidebar.evaluate_script("showAddBookmarkModal()");
-                } else if *key == Key::F1 || (ctrl && *key == Key::Character("/")) {
-                    let _ = sidebar.evaluate_script("showHelpModal()");
-                } else if ctrl && *key == Key::Character("q") {
-                    *control_flow = ControlFlow::Exit;
-                } else if *key == Key::F5 {
-                    let _ = content.evaluate_script("location.reload()");
-                } else if ctrl && *key == Key::Character("[") {
-                    let _ = content.evaluate_script("history.back()");
-                } else if ctrl && *key == Key::Character("]") {
-                    let _ = content.evaluate_script("history.forward()");
-                } else if *key == Key::Escape {
-                    let _ = sidebar.evaluate_script("closeModals()");
-                }
-            }
-            Event::WindowEvent {
-                event: WindowEvent::Resized(new_size),
-                ..
-            } => {
-                let scale = window.scale_factor();
-                let w = new_size.width as f64 / scale;
-                let h = new_size.height as f64 / scale;
+        let pushed = store_for_sync(&local);
+        assert!(pushed
+            .folders
+            .iter()
+            .flat_map(|f| &f.bookmarks)
+            .all(|bm| bm.thumbnail.is_none()));
+        // A thumbnail alone doesn't count as an unpushed change
+        assert_eq!(store_digest(&local), store_digest(&default_store()));
 
-                if sidebar_collapsed {
-                    let _ = sidebar.set_bounds(make_bounds(0.0, 0.0, 0.0, h));
-                    let _ = content.set_bounds(make_bounds(0.0, 0.0, w, h));
-                } else {
-                    let _ = sidebar.set_bounds(make_bounds(0.0, 0.0, SIDEBAR_WIDTH, h));
-                    let _ =
-                        content.set_bounds(make_bounds(SIDEBAR_WIDTH, 0.0, w - SIDEBAR_WIDTH, h));
-                }
-            }
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
-                ..
-            } => {
-                *control_flow = ControlFlow::Exit;
-            }
-            Event::UserEvent(UserEvent::Navigate(url)) => {
-                let _ = content.load_url(&url);
-            }
-            Event::UserEvent(UserEvent::ToggleSidebar) => {
-                sidebar_collapsed = !sidebar_collapsed;
-                settings.sidebar_collapsed = sidebar_collapsed;
-                let _ = settings.save();
-                let _ =
-                    sidebar.evaluate_script(&format!("setSidebarCollapsed({})", sidebar_collapsed));
-                #[cfg(target_os = "linux")]
-                {
-                    use gtk::prelude::*;
-                    let new_width = if sidebar_collapsed {
-                        STRIP_WIDTH
-                    } else {
-                        SIDEBAR_WIDTH
-                    };
-                    sidebar_gtk_box.set_size_request(new_width as i32, -1);
-                    sidebar_gtk_box.queue_resize();
-                }
-                #[cfg(not(target_os = "linux"))]
-                {
-                    let scale = window.scale_factor();
-                    let inner = window.inner_size();
-                    let w = inner.width as f64 / scale;
-                    let h = inner.height as f64 / scale;
-                    if sidebar_collapsed {
-                        let _ = sidebar.set_bounds(make_bounds(0.0, 0.0, 0.0, h));
-                        let _ = content.set_bounds(make_bounds(0.0, 0.0, w, h));
-                    } else {
-                        let _ = sidebar.set_bounds(make_bounds(0.0, 0.0, SIDEBAR_WIDTH, h));
-                        let _ = content.set_bounds(make_bounds(
-                            SIDEBAR_WIDTH,
-                            0.0,
-                            w - SIDEBAR_WIDTH,
-                            h,
-                        ));
-                    }
-                }
-            }
-            Event::UserEvent(UserEvent::ToggleFolder(index)) => {
-                if let Some(folder) = store.folders.get_mut(index) {
-                    folder.expanded = !folder.expanded;
-                    let _ = store.save();
-                    if let Ok(json) = serde_json::to_string(&store.folders) {
-                        let _ = sidebar.evaluate_script(&format!("renderBookmarks({json})"));
-                    }
-                }
-            }
-            Event::UserEvent(UserEvent::AddFolder(name)) => {
-                store.folders.push(Folder {
-                    name,
-                    expanded: true,
-                    bookmarks: vec![],
-                });
-                let _ = store.save();
-                if let Ok(json) = serde_json::to_string(&store.folders) {
-                    let _ = sidebar.evaluate_script(&format!("renderBookmarks({json})"));
-                }
-                let _ = sync_proxy.send_event(UserEvent::AutoSync);
-            }
-            Event::UserEvent(UserEvent::AddBookmark {
-                folder_index,
-                name,
-                url,
-            }) => {
-                if let Some(folder) = store.folders.get_mut(folder_index) {
-                    folder.bookmarks.push(Bookmark { name, url });
-                    let _ = store.save();
-                    if let Ok(json) = serde_json::to_string(&store.folders) {
-                        let _ = sidebar.evaluate_script(&format!("renderBookmarks({json})"));
-                    }
-                    let _ = sync_proxy.send_event(UserEvent::AutoSync);
-                }
-            }
-            Event::UserEvent(UserEvent::DeleteBookmark {
-                folder_index,
-                bookmark_index,
-            }) => {
-                if let Some(folder) = store.folders.get_mut(folder_index) {
-                    if bookmark_index < folder.bookmarks.len() {
-                        folder.bookmarks.remove(bookmark_index);
-                        let _ = store.save();
-                        if let Ok(json) = serde_json::to_string(&store.folders) {
-                            let _ = sidebar.evaluate_script(&format!("renderBookmarks({json})"));
-                        }
-                        let _ = sync_proxy.send_event(UserEvent::AutoSync);
-                    }
-                }
-            }
-            Event::UserEvent(UserEvent::DeleteFolder(index)) => {
-                if index < store.folders.len() {
-                    store.folders.remove(index);
-                    let _ = store.save();
-                    if let Ok(json) = serde_json::to_string(&store.folders) {
-                        let _ = sidebar.evaluate_script(&format!("renderBookmarks({json})"));
-                    }
-                    let _ = sync_proxy.send_event(UserEvent::AutoSync);
-                }
-            }
-            Event::UserEvent(UserEvent::SaveSettings {
-                github_token,
-                github_repo,
-            }) => {
-                if !github_token.is_empty() {
-                    settings.github_token = github_token;
-                }
-                if settings.github_repo != github_repo {
-                    remote_sha = None;
-                }
-                settings.github_repo = github_repo;
-                let _ = settings.save();
-                let has_token = !settings.github_token.is_empty();
-                let repo = settings.github_repo.replace('\'', "\\'");
-                let _ = sidebar.evaluate_script(&format!("updateSettings({has_token}, '{repo}')"));
-            }
-            Event::UserEvent(UserEvent::PushToGitHub) => {
-                if settings.github_token.is_empty() {
-                    let _ = sidebar
-                        .evaluate_script("updateSyncStatus('No token configured — open Settings')");
-                    return;
-                }
-                if settings.github_repo.is_empty() {
-                    let _ = sidebar
-                        .evaluate_script("updateSyncStatus('No repo configured — open Settings')");
-                    return;
-                }
-                if sync_in_progress {
-                    return;
-                }
-                sync_in_progress = true;
-                let token = settings.github_token.clone();
-                let repo = settings.github_repo.clone();
-                let sha = remote_sha.clone();
-                let bookmarks_json = serde_json::to_string_pretty(&store).unwrap_or_default();
-                let proxy = sync_proxy.clone();
-                let _ = sidebar.evaluate_script("updateSyncStatus('Pushing...')");
-                std::thread::spawn(move || {
-                    match do_push(&token, &repo, &bookmarks_json, sha.as_deref()) {
-                        Ok(new_sha) => {
-                            let _ = proxy.send_event(UserEvent::PushComplete(Some(new_sha)));
-                        }
-                        Err(e) => {
-                            let _ = proxy
-                                .send_event(UserEvent::SyncStatus(format!("Push failed: {e}")));
-                        }
-                    }
-                });
-            }
-            Event::UserEvent(UserEvent::PullFromGitHub) => {
-                if settings.github_token.is_empty() {
-                    let _ = sidebar
-                        .evaluate_script("updateSyncStatus('No token configured — open Settings')");
-                    return;
-                }
-                if settings.github_repo.is_empty() {
-                    let _ = sidebar
-                        .evaluate_script("updateSyncStatus('No repo configured — open Settings')");
-                    return;
-                }
-                if sync_in_progress {
-                    return;
-                }
-                sync_in_progress = true;
-                let token = settings.github_token.clone();
-                let repo = settings.github_repo.clone();
-                let proxy = sync_proxy.clone();
-                let _ = sidebar.evaluate_script("updateSyncStatus('Pulling...')");
-                std::thread::spawn(move || match do_pull(&token, &repo) {
-                    Ok((new_store, sha)) => {
-                        let _ = proxy.send_event(UserEvent::PullComplete(new_store, sha));
-                    }
-                    Err(e) => {
-                        let _ =
-                            proxy.send_event(UserEvent::SyncStatus(format!("Pull failed: {e}")));
-                    }
-                });
-            }
-            Event::UserEvent(UserEvent::SyncStatus(msg)) => {
-                sync_in_progress = false;
-                let escaped = msg.replace('\\', "\\\\").replace('\'', "\\'");
-                let _ = sidebar.evaluate_script(&format!("updateSyncStatus('{escaped}')"));
-            }
-            Event::UserEvent(UserEvent::PushComplete(new_sha)) => {
-                sync_in_progress = false;
-                remote_sha = new_sha;
-                let _ = sidebar.evaluate_script("updateSyncStatus('Pushed successfully')");
-            }
-            Event::UserEvent(UserEvent::PullComplete(new_store, sha)) => {
-                sync_in_progress = false;
-                remote_sha = Some(sha);
-                store = new_store;
-                let _ = store.save();
-                if let Ok(json) = serde_json::to_string(&store.folders) {
-                    let _ = sidebar.evaluate_script(&format!("renderBookmarks({json})"));
-                }
-                let _ = sidebar.evaluate_script("updateSyncStatus('Pulled successfully')");
-            }
-            Event::UserEvent(UserEvent::AutoSync) => {
-                if sync_in_progress
-                    || settings.github_token.is_empty()
-                    || settings.github_repo.is_empty()
-                {
-                    return;
-                }
-                sync_in_progress = true;
-                let token = settings.github_token.clone();
-                let repo = settings.github_repo.clone();
-                let sha = remote_sha.clone();
-                let bookmarks_json = serde_json::to_string_pretty(&store).unwrap_or_default();
-                let proxy = sync_proxy.clone();
-                std::thread::spawn(move || {
-                    match do_push(&token, &repo, &bookmarks_json, sha.as_deref()) {
-                        Ok(new_sha) => {
-                            let _ = proxy.send_event(UserEvent::PushComplete(Some(new_sha)));
-                        }
-                        Err(e) => {
-                            let _ = proxy
-                                .send_event(UserEvent::SyncStatus(format!("Sync failed: {e}")));
-                        }
-                    }
-                });
-            }
-            _ => {}
+        let merged = merge_excluded(pushed, &local);
+        assert_eq!(merged, local);
+    }
+
+    #[test]
+    fn thumbnail_images_are_validated() {
+        let png = format!("data:image/png;base64,{}", BASE64.encode(b"\x89PNG"));
+        assert_eq!(
+            decode_image_data_url(&png),
+            Some((b"\x89PNG".to_vec(), "png"))
+        );
+        assert!(decode_image_data_url("data:text/html;base64,PGI+").is_none());
+        assert!(decode_image_data_url("data:image/png,raw").is_none());
+
+        assert!(is_thumbnail_name("00112233445566ff.jpg"));
+        assert!(!is_thumbnail_name("../settings.json"));
+        assert!(!is_thumbnail_name("00112233445566ff.svg"));
+    }
+
+    #[test]
+    fn new_items_go_to_the_configured_end() {
+        let mut settings = Settings::default();
+        let mut items = vec![1, 2];
+        insert_at_position(&mut items, 3, settings.new_folder_position());
+        assert_eq!(items, [1, 2, 3]);
+
+        settings.new_folder_position = "top".into();
+        insert_at_position(&mut items, 0, settings.new_folder_position());
+        assert_eq!(items, [0, 1, 2, 3]);
+
+        settings.new_bookmark_position = "sideways".into();
+        assert_eq!(settings.new_bookmark_position(), "bottom");
+    }
+
+    #[test]
+    fn sync_api_urls_follow_the_provider() {
+        let github = SyncApi::new("", "https://ignored.example").unwrap();
+        assert_eq!(
+            github.contents_url("me/marks"),
+            "https://api.github.com/repos/me/marks/contents/bookmarks.json"
+        );
+
+        let gitea = SyncApi::new("gitea", " https://git.example.com/ ").unwrap();
+        assert_eq!(gitea.provider(), "gitea");
+        assert_eq!(
+            gitea.contents_url("me/marks"),
+            "https://git.example.com/api/v1/repos/me/marks/contents/bookmarks.json"
+        );
+        assert_eq!(gitea.url("/user"), "https://git.example.com/api/v1/user");
+        assert_eq!(SyncApi::new("github", ""), Ok(SyncApi::GitHub));
+    }
+
+    #[test]
+    fn sync_errors_name_the_provider() {
+        let gitea = SyncApi::Gitea("https://git.example.com".into());
+        assert_eq!(
+            format_ureq_error(&gitea, ureq::Error::StatusCode(401)),
+            "Invalid or expired Gitea token"
+        );
+        assert_eq!(
+            format_ureq_error(&gitea, ureq::Error::StatusCode(500)),
+            "Gitea API error (HTTP 500)"
+        );
+        assert_eq!(
+            format_ureq_error(&SyncApi::GitHub, ureq::Error::HostNotFound),
+            "Could not reach GitHub — check your connection"
+        );
+    }
+
+    #[test]
+    fn unknown_sync_providers_are_refused() {
+        // Never falls back to GitHub, which would receive the other host's token
+        assert!(SyncApi::new("gitlab", "https://gitlab.example.com").is_err());
+        assert!(SyncApi::new("GitHub ", "").is_err());
+
+        let settings: Settings = serde_json::from_str(
+            r#"{"sync_provider": "gitlab", "sync_mirrors": [
+                {"provider": "gitlab", "repo": "me/marks", "token": "t"}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(settings.sync_api().is_err());
+        let mirrors = settings.sync_mirrors();
+        assert!(mirrors[0].api().is_err());
+        let failures = push_mirrors(&mirrors, "{}", Duration::from_secs(1));
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].starts_with("me/marks (gitlab): Unknown sync provider"));
+    }
+
+    #[test]
+    fn webview_error_message_includes_the_cause() {
+        let message = webview_error_message("Couldn't find X11 Display");
+        assert!(message.starts_with("Could not start the web view: Couldn't find X11 Display"));
+        if cfg!(target_os = "linux") {
+            assert!(message.contains("webkit2gtk"));
         }
-    });
-}
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::env;
+    #[test]
+    fn startup_bookmarks_in_sidebar_order() {
+        let mut store = default_store();
+        assert!(startup_urls(&store).is_empty());
+
+        store.folders[1].bookmarks[0].open_on_startup = true;
+        store.folders[0].bookmarks[1].open_on_startup = true;
+        assert_eq!(
+            startup_urls(&store),
+            [
+                "https://wiki.archlinux.org/",
+                "https://news.ycombinator.com/"
+            ]
+        );
+
+        // Unflagged bookmarks don't grow an open_on_startup key on disk
+        let json = serde_json::to_string(&default_store()).unwrap();
+        assert!(!json.contains("open_on_startup"));
+    }
 
     #[test]
-    fn roundtrip_save_load() {
-        let dir = env::temp_dir().join("bookmarks-browser-test");
-        let path = dir.join("bookmarks.json");
+    fn open_external_refuses_non_web_urls() {
+        assert!(open_external("file:///etc/passwd").is_err());
+        assert!(open_external("javascript:alert(1)").is_err());
+    }
+
+    #[test]
+    fn host_of_handles_odd_urls() {
+        assert_eq!(
+            host_of("https://User:pw@WWW.Example.COM.:8443/a?b#c"),
+            Some("www.example.com".to_string())
+        );
+        assert_eq!(host_of("http://example.com"), Some("example.com".into()));
+        assert_eq!(
+            host_of("http://example.com?q=1"),
+            Some("example.com".into())
+        );
+        assert_eq!(host_of("http://[::1]:8080/"), Some("[::1]".into()));
+        assert_eq!(host_of("file:///home/me/page.html"), None);
+        assert_eq!(host_of("about:blank"), None);
+        assert_eq!(host_of("example.com/path"), None);
+    }
+
+    #[test]
+    fn zoom_steps_clamp_and_reset() {
+        assert_eq!(step_zoom(1.0, 1), 1.1);
+        assert_eq!(step_zoom(1.1, -1), 1.0);
+        assert_eq!(step_zoom(MAX_ZOOM, 1), MAX_ZOOM);
+        assert_eq!(step_zoom(MIN_ZOOM, -1), MIN_ZOOM);
+        assert_eq!(step_zoom(2.5, 0), 1.0);
+    }
+
+    #[test]
+    fn streaks_count_consecutive_days() {
+        let today = day_number("2024-03-01").unwrap();
+        assert_eq!(day_number("1970-01-01"), Some(0));
+        assert_eq!(today - day_number("2024-02-28").unwrap(), 2);
+        assert_eq!(day_number("2024-13-01"), None);
+
+        let dates = |list: &[&str]| list.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+        assert_eq!(current_streak(&[], today), 0);
+        assert_eq!(
+            current_streak(&dates(&["2024-02-28", "2024-02-29", "2024-03-01"]), today),
+            3
+        );
+        // A gap ends the streak
+        assert_eq!(
+            current_streak(
+                &dates(&["2024-02-26", "2024-02-28", "2024-02-29", "2024-03-01"]),
+                today
+            ),
+            3
+        );
+        // Not visited yet today: yesterday's streak still stands
+        assert_eq!(
+            current_streak(&dates(&["2024-02-28", "2024-02-29"]), today),
+            2
+        );
+        assert_eq!(
+            current_streak(&dates(&["2024-02-27", "2024-02-28"]), today),
+            0
+        );
+
+        // Several visits on one day are recorded once
+        let mut visits = VisitMap::new();
+        assert!(record_visit(
+            &mut visits,
+            "https://a.example/",
+            "2024-03-01"
+        ));
+        assert!(!record_visit(
+            &mut visits,
+            "https://a.example/",
+            "2024-03-01"
+        ));
+        assert_eq!(current_streak(&visits["https://a.example/"], today), 1);
+    }
+
+    #[test]
+    fn folder_sort_orders() {
+        let folder = |name: &str, count: usize, expanded: bool| Folder {
+            name: name.to_string(),
+            expanded,
+            icon: None,
+            exclude_from_sync: false,
+            locked: false,
+            pin: None,
+            bookmarks: (0..count)
+                .map(|i| Bookmark {
+                    name: format!("{name} {i}"),
+                    url: format!("https://example.com/{name}/{i}"),
+                    ..Default::default()
+                })
+                .collect(),
+        };
+        let names = |folders: &[Folder]| -> Vec<String> {
+            folders
+                .iter()
+                .map(|f| f.bookmarks.len().to_string() + &f.name)
+                .collect()
+        };
+        let original = vec![
+            folder("news", 1, true),
+            folder("Docs", 3, false),
+            folder("docs", 2, true),
+            folder("Art", 2, true),
+        ];
+
+        let mut folders = original.clone();
+        sort_folders(&mut folders, SortMode::NameAsc);
+        // Case-insensitive, and same-named folders keep their order
+        assert_eq!(names(&folders), ["2Art", "3Docs", "2docs", "1news"]);
+        assert!(!folders[1].expanded);
+
+        sort_folders(&mut folders, SortMode::NameDesc);
+        assert_eq!(names(&folders), ["1news", "3Docs", "2docs", "2Art"]);
+
+        let mut folders = original;
+        sort_folders(&mut folders, SortMode::BookmarkCount);
+        assert_eq!(names(&folders), ["3Docs", "2docs", "2Art", "1news"]);
+    }
+
+    #[test]
+    fn experiments_merge_env_and_settings() {
+        let configured = vec!["Tabs".to_string(), " ".to_string()];
+        let set = parse_experiments(Some(" split_view, ,GALLERY_V2,"), &configured);
+        assert_eq!(
+            set.into_iter().collect::<Vec<_>>(),
+            ["gallery_v2", "split_view", "tabs"]
+        );
+        assert!(parse_experiments(None, &[]).is_empty());
+    }
+
+    #[test]
+    fn omnibar_bookmarks_is_gated_by_its_experiment() {
+        let set = parse_experiments(Some("Omnibar_Bookmarks"), &[]);
+        assert!(experiment_in(&set, OMNIBAR_BOOKMARKS_EXPERIMENT));
+        assert!(!experiment_in(
+            &parse_experiments(Some("tabs"), &[]),
+            OMNIBAR_BOOKMARKS_EXPERIMENT
+        ));
+
+        let mut store = default_store();
+        assert_eq!(
+            omnibar_bookmark(&store, " arch wiki "),
+            Some("https://wiki.archlinux.org/".to_string())
+        );
+        assert_eq!(omnibar_bookmark(&store, "arch"), None);
+        store.folders[0].locked = true;
+        assert_eq!(omnibar_bookmark(&store, "Arch Wiki"), None);
+    }
+
+    #[test]
+    fn merge_stores_keeps_both_sides() {
+        let folder = |name: &str, expanded: bool, bookmarks: Vec<Bookmark>| Folder {
+            name: name.to_string(),
+            expanded,
+            icon: None,
+            exclude_from_sync: false,
+            locked: false,
+            pin: None,
+            bookmarks,
+        };
+        let local = BookmarkStore {
+            folders: vec![
+                folder(
+                    "Docs",
+                    false,
+                    vec![
+                        bookmark("Rust", "https://rust-lang.org/"),
+                        bookmark("Local", "https://local.example/"),
+                    ],
+                ),
+                folder("New", true, vec![bookmark("N", "https://n.example/")]),
+            ],
+        };
+        let remote = BookmarkStore {
+            folders: vec![
+                folder("Remote", true, vec![bookmark("R", "https://r.example/")]),
+                folder(
+                    "Docs",
+                    true,
+                    vec![
+                        bookmark("Rust lang", "https://RUST-lang.org"),
+                        bookmark("Theirs", "https://theirs.example/"),
+                    ],
+                ),
+            ],
+        };
+
+        let merged = merge_stores(&local, remote);
+        let names: Vec<&str> = merged.folders.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["Remote", "Docs", "New"]);
+        let docs: Vec<&str> = merged.folders[1]
+            .bookmarks
+            .iter()
+            .map(|bm| bm.name.as_str())
+            .collect();
+        // The remote copy of a shared URL wins; local-only ones are appended
+        assert_eq!(docs, ["Rust lang", "Theirs", "Local"]);
+        assert!(!merged.folders[1].expanded);
+
+        // Merging again changes nothing
+        assert_eq!(merge_stores(&local, merged.clone()), merged);
+    }
+
+    #[test]
+    fn folder_markdown_escapes_names_and_urls() {
+        let folder = Folder {
+            name: "Reading".to_string(),
+            expanded: true,
+            icon: None,
+            exclude_from_sync: false,
+            locked: false,
+            pin: None,
+            bookmarks: vec![
+                bookmark("Rust", "https://rust-lang.org/"),
+                bookmark(
+                    "[Draft] a\\b (v2)",
+                    "https://en.wikipedia.org/wiki/Rust_(language) x",
+                ),
+            ],
+        };
+        assert_eq!(
+            folder_to_markdown(&folder),
+            "- [Rust](https://rust-lang.org/)\n\
+             - [\\[Draft\\] a\\\\b (v2)](https://en.wikipedia.org/wiki/Rust_%28language%29%20x)\n"
+        );
+        assert_eq!(
+            folder_to_markdown(&Folder {
+                bookmarks: vec![],
+                ..folder
+            }),
+            ""
+        );
+    }
+
+    #[test]
+    fn current_page_matches_bookmarks_loosely() {
+        let store = default_store();
+        let book = Some("https://doc.rust-lang.org/book/".to_string());
+        assert_eq!(
+            bookmark_url_for(&store, "https://doc.rust-lang.org/book/"),
+            book
+        );
+        assert_eq!(
+            bookmark_url_for(&store, "https://DOC.rust-lang.org/book#intro"),
+            book
+        );
+        assert_eq!(
+            bookmark_url_for(&store, "https://doc.rust-lang.org/book/ch01"),
+            None
+        );
+        assert_eq!(
+            bookmark_url_for(&store, "https://not-bookmarked.example/"),
+            None
+        );
+    }
+
+    #[test]
+    fn folder_suggestion_follows_the_site() {
+        let folder = |name: &str, urls: &[&str]| Folder {
+            name: name.to_string(),
+            expanded: true,
+            icon: None,
+            exclude_from_sync: false,
+            locked: false,
+            pin: None,
+            bookmarks: urls.iter().map(|u| bookmark(u, u)).collect(),
+        };
+        let store = BookmarkStore {
+            folders: vec![
+                folder("Misc", &["https://github.com/a", "https://news.example/"]),
+                folder(
+                    "Dev",
+                    &[
+                        "https://github.com/b",
+                        "https://www.github.com/c",
+                        "https://docs.rs/",
+                    ],
+                ),
+                folder("News", &["https://news.example/x"]),
+                folder("More news", &["https://NEWS.example/y"]),
+            ],
+        };
+        assert_eq!(
+            suggest_folder(&store, "https://github.com/new/repo"),
+            Some(1)
+        );
+        assert_eq!(suggest_folder(&store, "https://www.GitHub.com/"), Some(1));
+        // Misc, News and More news have one each; the first wins
+        assert_eq!(suggest_folder(&store, "http://news.example/today"), Some(0));
+        assert_eq!(suggest_folder(&store, "https://gist.github.com/x"), None);
+        assert_eq!(suggest_folder(&store, "not a url"), None);
+    }
+
+    #[test]
+    fn history_pruning_keeps_the_cutoff_day() {
+        let cutoff = day_number("2026-07-18").unwrap();
+        let mut visits = VisitMap::new();
+        visits.insert(
+            "https://a.example/".into(),
+            vec![
+                "2026-07-17".into(),
+                "2026-07-18".into(),
+                "2026-10-16".into(),
+            ],
+        );
+        visits.insert("https://old.example/".into(), vec!["2025-01-01".into()]);
+        visits.insert("https://bad.example/".into(), vec!["yesterday".into()]);
+
+        assert!(prune_history(&mut visits, cutoff));
+        assert_eq!(visits.len(), 1);
+        assert_eq!(visits["https://a.example/"], ["2026-07-18", "2026-10-16"]);
+        assert!(!prune_history(&mut visits, cutoff));
+
+        assert_eq!(Settings::default().history_retention_days(), 90);
+    }
+
+    #[test]
+    fn standalone_html_escapes_and_only_links_web_urls() {
+        let mut store = default_store();
+        store.folders[0].name = "<Docs & \"more\">".to_string();
+        store.folders[0].bookmarks[0].name = "<script>alert(1)</script>".to_string();
+        store.folders[0].bookmarks[1].url = "javascript:alert(1)".to_string();
+        let html = to_standalone_html(&store, "light");
+
+        assert!(html.contains("<h2>&lt;Docs &amp; &quot;more&quot;&gt;</h2>"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("href=\"javascript:"));
+        assert!(html.contains("<a href=\"https://doc.rust-lang.org/book/\">"));
+        assert!(html.contains("#eff1f5"));
+    }
+
+    #[test]
+    fn move_item_shifts_and_guards_the_edges() {
+        let mut items = vec!['a', 'b', 'c', 'd'];
+        assert!(move_item(&mut items, 1, 0));
+        assert_eq!(items, ['b', 'a', 'c', 'd']);
+        assert!(move_item(&mut items, 0, 3));
+        assert_eq!(items, ['a', 'c', 'd', 'b']);
+        assert!(!move_item(&mut items, 2, 2));
+        assert!(!move_item(&mut items, 3, 4));
+        assert!(!move_item(&mut items, 4, 0));
+        assert_eq!(items, ['a', 'c', 'd', 'b']);
+    }
+
+    #[test]
+    fn bookmarks_move_within_and_between_folders() {
+        let mut folders = default_store().folders;
+        let names = |folders: &[Folder], fi: usize| -> Vec<String> {
+            folders[fi]
+                .bookmarks
+                .iter()
+                .map(|bm| bm.name.clone())
+                .collect()
+        };
+        let (rust, arch) = (names(&folders, 0)[0].clone(), names(&folders, 0)[1].clone());
+        assert!(move_bookmark(&mut folders, (0, 0), (0, 1)));
+        assert_eq!(names(&folders, 0), [arch.clone(), rust.clone()]);
+
+        let end = folders[1].bookmarks.len();
+        assert!(move_bookmark(&mut folders, (0, 1), (1, end)));
+        assert_eq!(names(&folders, 0), [arch]);
+        assert_eq!(names(&folders, 1).last(), Some(&rust));
+
+        assert!(!move_bookmark(&mut folders, (0, 0), (0, 0)));
+        assert!(!move_bookmark(&mut folders, (0, 5), (1, 0)));
+        assert!(!move_bookmark(&mut folders, (0, 0), (1, 99)));
+        assert!(!move_bookmark(&mut folders, (0, 0), (9, 0)));
+        assert_eq!(folders[0].bookmarks.len(), 1);
+    }
+
+    #[test]
+    fn reset_backup_survives_rotation() {
+        let dir = env::temp_dir().join("bookmarks-browser-reset-backup-test");
+        let _ = fs::remove_dir_all(&dir);
+        let json = serde_json::to_string_pretty(&default_store()).unwrap();
+
+        let path = write_reset_backup(&dir, &default_store()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), json);
+        write_backup(&dir, "{}", 1).unwrap();
+        rotate_backups(&dir, 0).unwrap();
+        assert!(path.exists());
+        // Importable the usual way
+        assert!(parse_import(&json).is_ok());
 
-        // Clean up from any previous run
         let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn mirrors_need_repo_and_token_and_report_per_target() {
+        let mut settings: Settings = serde_json::from_str(
+            r#"{"sync_mirrors": [
+                {"provider": "gitea", "base_url": "https://git.example.com/", "repo": "me/marks", "token": "t"},
+                {"repo": "me/marks"},
+                {"repo": " ", "token": "t"}
+            ]}"#,
+        )
+        .unwrap();
+        let mirrors = settings.sync_mirrors();
+        assert_eq!(mirrors.len(), 1);
+        assert_eq!(
+            mirrors[0].api(),
+            Ok(SyncApi::Gitea("https://git.example.com".into()))
+        );
+        settings.sync_mirrors.clear();
+        assert!(!serde_json::to_string(&settings)
+            .unwrap()
+            .contains("sync_mirrors"));
+
+        assert_eq!(push_summary(2, &[]), "Pushed to 2/2 targets");
+        assert_eq!(
+            push_summary(
+                3,
+                &["me/marks (gitea): Request timed out — try again".into()]
+            ),
+            "Pushed to 2/3 targets — me/marks (gitea): Request timed out — try again"
+        );
+    }
+
+    #[test]
+    fn no_op_edits_keep_the_modified_stamp() {
+        let mut bm = Bookmark {
+            name: "Rust".into(),
+            url: "https://rust-lang.org/".into(),
+            modified: Some("2026-01-01 00:00 UTC".into()),
+            ..Default::default()
+        };
+        let now = "2026-10-16 09:30 UTC";
+        assert!(!edit_bookmark(
+            &mut bm,
+            " Rust ",
+            "https://rust-lang.org/",
+            now
+        ));
+        assert!(!edit_bookmark(&mut bm, "", "https://x.example/", now));
+        assert_eq!(bm.modified.as_deref(), Some("2026-01-01 00:00 UTC"));
+
+        assert!(edit_bookmark(
+            &mut bm,
+            "Rust home",
+            "https://rust-lang.org/",
+            now
+        ));
+        assert_eq!(bm.name, "Rust home");
+        assert_eq!(bm.modified.as_deref(), Some(now));
+        assert!(bm.name_is_custom);
+    }
+
+    #[test]
+    fn remote_content_follows_the_encoding() {
+        let response = |json: &str| serde_json::from_str::<serde_json::Value>(json).unwrap();
+        assert_eq!(
+            remote_content(&response(r#"{"encoding": "base64", "content": "e30=\n"}"#)),
+            Ok(RemoteContent::Inline("{}".into()))
+        );
+        // Older responses may leave the encoding out
+        assert_eq!(
+            remote_content(&response(r#"{"content": "e30="}"#)),
+            Ok(RemoteContent::Inline("{}".into()))
+        );
+        assert_eq!(
+            remote_content(&response(
+                r#"{"encoding": "none", "content": "", "download_url": "https://raw.example/b.json"}"#
+            )),
+            Ok(RemoteContent::Download("https://raw.example/b.json".into()))
+        );
+        assert_eq!(
+            remote_content(&response(r#"{"encoding": "none", "content": ""}"#)),
+            Err(
+                "bookmarks.json is too large to be sent inline and no download URL was given"
+                    .into()
+            )
+        );
+        assert_eq!(
+            remote_content(&response(r#"{"encoding": "utf-16", "content": "x"}"#)),
+            Err("Unsupported content encoding \"utf-16\" for bookmarks.json".into())
+        );
+    }
+
+    #[test]
+    fn pins_verify_only_against_their_own_salt() {
+        let salt = new_pin_salt();
+        assert_eq!(salt.len(), 32);
+        assert_ne!(salt, new_pin_salt());
+        let hash = hash_pin(&salt, "2468");
+        assert!(!hash.contains("2468"));
+        assert!(verify_pin(&hash, &salt, "2468"));
+        assert!(!verify_pin(&hash, &salt, "2469"));
+        assert!(!verify_pin(&hash, &salt, ""));
+        assert!(!verify_pin(&hash, &new_pin_salt(), "2468"));
+        assert!(!verify_pin("", &salt, "2468"));
+    }
+
+    #[test]
+    fn folders_without_a_lock_load_unlocked() {
+        let folder: Folder = serde_json::from_str(r#"{"name":"Finance","bookmarks":[]}"#).unwrap();
+        assert!(!folder.locked);
+        assert_eq!(folder.pin, None);
+        let json = serde_json::to_string(&folder).unwrap();
+        assert!(!json.contains("pin"));
+        assert!(!json.contains("locked"));
+    }
+
+    #[test]
+    fn folder_locks_stay_local() {
+        let mut local = default_store();
+        local.folders[0].locked = true;
+        local.folders[0].pin = Some(PinHash {
+            hash: hash_pin("00ff", "2468"),
+            salt: "00ff".into(),
+        });
+
+        let pushed = store_for_sync(&local);
+        let json = sync_json(&local, false);
+        assert!(!json.contains("pin") && !json.contains("locked"));
+        assert!(pushed.folders.iter().all(|f| !f.locked && f.pin.is_none()));
+
+        let merged = merge_excluded(pushed, &local);
+        assert_eq!(merged, local);
+    }
+
+    #[test]
+    fn edit_during_pull_survives() {
+        let mut local = default_store();
+        let requested = store_digest(&local);
+        let mut remote = default_store();
+        remote.folders[1]
+            .bookmarks
+            .push(bookmark("Lobsters", "https://lobste.rs/"));
 
+        // Nothing changed meanwhile: the pull applies
+        assert_eq!(
+            apply_pull(&local, remote.clone(), requested),
+            Some(remote.clone())
+        );
+
+        // Renamed while the pull was in flight: the rename is kept
+        local.folders[0].bookmarks[0].name = "The Book".into();
+        assert_eq!(apply_pull(&local, remote.clone(), requested), None);
+
+        // A collapsed folder isn't synced, so it doesn't hold the pull back
+        let mut collapsed = default_store();
+        collapsed.folders[0].expanded = false;
+        assert!(apply_pull(&collapsed, remote, requested).is_some());
+    }
+
+    #[test]
+    fn restore_deleted_returns_items_to_their_place() {
+        let mut store = default_store();
+        let original = store.clone();
+
+        let bookmark = store.folders[0].bookmarks.remove(1);
+        let deleted = Deleted::Bookmark {
+            folder_index: 0,
+            folder_name: store.folders[0].name.clone(),
+            index: 1,
+            bookmark: bookmark.clone(),
+        };
+        // The folder moved meanwhile; it is found by name
+        store.folders.swap(0, 1);
+        restore_deleted(&mut store, deleted.clone());
+        store.folders.swap(0, 1);
+        assert_eq!(store, original);
+
+        let folder = store.folders.remove(0);
+        restore_deleted(
+            &mut store,
+            Deleted::Folder {
+                index: 5,
+                folder: folder.clone(),
+            },
+        );
+        assert_eq!(store.folders.last(), Some(&folder));
+
+        // With its folder gone the bookmark comes back in a recreated one
+        store.folders.pop();
+        restore_deleted(&mut store, deleted);
+        let recreated = store.folders.last().unwrap();
+        assert_eq!(recreated.name, folder.name);
+        assert_eq!(recreated.bookmarks, vec![bookmark]);
+    }
+
+    #[test]
+    fn import_skips_duplicates_and_unusable_entries() {
+        let mut folders = default_store().folders;
+        folders[1]
+            .bookmarks
+            .push(bookmark("HN again", "https://NEWS.ycombinator.com/#top"));
+        folders[1]
+            .bookmarks
+            .push(bookmark("Dark mode", "javascript:void(0)"));
+        let existing: HashMap<String, String> = folders[0]
+            .bookmarks
+            .iter()
+            .map(|bm| (normalize_url(&bm.url), bm.name.clone()))
+            .collect();
+        let mut updates = Vec::new();
+        let (kept, report) = filter_import(
+            folders,
+            &existing,
+            DuplicateStrategy::Skip,
+            |done, total| updates.push((done, total)),
+        );
+        // Everything in the first folder was already there, so it's dropped
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].name, "News");
+        assert_eq!(kept[0].bookmarks.len(), 1);
+        assert_eq!(report.added, 1);
+        assert_eq!(report.duplicates, 3);
+        assert_eq!(report.skipped, ["\"Dark mode\" is a bookmarklet"]);
+        assert_eq!(updates.last(), Some(&(5, 5)));
+        assert_eq!(
+            report.summary(),
+            "Imported 1 bookmarks, 3 duplicates left out, 1 skipped (\"Dark mode\" is a bookmarklet)"
+        );
+    }
+
+    #[test]
+    fn duplicate_strategies_on_import() {
+        let mut store = default_store();
+        let existing: HashMap<String, String> = store
+            .folders
+            .iter()
+            .flat_map(|f| &f.bookmarks)
+            .map(|bm| (normalize_url(&bm.url), bm.name.clone()))
+            .collect();
+        // One URL already saved (under another name), one new
+        let mut incoming = default_store().folders[..1].to_vec();
+        incoming[0].bookmarks[0].name = "Rust Book".into();
+        incoming[0].bookmarks[1].url = "https://new.example/".into();
+        let run = |strategy| filter_import(incoming.clone(), &existing, strategy, |_, _| {});
+
+        let (kept, report) = run(DuplicateStrategy::Skip);
+        assert_eq!(kept[0].bookmarks.len(), 1);
+        assert_eq!(
+            (report.added, report.duplicates, report.kept_both),
+            (1, 1, 0)
+        );
+        assert!(report.renames.is_empty());
+
+        let (kept, report) = run(DuplicateStrategy::KeepBoth);
+        assert_eq!(kept[0].bookmarks.len(), 2);
+        assert_eq!(
+            (report.added, report.duplicates, report.kept_both),
+            (2, 0, 1)
+        );
+
+        let (kept, report) = run(DuplicateStrategy::UpdateName);
+        assert_eq!(kept[0].bookmarks.len(), 1);
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(apply_import_renames(&mut store, &report.renames, "now"), 1);
+        assert_eq!(store.folders[0].bookmarks[0].name, "Rust Book");
+        assert_eq!(
+            report.summary(),
+            "Imported 1 bookmarks, 1 duplicates left out, 1 names updated"
+        );
+
+        assert_eq!(
+            Settings::default().duplicate_strategy(),
+            DuplicateStrategy::Skip
+        );
+    }
+
+    #[test]
+    fn home_url_is_validated_on_save() {
+        assert_eq!(validate_home_url("  ").unwrap(), "");
+        assert_eq!(
+            validate_home_url(" start.example/dash ").unwrap(),
+            "https://start.example/dash"
+        );
+        assert_eq!(
+            validate_home_url("http://localhost:3000").unwrap(),
+            "http://localhost:3000"
+        );
+        assert!(validate_home_url("file:///etc/passwd").is_err());
+        assert!(validate_home_url("javascript:alert(1)").is_err());
+        assert!(validate_home_url("mailto:me@example.com").is_err());
+        assert_eq!(
+            validate_home_url("localhost:3000/").unwrap(),
+            "https://localhost:3000/"
+        );
+        assert!(validate_home_url("https://").is_err());
+        assert!(validate_home_url("my dashboard").is_err());
+
+        let mut settings = Settings::default();
+        assert_eq!(settings.home_url(), None);
+        settings.home_url = "https://start.example/".into();
+        assert_eq!(settings.home_url(), Some("https://start.example/"));
+    }
+
+    #[test]
+    fn expanded_state_stays_local() {
+        let mut local = default_store();
+        local.folders[1].expanded = false;
+
+        let saved = serde_json::to_string(&local).unwrap();
+        assert_eq!(saved.matches("\"expanded\"").count(), 1);
+        assert_eq!(
+            serde_json::from_str::<BookmarkStore>(&saved).unwrap(),
+            local
+        );
+
+        // Collapsing doesn't change what gets pushed, and old remote files
+        // lose the flag on the next push
+        let pushed = serde_json::to_string(&store_for_sync(&local)).unwrap();
+        assert!(!pushed.contains("expanded"));
+        assert_eq!(store_digest(&local), store_digest(&default_store()));
+
+        let pulled = merge_excluded(serde_json::from_str(&pushed).unwrap(), &local);
+        assert_eq!(pulled, local);
+    }
+
+    #[test]
+    fn validation_reports_each_kind_of_issue() {
+        let mut store = default_store();
+        assert!(validate_store(&store).is_empty());
+
+        let kinds = |store: &BookmarkStore| -> Vec<(IssueKind, usize, Option<usize>)> {
+            validate_store(store)
+                .into_iter()
+                .map(|issue| (issue.kind, issue.folder_index, issue.bookmark_index))
+                .collect()
+        };
+        let docs = &mut store.folders[0];
+        docs.name = " Documentation ".into();
+        docs.locked = true;
+        docs.bookmarks[0].name = "   ".into();
+        docs.bookmarks[1].url = " https://wiki.archlinux.org/ ".into();
+        docs.bookmarks[1].thumbnail = Some("../../etc/passwd".into());
+        let news = &mut store.folders[1];
+        news.name = "Documentation".into();
+        news.bookmarks[0].url = "https://".into();
+        news.bookmarks
+            .push(bookmark("Book again", "https://DOC.rust-lang.org/book"));
+        news.bookmarks
+            .push(bookmark("Mail", "mailto:me@example.com"));
+
+        assert_eq!(
+            kinds(&store),
+            [
+                (IssueKind::UntrimmedName, 0, None),
+                (IssueKind::OrphanedLock, 0, None),
+                (IssueKind::EmptyName, 0, Some(0)),
+                (IssueKind::UntrimmedUrl, 0, Some(1)),
+                (IssueKind::OrphanedThumbnail, 0, Some(1)),
+                (IssueKind::DuplicateFolderName, 1, None),
+                (IssueKind::MalformedUrl, 1, Some(0)),
+                (IssueKind::DuplicateUrl, 1, Some(1)),
+            ]
+        );
+
+        // Safe fixes leave only what needs a decision
+        assert_eq!(fix_store(&mut store), 4);
+        assert_eq!(store.folders[0].name, "Documentation");
+        assert_eq!(
+            store.folders[0].bookmarks[1].url,
+            "https://wiki.archlinux.org/"
+        );
+        assert!(validate_store(&store).iter().all(|issue| !issue.fixable));
+        assert_eq!(fix_store(&mut store), 0);
+    }
+
+    #[test]
+    fn page_titles_are_extracted_and_tidied() {
+        let html = "<html><head><meta charset=utf-8><TITLE lang=en>\n  Rust &amp; Cargo\n\t Docs </TITLE></head>";
+        assert_eq!(extract_title(html).as_deref(), Some("Rust & Cargo Docs"));
+        assert_eq!(
+            extract_title("<titlebar>x</titlebar><title>Real</title>").as_deref(),
+            Some("Real")
+        );
+        assert_eq!(extract_title("<title>   </title>"), None);
+        assert_eq!(extract_title("<p>no title</p>"), None);
+    }
+
+    #[test]
+    fn title_refresh_keeps_custom_names() {
+        let mut store = default_store();
+        store.folders[0].bookmarks[1].name_is_custom = true;
+        let titles: HashMap<String, String> = store.folders[0]
+            .bookmarks
+            .iter()
+            .map(|bm| (bm.url.clone(), format!("{} (new)", bm.name)))
+            .collect();
+        let changes = title_changes(&store, &titles);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].new, "The Rust Programming Language (new)");
+
+        // A rename made after the preview wins over the fetched title
+        let mut renamed = store.clone();
+        renamed.folders[0].bookmarks[0].name = "The Book".into();
+        assert_eq!(apply_title_changes(&mut renamed, &changes, "now"), 0);
+
+        assert_eq!(apply_title_changes(&mut store, &changes, "now"), 1);
+        let bm = &store.folders[0].bookmarks[0];
+        assert_eq!(bm.name, "The Rust Programming Language (new)");
+        assert!(!bm.name_is_custom);
+        assert_eq!(store.folders[0].bookmarks[1].name, "Arch Wiki");
+    }
+
+    #[test]
+    fn reference_frame_stays_in_the_content_corner() {
+        assert_eq!(
+            pip_rect(SIDEBAR_WIDTH, 1000.0, 800.0),
+            (
+                SIDEBAR_WIDTH + 1000.0 - PIP_WIDTH - PIP_MARGIN,
+                800.0 - PIP_HEIGHT - PIP_MARGIN,
+                PIP_WIDTH,
+                PIP_HEIGHT
+            )
+        );
+        // A small window halves the frame instead of covering the page
+        let (x, y, width, height) = pip_rect(0.0, 400.0, 300.0);
+        assert_eq!((width, height), (200.0, 150.0));
+        assert_eq!(
+            (x, y),
+            (400.0 - 200.0 - PIP_MARGIN, 300.0 - 150.0 - PIP_MARGIN)
+        );
+    }
+
+    #[test]
+    fn injections_stay_on_the_bookmarks_own_page() {
+        let mut store = default_store();
+        let bm = &mut store.folders[0].bookmarks[0];
+        bm.url = "https://example.com/app/".into();
+        set_injection(bm, Some("body { color: red }"), Some("run()"));
+        assert_eq!(
+            injection_scripts(bm, "https://EXAMPLE.com/app#top").len(),
+            2
+        );
+        // Redirected elsewhere after the click: nothing runs there
+        assert!(injection_scripts(bm, "https://evil.example.net/app/").is_empty());
+        assert!(injection_scripts(bm, "https://example.com/login").is_empty());
+    }
+
+    #[test]
+    fn injections_never_leave_or_enter_by_sync() {
+        let mut local = default_store();
+        let bm = &mut local.folders[0].bookmarks[0];
+        assert!(set_injection(bm, Some(" body { color: red } "), Some("  ")));
+        assert_eq!(bm.inject_css.as_deref(), Some("body { color: red }"));
+        assert_eq!(bm.inject_js, None);
+        assert!(!set_injection(bm, Some("body { color: red }"), None));
+        assert_eq!(injection_scripts(bm, &bm.url).len(), 1);
+
+        let mut pushed = store_for_sync(&local);
+        assert!(pushed.folders[0].bookmarks[0].inject_css.is_none());
+        // A script arriving from the remote is dropped; the local CSS stays
+        pushed.folders[0].bookmarks[1].inject_js = Some("steal()".into());
+        let merged = merge_excluded(pushed, &local);
+        assert_eq!(merged, local);
+    }
+
+    #[test]
+    fn batch_tagging_skips_bookmarks_already_in_that_state() {
+        let mut store = default_store();
+        store.folders[0].bookmarks[0].tags = vec!["Rust".into(), "rust ".into()];
+        let targets = [(0, 0), (0, 1), (9, 9)];
+
+        // Already tagged (in another case): only the other bookmark changes,
+        // though the repeat tag is tidied away along the way
+        let (first, later) = ("2026-10-01 08:00 UTC", "2026-10-02 09:00 UTC");
+        let modified = |store: &BookmarkStore| {
+            store.folders[0]
+                .bookmarks
+                .iter()
+                .map(|bm| bm.modified.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            tag_bookmarks(&mut store, &targets, " rust ", true, first),
+            2
+        );
+        assert_eq!(store.folders[0].bookmarks[0].tags, ["Rust"]);
+        assert_eq!(store.folders[0].bookmarks[1].tags, ["rust"]);
+        assert_eq!(modified(&store), [Some(first.into()), Some(first.into())]);
+        assert_eq!(tag_bookmarks(&mut store, &targets, "RUST", true, later), 0);
+
+        // Removing a tag nobody has is a no-op, and leaves the stamps alone
+        assert_eq!(tag_bookmarks(&mut store, &targets, "docs", false, later), 0);
+        assert_eq!(modified(&store), [Some(first.into()), Some(first.into())]);
+        assert_eq!(tag_bookmarks(&mut store, &targets, "rust", false, later), 2);
+        assert_eq!(modified(&store), [Some(later.into()), Some(later.into())]);
+        assert!(store.folders[0]
+            .bookmarks
+            .iter()
+            .all(|bm| bm.tags.is_empty()));
+        assert_eq!(tag_bookmarks(&mut store, &targets, "   ", true, later), 0);
+    }
+
+    #[test]
+    fn pull_changes_are_summed_up() {
+        let before = default_store();
+        assert_eq!(diff_stores(&before, &before).summary(), "no changes");
+
+        let mut after = before.clone();
+        after.folders[0].name = "Docs".into();
+        after.folders[1].bookmarks[0].name = "HN".into();
+        let moved = after.folders[0].bookmarks.pop().unwrap();
+        after.folders[1].bookmarks.push(moved);
+        after.folders[1].bookmarks.push(Bookmark {
+            url: "https://new.example/".into(),
+            ..after.folders[1].bookmarks[0].clone()
+        });
+        let diff = diff_stores(&before, &after);
+        // "Documentation" lost a bookmark, so it reads as removed and re-added
+        assert_eq!(diff.bookmarks_added, 1);
+        assert_eq!(diff.bookmarks_renamed, 1);
+        assert_eq!(diff.bookmarks_moved, 2);
+
+        let mut renamed = before.clone();
+        renamed.folders[0].name = "Docs".into();
+        assert_eq!(diff_stores(&before, &renamed).summary(), "1 folder renamed");
+        let mut trimmed = before.clone();
+        trimmed.folders.remove(1);
+        assert_eq!(
+            diff_stores(&before, &trimmed).summary(),
+            "1 bookmark removed, 1 folder removed"
+        );
+    }
+
+    #[test]
+    fn sync_health_redacts_and_reads_the_test() {
         let store = default_store();
-        store.save_to(&path).expect("save should succeed");
+        let settings = Settings {
+            github_token: "ghp_secret".into(),
+            github_repo: "someone/bookmarks".into(),
+            ..Settings::default()
+        };
+        let rows = sync_health_rows(
+            &store,
+            &settings,
+            false,
+            Some("0123456789abcdef"),
+            None,
+            None,
+        );
+        let value = |label: &str| rows.iter().find(|(l, _)| *l == label).unwrap().1.clone();
+        assert_eq!(value("Repository"), "***/bookmarks");
+        assert_eq!(value("Token"), "set");
+        assert_eq!(value("Remote SHA"), "0123456789ab");
+        assert!(rows.iter().all(|(_, v)| !v.contains("ghp_secret")));
 
-        let loaded = BookmarkStore::load_from(&path);
-        assert_eq!(store, loaded);
+        let same = sync_test_message(&Ok(Some("abc".into())), Some("abc"));
+        assert!(same.1 && same.0.contains("last synced"));
+        let moved = sync_test_message(&Ok(Some("def".into())), Some("abc"));
+        assert!(moved.1 && moved.0.contains("changed"));
+        assert!(sync_test_message(&Ok(None), None).1);
+        assert_eq!(
+            sync_test_message(&Err("Not found".into()), None),
+            ("Not found".to_string(), false)
+        );
+    }
 
-        // Clean up
+    #[test]
+    fn creation_stamps_are_optional_and_synced() {
+        let json = r#"{"name":"Rust","url":"https://rust-lang.org/"}"#;
+        let mut bm: Bookmark = serde_json::from_str(json).unwrap();
+        assert_eq!(bm.created, None);
+        assert!(!serde_json::to_string(&bm).unwrap().contains("created"));
+
+        bm.created = Some("2026-10-16 09:30 UTC".into());
+        let store = BookmarkStore {
+            folders: vec![Folder {
+                bookmarks: vec![bm],
+                ..default_store().folders[0].clone()
+            }],
+        };
+        assert!(sync_json(&store, false).contains(r#""created":"2026-10-16 09:30 UTC""#));
+
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.recently_added, DEFAULT_RECENTLY_ADDED);
+    }
+
+    #[test]
+    fn containers_are_checked_and_looked_up_by_url() {
+        assert_eq!(normalize_container("  "), Ok(None));
+        assert_eq!(
+            normalize_container(" Work_2 "),
+            Ok(Some("work_2".to_string()))
+        );
+        assert!(normalize_container("../home").is_err());
+        assert!(normalize_container("two words").is_err());
+        assert!(normalize_container(&"x".repeat(33)).is_err());
+
+        let mut store = default_store();
+        let url = store.folders[0].bookmarks[0].url.clone();
+        assert_eq!(bookmark_container(&store, &url), Some(None));
+        store.folders[0].bookmarks[0].container = Some("work".to_string());
+        assert_eq!(
+            bookmark_container(&store, &url),
+            Some(Some("work".to_string()))
+        );
+        assert_eq!(
+            bookmark_container(&store, "https://not-saved.example/"),
+            None
+        );
+        let json = serde_json::to_string(&store).unwrap();
+        assert!(json.contains(r#""container":"work""#));
+
+        // Arrived by a pull or import without being checked
+        store.folders[0].bookmarks[0].container = Some("../../.ssh".to_string());
+        assert_eq!(bookmark_container(&store, &url), Some(None));
+        store.folders[0].bookmarks[0].container = Some(" Work ".to_string());
+        assert_eq!(
+            bookmark_container(&store, &url),
+            Some(Some("work".to_string()))
+        );
+    }
+
+    #[test]
+    fn mnemonics_are_unique_and_need_the_leader() {
+        let mut store = default_store();
+        assert_eq!(set_mnemonic(&mut store, 0, 0, Some('R')), Ok(true));
+        assert_eq!(store.folders[0].bookmarks[0].mnemonic, Some('r'));
+        assert_eq!(set_mnemonic(&mut store, 0, 0, Some('r')), Ok(false));
+        assert!(set_mnemonic(&mut store, 1, 0, Some('r')).is_err());
+        assert!(set_mnemonic(&mut store, 1, 0, Some('?')).is_err());
+        assert_eq!(store.folders[1].bookmarks[0].mnemonic, None);
+        assert_eq!(mnemonic_owner(&store, 'R'), Some((0, 0)));
+
+        let start = Instant::now();
+        let mut leader = MnemonicLeader::default();
+        assert_eq!(leader.finish("r", start), None);
+        leader.start(start);
+        assert_eq!(
+            leader.finish("R", start + Duration::from_millis(200)),
+            Some('r')
+        );
+        // One key ends it, as does waiting too long
+        assert_eq!(leader.finish("r", start), None);
+        leader.start(start);
+        assert_eq!(leader.finish("r", start + MNEMONIC_TIMEOUT * 2), None);
+
+        assert_eq!(set_mnemonic(&mut store, 0, 0, None), Ok(true));
+        assert_eq!(mnemonic_owner(&store, 'r'), None);
+    }
+
+    #[test]
+    fn bookmarklets_are_recognised_and_decoded() {
+        assert!(is_bookmarklet("javascript:alert(1)"));
+        assert!(is_bookmarklet("  JavaScript:void(0)"));
+        assert!(!is_bookmarklet("https://example.com/javascript:"));
+        assert!(!is_bookmarklet("java"));
+        assert_eq!(bookmarklet_code("https://example.com/"), None);
+        assert_eq!(
+            bookmarklet_code("javascript:alert(%22hi%20there%22)%3B").as_deref(),
+            Some("alert(\"hi there\");")
+        );
+        // A stray % that isn't an escape is kept as written
+        assert_eq!(
+            bookmarklet_code("javascript:x=5%2;y=%E2%9C%93").as_deref(),
+            Some("x=5%2;y=\u{2713}")
+        );
+    }
+
+    #[test]
+    fn wayback_snapshots_are_resolved_and_recorded() {
+        assert_eq!(
+            wayback_snapshot_url("/web/20261016093000/https://example.com/").as_deref(),
+            Some("https://web.archive.org/web/20261016093000/https://example.com/")
+        );
+        assert_eq!(
+            wayback_snapshot_url(" https://web.archive.org/web/1/x ").as_deref(),
+            Some("https://web.archive.org/web/1/x")
+        );
+        assert_eq!(wayback_snapshot_url("/save/status"), None);
+        assert!(archive_to_wayback("file:///etc/hosts").is_err());
+
+        let mut store = default_store();
+        let url = store.folders[0].bookmarks[0].url.clone();
+        let snapshot = "https://web.archive.org/web/1/x";
+        assert_eq!(set_wayback_url(&mut store, &url, snapshot), 1);
+        assert_eq!(set_wayback_url(&mut store, &url, snapshot), 0);
+        assert_eq!(
+            store.folders[0].bookmarks[0].wayback_url.as_deref(),
+            Some(snapshot)
+        );
+    }
+
+    #[test]
+    fn taken_folder_names_get_a_number() {
+        let existing = vec!["News".to_string(), "Work".to_string()];
+        assert_eq!(unique_folder_name(&existing, "Recipes"), "Recipes");
+        assert_eq!(unique_folder_name(&existing, "news"), "news (2)");
+        let existing = vec![
+            "News".to_string(),
+            "News (2)".to_string(),
+            "news (3)".to_string(),
+        ];
+        assert_eq!(unique_folder_name(&existing, "News"), "News (4)");
+        assert_eq!(unique_folder_name(&existing, "News (2)"), "News (2) (2)");
+        let mut settings = Settings::default();
+        assert_eq!(settings.on_duplicate_folder(), "suffix");
+        settings.on_duplicate_folder = "reject".to_string();
+        assert_eq!(settings.on_duplicate_folder(), "reject");
+    }
+
+    #[test]
+    fn blocklist_reads_hosts_files_and_covers_subdomains() {
+        let list = parse_blocklist(
+            "# ads\n127.0.0.1 localhost\n::1 localhost ip6-localhost\n\
+             0.0.0.0 Ads.Example.com tracker.net. # inline\nmetrics.io\n\n",
+        );
+        let mut domains: Vec<&str> = list.iter().map(String::as_str).collect();
+        domains.sort();
+        assert_eq!(domains, ["ads.example.com", "metrics.io", "tracker.net"]);
+        assert!(is_blocked_host(&list, "ads.example.com"));
+        assert!(is_blocked_host(&list, "cdn.tracker.net"));
+        assert!(!is_blocked_host(&list, "example.com"));
+        assert!(!is_blocked_host(&list, "nottracker.net"));
+        assert!(blocker_script(&HashSet::new()).is_empty());
+        let script = blocker_script(&list);
+        assert!(script.contains(r#"new Set(["ads.example.com","metrics.io","tracker.net"])"#));
+        assert!(!script.contains("__BLOCKLIST__"));
+    }
+
+    #[test]
+    fn scratchpad_survives_a_restart() {
+        let dir = env::temp_dir().join("bookmarks-browser-scratchpad-test");
         let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("scratchpad.txt");
+        assert_eq!(load_scratchpad(&path), "");
+        save_scratchpad(&path, "call Sam\n  - about the sync PR\n").unwrap();
+        assert_eq!(load_scratchpad(&path), "call Sam\n  - about the sync PR\n");
+        save_scratchpad(&path, "").unwrap();
+        assert_eq!(load_scratchpad(&path), "");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn showing_a_bookmark_expands_its_folder() {
+        let mut store = default_store();
+        let url = store.folders[1].bookmarks[0].url.clone();
+        store.folders[1].expanded = false;
+        assert!(expand_folder_of(&mut store, &url));
+        assert!(store.folders[1].expanded);
+        assert!(!expand_folder_of(&mut store, &url));
+        store.folders[1].expanded = false;
+        store.folders[1].locked = true;
+        assert!(!expand_folder_of(&mut store, &url));
+        assert!(!store.folders[1].expanded);
+        assert!(!expand_folder_of(&mut store, "https://not-saved.example/"));
+        assert!(Settings::default().auto_expand_active);
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert!(settings.auto_expand_active);
+    }
+
+    #[test]
+    fn refresh_intervals_have_a_floor() {
+        assert_eq!(refresh_interval(0), None);
+        assert_eq!(refresh_interval(1), Some(MIN_REFRESH_SECS));
+        assert_eq!(refresh_interval(300), Some(300));
+        let mut store = default_store();
+        store.folders[0].bookmarks[0].refresh_secs = refresh_interval(60);
+        let json = serde_json::to_string(&store).unwrap();
+        assert!(json.contains(r#""refresh_secs":60"#));
+        assert!(!serde_json::to_string(&default_store())
+            .unwrap()
+            .contains("refresh_secs"));
+    }
+
+    #[test]
+    fn notes_are_trimmed_and_capped() {
+        assert_eq!(clean_notes("  \n "), None);
+        assert_eq!(clean_notes("  a quote\n").as_deref(), Some("a quote"));
+        let long = "é".repeat(MAX_NOTES_CHARS + 10);
+        assert_eq!(clean_notes(&long).unwrap().chars().count(), MAX_NOTES_CHARS);
+        let mut store = default_store();
+        store.folders[0].bookmarks[0].notes = clean_notes("Worth a reread");
+        let json = serde_json::to_string(&store).unwrap();
+        assert!(json.contains(r#""notes":"Worth a reread""#));
+        let back: BookmarkStore = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.folders, store.folders);
+    }
+
+    #[test]
+    fn sidebar_reports_ready_and_has_a_fallback() {
+        let html = sidebar_html(
+            &default_store(),
+            &Settings::default(),
+            "dark",
+            &HealthMap::new(),
+        );
+        assert!(html.contains("action: 'sidebar_ready'"));
+        assert!(html.trim_end().ends_with("</html>"));
+        let fallback = sidebar_failed_html("light");
+        assert!(fallback.contains("Sidebar failed to initialize"));
+        assert!(fallback.contains("action: 'reload_sidebar'"));
+        assert!(fallback.contains("#eff1f5"));
+    }
+
+    #[test]
+    fn stored_text_cannot_end_the_sidebar_script() {
+        let plain = sidebar_html(
+            &default_store(),
+            &Settings::default(),
+            "dark",
+            &HealthMap::new(),
+        );
+        let mut store = default_store();
+        let note = "</script><script>alert(1)</script>";
+        store.folders[0].bookmarks[0].notes = clean_notes(note);
+        store.folders[0].bookmarks[1].inject_js = Some(note.to_string());
+        let html = sidebar_html(&store, &Settings::default(), "dark", &HealthMap::new());
+        assert_eq!(
+            html.matches("</script>").count(),
+            plain.matches("</script>").count()
+        );
+        let json = script_json(&store.folders, "[]");
+        assert!(!json.contains('<'));
+        let back: Vec<Folder> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, store.folders);
+    }
+
+    #[test]
+    fn bookmark_debug_info_includes_local_data() {
+        let mut store = default_store();
+        let bm = &mut store.folders[0].bookmarks[0];
+        bm.inject_css = Some("body {}".to_string());
+        let url = bm.url.clone();
+        let mut health = HealthMap::new();
+        health.insert(
+            url.clone(),
+            LinkHealth {
+                last_status: Some(404),
+                ..Default::default()
+            },
+        );
+        let mut visits = VisitMap::new();
+        record_visit(&mut visits, &url, "2026-01-02");
+        let mut zoom = ZoomMap::new();
+        zoom.insert(host_of(&url).unwrap(), 1.25);
+
+        let folder = &store.folders[0];
+        let json = bookmark_debug_json(folder, &folder.bookmarks[0], &health, &visits, &zoom);
+        let report: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(report["folder"], folder.name.as_str());
+        assert_eq!(report["bookmark"]["url"], url.as_str());
+        assert_eq!(report["bookmark"]["inject_css"], "body {}");
+        assert_eq!(report["link_health"]["last_status"], 404);
+        assert_eq!(report["visits"], serde_json::json!(["2026-01-02"]));
+        assert_eq!(report["zoom"], 1.25);
+        assert!(json.contains('\n'));
+
+        let bare = bookmark_debug_json(
+            folder,
+            &folder.bookmarks[0],
+            &HealthMap::new(),
+            &VisitMap::new(),
+            &ZoomMap::new(),
+        );
+        let report: serde_json::Value = serde_json::from_str(&bare).unwrap();
+        assert!(report["link_health"].is_null() && report["zoom"].is_null());
     }
 
     #[test]
@@ -1546,6 +14248,10 @@ mod tests {
         assert_eq!(loaded.sidebar_collapsed, false);
         assert!(loaded.github_token.is_empty());
         assert!(loaded.github_repo.is_empty());
+        assert_eq!(loaded.density(), "comfortable");
+        assert_eq!(loaded.scrollbars(), "always");
+        assert!(loaded.decorations);
+        assert!(!loaded.always_on_top);
     }
 
     #[test]